@@ -0,0 +1,620 @@
+//! Color Graphics Adapter (CGA) emulation
+//!
+//! The CGA provides:
+//! - 16KB video RAM at 0xB8000-0xBBFFF, mirrored at 0xBC000-0xBFFFF
+//! - 40x25 and 80x25 color text with 8x8 pixel characters (modes 0-3)
+//! - 320x200 graphics in 4 colors (modes 4 and 5)
+//! - 640x200 graphics in 2 colors (mode 6)
+//!
+//! Every mode renders to a 640x200 display; 40-column text and 320x200
+//! graphics pixels are doubled horizontally.
+
+use crate::components::mda::{FONT_8X8_OFFSET, FONT_ROM_DATA};
+use crate::components::pic::Pic;
+use crate::io::{DeviceState, IoDevice};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::ops::RangeInclusive;
+
+/// Size of video RAM in bytes
+pub const CGA_VRAM_SIZE: usize = 0x4000;
+
+/// Width of the rendered display in pixels
+pub const DISPLAY_WIDTH: usize = 640;
+
+/// Height of the rendered display in pixels
+pub const DISPLAY_HEIGHT: usize = 200;
+
+/// CPU cycles per displayed frame (60 Hz at 4.77 MHz)
+const FRAME_CYCLES: u64 = 79_500;
+
+/// Character clocks per scanline in 40-column mode, including retrace
+const HORIZONTAL_TOTAL: u64 = 57;
+
+/// Character clocks displayed per scanline in 40-column mode
+const HORIZONTAL_DISPLAYED: u64 = 40;
+
+/// Scanlines per frame, including vertical retrace
+const VERTICAL_TOTAL: u64 = 262;
+
+/// Scanlines displayed per frame
+const VERTICAL_DISPLAYED: u64 = 200;
+
+/// Cycle within a frame at which vertical retrace begins
+const VRETRACE_START: u64 = FRAME_CYCLES * VERTICAL_DISPLAYED / VERTICAL_TOTAL;
+
+/// Offset of the odd scanline bank in graphics modes
+const ODD_BANK_OFFSET: usize = 0x2000;
+
+/// Bytes of video RAM per scanline in graphics modes
+const GRAPHICS_BYTES_PER_LINE: usize = 80;
+
+/// Most snow streaks kept per frame
+const MAX_SNOW: usize = 64;
+
+/// Mode control bit 0: 80-column text
+const MODE_HIRES_TEXT: u8 = 0x01;
+
+/// Mode control bit 1: graphics instead of text
+const MODE_GRAPHICS: u8 = 0x02;
+
+/// Mode control bit 2: black and white (selects the third 320x200 palette)
+const MODE_BW: u8 = 0x04;
+
+/// Mode control bit 3: video output enabled
+const MODE_VIDEO_ENABLE: u8 = 0x08;
+
+/// Mode control bit 4: 640x200 two-color graphics
+const MODE_HIRES_GRAPHICS: u8 = 0x10;
+
+/// Mode control bit 5: attribute bit 7 blinks instead of brightening the background
+const MODE_BLINK: u8 = 0x20;
+
+/// Color select bit 4: intense 320x200 palette
+const COLOR_INTENSE: u8 = 0x10;
+
+/// Color select bit 5: cyan/magenta/white instead of green/red/brown
+const COLOR_PALETTE: u8 = 0x20;
+
+/// The 16 RGBI colors, with the monitor's dark yellow shown as brown
+const RGBI_COLORS: [[u8; 3]; 16] = [
+    [0x00, 0x00, 0x00], // Black
+    [0x00, 0x00, 0xAA], // Blue
+    [0x00, 0xAA, 0x00], // Green
+    [0x00, 0xAA, 0xAA], // Cyan
+    [0xAA, 0x00, 0x00], // Red
+    [0xAA, 0x00, 0xAA], // Magenta
+    [0xAA, 0x55, 0x00], // Brown
+    [0xAA, 0xAA, 0xAA], // Light gray
+    [0x55, 0x55, 0x55], // Dark gray
+    [0x55, 0x55, 0xFF], // Light blue
+    [0x55, 0xFF, 0x55], // Light green
+    [0x55, 0xFF, 0xFF], // Light cyan
+    [0xFF, 0x55, 0x55], // Light red
+    [0xFF, 0x55, 0xFF], // Light magenta
+    [0xFF, 0xFF, 0x55], // Yellow
+    [0xFF, 0xFF, 0xFF], // White
+];
+
+/// Display mode selected through the mode control register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CgaMode {
+    /// 40x25 text (BIOS modes 0 and 1)
+    Text40,
+    /// 80x25 text (BIOS modes 2 and 3)
+    Text80,
+    /// 320x200 four-color graphics (BIOS modes 4 and 5)
+    Graphics320,
+    /// 640x200 two-color graphics (BIOS mode 6)
+    Graphics640,
+}
+
+/// Snapshot of the CGA registers for debugging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CgaState {
+    /// Mode control register (port 0x3D8)
+    pub mode_control: u8,
+    /// Color select register (port 0x3D9)
+    pub color_select: u8,
+}
+
+/// Garbage the adapter showed where the beam was during a CPU write
+#[derive(Debug, Clone, Copy)]
+struct Snow {
+    /// Frame the write happened in
+    frame: u64,
+    /// Scanline the beam was on
+    line: usize,
+    /// Character clock (in 40-column units) the beam was at
+    column: usize,
+    /// Byte on the bus instead of the adapter's own fetch
+    value: u8,
+}
+
+/// CGA (Color Graphics Adapter)
+#[derive(Clone)]
+pub struct Cga {
+    /// Video RAM: text cells or interleaved graphics scanlines
+    vram: [u8; CGA_VRAM_SIZE],
+
+    /// 8x8 font from the character ROM
+    font_8x8: [u8; 256 * 8],
+
+    /// Mode control register (port 0x3D8)
+    mode_control: u8,
+
+    /// Color select register (port 0x3D9)
+    color_select: u8,
+
+    /// Cycles run since reset, locating the beam for the status register
+    cycle_count: u64,
+
+    /// Set when the guest writes a different mode control value
+    mode_changed: bool,
+
+    /// Set when the beam enters vertical retrace
+    vretrace_started: bool,
+
+    /// Show snow for video RAM writes during active display
+    snow_enabled: bool,
+
+    /// Snow drawn until the beam passes over it again
+    snow: Vec<Snow>,
+}
+
+impl Cga {
+    /// Create a new CGA with blank video RAM and the display disabled
+    pub fn new() -> Self {
+        let mut font_8x8 = [0u8; 256 * 8];
+        font_8x8.copy_from_slice(&FONT_ROM_DATA[FONT_8X8_OFFSET..FONT_8X8_OFFSET + 256 * 8]);
+
+        Self {
+            vram: [0; CGA_VRAM_SIZE],
+            font_8x8,
+            mode_control: 0,
+            color_select: 0,
+            cycle_count: 0,
+            mode_changed: false,
+            vretrace_started: false,
+            snow_enabled: false,
+            snow: Vec::new(),
+        }
+    }
+
+    /// Read from video RAM
+    #[inline(always)]
+    pub fn read_vram(&self, offset: u16) -> u8 {
+        self.vram[offset as usize & (CGA_VRAM_SIZE - 1)]
+    }
+
+    /// Write to video RAM
+    ///
+    /// With snow enabled, a write during active display also leaves a
+    /// streak of garbage where the beam is.
+    #[inline(always)]
+    pub fn write_vram(&mut self, offset: u16, value: u8) {
+        self.vram[offset as usize & (CGA_VRAM_SIZE - 1)] = value;
+        if self.snow_enabled {
+            self.latch_snow(value);
+        }
+    }
+
+    /// Enable or disable snow on video RAM writes outside retrace
+    ///
+    /// The real adapter has no arbitration between CPU and CRTC accesses
+    /// to video RAM, so during active display the byte on the bus replaces
+    /// the one being fetched for the beam. The setting survives a reset.
+    pub fn set_snow(&mut self, enabled: bool) {
+        self.snow_enabled = enabled;
+        if !enabled {
+            self.snow.clear();
+        }
+    }
+
+    /// Check whether snow is simulated
+    pub fn snow(&self) -> bool {
+        self.snow_enabled
+    }
+
+    /// Record a snow streak at the beam position, unless it is in retrace
+    fn latch_snow(&mut self, value: u8) {
+        if self.in_vertical_retrace() || self.in_horizontal_retrace() {
+            return;
+        }
+        if self.snow.len() == MAX_SNOW {
+            return;
+        }
+
+        let frame_phase = self.cycle_count % FRAME_CYCLES;
+        let line_phase = frame_phase * VERTICAL_TOTAL % FRAME_CYCLES;
+        self.snow.push(Snow {
+            frame: self.cycle_count / FRAME_CYCLES,
+            line: (frame_phase * VERTICAL_TOTAL / FRAME_CYCLES) as usize,
+            column: (line_phase * HORIZONTAL_TOTAL / FRAME_CYCLES) as usize,
+            value,
+        });
+    }
+
+    /// Describe the adapter registers without side effects
+    pub fn describe(&self) -> CgaState {
+        CgaState {
+            mode_control: self.mode_control,
+            color_select: self.color_select,
+        }
+    }
+
+    /// Get the current mode control register value
+    pub fn mode_control(&self) -> u8 {
+        self.mode_control
+    }
+
+    /// Get the current color select register value
+    pub fn color_select(&self) -> u8 {
+        self.color_select
+    }
+
+    /// Get the display mode selected by the mode control register
+    pub fn mode(&self) -> CgaMode {
+        if self.mode_control & MODE_GRAPHICS == 0 {
+            if self.mode_control & MODE_HIRES_TEXT != 0 {
+                CgaMode::Text80
+            } else {
+                CgaMode::Text40
+            }
+        } else if self.mode_control & MODE_HIRES_GRAPHICS != 0 {
+            CgaMode::Graphics640
+        } else {
+            CgaMode::Graphics320
+        }
+    }
+
+    /// Number of text columns per row in the current mode
+    pub fn text_columns(&self) -> usize {
+        match self.mode() {
+            CgaMode::Text40 => 40,
+            _ => 80,
+        }
+    }
+
+    /// Number of text rows displayed
+    pub fn text_rows(&self) -> usize {
+        DISPLAY_HEIGHT / 8
+    }
+
+    /// Get the (character, attribute) of a text cell
+    pub fn cell(&self, row: usize, col: usize) -> (u8, u8) {
+        let offset = ((row * self.text_columns() + col) * 2) as u16;
+        (
+            self.read_vram(offset),
+            self.read_vram(offset.wrapping_add(1)),
+        )
+    }
+
+    /// Get the RGBI color index of a pixel in 320x200 graphics mode
+    ///
+    /// Color 0 is the background from the color select register; colors
+    /// 1-3 come from the palette chosen by the color select and mode
+    /// control registers.
+    pub fn pixel_320(&self, x: usize, y: usize) -> u8 {
+        let byte = self.read_vram(Self::graphics_offset(x / 4, y));
+        let color = (byte >> (6 - (x % 4) * 2)) & 0x03;
+        if color == 0 {
+            return self.color_select & 0x0F;
+        }
+
+        let palette: [u8; 3] = if self.mode_control & MODE_BW != 0 {
+            [3, 4, 7] // Cyan, red, light gray
+        } else if self.color_select & COLOR_PALETTE != 0 {
+            [3, 5, 7] // Cyan, magenta, light gray
+        } else {
+            [2, 4, 6] // Green, red, brown
+        };
+        let intensity = if self.color_select & COLOR_INTENSE != 0 {
+            8
+        } else {
+            0
+        };
+        palette[color as usize - 1] | intensity
+    }
+
+    /// Get the RGBI color index of a pixel in 640x200 graphics mode
+    ///
+    /// Set bits show the foreground color from the color select register,
+    /// clear bits are black.
+    pub fn pixel_640(&self, x: usize, y: usize) -> u8 {
+        let byte = self.read_vram(Self::graphics_offset(x / 8, y));
+        if (byte >> (7 - x % 8)) & 1 != 0 {
+            self.color_select & 0x0F
+        } else {
+            0
+        }
+    }
+
+    /// Video RAM offset of a byte in a graphics scanline
+    ///
+    /// Even scanlines are stored in the first 8KB, odd ones in the second.
+    fn graphics_offset(byte: usize, y: usize) -> u16 {
+        ((y & 1) * ODD_BANK_OFFSET + (y / 2) * GRAPHICS_BYTES_PER_LINE + byte) as u16
+    }
+
+    /// Take a pending mode change, returning the new mode control value
+    ///
+    /// Returns None if the mode has not changed since the last call.
+    pub fn take_mode_change(&mut self) -> Option<u8> {
+        if self.mode_changed {
+            self.mode_changed = false;
+            Some(self.mode_control)
+        } else {
+            None
+        }
+    }
+
+    /// Take a pending start of vertical retrace
+    ///
+    /// Returns true once per frame, after the beam has finished the last
+    /// displayed scanline.
+    pub fn take_vertical_retrace(&mut self) -> bool {
+        core::mem::take(&mut self.vretrace_started)
+    }
+
+    /// Check whether the beam is in vertical retrace
+    pub fn in_vertical_retrace(&self) -> bool {
+        self.cycle_count % FRAME_CYCLES >= VRETRACE_START
+    }
+
+    /// Check whether the beam is in horizontal retrace
+    pub fn in_horizontal_retrace(&self) -> bool {
+        // Position within the current scanline, in units of 1/FRAME_CYCLES lines
+        let line_phase = (self.cycle_count % FRAME_CYCLES) * VERTICAL_TOTAL % FRAME_CYCLES;
+        line_phase * HORIZONTAL_TOTAL >= FRAME_CYCLES * HORIZONTAL_DISPLAYED
+    }
+
+    /// Number of vertical retraces begun by the time `cycle_count` cycles have run
+    fn retraces_by(cycle_count: u64) -> u64 {
+        (cycle_count + FRAME_CYCLES - VRETRACE_START) / FRAME_CYCLES
+    }
+
+    /// Render the current mode to an RGBA framebuffer
+    ///
+    /// The framebuffer is `DISPLAY_WIDTH` x `DISPLAY_HEIGHT` pixels. With
+    /// video output disabled the whole display is black.
+    pub fn render_to_framebuffer(&self, framebuffer: &mut [u8]) {
+        let framebuffer = &mut framebuffer[..DISPLAY_WIDTH * DISPLAY_HEIGHT * 4];
+        if self.mode_control & MODE_VIDEO_ENABLE == 0 {
+            for pixel in framebuffer.chunks_exact_mut(4) {
+                pixel.copy_from_slice(&[0x00, 0x00, 0x00, 0xFF]);
+            }
+            return;
+        }
+
+        match self.mode() {
+            CgaMode::Text40 | CgaMode::Text80 => self.render_text(framebuffer),
+            CgaMode::Graphics320 => {
+                for y in 0..DISPLAY_HEIGHT {
+                    for x in 0..DISPLAY_WIDTH {
+                        Self::put_pixel(framebuffer, x, y, self.pixel_320(x / 2, y));
+                    }
+                }
+            }
+            CgaMode::Graphics640 => {
+                for y in 0..DISPLAY_HEIGHT {
+                    for x in 0..DISPLAY_WIDTH {
+                        Self::put_pixel(framebuffer, x, y, self.pixel_640(x, y));
+                    }
+                }
+            }
+        }
+
+        self.render_snow(framebuffer);
+    }
+
+    /// Draw each snow streak over one character clock of its scanline
+    ///
+    /// Bits of the stray byte are shown white on black, two pixels each.
+    fn render_snow(&self, framebuffer: &mut [u8]) {
+        let clock_width = DISPLAY_WIDTH / HORIZONTAL_DISPLAYED as usize;
+        for snow in &self.snow {
+            for x in 0..clock_width {
+                let bit = (snow.value >> (7 - x * 8 / clock_width)) & 1;
+                let color = if bit != 0 { 0x0F } else { 0x00 };
+                Self::put_pixel(framebuffer, snow.column * clock_width + x, snow.line, color);
+            }
+        }
+    }
+
+    /// Render 25 rows of 8x8 character cells
+    fn render_text(&self, framebuffer: &mut [u8]) {
+        let columns = self.text_columns();
+        // 40-column characters are drawn two pixels wide
+        let scale = 80 / columns;
+
+        for row in 0..self.text_rows() {
+            for col in 0..columns {
+                let (char_code, attribute) = self.cell(row, col);
+                let foreground = attribute & 0x0F;
+                let background = if self.mode_control & MODE_BLINK != 0 {
+                    (attribute >> 4) & 0x07
+                } else {
+                    attribute >> 4
+                };
+
+                for scan_line in 0..8 {
+                    let font_byte = self.font_8x8[char_code as usize * 8 + scan_line];
+                    for x in 0..8 * scale {
+                        let pixel_on = (font_byte >> (7 - x / scale)) & 1 != 0;
+                        let color = if pixel_on { foreground } else { background };
+                        Self::put_pixel(
+                            framebuffer,
+                            col * 8 * scale + x,
+                            row * 8 + scan_line,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Write one RGBI-colored pixel to the framebuffer
+    fn put_pixel(framebuffer: &mut [u8], x: usize, y: usize, color: u8) {
+        let [r, g, b] = RGBI_COLORS[color as usize & 0x0F];
+        let idx = (y * DISPLAY_WIDTH + x) * 4;
+        framebuffer[idx..idx + 4].copy_from_slice(&[r, g, b, 0xFF]);
+    }
+}
+
+impl Default for Cga {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IoDevice for Cga {
+    fn read_u8(&mut self, port: u16) -> u8 {
+        match port {
+            0x3DA => {
+                // Status register
+                // Bit 0: display inactive (horizontal or vertical retrace)
+                // Bit 3: vertical retrace
+                let vretrace = self.in_vertical_retrace();
+                let inactive = vretrace || self.in_horizontal_retrace();
+                inactive as u8 | ((vretrace as u8) << 3)
+            }
+            _ => 0xFF,
+        }
+    }
+
+    fn write_u8(&mut self, port: u16, value: u8) {
+        match port {
+            0x3D8 => {
+                let value = value & 0x3F;
+                if value != self.mode_control {
+                    self.mode_control = value;
+                    self.mode_changed = true;
+                }
+            }
+            0x3D9 => self.color_select = value & 0x3F,
+            // The 6845 CRTC registers don't affect rendering yet
+            _ => {}
+        }
+    }
+
+    fn port_range(&self) -> RangeInclusive<u16> {
+        0x3D0..=0x3DF
+    }
+
+    fn tick(&mut self, cycles: u16, _pic: &mut Pic) {
+        let before = self.cycle_count;
+        self.cycle_count += cycles as u64;
+
+        if Self::retraces_by(self.cycle_count) > Self::retraces_by(before) {
+            self.vretrace_started = true;
+
+            // Snow lasts until the beam has drawn the next frame over it
+            let frame = self.cycle_count / FRAME_CYCLES;
+            self.snow.retain(|snow| snow.frame >= frame);
+        }
+    }
+
+    fn reset(&mut self) {
+        // Video RAM is not cleared, just like system RAM on a warm reset
+        self.mode_control = 0;
+        self.color_select = 0;
+        self.cycle_count = 0;
+        self.mode_changed = false;
+        self.vretrace_started = false;
+        self.snow.clear();
+    }
+
+    fn snapshot(&self) -> Option<DeviceState> {
+        Some(DeviceState::Cga(self.describe()))
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) {
+        if let Some(state) = state.downcast_ref::<Self>() {
+            *self = state.clone();
+        }
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_from_mode_control() {
+        let mut cga = Cga::new();
+        cga.write_u8(0x3D8, 0x28);
+        assert_eq!(cga.mode(), CgaMode::Text40);
+        cga.write_u8(0x3D8, 0x29);
+        assert_eq!(cga.mode(), CgaMode::Text80);
+        cga.write_u8(0x3D8, 0x0A);
+        assert_eq!(cga.mode(), CgaMode::Graphics320);
+        cga.write_u8(0x3D8, 0x1E);
+        assert_eq!(cga.mode(), CgaMode::Graphics640);
+    }
+
+    #[test]
+    fn test_640_graphics_uses_foreground_color() {
+        let mut cga = Cga::new();
+        cga.write_u8(0x3D8, 0x1E);
+        cga.write_u8(0x3D9, 0x0E); // Yellow
+        cga.write_vram(0x2000, 0x81); // Scanline 1, pixels 0 and 7
+
+        assert_eq!(cga.pixel_640(0, 1), 0x0E);
+        assert_eq!(cga.pixel_640(1, 1), 0x00);
+        assert_eq!(cga.pixel_640(7, 1), 0x0E);
+        assert_eq!(cga.pixel_640(0, 0), 0x00);
+    }
+
+    #[test]
+    fn test_40_column_text_doubles_pixels() {
+        let mut cga = Cga::new();
+        cga.write_u8(0x3D8, 0x08);
+        cga.write_vram(0, 0xDB); // Full block
+        cga.write_vram(1, 0x1F); // White on blue
+
+        let mut framebuffer = vec![0u8; DISPLAY_WIDTH * DISPLAY_HEIGHT * 4];
+        cga.render_to_framebuffer(&mut framebuffer);
+
+        // The block covers 16 pixels, then the blank second cell begins
+        assert_eq!(&framebuffer[15 * 4..16 * 4], &[0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(&framebuffer[16 * 4..17 * 4], &[0x00, 0x00, 0x00, 0xFF]);
+    }
+
+    #[test]
+    fn test_status_reports_vertical_retrace() {
+        let mut cga = Cga::new();
+        let mut pic = Pic::new(0x08);
+        assert_eq!(cga.read_u8(0x3DA) & 0x08, 0);
+
+        cga.tick(VRETRACE_START as u16, &mut pic);
+        assert_eq!(cga.read_u8(0x3DA) & 0x09, 0x09);
+    }
+
+    #[test]
+    fn test_no_snow_during_vertical_retrace() {
+        let mut cga = Cga::new();
+        let mut pic = Pic::new(0x08);
+        cga.write_u8(0x3D8, 0x09);
+        cga.set_snow(true);
+        cga.tick(VRETRACE_START as u16, &mut pic);
+
+        cga.write_vram(0x3000, 0xFF);
+
+        let mut framebuffer = vec![0u8; DISPLAY_WIDTH * DISPLAY_HEIGHT * 4];
+        cga.render_to_framebuffer(&mut framebuffer);
+        assert!(framebuffer
+            .chunks_exact(4)
+            .all(|pixel| pixel == [0x00, 0x00, 0x00, 0xFF]));
+    }
+}
@@ -0,0 +1,350 @@
+//! Battery-backed CMOS RAM (MC146818 style, ports 0x70/0x71)
+//!
+//! Software writes a register index to 0x70 and then reads or writes the
+//! byte through 0x71. Bit 7 of the index write is the NMI mask on AT-class
+//! boards and is ignored here. The standard configuration area 0x10..=0x2D
+//! is covered by a 16-bit sum stored big-endian at 0x2E/0x2F, which is
+//! kept up to date as bytes in that range change.
+//!
+//! The clock and status registers are served by the real-time clock (see
+//! `rtc`) once it has a clock source; until then they are plain RAM.
+//!
+//! With `std`, the contents can be bound to a file so settings survive
+//! between runs the way a battery would keep them.
+
+use crate::components::rtc::{ClockSource, Rtc};
+use crate::io::IoDevice;
+use alloc::boxed::Box;
+use core::any::Any;
+use core::ops::RangeInclusive;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+/// Index register port
+pub const CMOS_INDEX_PORT: u16 = 0x70;
+
+/// Data register port
+pub const CMOS_DATA_PORT: u16 = 0x71;
+
+/// Bytes of CMOS RAM, including the clock registers
+pub const CMOS_SIZE: usize = 64;
+
+/// First and last bytes covered by the checksum
+const CHECKSUM_RANGE: RangeInclusive<usize> = 0x10..=0x2D;
+
+/// High byte of the checksum; the low byte follows it
+const CHECKSUM_HIGH: usize = 0x2E;
+
+/// Low byte of the checksum
+const CHECKSUM_LOW: usize = 0x2F;
+
+/// Index bits that select a register; bit 7 is the NMI mask
+const INDEX_MASK: u8 = (CMOS_SIZE - 1) as u8;
+
+/// CMOS RAM with optional file persistence
+#[derive(Clone)]
+pub struct Cmos {
+    /// RAM contents
+    data: [u8; CMOS_SIZE],
+    /// Register selected by the last write to the index port
+    index: u8,
+    /// Modified since the last load or save
+    dirty: bool,
+    /// Clock and status registers
+    rtc: Rtc,
+    /// File the contents are saved to
+    #[cfg(feature = "std")]
+    path: Option<PathBuf>,
+}
+
+impl Cmos {
+    /// Create a CMOS with all bytes zeroed and a matching checksum
+    pub fn new() -> Self {
+        let mut cmos = Self {
+            data: [0; CMOS_SIZE],
+            index: 0,
+            dirty: false,
+            rtc: Rtc::new(),
+            #[cfg(feature = "std")]
+            path: None,
+        };
+        cmos.update_checksum();
+        cmos
+    }
+
+    /// Create a CMOS holding `data`, as if restored from a battery
+    ///
+    /// The checksum bytes are kept as given; use `checksum_valid` to find
+    /// out whether they match.
+    pub fn from_bytes(data: [u8; CMOS_SIZE]) -> Self {
+        let mut cmos = Self::new();
+        cmos.data = data;
+        cmos
+    }
+
+    /// Load CMOS contents from `path` and save back to it from then on
+    ///
+    /// A missing file starts from a blank CMOS, which `save` creates. A file
+    /// of the wrong size is an error. A bad checksum is only logged, because
+    /// that is the state a BIOS expects to find after a battery failure and
+    /// it reports it itself.
+    #[cfg(feature = "std")]
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let mut cmos = match std::fs::read(path) {
+            Ok(bytes) => {
+                let data: [u8; CMOS_SIZE] = bytes.as_slice().try_into().map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("CMOS file is {} bytes, expected {}", bytes.len(), CMOS_SIZE),
+                    )
+                })?;
+                let cmos = Self::from_bytes(data);
+                if !cmos.checksum_valid() {
+                    log_warn!("[CMOS] Checksum mismatch in {}", path.display());
+                }
+                cmos
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::new(),
+            Err(e) => return Err(e),
+        };
+        cmos.path = Some(path.to_path_buf());
+        Ok(cmos)
+    }
+
+    /// Write the contents back to the file they were loaded from
+    #[cfg(feature = "std")]
+    pub fn save(&mut self) -> io::Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No CMOS file path"))?;
+        std::fs::write(path, self.data)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Get the file path (if bound to one)
+    #[cfg(feature = "std")]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Set or clear the real-time clock's source of the current time
+    pub fn set_clock(&mut self, clock: Option<ClockSource>) {
+        self.rtc.set_clock(clock);
+    }
+
+    /// Get the real-time clock
+    pub fn rtc(&self) -> &Rtc {
+        &self.rtc
+    }
+
+    /// Check if the contents changed since the last load or save
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Read a byte without going through the ports
+    pub fn byte(&self, index: u8) -> u8 {
+        self.data[(index & INDEX_MASK) as usize]
+    }
+
+    /// Write a byte without going through the ports
+    ///
+    /// Writes inside the checksummed area update the checksum.
+    pub fn set_byte(&mut self, index: u8, value: u8) {
+        let index = (index & INDEX_MASK) as usize;
+        self.data[index] = value;
+        if CHECKSUM_RANGE.contains(&index) {
+            self.update_checksum();
+        }
+        self.dirty = true;
+    }
+
+    /// Get the raw contents
+    pub fn bytes(&self) -> &[u8; CMOS_SIZE] {
+        &self.data
+    }
+
+    /// Sum of the checksummed area
+    pub fn compute_checksum(&self) -> u16 {
+        self.data[CHECKSUM_RANGE]
+            .iter()
+            .fold(0u16, |sum, &byte| sum.wrapping_add(byte as u16))
+    }
+
+    /// Checksum currently stored at 0x2E/0x2F
+    pub fn stored_checksum(&self) -> u16 {
+        u16::from_be_bytes([self.data[CHECKSUM_HIGH], self.data[CHECKSUM_LOW]])
+    }
+
+    /// Check whether the stored checksum matches the contents
+    pub fn checksum_valid(&self) -> bool {
+        self.stored_checksum() == self.compute_checksum()
+    }
+
+    fn update_checksum(&mut self) {
+        let [high, low] = self.compute_checksum().to_be_bytes();
+        self.data[CHECKSUM_HIGH] = high;
+        self.data[CHECKSUM_LOW] = low;
+    }
+}
+
+impl Default for Cmos {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IoDevice for Cmos {
+    fn read_u8(&mut self, port: u16) -> u8 {
+        match port {
+            CMOS_DATA_PORT => self
+                .rtc
+                .read(self.index)
+                .unwrap_or_else(|| self.byte(self.index)),
+            // The index register is write-only
+            _ => 0xFF,
+        }
+    }
+
+    fn write_u8(&mut self, port: u16, value: u8) {
+        match port {
+            CMOS_INDEX_PORT => self.index = value & INDEX_MASK,
+            _ => {
+                if !self.rtc.write(self.index, value) {
+                    self.set_byte(self.index, value);
+                }
+            }
+        }
+    }
+
+    fn port_range(&self) -> RangeInclusive<u16> {
+        CMOS_INDEX_PORT..=CMOS_DATA_PORT
+    }
+
+    // Reset leaves the contents alone: that's what the battery is for.
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) {
+        if let Some(state) = state.downcast_ref::<Self>() {
+            // Keep our own file binding
+            self.data = state.data;
+            self.index = state.index;
+            self.rtc.restore_registers(&state.rtc);
+            self.dirty = true;
+        }
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_port_write_updates_checksum() {
+        let mut cmos = Cmos::new();
+        cmos.write_u8(CMOS_INDEX_PORT, 0x90); // NMI mask bit set, register 0x10
+        cmos.write_u8(CMOS_DATA_PORT, 0x44);
+
+        cmos.write_u8(CMOS_INDEX_PORT, 0x10);
+        assert_eq!(cmos.read_u8(CMOS_DATA_PORT), 0x44);
+        assert_eq!(cmos.stored_checksum(), 0x0044);
+        assert!(cmos.checksum_valid());
+        assert!(cmos.is_dirty());
+    }
+
+    #[test]
+    fn test_bytes_outside_checksum_range_leave_it_alone() {
+        let mut cmos = Cmos::new();
+        cmos.set_byte(0x0E, 0x80); // Diagnostic status
+        cmos.set_byte(0x32, 0x19); // Century
+        assert_eq!(cmos.stored_checksum(), 0);
+    }
+
+    #[test]
+    fn test_clock_reads_bcd_time_through_ports() {
+        use crate::components::rtc::RtcTime;
+        use alloc::rc::Rc;
+
+        let mut cmos = Cmos::new();
+        let time = RtcTime {
+            year: 1987,
+            month: 4,
+            day: 2,
+            weekday: 5,
+            hour: 13,
+            minute: 45,
+            second: 59,
+        };
+        cmos.set_clock(Some(Rc::new(move || time)));
+
+        let mut read = |index: u8| {
+            cmos.write_u8(CMOS_INDEX_PORT, index);
+            cmos.read_u8(CMOS_DATA_PORT)
+        };
+        assert_eq!(read(0x00), 0x59); // Seconds
+        assert_eq!(read(0x02), 0x45); // Minutes
+        assert_eq!(read(0x04), 0x13); // Hours
+        assert_eq!(read(0x09), 0x87); // Year
+        assert_eq!(read(0x0D), 0x80); // Valid RAM and time
+
+        // Switching register B to binary changes the encoding
+        cmos.write_u8(CMOS_INDEX_PORT, 0x0B);
+        cmos.write_u8(CMOS_DATA_PORT, 0x06);
+        cmos.write_u8(CMOS_INDEX_PORT, 0x00);
+        assert_eq!(cmos.read_u8(CMOS_DATA_PORT), 59);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_save_and_reload_restores_byte_and_checksum() {
+        let path =
+            std::env::temp_dir().join(format!("ezpc-cmos-{}-roundtrip.bin", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut cmos = Cmos::from_file(&path).unwrap();
+        assert_eq!(cmos.stored_checksum(), 0);
+        cmos.write_u8(CMOS_INDEX_PORT, 0x14); // Equipment byte
+        cmos.write_u8(CMOS_DATA_PORT, 0x41);
+        cmos.save().unwrap();
+        assert!(!cmos.is_dirty());
+
+        let mut reloaded = Cmos::from_file(&path).unwrap();
+        reloaded.write_u8(CMOS_INDEX_PORT, 0x14);
+        assert_eq!(reloaded.read_u8(CMOS_DATA_PORT), 0x41);
+        assert_eq!(reloaded.stored_checksum(), 0x0041);
+        assert!(reloaded.checksum_valid());
+
+        // A later write recomputes the checksum from the reloaded contents
+        reloaded.set_byte(0x15, 0x80);
+        assert_eq!(reloaded.stored_checksum(), 0x00C1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_load_detects_bad_checksum() {
+        let path =
+            std::env::temp_dir().join(format!("ezpc-cmos-{}-corrupt.bin", std::process::id()));
+        let mut data = [0u8; CMOS_SIZE];
+        data[0x10] = 0x40; // Floppy types, checksum left at zero
+        std::fs::write(&path, data).unwrap();
+
+        let cmos = Cmos::from_file(&path).unwrap();
+        assert!(!cmos.checksum_valid());
+        assert_eq!(cmos.compute_checksum(), 0x0040);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
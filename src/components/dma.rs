@@ -18,8 +18,8 @@
 //! - 0x08-0x0F: Control registers
 //! - 0x81, 0x82, 0x83, 0x87: Page registers (extend to 20-bit addressing)
 
-use crate::io::IoDevice;
-use std::ops::RangeInclusive;
+use crate::io::{DeviceState, IoDevice};
+use core::ops::RangeInclusive;
 
 // =============================================================================
 // Constants
@@ -189,10 +189,14 @@ impl DmaChannel {
         if self.current_count == 0 {
             self.terminal_count = true;
 
-            // Auto-init: reload base values
+            // Auto-init reloads the base values; otherwise the count wraps
+            // and the channel masks itself until software re-arms it
             if self.auto_init() {
                 self.current_address = self.base_address;
                 self.current_count = self.base_count;
+            } else {
+                self.current_count = 0xFFFF;
+                self.masked = true;
             }
 
             return true;
@@ -243,6 +247,29 @@ impl DmaChannel {
     }
 }
 
+/// Snapshot of a DMA channel for debugging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DmaChannelState {
+    /// Current address register
+    pub address: u16,
+    /// Current count register
+    pub count: u16,
+    /// Page register (address bits 16-19)
+    pub page: u8,
+    /// Mode register
+    pub mode: u8,
+    /// Channel is masked
+    pub masked: bool,
+}
+
+/// Snapshot of the DMA controller for debugging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DmaState {
+    pub channels: [DmaChannelState; 4],
+    /// Command register
+    pub command: u8,
+}
+
 impl Default for DmaChannel {
     fn default() -> Self {
         Self::new()
@@ -321,6 +348,29 @@ impl Dma {
         }
     }
 
+    /// Describe the controller without side effects
+    ///
+    /// Unlike reading the address/count ports, this does not toggle the
+    /// byte flip-flop or clear terminal count status.
+    pub fn describe(&self) -> DmaState {
+        let channel = |ch: &DmaChannel| DmaChannelState {
+            address: ch.current_address,
+            count: ch.current_count,
+            page: ch.page,
+            mode: ch.mode,
+            masked: ch.masked,
+        };
+        DmaState {
+            channels: [
+                channel(&self.channels[0]),
+                channel(&self.channels[1]),
+                channel(&self.channels[2]),
+                channel(&self.channels[3]),
+            ],
+            command: self.command,
+        }
+    }
+
     /// Master reset - resets controller to initial state
     pub fn master_reset(&mut self) {
         for channel in &mut self.channels {
@@ -592,6 +642,14 @@ impl IoDevice for Dma {
         // DMA is hardwired in MemoryBus, not routed via this range
         0..=0
     }
+
+    fn reset(&mut self) {
+        self.master_reset();
+    }
+
+    fn snapshot(&self) -> Option<DeviceState> {
+        Some(DeviceState::Dma(self.describe()))
+    }
 }
 
 // =============================================================================
@@ -824,7 +882,9 @@ mod tests {
         // Third advance: count 0 -> underflow = TC
         assert!(ch.advance());
         assert_eq!(ch.current_address, 0x1003);
+        assert_eq!(ch.current_count, 0xFFFF);
         assert!(ch.terminal_count);
+        assert!(ch.masked);
     }
 
     #[test]
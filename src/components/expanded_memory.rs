@@ -0,0 +1,245 @@
+//! LIM EMS expanded memory board
+//!
+//! Expanded memory lives outside the 8088's address space. A 64KB page
+//! frame in the upper memory area is split into four 16KB physical pages,
+//! and each can be pointed at any 16KB logical page of the board's memory.
+//! The EMS driver (EMM.SYS or similar) moves pages in and out of the frame
+//! on behalf of programs.
+//!
+//! The register interface follows the Lo-tech 2MB EMS board: one port per
+//! physical page, starting at `EMS_DEFAULT_PORT`. Writing a logical page
+//! number maps that page; reading returns the current mapping. Page numbers
+//! past the end of the board unmap the physical page, leaving it reading as
+//! open bus.
+
+use crate::io::IoDevice;
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::ops::RangeInclusive;
+
+/// Size of a physical or logical EMS page
+pub const EMS_PAGE_SIZE: usize = 16 * 1024;
+
+/// Physical pages in the 64KB page frame
+pub const EMS_PHYSICAL_PAGES: usize = 4;
+
+/// Default page frame segment base (E000:0000)
+pub const EMS_DEFAULT_FRAME: u32 = 0xE0000;
+
+/// Default first page register port
+pub const EMS_DEFAULT_PORT: u16 = 0x260;
+
+/// Default board size in logical pages (2MB)
+pub const EMS_DEFAULT_PAGES: u16 = 128;
+
+/// Page register value read back for an unmapped physical page
+const UNMAPPED: u8 = 0xFF;
+
+/// EMS board: page frame, page registers and backing store
+#[derive(Clone)]
+pub struct ExpandedMemory {
+    /// Linear address of the page frame
+    frame_base: u32,
+
+    /// First page register port
+    port_base: u16,
+
+    /// Logical page mapped at each physical page, if any
+    mapping: [Option<u16>; EMS_PHYSICAL_PAGES],
+
+    /// Backing store, `EMS_PAGE_SIZE` bytes per logical page
+    memory: Vec<u8>,
+}
+
+impl ExpandedMemory {
+    /// Create a board with `pages` logical pages and the default frame/port
+    pub fn new(pages: u16) -> Self {
+        Self::with_frame(pages, EMS_DEFAULT_FRAME, EMS_DEFAULT_PORT)
+    }
+
+    /// Create a board with the page frame at `frame_base` and the page
+    /// registers at `port_base..port_base + 4`
+    ///
+    /// Panics unless the frame is 16KB aligned and sits between the end of
+    /// video memory (0xC0000) and the BIOS ROM (0xF0000).
+    pub fn with_frame(pages: u16, frame_base: u32, port_base: u16) -> Self {
+        assert!(
+            frame_base.is_multiple_of(EMS_PAGE_SIZE as u32)
+                && (0xC0000..=0xE0000).contains(&frame_base),
+            "EMS page frame {:05X} must be 16KB aligned within C0000-EFFFF",
+            frame_base
+        );
+        Self {
+            frame_base,
+            port_base,
+            mapping: [None; EMS_PHYSICAL_PAGES],
+            memory: vec![0; pages as usize * EMS_PAGE_SIZE],
+        }
+    }
+
+    /// Get the linear address of the page frame
+    pub fn frame_base(&self) -> u32 {
+        self.frame_base
+    }
+
+    /// Get the number of logical pages on the board
+    pub fn page_count(&self) -> u16 {
+        (self.memory.len() / EMS_PAGE_SIZE) as u16
+    }
+
+    /// Map logical page `logical` at physical page `physical` (0-3)
+    ///
+    /// Panics if either page number is out of range.
+    pub fn map(&mut self, physical: usize, logical: u16) {
+        assert!(
+            logical < self.page_count(),
+            "EMS logical page {} out of range",
+            logical
+        );
+        self.mapping[physical] = Some(logical);
+    }
+
+    /// Unmap physical page `physical` (0-3)
+    pub fn unmap(&mut self, physical: usize) {
+        self.mapping[physical] = None;
+    }
+
+    /// Get the logical page mapped at physical page `physical`, if any
+    pub fn mapping(&self, physical: usize) -> Option<u16> {
+        self.mapping[physical]
+    }
+
+    /// Get the contents of a logical page, mapped or not
+    pub fn logical_page(&self, logical: u16) -> &[u8] {
+        let start = logical as usize * EMS_PAGE_SIZE;
+        &self.memory[start..start + EMS_PAGE_SIZE]
+    }
+
+    /// Check whether a linear address falls inside the page frame
+    #[inline(always)]
+    pub fn in_frame(&self, addr: u32) -> bool {
+        addr.wrapping_sub(self.frame_base) < (EMS_PHYSICAL_PAGES * EMS_PAGE_SIZE) as u32
+    }
+
+    /// Read a byte through the page frame
+    ///
+    /// Returns None outside the frame or in an unmapped physical page.
+    pub fn read_frame(&self, addr: u32) -> Option<u8> {
+        self.backing_offset(addr).map(|offset| self.memory[offset])
+    }
+
+    /// Write a byte through the page frame
+    ///
+    /// Returns false, storing nothing, outside the frame or in an unmapped
+    /// physical page.
+    pub fn write_frame(&mut self, addr: u32, value: u8) -> bool {
+        match self.backing_offset(addr) {
+            Some(offset) => {
+                self.memory[offset] = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Offset into the backing store for a frame address, if it is mapped
+    fn backing_offset(&self, addr: u32) -> Option<usize> {
+        if !self.in_frame(addr) {
+            return None;
+        }
+        let offset = (addr - self.frame_base) as usize;
+        let logical = self.mapping[offset / EMS_PAGE_SIZE]?;
+        Some(logical as usize * EMS_PAGE_SIZE + offset % EMS_PAGE_SIZE)
+    }
+}
+
+impl Default for ExpandedMemory {
+    fn default() -> Self {
+        Self::new(EMS_DEFAULT_PAGES)
+    }
+}
+
+impl IoDevice for ExpandedMemory {
+    fn read_u8(&mut self, port: u16) -> u8 {
+        let physical = (port - self.port_base) as usize;
+        self.mapping[physical].map_or(UNMAPPED, |logical| logical as u8)
+    }
+
+    fn write_u8(&mut self, port: u16, value: u8) {
+        let physical = (port - self.port_base) as usize;
+        if (value as u16) < self.page_count() {
+            self.map(physical, value as u16);
+        } else {
+            self.unmap(physical);
+        }
+    }
+
+    fn port_range(&self) -> RangeInclusive<u16> {
+        self.port_base..=self.port_base + EMS_PHYSICAL_PAGES as u16 - 1
+    }
+
+    fn reset(&mut self) {
+        // Like the memory itself, the board's contents survive a reset, but
+        // the page registers come up cleared
+        self.mapping = [None; EMS_PHYSICAL_PAGES];
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) {
+        if let Some(state) = state.downcast_ref::<Self>() {
+            *self = state.clone();
+        }
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unmapped_frame_reads_nothing() {
+        let mut ems = ExpandedMemory::new(8);
+        assert_eq!(ems.read_frame(EMS_DEFAULT_FRAME), None);
+        assert!(!ems.write_frame(EMS_DEFAULT_FRAME, 0x12));
+        assert_eq!(ems.read_u8(EMS_DEFAULT_PORT), UNMAPPED);
+    }
+
+    #[test]
+    fn test_page_register_maps_and_unmaps() {
+        let mut ems = ExpandedMemory::new(8);
+        ems.write_u8(EMS_DEFAULT_PORT + 2, 5);
+        assert_eq!(ems.mapping(2), Some(5));
+        assert_eq!(ems.read_u8(EMS_DEFAULT_PORT + 2), 5);
+
+        // Past the end of the board
+        ems.write_u8(EMS_DEFAULT_PORT + 2, 8);
+        assert_eq!(ems.mapping(2), None);
+    }
+
+    #[test]
+    fn test_same_logical_page_in_two_windows() {
+        let mut ems = ExpandedMemory::new(8);
+        ems.map(0, 3);
+        ems.map(1, 3);
+        assert!(ems.write_frame(EMS_DEFAULT_FRAME + 0x10, 0xAB));
+        assert_eq!(
+            ems.read_frame(EMS_DEFAULT_FRAME + EMS_PAGE_SIZE as u32 + 0x10),
+            Some(0xAB)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "16KB aligned")]
+    fn test_frame_must_be_aligned() {
+        ExpandedMemory::with_frame(8, 0xD2000, EMS_DEFAULT_PORT);
+    }
+}
@@ -10,11 +10,12 @@
 //! - 0x3F7: Digital Input Register (DIR, read) / Config Control (CCR, write)
 
 use crate::components::dma::DmaCapable;
-use crate::components::floppy::FloppyDisk;
+use crate::components::floppy::{FloppyDisk, SectorError};
 use crate::components::pic::Pic;
 use crate::io::IoDevice;
-use std::collections::VecDeque;
-use std::ops::RangeInclusive;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
 
 // =============================================================================
 // Constants
@@ -159,6 +160,7 @@ impl Default for DriveState {
 // =============================================================================
 
 /// NEC μPD765A Floppy Disk Controller
+#[derive(Clone)]
 pub struct Fdc {
     // State machine
     phase: FdcPhase,
@@ -190,8 +192,9 @@ pub struct Fdc {
     dma_pending: bool,
     transfer_buffer: Vec<u8>,
     transfer_index: usize,
-    transfer_drive: u8,      // Drive being used for current transfer
-    transfer_is_write: bool, // true = write to disk, false = read from disk
+    transfer_drive: u8,       // Drive being used for current transfer
+    transfer_is_write: bool,  // true = write to disk, false = read from disk
+    transfer_error: (u8, u8), // (ST1, ST2) to report when a read transfer completes
 
     // Interrupt state
     irq_pending: bool,
@@ -238,6 +241,7 @@ impl Fdc {
             transfer_index: 0,
             transfer_drive: 0,
             transfer_is_write: false,
+            transfer_error: (0, 0),
             irq_pending: false,
             pending_interrupts: VecDeque::new(),
             step_rate_time: 0,
@@ -302,7 +306,7 @@ impl Fdc {
     /// Complete reset sequence (DOR bit 2 goes high)
     fn complete_reset(&mut self) {
         #[cfg(debug_assertions)]
-        println!("[FDC] complete_reset: Exiting reset, queuing 4 interrupts");
+        log_debug!("[FDC] complete_reset: Exiting reset, queuing 4 interrupts");
 
         // Reset drive positions
         for drive in &mut self.drives {
@@ -321,7 +325,7 @@ impl Fdc {
         self.reset_pending = false;
 
         #[cfg(debug_assertions)]
-        println!(
+        log_debug!(
             "[FDC] complete_reset: irq_pending={}, pending_interrupts={}",
             self.irq_pending,
             self.pending_interrupts.len()
@@ -394,7 +398,7 @@ impl Fdc {
     /// Handle a command byte written to the data register
     fn write_command_byte(&mut self, value: u8) {
         #[cfg(debug_assertions)]
-        println!(
+        log_debug!(
             "[FDC] write_command_byte: 0x{:02X}, phase={:?}, buffer_len={}",
             value,
             self.phase,
@@ -408,9 +412,10 @@ impl Fdc {
             self.command_bytes_expected = Self::command_length(value);
 
             #[cfg(debug_assertions)]
-            println!(
+            log_debug!(
                 "[FDC] New command 0x{:02X}, expecting {} bytes",
-                value, self.command_bytes_expected
+                value,
+                self.command_bytes_expected
             );
 
             if self.command_bytes_expected == 1 {
@@ -423,7 +428,7 @@ impl Fdc {
             self.command_buffer.push(value);
 
             #[cfg(debug_assertions)]
-            println!(
+            log_debug!(
                 "[FDC] Command byte {}/{}: 0x{:02X}",
                 self.command_buffer.len(),
                 self.command_bytes_expected,
@@ -520,7 +525,7 @@ impl Fdc {
         let drive = (byte1 & 0x03) as usize;
 
         #[cfg(debug_assertions)]
-        println!("[FDC] RECALIBRATE: drive={}", drive);
+        log_debug!("[FDC] RECALIBRATE: drive={}", drive);
 
         // Move head to track 0
         self.drives[drive].cylinder = 0;
@@ -532,9 +537,10 @@ impl Fdc {
         self.irq_pending = true;
 
         #[cfg(debug_assertions)]
-        println!(
+        log_debug!(
             "[FDC] RECALIBRATE: queued interrupt ST0=0x{:02X}, irq_pending={}",
-            st0, self.irq_pending
+            st0,
+            self.irq_pending
         );
 
         // No result phase - must use Sense Interrupt Status
@@ -548,7 +554,7 @@ impl Fdc {
 
         if let Some((st0, cylinder)) = self.pending_interrupts.pop_front() {
             #[cfg(debug_assertions)]
-            println!(
+            log_debug!(
                 "[FDC] SENSE INTERRUPT: ST0=0x{:02X}, cylinder={}, remaining={}",
                 st0,
                 cylinder,
@@ -569,7 +575,7 @@ impl Fdc {
             self.irq_pending = false;
         } else {
             #[cfg(debug_assertions)]
-            println!("[FDC] SENSE INTERRUPT: No pending interrupt (invalid)");
+            log_debug!("[FDC] SENSE INTERRUPT: No pending interrupt (invalid)");
 
             // No pending interrupt - invalid command
             self.result_buffer.push(ST0_IC_INVALID);
@@ -644,23 +650,40 @@ impl Fdc {
         // Read sectors from start sector to EOT into transfer buffer
         self.transfer_buffer.clear();
         let mut current_sector = self.sector;
-        let mut error_st1 = 0u8;
+        let mut error = (0u8, 0u8);
 
         while current_sector <= self.eot {
-            if let Some(sector_data) = disk.read_sector(cylinder, head_param, current_sector) {
+            let sector_error = disk.sector_error(cylinder, head_param, current_sector);
+            if let Some(kind) = sector_error {
+                if kind != SectorError::Crc {
+                    error = Self::sector_error_status(kind);
+                    break;
+                }
+            }
+
+            if let Ok(sector_data) = disk.read_sector(cylinder, head_param, current_sector) {
                 self.transfer_buffer
                     .extend_from_slice(&sector_data[..sector_bytes.min(sector_data.len())]);
-                current_sector += 1;
             } else {
                 // Sector not found
-                error_st1 = ST1_ND;
+                error = (ST1_ND, 0);
+                break;
+            }
+
+            // A CRC error still transfers the bad sector, then terminates
+            if let Some(kind) = sector_error {
+                error = Self::sector_error_status(kind);
                 break;
             }
+            current_sector += 1;
         }
 
+        // The result phase reports the sector the command stopped at
+        self.sector = current_sector;
+
         if self.transfer_buffer.is_empty() {
             // No data read - return error
-            self.setup_read_write_result(drive as u8, error_st1, 0);
+            self.setup_read_write_result(drive as u8, error.0, error.1);
             return;
         }
 
@@ -668,6 +691,7 @@ impl Fdc {
         self.transfer_index = 0;
         self.transfer_drive = drive as u8;
         self.transfer_is_write = false;
+        self.transfer_error = error;
         self.dma_pending = true;
         self.phase = FdcPhase::Execution;
         self.current_command = FdcCommand::ReadData;
@@ -834,6 +858,15 @@ impl Fdc {
         self.current_command = FdcCommand::Invalid;
     }
 
+    /// ST1/ST2 status bits for a simulated bad sector
+    fn sector_error_status(kind: SectorError) -> (u8, u8) {
+        match kind {
+            SectorError::Crc => (ST1_DE, ST2_DD),
+            SectorError::MissingAddressMark => (ST1_MA, ST2_MD),
+            SectorError::NotFound => (ST1_ND, 0),
+        }
+    }
+
     /// Set up result phase for read/write commands
     fn setup_read_write_result(&mut self, drive: u8, st1: u8, st2: u8) {
         let cylinder = self.drives[drive as usize].cylinder;
@@ -892,31 +925,39 @@ impl Fdc {
             let sector_bytes = 128usize << sector_size_code;
 
             // Write sectors to disk
-            let mut error_st1 = 0u8;
+            let mut error = (0u8, 0u8);
             let mut offset = 0usize;
             let mut current_sector = start_sector;
 
             if let Some(disk) = self.disks[drive as usize].as_mut() {
                 while offset + sector_bytes <= self.transfer_buffer.len() {
+                    // Bad sectors are left unwritten
+                    if let Some(kind) = disk.sector_error(cylinder, head_param, current_sector) {
+                        error = Self::sector_error_status(kind);
+                        break;
+                    }
+
                     let sector_data = &self.transfer_buffer[offset..offset + sector_bytes];
                     if let Err(_) =
                         disk.write_sector(cylinder, head_param, current_sector, sector_data)
                     {
-                        error_st1 = ST1_NW;
+                        error = (ST1_NW, 0);
                         break;
                     }
                     offset += sector_bytes;
                     current_sector += 1;
                 }
             } else {
-                error_st1 = ST1_ND;
+                error = (ST1_ND, 0);
             }
 
             self.sector = current_sector;
-            self.setup_read_write_result(drive, error_st1, 0);
+            self.setup_read_write_result(drive, error.0, error.1);
         } else {
-            // Read operation - data already transferred, just set up result
-            self.setup_read_write_result(drive, 0, 0);
+            // Read operation - data already transferred, report any error
+            // that cut the read short
+            let (st1, st2) = core::mem::take(&mut self.transfer_error);
+            self.setup_read_write_result(drive, st1, st2);
         }
     }
 }
@@ -971,7 +1012,7 @@ impl IoDevice for Fdc {
 
                 #[cfg(debug_assertions)]
                 if old_dor != value {
-                    println!(
+                    log_debug!(
                         "[FDC] DOR write: 0x{:02X} -> 0x{:02X} (reset: {} -> {})",
                         old_dor,
                         value,
@@ -987,7 +1028,7 @@ impl IoDevice for Fdc {
                 } else if (old_dor & DOR_RESET) != 0 && (value & DOR_RESET) == 0 {
                     // Entering reset - clear state
                     #[cfg(debug_assertions)]
-                    println!("[FDC] DOR: ENTERING RESET - clearing pending interrupts!");
+                    log_debug!("[FDC] DOR: ENTERING RESET - clearing pending interrupts!");
                     self.enter_reset();
                 }
 
@@ -1024,7 +1065,7 @@ impl IoDevice for Fdc {
             static mut LAST_SIGNAL: bool = false;
             // Only print on transitions to reduce noise
             if should_signal != unsafe { LAST_SIGNAL } {
-                println!(
+                log_debug!(
                     "[FDC] tick: IRQ6 {} (irq_pending={}, dor=0x{:02X}, IMR=0x{:02X})",
                     if should_signal { "HIGH" } else { "LOW" },
                     self.irq_pending,
@@ -1041,6 +1082,13 @@ impl IoDevice for Fdc {
             pic.set_irq_level(6, false);
         }
     }
+
+    fn reset(&mut self) {
+        // Inserted disks are media, not controller state - keep them
+        let disks = core::mem::take(&mut self.disks);
+        *self = Self::new();
+        self.disks = disks;
+    }
 }
 
 // =============================================================================
@@ -1307,7 +1355,7 @@ mod tests {
     // FDC + Disk Integration Tests
     // =========================================================================
 
-    use crate::components::floppy::{DiskGeometry, FloppyDisk};
+    use crate::components::floppy::{DiskGeometry, FloppyDisk, SectorError};
 
     #[test]
     fn test_insert_disk_clears_disk_changed() {
@@ -1461,6 +1509,118 @@ mod tests {
         assert_eq!(sector_data[255], 0xFF);
     }
 
+    /// Issue a single-sector READ DATA and return (bytes transferred, ST0, ST1, ST2)
+    fn read_one_sector(fdc: &mut Fdc, sector: u8) -> (usize, u8, u8, u8) {
+        fdc.write_u8(FDC_DATA, 0x66); // MF + SK + READ
+        fdc.write_u8(FDC_DATA, 0x00); // Drive 0, Head 0
+        fdc.write_u8(FDC_DATA, 0); // Cylinder 0
+        fdc.write_u8(FDC_DATA, 0); // Head 0
+        fdc.write_u8(FDC_DATA, sector);
+        fdc.write_u8(FDC_DATA, 2); // Sector size (512 bytes)
+        fdc.write_u8(FDC_DATA, sector); // EOT
+        fdc.write_u8(FDC_DATA, 0x1B); // GPL
+        fdc.write_u8(FDC_DATA, 0xFF); // DTL
+
+        let mut transferred = 0;
+        if fdc.phase == FdcPhase::Execution {
+            while fdc.dma_read_byte().is_some() {
+                transferred += 1;
+            }
+            fdc.dma_terminal_count();
+        }
+
+        let st0 = fdc.read_u8(FDC_DATA);
+        let st1 = fdc.read_u8(FDC_DATA);
+        let st2 = fdc.read_u8(FDC_DATA);
+        for _ in 0..4 {
+            fdc.read_u8(FDC_DATA);
+        }
+        (transferred, st0, st1, st2)
+    }
+
+    #[test]
+    fn test_read_data_bad_sector_reports_crc_error() {
+        let mut fdc = Fdc::new();
+        fdc.write_u8(FDC_DOR, DOR_RESET | DOR_DMA_ENABLE);
+        fdc.pending_interrupts.clear();
+
+        let geometry = DiskGeometry::new(40, 2, 9, 512);
+        let mut disk = FloppyDisk::new(geometry);
+        disk.set_sector_error((0, 0, 2), Some(SectorError::Crc));
+        fdc.insert_disk(0, disk);
+
+        // Flagged sector: data is transferred, then the command fails
+        let (transferred, st0, st1, st2) = read_one_sector(&mut fdc, 2);
+        assert_eq!(transferred, 512);
+        assert_eq!(st0 & ST0_IC_MASK, ST0_IC_ABNORMAL);
+        assert_eq!(st1, ST1_DE);
+        assert_eq!(st2, ST2_DD);
+
+        // Neighboring sectors read fine
+        for sector in [1, 3] {
+            let (transferred, st0, st1, st2) = read_one_sector(&mut fdc, sector);
+            assert_eq!(transferred, 512);
+            assert_eq!(st0 & ST0_IC_MASK, ST0_IC_NORMAL);
+            assert_eq!(st1, 0);
+            assert_eq!(st2, 0);
+        }
+    }
+
+    #[test]
+    fn test_read_data_missing_address_mark_transfers_nothing() {
+        let mut fdc = Fdc::new();
+        fdc.write_u8(FDC_DOR, DOR_RESET | DOR_DMA_ENABLE);
+        fdc.pending_interrupts.clear();
+
+        let geometry = DiskGeometry::new(40, 2, 9, 512);
+        let mut disk = FloppyDisk::new(geometry);
+        disk.set_sector_error((0, 0, 1), Some(SectorError::MissingAddressMark));
+        fdc.insert_disk(0, disk);
+
+        let (transferred, st0, st1, st2) = read_one_sector(&mut fdc, 1);
+        assert_eq!(transferred, 0);
+        assert_eq!(st0 & ST0_IC_MASK, ST0_IC_ABNORMAL);
+        assert_eq!(st1, ST1_MA);
+        assert_eq!(st2, ST2_MD);
+    }
+
+    #[test]
+    fn test_write_data_bad_sector_not_written() {
+        let mut fdc = Fdc::new();
+        fdc.write_u8(FDC_DOR, DOR_RESET | DOR_DMA_ENABLE);
+        fdc.pending_interrupts.clear();
+
+        let geometry = DiskGeometry::new(40, 2, 9, 512);
+        let mut disk = FloppyDisk::new(geometry);
+        disk.set_write_protected(false);
+        disk.set_sector_error((0, 0, 1), Some(SectorError::Crc));
+        fdc.insert_disk(0, disk);
+
+        // Send Write Data command for sector 1
+        fdc.write_u8(FDC_DATA, 0x45); // MF + WRITE
+        fdc.write_u8(FDC_DATA, 0x00);
+        fdc.write_u8(FDC_DATA, 0);
+        fdc.write_u8(FDC_DATA, 0);
+        fdc.write_u8(FDC_DATA, 1);
+        fdc.write_u8(FDC_DATA, 2);
+        fdc.write_u8(FDC_DATA, 1);
+        fdc.write_u8(FDC_DATA, 0x1B);
+        fdc.write_u8(FDC_DATA, 0xFF);
+
+        for _ in 0..512 {
+            fdc.dma_write_byte(0xAA);
+        }
+        fdc.dma_terminal_count();
+
+        let st0 = fdc.read_u8(FDC_DATA);
+        let st1 = fdc.read_u8(FDC_DATA);
+        assert_eq!(st0 & ST0_IC_MASK, ST0_IC_ABNORMAL);
+        assert_eq!(st1, ST1_DE);
+
+        let disk = fdc.disks[0].as_ref().unwrap();
+        assert!(disk.read_sector(0, 0, 1).unwrap().iter().all(|&b| b == 0));
+    }
+
     #[test]
     fn test_write_data_write_protected() {
         let mut fdc = Fdc::new();
@@ -3,8 +3,16 @@
 //! Supports raw sector images (.img) with auto-detected geometry.
 //! Common formats: 160KB, 180KB, 320KB, 360KB, 720KB, 1.2MB, 1.44MB
 
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
 use std::fs::{File, OpenOptions};
+#[cfg(feature = "std")]
 use std::io::{self, Read, Write};
+#[cfg(feature = "std")]
 use std::path::{Path, PathBuf};
 
 // =============================================================================
@@ -14,6 +22,24 @@ use std::path::{Path, PathBuf};
 /// Standard bytes per sector for IBM PC floppy disks
 pub const BYTES_PER_SECTOR: u16 = 512;
 
+/// Byte written to every sector by a low-level format (IBM PC BIOS default)
+pub const FORMAT_FILL_BYTE: u8 = 0xF6;
+
+/// Geometries `from_size` recognizes, smallest first
+const STANDARD_GEOMETRIES: [DiskGeometry; 8] = [
+    DiskGeometry::new(40, 1, 8, 512),
+    DiskGeometry::new(40, 1, 9, 512),
+    DiskGeometry::new(40, 2, 8, 512),
+    DiskGeometry::new(40, 2, 9, 512),
+    DiskGeometry::new(80, 2, 9, 512),
+    DiskGeometry::new(80, 2, 15, 512),
+    DiskGeometry::new(80, 2, 18, 512),
+    DiskGeometry::new(80, 2, 36, 512),
+];
+
+/// Largest size mismatch `DiskGeometry::nearest` accepts: one sector
+const NEAREST_SIZE_TOLERANCE: usize = BYTES_PER_SECTOR as usize;
+
 // =============================================================================
 // DiskGeometry
 // =============================================================================
@@ -33,7 +59,12 @@ pub struct DiskGeometry {
 
 impl DiskGeometry {
     /// Create a new geometry
-    pub fn new(cylinders: u8, heads: u8, sectors_per_track: u8, bytes_per_sector: u16) -> Self {
+    pub const fn new(
+        cylinders: u8,
+        heads: u8,
+        sectors_per_track: u8,
+        bytes_per_sector: u16,
+    ) -> Self {
         Self {
             cylinders,
             heads,
@@ -77,6 +108,57 @@ impl DiskGeometry {
         }
     }
 
+    /// Pick the standard geometry closest in size to an image
+    ///
+    /// For images that `from_size` rejects, e.g. ones with a few bytes of
+    /// copier padding. Returns None if no standard size is within one sector
+    /// of `size`, since such an image is probably not a floppy at all.
+    pub fn nearest(size: usize) -> Option<Self> {
+        STANDARD_GEOMETRIES
+            .iter()
+            .copied()
+            .find(|g| g.total_size().abs_diff(size) <= NEAREST_SIZE_TOLERANCE)
+    }
+
+    /// Parse an explicit "C:H:S" geometry with 512-byte sectors
+    ///
+    /// All three values must be non-zero, e.g. "80:2:10" for an 800KB disk.
+    pub fn from_chs(spec: &str) -> Option<Self> {
+        let mut parts = spec.split(':').map(|part| part.trim().parse::<u8>().ok());
+        let cylinders = parts.next()??;
+        let heads = parts.next()??;
+        let sectors_per_track = parts.next()??;
+        if parts.next().is_some() || cylinders == 0 || heads == 0 || sectors_per_track == 0 {
+            return None;
+        }
+        Some(Self::new(
+            cylinders,
+            heads,
+            sectors_per_track,
+            BYTES_PER_SECTOR,
+        ))
+    }
+
+    /// Look up a standard geometry by name
+    ///
+    /// Accepts capacities such as "360k", "720k", "1.2m" or "1.44m"
+    /// (case-insensitive; a trailing "b" is allowed, e.g. "1.44MB").
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        let name = name.strip_suffix('b').unwrap_or(&name);
+        match name {
+            "160k" => Some(Self::new(40, 1, 8, 512)),
+            "180k" => Some(Self::new(40, 1, 9, 512)),
+            "320k" => Some(Self::new(40, 2, 8, 512)),
+            "360k" => Some(Self::new(40, 2, 9, 512)),
+            "720k" => Some(Self::new(80, 2, 9, 512)),
+            "1.2m" => Some(Self::new(80, 2, 15, 512)),
+            "1.44m" => Some(Self::new(80, 2, 18, 512)),
+            "2.88m" => Some(Self::new(80, 2, 36, 512)),
+            _ => None,
+        }
+    }
+
     /// Convert CHS address to linear byte offset
     pub fn chs_to_offset(&self, cylinder: u8, head: u8, sector: u8) -> Option<usize> {
         // Sector numbers are 1-based
@@ -103,12 +185,77 @@ impl DiskGeometry {
     }
 }
 
+// =============================================================================
+// SectorError
+// =============================================================================
+
+/// Simulated media defect on a single sector
+///
+/// Flagged sectors make the FDC terminate READ DATA and WRITE DATA with the
+/// matching error status, so guest error handling can be exercised.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectorError {
+    /// Data field fails its CRC check (the data is still transferred)
+    Crc,
+    /// The data address mark cannot be found
+    MissingAddressMark,
+    /// The sector ID cannot be found on the track
+    NotFound,
+}
+
+// =============================================================================
+// DiskError
+// =============================================================================
+
+/// Why a write to the disk image was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskError {
+    /// The disk is write-protected
+    WriteProtected,
+    /// The CHS address is outside the disk geometry
+    InvalidAddress { cylinder: u8, head: u8, sector: u8 },
+    /// The sector lies past the end of the image data
+    BeyondImage,
+}
+
+impl fmt::Display for DiskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WriteProtected => write!(f, "Disk is write-protected"),
+            Self::InvalidAddress {
+                cylinder,
+                head,
+                sector,
+            } => write!(
+                f,
+                "Invalid CHS address: C={}, H={}, S={}",
+                cylinder, head, sector
+            ),
+            Self::BeyondImage => write!(f, "Sector extends beyond disk image"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DiskError {}
+
+#[cfg(feature = "std")]
+impl From<DiskError> for io::Error {
+    fn from(error: DiskError) -> Self {
+        let kind = match error {
+            DiskError::WriteProtected => io::ErrorKind::PermissionDenied,
+            _ => io::ErrorKind::InvalidInput,
+        };
+        io::Error::new(kind, error)
+    }
+}
+
 // =============================================================================
 // FloppyDisk
 // =============================================================================
 
 /// A floppy disk image
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FloppyDisk {
     /// Raw sector data
     data: Vec<u8>,
@@ -119,7 +266,12 @@ pub struct FloppyDisk {
     /// Modified since load
     dirty: bool,
     /// Source file path (for saving)
+    #[cfg(feature = "std")]
     path: Option<PathBuf>,
+    /// Simulated bad sectors, keyed by (cylinder, head, sector)
+    sector_errors: BTreeMap<(u8, u8, u8), SectorError>,
+    /// Size mismatch found while loading the image
+    size_warning: Option<String>,
 }
 
 impl FloppyDisk {
@@ -131,7 +283,28 @@ impl FloppyDisk {
             geometry,
             write_protected: false,
             dirty: false,
+            #[cfg(feature = "std")]
             path: None,
+            sector_errors: BTreeMap::new(),
+            size_warning: None,
+        }
+    }
+
+    /// Create a freshly formatted disk with the given geometry
+    ///
+    /// Every sector is filled with `FORMAT_FILL_BYTE`, as if the disk had
+    /// been low-level formatted. Unlike `from_file`, the disk is not
+    /// associated with a path; use `save_as` to write it out.
+    pub fn create_blank(geometry: DiskGeometry, writable: bool) -> Self {
+        Self {
+            data: vec![FORMAT_FILL_BYTE; geometry.total_size()],
+            geometry,
+            write_protected: !writable,
+            dirty: false,
+            #[cfg(feature = "std")]
+            path: None,
+            sector_errors: BTreeMap::new(),
+            size_warning: None,
         }
     }
 
@@ -139,20 +312,60 @@ impl FloppyDisk {
     ///
     /// Geometry is auto-detected from file size.
     /// The disk is read-only by default; use `set_write_protected(false)` to enable writes.
+    #[cfg(feature = "std")]
     pub fn from_file(path: &Path) -> io::Result<Self> {
+        Self::from_file_with_geometry(path, None)
+    }
+
+    /// Load a floppy disk image, optionally forcing its geometry
+    ///
+    /// Without an explicit geometry, an image within one sector of a standard
+    /// size is treated as that geometry, and any other size is an error.
+    /// Whenever the image and the geometry disagree, the data is zero-padded
+    /// or truncated to fit and a warning is logged and kept in `size_warning`.
+    #[cfg(feature = "std")]
+    pub fn from_file_with_geometry(
+        path: &Path,
+        geometry: Option<DiskGeometry>,
+    ) -> io::Result<Self> {
         let mut file = File::open(path)?;
         let mut data = Vec::new();
         file.read_to_end(&mut data)?;
 
-        let geometry = DiskGeometry::from_size(data.len()).ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!(
-                    "Unknown disk image size: {} bytes. Expected standard floppy size.",
-                    data.len()
-                ),
-            )
-        })?;
+        let geometry = geometry
+            .or_else(|| DiskGeometry::from_size(data.len()))
+            .or_else(|| DiskGeometry::nearest(data.len()))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Disk image is {} bytes, which matches no standard floppy size",
+                        data.len()
+                    ),
+                )
+            })?;
+
+        let size_warning = if data.len() != geometry.total_size() {
+            let warning = format!(
+                "{}: image is {} bytes but {}x{}x{} needs {}; {}",
+                path.display(),
+                data.len(),
+                geometry.cylinders,
+                geometry.heads,
+                geometry.sectors_per_track,
+                geometry.total_size(),
+                if data.len() > geometry.total_size() {
+                    "ignoring trailing data"
+                } else {
+                    "zero-filling the rest"
+                }
+            );
+            log_warn!("[FLOPPY] {}", warning);
+            data.resize(geometry.total_size(), 0);
+            Some(warning)
+        } else {
+            None
+        };
 
         Ok(Self {
             data,
@@ -160,9 +373,16 @@ impl FloppyDisk {
             write_protected: true, // Read-only by default
             dirty: false,
             path: Some(path.to_path_buf()),
+            sector_errors: BTreeMap::new(),
+            size_warning,
         })
     }
 
+    /// Describe how the image size disagreed with its geometry, if it did
+    pub fn size_warning(&self) -> Option<&str> {
+        self.size_warning.as_deref()
+    }
+
     /// Get the disk geometry
     pub fn geometry(&self) -> DiskGeometry {
         self.geometry
@@ -183,59 +403,53 @@ impl FloppyDisk {
         self.dirty
     }
 
-    /// Read a sector from the disk
+    /// Mark a sector as bad, or clear its error with `None`
     ///
-    /// Returns None if the CHS address is invalid.
-    pub fn read_sector(&self, cylinder: u8, head: u8, sector: u8) -> Option<&[u8]> {
-        let offset = self.geometry.chs_to_offset(cylinder, head, sector)?;
-        let end = offset + (self.geometry.bytes_per_sector as usize);
-
-        if end <= self.data.len() {
-            Some(&self.data[offset..end])
-        } else {
-            None
+    /// `chs` is (cylinder, head, sector). The sector contents are untouched;
+    /// only FDC commands observe the error.
+    pub fn set_sector_error(&mut self, chs: (u8, u8, u8), kind: Option<SectorError>) {
+        match kind {
+            Some(kind) => {
+                self.sector_errors.insert(chs, kind);
+            }
+            None => {
+                self.sector_errors.remove(&chs);
+            }
         }
     }
 
+    /// Get the simulated error for a sector, if any
+    pub fn sector_error(&self, cylinder: u8, head: u8, sector: u8) -> Option<SectorError> {
+        self.sector_errors.get(&(cylinder, head, sector)).copied()
+    }
+
+    /// Read a sector from the disk
+    ///
+    /// Sectors are numbered from 1. Returns an error if the CHS address is
+    /// outside `geometry()`.
+    pub fn read_sector(&self, cylinder: u8, head: u8, sector: u8) -> Result<&[u8], DiskError> {
+        let (offset, end) = self.sector_span(cylinder, head, sector)?;
+        Ok(&self.data[offset..end])
+    }
+
     /// Write a sector to the disk
     ///
-    /// Returns an error if write-protected or CHS address is invalid.
+    /// Data shorter than a sector is zero-padded and longer data is
+    /// truncated. Returns an error if write-protected or the CHS address is
+    /// outside `geometry()`.
     pub fn write_sector(
         &mut self,
         cylinder: u8,
         head: u8,
         sector: u8,
         data: &[u8],
-    ) -> io::Result<()> {
+    ) -> Result<(), DiskError> {
         if self.write_protected {
-            return Err(io::Error::new(
-                io::ErrorKind::PermissionDenied,
-                "Disk is write-protected",
-            ));
+            return Err(DiskError::WriteProtected);
         }
 
-        let offset = self
-            .geometry
-            .chs_to_offset(cylinder, head, sector)
-            .ok_or_else(|| {
-                io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    format!(
-                        "Invalid CHS address: C={}, H={}, S={}",
-                        cylinder, head, sector
-                    ),
-                )
-            })?;
-
-        let sector_size = self.geometry.bytes_per_sector as usize;
-        let end = offset + sector_size;
-
-        if end > self.data.len() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "Sector extends beyond disk image",
-            ));
-        }
+        let (offset, end) = self.sector_span(cylinder, head, sector)?;
+        let sector_size = end - offset;
 
         // Copy data, padding or truncating as needed
         let copy_len = data.len().min(sector_size);
@@ -250,13 +464,27 @@ impl FloppyDisk {
         Ok(())
     }
 
+    /// Byte range of a sector within the image data
+    fn sector_span(&self, cylinder: u8, head: u8, sector: u8) -> Result<(usize, usize), DiskError> {
+        let offset = self.geometry.chs_to_offset(cylinder, head, sector).ok_or(
+            DiskError::InvalidAddress {
+                cylinder,
+                head,
+                sector,
+            },
+        )?;
+        let end = offset + self.geometry.bytes_per_sector as usize;
+
+        if end > self.data.len() {
+            return Err(DiskError::BeyondImage);
+        }
+        Ok((offset, end))
+    }
+
     /// Format a track (fill all sectors with a pattern)
-    pub fn format_track(&mut self, cylinder: u8, head: u8, fill_byte: u8) -> io::Result<()> {
+    pub fn format_track(&mut self, cylinder: u8, head: u8, fill_byte: u8) -> Result<(), DiskError> {
         if self.write_protected {
-            return Err(io::Error::new(
-                io::ErrorKind::PermissionDenied,
-                "Disk is write-protected",
-            ));
+            return Err(DiskError::WriteProtected);
         }
 
         let sector_size = self.geometry.bytes_per_sector as usize;
@@ -276,6 +504,7 @@ impl FloppyDisk {
     }
 
     /// Save changes back to the source file
+    #[cfg(feature = "std")]
     pub fn save(&mut self) -> io::Result<()> {
         let path = self
             .path
@@ -296,7 +525,21 @@ impl FloppyDisk {
         Ok(())
     }
 
+    /// Write the image to a new file and use it as the source path from now on
+    ///
+    /// Works regardless of write protection, since the guest cannot observe it.
+    /// Fails with `AlreadyExists` rather than overwrite an existing file.
+    #[cfg(feature = "std")]
+    pub fn save_as(&mut self, path: &Path) -> io::Result<()> {
+        let mut file = OpenOptions::new().write(true).create_new(true).open(path)?;
+        file.write_all(&self.data)?;
+        self.path = Some(path.to_path_buf());
+        self.dirty = false;
+        Ok(())
+    }
+
     /// Get the file path (if loaded from file)
+    #[cfg(feature = "std")]
     pub fn path(&self) -> Option<&Path> {
         self.path.as_deref()
     }
@@ -402,6 +645,43 @@ mod tests {
         assert!(other_sector.iter().all(|&b| b == 0));
     }
 
+    #[test]
+    fn test_sector_round_trip_by_chs() {
+        let g = DiskGeometry::new(40, 2, 9, 512);
+        let mut disk = FloppyDisk::create_blank(g, true);
+
+        // Boot sector of a freshly formatted disk
+        let boot = disk.read_sector(0, 0, 1).unwrap();
+        assert_eq!(boot.len(), 512);
+        assert!(boot.iter().all(|&b| b == FORMAT_FILL_BYTE));
+
+        let data: Vec<u8> = (0..512).map(|i| i as u8).collect();
+        disk.write_sector(0, 1, 3, &data).unwrap();
+        assert_eq!(disk.read_sector(0, 1, 3).unwrap(), &data[..]);
+
+        // Same sector number on the other head is untouched
+        assert!(disk
+            .read_sector(0, 0, 3)
+            .unwrap()
+            .iter()
+            .all(|&b| b == FORMAT_FILL_BYTE));
+    }
+
+    #[test]
+    fn test_out_of_range_chs_is_an_error() {
+        let g = DiskGeometry::new(40, 2, 9, 512);
+        let mut disk = FloppyDisk::new(g);
+
+        let invalid = DiskError::InvalidAddress {
+            cylinder: 0,
+            head: 2,
+            sector: 1,
+        };
+        assert_eq!(disk.read_sector(0, 2, 1), Err(invalid));
+        assert_eq!(disk.write_sector(0, 2, 1, &[0; 512]), Err(invalid));
+        assert!(!disk.is_dirty());
+    }
+
     #[test]
     fn test_write_protected() {
         let g = DiskGeometry::new(40, 2, 9, 512);
@@ -433,14 +713,175 @@ mod tests {
         assert!(other_track.iter().all(|&b| b == 0));
     }
 
+    #[test]
+    fn test_geometry_from_name() {
+        assert_eq!(
+            DiskGeometry::from_name("360k"),
+            Some(DiskGeometry::new(40, 2, 9, 512))
+        );
+        assert_eq!(
+            DiskGeometry::from_name("1.44MB"),
+            Some(DiskGeometry::new(80, 2, 18, 512))
+        );
+        assert_eq!(DiskGeometry::from_name("100k"), None);
+    }
+
+    #[test]
+    fn test_create_blank_boot_sector_round_trip() {
+        let g = DiskGeometry::from_name("1.44m").unwrap();
+        let mut disk = FloppyDisk::create_blank(g, true);
+
+        assert_eq!(disk.data.len(), 1_474_560);
+        assert!(disk.data.iter().all(|&b| b == FORMAT_FILL_BYTE));
+        assert!(!disk.is_write_protected());
+
+        // Boot sector: JMP SHORT, NOP, OEM name, ..., 0x55AA signature
+        let mut boot = vec![0u8; 512];
+        boot[0..3].copy_from_slice(&[0xEB, 0x3C, 0x90]);
+        boot[3..11].copy_from_slice(b"EZPC1.0 ");
+        boot[510] = 0x55;
+        boot[511] = 0xAA;
+        disk.write_sector(0, 0, 1, &boot).unwrap();
+
+        assert_eq!(disk.read_sector(0, 0, 1).unwrap(), &boot[..]);
+
+        // The rest of the disk keeps the format fill
+        let next = disk.read_sector(0, 0, 2).unwrap();
+        assert!(next.iter().all(|&b| b == FORMAT_FILL_BYTE));
+    }
+
+    #[test]
+    fn test_create_blank_read_only() {
+        let g = DiskGeometry::new(40, 2, 9, 512);
+        let mut disk = FloppyDisk::create_blank(g, false);
+
+        assert!(disk.is_write_protected());
+        assert!(disk.write_sector(0, 0, 1, &[0; 512]).is_err());
+    }
+
+    #[test]
+    fn test_sector_error_set_and_clear() {
+        let g = DiskGeometry::new(40, 2, 9, 512);
+        let mut disk = FloppyDisk::new(g);
+
+        disk.set_sector_error((1, 0, 3), Some(SectorError::Crc));
+        assert_eq!(disk.sector_error(1, 0, 3), Some(SectorError::Crc));
+        assert_eq!(disk.sector_error(1, 0, 4), None);
+
+        disk.set_sector_error((1, 0, 3), None);
+        assert_eq!(disk.sector_error(1, 0, 3), None);
+    }
+
     #[test]
     fn test_invalid_chs() {
         let g = DiskGeometry::new(40, 2, 9, 512);
         let disk = FloppyDisk::new(g);
 
-        // Invalid reads return None
-        assert!(disk.read_sector(0, 0, 0).is_none()); // Sector 0 invalid
-        assert!(disk.read_sector(0, 0, 10).is_none()); // Sector > SPT
-        assert!(disk.read_sector(40, 0, 1).is_none()); // Cylinder out of range
+        // Invalid reads return an error
+        assert!(disk.read_sector(0, 0, 0).is_err()); // Sector 0 invalid
+        assert!(disk.read_sector(0, 0, 10).is_err()); // Sector > SPT
+        assert!(disk.read_sector(40, 0, 1).is_err()); // Cylinder out of range
+    }
+
+    /// Write `data` to a scratch file unique to this test
+    fn scratch_image(name: &str, data: &[u8]) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("ezpc-floppy-{}-{}.img", std::process::id(), name));
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_geometry_from_chs() {
+        assert_eq!(
+            DiskGeometry::from_chs("80:2:10"),
+            Some(DiskGeometry::new(80, 2, 10, 512))
+        );
+        assert_eq!(DiskGeometry::from_chs("80:2"), None);
+        assert_eq!(DiskGeometry::from_chs("80:2:10:1"), None);
+        assert_eq!(DiskGeometry::from_chs("80:0:10"), None);
+        assert_eq!(DiskGeometry::from_chs("300:2:9"), None);
+    }
+
+    #[test]
+    fn test_from_file_explicit_geometry_override() {
+        // 800KB: 80 cylinders, 2 heads, 10 sectors - not a standard size
+        let mut data = vec![0u8; 819_200];
+        data[512] = 0x42; // First byte of C0 H0 S2
+        let path = scratch_image("override", &data);
+
+        let disk =
+            FloppyDisk::from_file_with_geometry(&path, DiskGeometry::from_chs("80:2:10")).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(disk.geometry(), DiskGeometry::new(80, 2, 10, 512));
+        assert_eq!(disk.size_warning(), None);
+        assert_eq!(disk.read_sector(0, 0, 2).unwrap()[0], 0x42);
+        assert!(disk.read_sector(79, 1, 10).is_ok());
+    }
+
+    #[test]
+    fn test_from_file_snaps_oversized_image() {
+        // A 360KB image with 100 bytes of padding on the end
+        let mut data = vec![0u8; 368_640 + 100];
+        data[368_640 - 1] = 0x55;
+        let path = scratch_image("oversized", &data);
+
+        let disk = FloppyDisk::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(disk.geometry(), DiskGeometry::new(40, 2, 9, 512));
+        let warning = disk.size_warning().unwrap();
+        assert!(warning.contains("368740 bytes"), "{}", warning);
+        assert!(warning.contains("ignoring trailing data"), "{}", warning);
+        assert_eq!(disk.data.len(), 368_640);
+        assert_eq!(disk.read_sector(39, 1, 9).unwrap()[511], 0x55);
+    }
+
+    #[test]
+    fn test_save_as_refuses_to_overwrite() {
+        let path = scratch_image("save-as-existing", &[0x42; 16]);
+
+        let mut disk = FloppyDisk::create_blank(DiskGeometry::new(40, 1, 9, 512), false);
+        let err = disk.save_as(&path).unwrap_err();
+        let contents = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(contents, [0x42; 16]);
+        assert_eq!(disk.path(), None);
+    }
+
+    #[test]
+    fn test_nearest_tolerates_at_most_one_sector() {
+        let g360 = DiskGeometry::new(40, 2, 9, 512);
+        assert_eq!(DiskGeometry::nearest(368_640 + 512), Some(g360));
+        assert_eq!(DiskGeometry::nearest(368_640 - 512), Some(g360));
+        assert_eq!(DiskGeometry::nearest(368_640 + 513), None);
+        assert_eq!(DiskGeometry::nearest(368_640 - 4096), None);
+        assert_eq!(DiskGeometry::nearest(0), None);
+    }
+
+    #[test]
+    fn test_from_file_rejects_far_from_standard_size() {
+        // 400KB is between 360KB and 720KB but close to neither
+        let path = scratch_image("far-from-standard", &[0u8; 409_600]);
+
+        let err = FloppyDisk::from_file(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("409600 bytes"), "{}", err);
+    }
+
+    #[test]
+    fn test_from_file_exact_size_has_no_warning() {
+        let path = scratch_image("exact", &[0u8; 184_320]);
+
+        let disk = FloppyDisk::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(disk.geometry(), DiskGeometry::new(40, 1, 9, 512));
+        assert_eq!(disk.size_warning(), None);
     }
 }
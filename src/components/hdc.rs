@@ -0,0 +1,588 @@
+//! IBM/Xebec XT fixed disk controller (ST-506 interface)
+//!
+//! The controller uses DMA channel 3 for data transfers and IRQ5 for
+//! completion interrupts.
+//!
+//! ## I/O Ports
+//! - 0x320: Data - command block, sense bytes and PIO data (read/write)
+//! - 0x321: Hardware status (read) / controller reset (write)
+//! - 0x322: Drive type switches (read) / controller select (write)
+//! - 0x323: DMA and interrupt mask (write)
+//!
+//! ## Protocol
+//! The host pulses select, then writes a 6-byte command block while the
+//! status register shows REQ with C/D set. Reads and writes then move
+//! their data through DMA (or the data port). Every command ends with a
+//! single completion byte read from the data port; bit 1 set means the
+//! host should issue Request Sense for the error code.
+//!
+//! Unlike the FDC, sector numbers in the command block count from 0.
+
+use crate::components::dma::DmaCapable;
+use crate::components::hdd::{HardDisk, HDD_SECTOR_SIZE};
+use crate::components::pic::Pic;
+use crate::io::IoDevice;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::ops::RangeInclusive;
+
+// =============================================================================
+// Constants
+// =============================================================================
+
+/// HDC I/O port base
+pub const HDC_PORT_BASE: u16 = 0x320;
+const HDC_PORT_END: u16 = 0x323;
+
+/// Individual ports
+const HDC_DATA: u16 = 0x320;
+const HDC_STATUS: u16 = 0x321; // Status (read) / reset (write)
+const HDC_SELECT: u16 = 0x322; // Drive type (read) / select (write)
+const HDC_MASK: u16 = 0x323;
+
+/// IRQ line raised when a command completes
+pub const HDC_IRQ: u8 = 5;
+
+/// DMA channel used for sector data
+pub const HDC_DMA_CHANNEL: u8 = 3;
+
+/// Hardware status register bits
+const STATUS_REQ: u8 = 0x01; // Controller ready for the next byte
+const STATUS_IO: u8 = 0x02; // Direction: 1 = controller to host
+const STATUS_CD: u8 = 0x04; // 1 = command/status byte, 0 = data
+const STATUS_BUSY: u8 = 0x08; // Selected and processing a command
+const STATUS_INT: u8 = 0x20; // Interrupt pending
+
+/// Mask register bits
+const MASK_DMA: u8 = 0x01;
+const MASK_IRQ: u8 = 0x02;
+
+/// Command opcodes (byte 0 of the command block)
+const CMD_TEST_READY: u8 = 0x00;
+const CMD_RECALIBRATE: u8 = 0x01;
+const CMD_REQUEST_SENSE: u8 = 0x03;
+const CMD_VERIFY: u8 = 0x05;
+const CMD_READ: u8 = 0x08;
+const CMD_WRITE: u8 = 0x0A;
+const CMD_SEEK: u8 = 0x0B;
+const CMD_INIT_DRIVE: u8 = 0x0C;
+const CMD_RAM_DIAGNOSTIC: u8 = 0xE0;
+const CMD_DRIVE_DIAGNOSTIC: u8 = 0xE3;
+const CMD_CONTROLLER_DIAGNOSTIC: u8 = 0xE4;
+
+/// Length of a command block
+const COMMAND_LENGTH: usize = 6;
+
+/// Bytes of drive parameters that follow Initialize Drive Characteristics
+const INIT_DRIVE_LENGTH: usize = 8;
+
+/// Error codes returned by Request Sense
+const SENSE_NONE: u8 = 0x00;
+const SENSE_NOT_READY: u8 = 0x04;
+const SENSE_INVALID_COMMAND: u8 = 0x20;
+const SENSE_ILLEGAL_ADDRESS: u8 = 0x21;
+
+/// Request Sense: the disk address bytes are valid
+const SENSE_ADDRESS_VALID: u8 = 0x80;
+
+/// Completion byte: the command failed
+const COMPLETION_ERROR: u8 = 0x02;
+
+// =============================================================================
+// Hdc
+// =============================================================================
+
+/// Controller state machine phase
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HdcPhase {
+    /// Not selected
+    Idle,
+    /// Receiving the command block
+    Command,
+    /// Receiving data from the host (Write, Initialize Drive)
+    DataIn,
+    /// Sending data to the host (Read, Request Sense)
+    DataOut,
+    /// Completion byte waiting to be read
+    Status,
+}
+
+/// XT fixed disk controller with up to two drives
+#[derive(Clone)]
+pub struct Hdc {
+    /// Attached drives
+    drives: [Option<HardDisk>; 2],
+
+    phase: HdcPhase,
+
+    /// Command block being received or executed
+    command: [u8; COMMAND_LENGTH],
+    command_len: usize,
+
+    /// Data moving in either direction during a data phase
+    buffer: Vec<u8>,
+    buffer_index: usize,
+    /// Bytes the host must send before a DataIn phase completes
+    data_expected: usize,
+
+    /// DMA and interrupt enables written to the mask register
+    mask: u8,
+
+    /// Completion byte for the status phase
+    completion: u8,
+
+    /// Bytes returned by the next Request Sense
+    sense: [u8; 4],
+
+    /// Command completed and the interrupt has not been serviced
+    irq_pending: bool,
+
+    /// Head position of each drive
+    cylinders: [u16; 2],
+}
+
+impl Hdc {
+    /// Create a controller with no drives attached
+    pub fn new() -> Self {
+        Self {
+            drives: [None, None],
+            phase: HdcPhase::Idle,
+            command: [0; COMMAND_LENGTH],
+            command_len: 0,
+            buffer: Vec::new(),
+            buffer_index: 0,
+            data_expected: 0,
+            mask: 0,
+            completion: 0,
+            sense: [0; 4],
+            irq_pending: false,
+            cylinders: [0; 2],
+        }
+    }
+
+    /// Attach a drive (0 or 1)
+    ///
+    /// Returns the previously attached disk, if any.
+    pub fn insert_disk(&mut self, drive: u8, disk: HardDisk) -> Option<HardDisk> {
+        self.drives[drive as usize].replace(disk)
+    }
+
+    /// Get an attached drive
+    pub fn disk(&self, drive: u8) -> Option<&HardDisk> {
+        self.drives[drive as usize].as_ref()
+    }
+
+    /// Get the current phase
+    pub fn phase(&self) -> HdcPhase {
+        self.phase
+    }
+
+    /// Build the hardware status register
+    fn status(&self) -> u8 {
+        let mut status = match self.phase {
+            HdcPhase::Idle => 0,
+            HdcPhase::Command => STATUS_BUSY | STATUS_REQ | STATUS_CD,
+            HdcPhase::DataIn => STATUS_BUSY | STATUS_REQ,
+            HdcPhase::DataOut => STATUS_BUSY | STATUS_REQ | STATUS_IO,
+            HdcPhase::Status => STATUS_BUSY | STATUS_REQ | STATUS_IO | STATUS_CD,
+        };
+        if self.irq_pending {
+            status |= STATUS_INT;
+        }
+        status
+    }
+
+    /// Drive number from the command block
+    fn drive(&self) -> usize {
+        ((self.command[1] >> 5) & 0x01) as usize
+    }
+
+    /// (cylinder, head, sector) from the command block
+    fn chs(&self) -> (u16, u8, u8) {
+        let cylinder = ((self.command[2] as u16 & 0xC0) << 2) | self.command[3] as u16;
+        (cylinder, self.command[1] & 0x1F, self.command[2] & 0x3F)
+    }
+
+    /// Sector count from the command block (0 means 256)
+    fn block_count(&self) -> usize {
+        match self.command[4] {
+            0 => 256,
+            count => count as usize,
+        }
+    }
+
+    /// Validate the command block address for a run of sectors
+    ///
+    /// Returns the starting linear sector, or the sense code to fail with.
+    fn target(&self, count: usize) -> Result<usize, u8> {
+        let disk = self.drives[self.drive()].as_ref().ok_or(SENSE_NOT_READY)?;
+        let (cylinder, head, sector) = self.chs();
+        let lba = disk
+            .geometry()
+            .chs_to_lba(cylinder, head, sector)
+            .ok_or(SENSE_ILLEGAL_ADDRESS)?;
+        if lba + count > disk.geometry().total_sectors() {
+            return Err(SENSE_ILLEGAL_ADDRESS);
+        }
+        Ok(lba)
+    }
+
+    /// Receive one command block byte, executing the command once complete
+    fn write_command_byte(&mut self, value: u8) {
+        self.command[self.command_len] = value;
+        self.command_len += 1;
+        if self.command_len == COMMAND_LENGTH {
+            self.execute_command();
+        }
+    }
+
+    fn execute_command(&mut self) {
+        #[cfg(debug_assertions)]
+        log_debug!("[HDC] Command {:02X?}", self.command);
+
+        match self.command[0] {
+            CMD_TEST_READY => {
+                let error = self.drives[self.drive()]
+                    .is_none()
+                    .then_some(SENSE_NOT_READY);
+                self.finish(error);
+            }
+            CMD_RECALIBRATE => match self.drives[self.drive()] {
+                Some(_) => {
+                    self.cylinders[self.drive()] = 0;
+                    self.finish(None);
+                }
+                None => self.finish(Some(SENSE_NOT_READY)),
+            },
+            CMD_REQUEST_SENSE => {
+                self.start_data_out(self.sense.to_vec());
+                self.sense = [SENSE_NONE; 4];
+            }
+            CMD_VERIFY => {
+                let result = self.target(self.block_count());
+                self.finish(result.err());
+            }
+            CMD_READ => match self.target(self.block_count()) {
+                Ok(lba) => {
+                    let data = self.drives[self.drive()]
+                        .as_ref()
+                        .and_then(|disk| disk.sectors(lba, self.block_count()))
+                        .unwrap_or(&[])
+                        .to_vec();
+                    self.cylinders[self.drive()] = self.chs().0;
+                    self.start_data_out(data);
+                }
+                Err(sense) => self.finish(Some(sense)),
+            },
+            CMD_WRITE => match self.target(self.block_count()) {
+                Ok(_) => self.start_data_in(self.block_count() * HDD_SECTOR_SIZE),
+                Err(sense) => self.finish(Some(sense)),
+            },
+            CMD_SEEK => match self.target(0) {
+                Ok(_) => {
+                    self.cylinders[self.drive()] = self.chs().0;
+                    self.finish(None);
+                }
+                Err(sense) => self.finish(Some(sense)),
+            },
+            CMD_INIT_DRIVE => self.start_data_in(INIT_DRIVE_LENGTH),
+            CMD_RAM_DIAGNOSTIC | CMD_DRIVE_DIAGNOSTIC | CMD_CONTROLLER_DIAGNOSTIC => {
+                self.finish(None)
+            }
+            _ => {
+                log_warn!("[HDC] Unsupported command {:02X}", self.command[0]);
+                self.finish(Some(SENSE_INVALID_COMMAND));
+            }
+        }
+    }
+
+    fn start_data_out(&mut self, data: Vec<u8>) {
+        self.buffer = data;
+        self.buffer_index = 0;
+        self.phase = HdcPhase::DataOut;
+    }
+
+    fn start_data_in(&mut self, length: usize) {
+        self.buffer.clear();
+        self.data_expected = length;
+        self.phase = HdcPhase::DataIn;
+    }
+
+    /// Hand one byte of a DataOut phase to the host
+    fn next_data_byte(&mut self) -> Option<u8> {
+        let byte = *self.buffer.get(self.buffer_index)?;
+        self.buffer_index += 1;
+        if self.buffer_index == self.buffer.len() {
+            self.finish(None);
+        }
+        Some(byte)
+    }
+
+    /// Take one byte of a DataIn phase from the host
+    fn accept_data_byte(&mut self, value: u8) {
+        self.buffer.push(value);
+        if self.buffer.len() == self.data_expected {
+            self.complete_data_in();
+        }
+    }
+
+    /// Act on the data received for Write or Initialize Drive
+    fn complete_data_in(&mut self) {
+        if self.command[0] != CMD_WRITE {
+            // Drive parameters come from the geometry of the attached image
+            self.finish(None);
+            return;
+        }
+
+        let error = match self.target(self.buffer.len() / HDD_SECTOR_SIZE) {
+            Ok(lba) => {
+                let drive = self.drive();
+                let data = core::mem::take(&mut self.buffer);
+                self.cylinders[drive] = self.chs().0;
+                let written = self.drives[drive]
+                    .as_mut()
+                    .is_some_and(|disk| disk.write_sectors(lba, &data));
+                (!written).then_some(SENSE_ILLEGAL_ADDRESS)
+            }
+            Err(sense) => Some(sense),
+        };
+        self.finish(error);
+    }
+
+    /// End the command, recording sense data and raising the interrupt
+    fn finish(&mut self, error: Option<u8>) {
+        let drive = self.drive() as u8;
+        let (cylinder, head, sector) = self.chs();
+        match error {
+            Some(code) => {
+                self.sense = [
+                    code | SENSE_ADDRESS_VALID,
+                    (drive << 5) | head,
+                    ((cylinder >> 2) as u8 & 0xC0) | sector,
+                    cylinder as u8,
+                ];
+                self.completion = (drive << 5) | COMPLETION_ERROR;
+            }
+            None => self.completion = drive << 5,
+        }
+        self.buffer.clear();
+        self.buffer_index = 0;
+        self.data_expected = 0;
+        self.phase = HdcPhase::Status;
+        self.irq_pending = true;
+    }
+
+    /// Return the controller to its power-on state, keeping the drives
+    fn controller_reset(&mut self) {
+        let drives = core::mem::take(&mut self.drives);
+        *self = Self::new();
+        self.drives = drives;
+    }
+}
+
+impl Default for Hdc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// =============================================================================
+// IoDevice Implementation
+// =============================================================================
+
+impl IoDevice for Hdc {
+    fn port_range(&self) -> RangeInclusive<u16> {
+        HDC_PORT_BASE..=HDC_PORT_END
+    }
+
+    fn read_u8(&mut self, port: u16) -> u8 {
+        match port {
+            HDC_DATA => match self.phase {
+                HdcPhase::DataOut => self.next_data_byte().unwrap_or(0xFF),
+                HdcPhase::Status => {
+                    self.phase = HdcPhase::Idle;
+                    self.irq_pending = false;
+                    self.completion
+                }
+                _ => 0xFF,
+            },
+            HDC_STATUS => self.status(),
+            // Drive type switches: both drives use table entry 0
+            HDC_SELECT => 0x00,
+            _ => 0xFF,
+        }
+    }
+
+    fn write_u8(&mut self, port: u16, value: u8) {
+        match port {
+            HDC_DATA => match self.phase {
+                HdcPhase::Command => self.write_command_byte(value),
+                HdcPhase::DataIn => self.accept_data_byte(value),
+                _ => {}
+            },
+            HDC_STATUS => self.controller_reset(),
+            HDC_SELECT => {
+                self.phase = HdcPhase::Command;
+                self.command_len = 0;
+            }
+            HDC_MASK => self.mask = value,
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, _cycles: u16, pic: &mut Pic) {
+        pic.set_irq_level(HDC_IRQ, self.irq_pending && self.mask & MASK_IRQ != 0);
+    }
+
+    fn reset(&mut self) {
+        self.controller_reset();
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) {
+        if let Some(state) = state.downcast_ref::<Self>() {
+            *self = state.clone();
+        }
+    }
+}
+
+// =============================================================================
+// DmaCapable Implementation
+// =============================================================================
+
+impl DmaCapable for Hdc {
+    fn dma_dreq(&self) -> bool {
+        self.mask & MASK_DMA != 0
+            && (self.phase == HdcPhase::DataOut || self.phase == HdcPhase::DataIn)
+    }
+
+    fn dma_read_byte(&mut self) -> Option<u8> {
+        if self.phase == HdcPhase::DataOut {
+            self.next_data_byte()
+        } else {
+            None
+        }
+    }
+
+    fn dma_write_byte(&mut self, value: u8) {
+        if self.phase == HdcPhase::DataIn {
+            self.accept_data_byte(value);
+        }
+    }
+
+    fn dma_terminal_count(&mut self) {
+        // The channel ran out before the controller did; end the command
+        // with whatever was transferred
+        match self.phase {
+            HdcPhase::DataOut => self.finish(None),
+            HdcPhase::DataIn => {
+                let whole_sectors = self.buffer.len() / HDD_SECTOR_SIZE * HDD_SECTOR_SIZE;
+                self.buffer.truncate(whole_sectors);
+                self.data_expected = self.buffer.len();
+                self.complete_data_in();
+            }
+            _ => {}
+        }
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::hdd::HddGeometry;
+
+    fn disk_with_pattern() -> HardDisk {
+        let geometry = HddGeometry::new(4, 2, 4);
+        let data = (0..geometry.total_size())
+            .map(|i| (i / HDD_SECTOR_SIZE) as u8)
+            .collect();
+        HardDisk::new(data, geometry)
+    }
+
+    fn send_command(hdc: &mut Hdc, command: [u8; 6]) {
+        hdc.write_u8(HDC_SELECT, 0);
+        assert_eq!(
+            hdc.read_u8(HDC_STATUS),
+            STATUS_BUSY | STATUS_REQ | STATUS_CD
+        );
+        for byte in command {
+            hdc.write_u8(HDC_DATA, byte);
+        }
+    }
+
+    #[test]
+    fn test_pio_read_then_status() {
+        let mut hdc = Hdc::new();
+        hdc.insert_disk(0, disk_with_pattern());
+
+        // Read 2 sectors from C0 H1 S3 (linear sector 7)
+        send_command(&mut hdc, [CMD_READ, 0x01, 0x03, 0x00, 2, 0]);
+        assert_eq!(hdc.phase(), HdcPhase::DataOut);
+        let data: Vec<u8> = (0..2 * HDD_SECTOR_SIZE)
+            .map(|_| hdc.read_u8(HDC_DATA))
+            .collect();
+        assert_eq!(data[0], 7);
+        assert_eq!(data[HDD_SECTOR_SIZE], 8);
+
+        assert_eq!(
+            hdc.read_u8(HDC_STATUS),
+            STATUS_BUSY | STATUS_REQ | STATUS_IO | STATUS_CD | STATUS_INT
+        );
+        assert_eq!(hdc.read_u8(HDC_DATA), 0x00);
+        assert_eq!(hdc.read_u8(HDC_STATUS), 0);
+    }
+
+    #[test]
+    fn test_read_past_end_reports_sense() {
+        let mut hdc = Hdc::new();
+        hdc.insert_disk(0, disk_with_pattern());
+
+        // Cylinder 4 is one past the last
+        send_command(&mut hdc, [CMD_READ, 0x00, 0x00, 0x04, 1, 0]);
+        assert_eq!(hdc.read_u8(HDC_DATA), COMPLETION_ERROR);
+
+        send_command(&mut hdc, [CMD_REQUEST_SENSE, 0, 0, 0, 0, 0]);
+        let sense: Vec<u8> = (0..4).map(|_| hdc.read_u8(HDC_DATA)).collect();
+        assert_eq!(
+            sense,
+            vec![SENSE_ADDRESS_VALID | SENSE_ILLEGAL_ADDRESS, 0, 0, 4]
+        );
+        assert_eq!(hdc.read_u8(HDC_DATA), 0x00);
+    }
+
+    #[test]
+    fn test_dma_write_lands_on_disk() {
+        let mut hdc = Hdc::new();
+        hdc.insert_disk(1, disk_with_pattern());
+        hdc.write_u8(HDC_MASK, MASK_DMA | MASK_IRQ);
+
+        // Write 1 sector to drive 1, C1 H0 S0 (linear sector 8)
+        send_command(&mut hdc, [CMD_WRITE, 0x20, 0x00, 0x01, 1, 0]);
+        assert!(hdc.dma_dreq());
+        for _ in 0..HDD_SECTOR_SIZE {
+            hdc.dma_write_byte(0xA5);
+        }
+        assert!(!hdc.dma_dreq());
+        assert_eq!(hdc.read_u8(HDC_DATA), 0x20);
+
+        let disk = hdc.disk(1).unwrap();
+        assert!(disk.is_dirty());
+        assert!(disk.sectors(8, 1).unwrap().iter().all(|&b| b == 0xA5));
+        assert_eq!(disk.sectors(9, 1).unwrap()[0], 9);
+    }
+
+    #[test]
+    fn test_missing_drive_not_ready() {
+        let mut hdc = Hdc::new();
+        send_command(&mut hdc, [CMD_TEST_READY, 0, 0, 0, 0, 0]);
+        assert_eq!(hdc.read_u8(HDC_DATA), COMPLETION_ERROR);
+    }
+}
@@ -0,0 +1,353 @@
+//! Hard disk image handling
+//!
+//! A hard disk is a raw image of 512-byte sectors in CHS order, shared by
+//! the XT fixed disk controller (see `hdc`) and the IDE controller (see
+//! `ide`). Sectors are addressed linearly (LBA); the geometry maps CHS
+//! addresses onto them.
+
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+// =============================================================================
+// Constants
+// =============================================================================
+
+/// Bytes per sector; ST-506 and ATA drives are always formatted with 512
+pub const HDD_SECTOR_SIZE: usize = 512;
+
+/// Heads in the geometry `from_size` picks for arbitrary images
+const DEFAULT_HEADS: u8 = 16;
+
+/// Sectors per track in the geometry `from_size` picks for arbitrary images
+const DEFAULT_SECTORS_PER_TRACK: u8 = 63;
+
+// =============================================================================
+// HddGeometry
+// =============================================================================
+
+/// Fixed disk geometry (CHS layout)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HddGeometry {
+    /// Number of cylinders
+    pub cylinders: u16,
+    /// Number of heads
+    pub heads: u8,
+    /// Sectors per track
+    pub sectors_per_track: u8,
+}
+
+impl HddGeometry {
+    /// Seagate ST-412, the 10MB drive IBM shipped in the XT
+    pub const ST412: Self = Self::new(306, 4, 17);
+
+    /// Create a new geometry
+    pub const fn new(cylinders: u16, heads: u8, sectors_per_track: u8) -> Self {
+        Self {
+            cylinders,
+            heads,
+            sectors_per_track,
+        }
+    }
+
+    /// Parse an explicit "cylinders:heads:sectors" geometry such as "615:4:17"
+    ///
+    /// Returns None unless there are exactly three non-zero parts. Heads
+    /// are limited to 16 and sectors per track to 63, as ATA CHS
+    /// addressing allows.
+    pub fn from_chs(spec: &str) -> Option<Self> {
+        let mut parts = spec.split(':').map(|part| part.trim());
+        let cylinders = parts.next()?.parse::<u16>().ok()?;
+        let heads = parts.next()?.parse::<u8>().ok()?;
+        let sectors_per_track = parts.next()?.parse::<u8>().ok()?;
+        if parts.next().is_some()
+            || cylinders == 0
+            || !(1..=16).contains(&heads)
+            || !(1..=63).contains(&sectors_per_track)
+        {
+            return None;
+        }
+        Some(Self::new(cylinders, heads, sectors_per_track))
+    }
+
+    /// Pick a geometry for a raw image of `size` bytes
+    ///
+    /// An image exactly the size of an ST-412 gets that drive's geometry.
+    /// Anything else gets 16 heads and 63 sectors per track, as BIOSes
+    /// translate large drives, with enough cylinders to hold the image.
+    /// Returns None for an empty image.
+    pub fn from_size(size: usize) -> Option<Self> {
+        if size == 0 {
+            return None;
+        }
+        if size == Self::ST412.total_size() {
+            return Some(Self::ST412);
+        }
+        let cylinder_size =
+            DEFAULT_HEADS as usize * DEFAULT_SECTORS_PER_TRACK as usize * HDD_SECTOR_SIZE;
+        let cylinders = size.div_ceil(cylinder_size).min(u16::MAX as usize);
+        Some(Self::new(
+            cylinders as u16,
+            DEFAULT_HEADS,
+            DEFAULT_SECTORS_PER_TRACK,
+        ))
+    }
+
+    /// Total number of sectors
+    pub fn total_sectors(&self) -> usize {
+        self.cylinders as usize * self.heads as usize * self.sectors_per_track as usize
+    }
+
+    /// Total size in bytes
+    pub fn total_size(&self) -> usize {
+        self.total_sectors() * HDD_SECTOR_SIZE
+    }
+
+    /// Convert a CHS address (sector counted from 0) to a linear sector number
+    ///
+    /// Returns None if any part is outside the geometry.
+    pub fn chs_to_lba(&self, cylinder: u16, head: u8, sector: u8) -> Option<usize> {
+        if cylinder >= self.cylinders || head >= self.heads || sector >= self.sectors_per_track {
+            return None;
+        }
+        Some(
+            (cylinder as usize * self.heads as usize + head as usize)
+                * self.sectors_per_track as usize
+                + sector as usize,
+        )
+    }
+}
+
+// =============================================================================
+// HardDisk
+// =============================================================================
+
+/// A fixed disk: a flat image of sectors in CHS order
+#[derive(Clone)]
+pub struct HardDisk {
+    geometry: HddGeometry,
+    data: Vec<u8>,
+    /// Write protection flag
+    write_protected: bool,
+    dirty: bool,
+    /// Source file path (for saving)
+    #[cfg(feature = "std")]
+    path: Option<PathBuf>,
+}
+
+impl HardDisk {
+    /// Wrap a flat image with the given geometry
+    ///
+    /// An image shorter than the geometry is padded with zeros, as an
+    /// unformatted drive would read; a longer one is truncated.
+    pub fn new(mut data: Vec<u8>, geometry: HddGeometry) -> Self {
+        data.resize(geometry.total_size(), 0);
+        Self {
+            geometry,
+            data,
+            write_protected: false,
+            dirty: false,
+            #[cfg(feature = "std")]
+            path: None,
+        }
+    }
+
+    /// Load a raw hard disk image from file
+    ///
+    /// Geometry is picked by `HddGeometry::from_size`.
+    /// The disk is read-only by default; use `set_write_protected(false)` to enable writes.
+    #[cfg(feature = "std")]
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        Self::from_file_with_geometry(path, None)
+    }
+
+    /// Load a raw hard disk image, optionally forcing its geometry
+    ///
+    /// An image that doesn't fill the geometry is zero-padded, and `save`
+    /// writes it back at the full size. An image larger than a forced
+    /// geometry is rejected rather than truncated.
+    #[cfg(feature = "std")]
+    pub fn from_file_with_geometry(path: &Path, geometry: Option<HddGeometry>) -> io::Result<Self> {
+        let data = std::fs::read(path)?;
+        if let Some(geometry) = geometry.filter(|geometry| data.len() > geometry.total_size()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Disk image is {} bytes but {}x{}x{} only holds {}",
+                    data.len(),
+                    geometry.cylinders,
+                    geometry.heads,
+                    geometry.sectors_per_track,
+                    geometry.total_size()
+                ),
+            ));
+        }
+        let geometry = geometry
+            .or_else(|| HddGeometry::from_size(data.len()))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Disk image is empty"))?;
+
+        if data.len() != geometry.total_size() {
+            log_warn!(
+                "[HDD] {}: image is {} bytes but {}x{}x{} needs {}",
+                path.display(),
+                data.len(),
+                geometry.cylinders,
+                geometry.heads,
+                geometry.sectors_per_track,
+                geometry.total_size()
+            );
+        }
+
+        let mut disk = Self::new(data, geometry);
+        disk.write_protected = true; // Read-only by default
+        disk.path = Some(path.to_path_buf());
+        Ok(disk)
+    }
+
+    /// Get the disk geometry
+    pub fn geometry(&self) -> HddGeometry {
+        self.geometry
+    }
+
+    /// Get the image contents, e.g. to save them back to a file
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Check if the disk is write-protected
+    pub fn is_write_protected(&self) -> bool {
+        self.write_protected
+    }
+
+    /// Set write protection status
+    pub fn set_write_protected(&mut self, protected: bool) {
+        self.write_protected = protected;
+    }
+
+    /// Check if the guest has written to the disk
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Get a run of `count` sectors starting at linear sector `lba`
+    pub fn sectors(&self, lba: usize, count: usize) -> Option<&[u8]> {
+        let start = lba * HDD_SECTOR_SIZE;
+        self.data.get(start..start + count * HDD_SECTOR_SIZE)
+    }
+
+    /// Overwrite sectors starting at linear sector `lba`
+    ///
+    /// Returns false, writing nothing, if the disk is write-protected or the
+    /// run extends past the end.
+    pub fn write_sectors(&mut self, lba: usize, data: &[u8]) -> bool {
+        if self.write_protected {
+            return false;
+        }
+        let start = lba * HDD_SECTOR_SIZE;
+        match self.data.get_mut(start..start + data.len()) {
+            Some(target) => {
+                target.copy_from_slice(data);
+                self.dirty = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Save changes back to the source file
+    #[cfg(feature = "std")]
+    pub fn save(&mut self) -> io::Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No source file path"))?;
+
+        if self.write_protected {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "Disk is write-protected",
+            ));
+        }
+
+        std::fs::write(path, &self.data)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Get the file path (if loaded from file)
+    #[cfg(feature = "std")]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chs_to_lba() {
+        let geometry = HddGeometry::ST412;
+        assert_eq!(geometry.total_size(), 10_653_696);
+        assert_eq!(geometry.chs_to_lba(0, 0, 0), Some(0));
+        assert_eq!(geometry.chs_to_lba(1, 2, 3), Some(4 * 17 + 2 * 17 + 3));
+        assert_eq!(geometry.chs_to_lba(0, 0, 17), None);
+        assert_eq!(geometry.chs_to_lba(306, 0, 0), None);
+    }
+
+    #[test]
+    fn test_from_size() {
+        assert_eq!(HddGeometry::from_size(0), None);
+        assert_eq!(HddGeometry::from_size(10_653_696), Some(HddGeometry::ST412));
+        // 20MB rounds up to whole 16-head cylinders
+        let geometry = HddGeometry::from_size(20 * 1024 * 1024).unwrap();
+        assert_eq!(geometry, HddGeometry::new(41, 16, 63));
+        assert!(geometry.total_size() >= 20 * 1024 * 1024);
+    }
+
+    #[test]
+    fn test_from_chs() {
+        assert_eq!(
+            HddGeometry::from_chs("615:4:17"),
+            Some(HddGeometry::new(615, 4, 17))
+        );
+        assert_eq!(HddGeometry::from_chs("615:4"), None);
+        assert_eq!(HddGeometry::from_chs("615:4:17:1"), None);
+        assert_eq!(HddGeometry::from_chs("0:4:17"), None);
+        assert_eq!(HddGeometry::from_chs("615:17:17"), None);
+        assert_eq!(HddGeometry::from_chs("615:4:64"), None);
+    }
+
+    #[test]
+    fn test_from_file_rejects_image_larger_than_geometry() {
+        let geometry = HddGeometry::new(2, 2, 4);
+        let path = std::env::temp_dir().join(format!("ezpc-hdd-{}-large.img", std::process::id()));
+        std::fs::write(&path, vec![0u8; geometry.total_size() + HDD_SECTOR_SIZE]).unwrap();
+
+        let result = HardDisk::from_file_with_geometry(&path, Some(geometry));
+        let padded = HardDisk::from_file_with_geometry(&path, Some(HddGeometry::new(3, 2, 4)));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(padded.unwrap().geometry(), HddGeometry::new(3, 2, 4));
+    }
+
+    #[test]
+    fn test_write_protected_disk_rejects_writes() {
+        let mut disk = HardDisk::new(Vec::new(), HddGeometry::new(2, 2, 4));
+        disk.set_write_protected(true);
+        assert!(!disk.write_sectors(0, &[0xAA; HDD_SECTOR_SIZE]));
+        assert!(!disk.is_dirty());
+        assert_eq!(disk.sectors(0, 1).unwrap()[0], 0);
+
+        disk.set_write_protected(false);
+        assert!(disk.write_sectors(0, &[0xAA; HDD_SECTOR_SIZE]));
+        assert!(disk.is_dirty());
+    }
+}
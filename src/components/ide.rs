@@ -0,0 +1,715 @@
+//! ATA (IDE) hard disk controller
+//!
+//! Up to two drives (master and slave) share one register block. Sector
+//! data moves by PIO through the 16-bit data port, one 512-byte sector per
+//! DRQ; there is no DMA.
+//!
+//! ## I/O Ports
+//! - 0x1F0: Data (16-bit)
+//! - 0x1F1: Error (read) / features (write)
+//! - 0x1F2: Sector count (0 means 256)
+//! - 0x1F3: Sector number / LBA bits 0-7
+//! - 0x1F4: Cylinder low / LBA bits 8-15
+//! - 0x1F5: Cylinder high / LBA bits 16-23
+//! - 0x1F6: Drive/head / LBA bits 24-27
+//! - 0x1F7: Status (read) / command (write)
+//! - 0x3F6: Alternate status (read) / device control (write)
+//!
+//! 0x3F6 falls inside the floppy controller's block, so rather than
+//! claiming it through `port_range`, the bus routes it here itself once the
+//! controller is installed (see `MemoryBus::install_ide`).
+//!
+//! The AT raises IRQ14 on its second PIC. The PC only has one, so the line
+//! is chosen when the controller is created; `IDE_IRQ` is IRQ5, where XT
+//! hard disk cards sit.
+//!
+//! Commands complete as soon as they are issued, so BSY never shows.
+
+use crate::components::hdd::{HardDisk, HddGeometry, HDD_SECTOR_SIZE};
+use crate::components::pic::Pic;
+use crate::io::{IoDevice, IoWidth};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::ops::RangeInclusive;
+
+// =============================================================================
+// Constants
+// =============================================================================
+
+/// IDE command block base
+pub const IDE_PORT_BASE: u16 = 0x1F0;
+const IDE_PORT_END: u16 = 0x1F7;
+
+/// Alternate status (read) / device control (write)
+pub const IDE_CONTROL_PORT: u16 = 0x3F6;
+
+/// Default IRQ line, standing in for the AT's IRQ14
+pub const IDE_IRQ: u8 = 5;
+
+/// Command block registers
+const IDE_DATA: u16 = 0x1F0;
+const IDE_ERROR: u16 = 0x1F1; // Error (read) / features (write)
+const IDE_SECTOR_COUNT: u16 = 0x1F2;
+const IDE_SECTOR: u16 = 0x1F3;
+const IDE_CYLINDER_LOW: u16 = 0x1F4;
+const IDE_CYLINDER_HIGH: u16 = 0x1F5;
+const IDE_DRIVE_HEAD: u16 = 0x1F6;
+const IDE_STATUS: u16 = 0x1F7; // Status (read) / command (write)
+
+/// Status register bits
+const STATUS_ERR: u8 = 0x01; // Error register holds the reason
+const STATUS_DRQ: u8 = 0x08; // Data port ready for the next transfer
+const STATUS_DSC: u8 = 0x10; // Seek complete
+const STATUS_DRDY: u8 = 0x40; // Drive ready for commands
+
+/// Error register bits
+const ERROR_ABRT: u8 = 0x04; // Command aborted
+const ERROR_IDNF: u8 = 0x10; // Sector address not found
+
+/// Error register after a reset: diagnostics passed
+const DIAGNOSTIC_PASSED: u8 = 0x01;
+
+/// Drive/head register bits
+const DRIVE_HEAD_SLAVE: u8 = 0x10;
+const DRIVE_HEAD_LBA: u8 = 0x40;
+const DRIVE_HEAD_HEAD: u8 = 0x0F;
+
+/// Device control register bits
+const CONTROL_NIEN: u8 = 0x02; // Interrupts disabled
+const CONTROL_SRST: u8 = 0x04; // Software reset
+
+/// Command opcodes
+const CMD_RECALIBRATE: RangeInclusive<u8> = 0x10..=0x1F;
+const CMD_READ_SECTORS: u8 = 0x20;
+const CMD_READ_SECTORS_NO_RETRY: u8 = 0x21;
+const CMD_WRITE_SECTORS: u8 = 0x30;
+const CMD_WRITE_SECTORS_NO_RETRY: u8 = 0x31;
+const CMD_SEEK: RangeInclusive<u8> = 0x70..=0x7F;
+const CMD_INITIALIZE_PARAMETERS: u8 = 0x91;
+const CMD_IDENTIFY: u8 = 0xEC;
+
+/// Words returned by Identify Device
+const IDENTIFY_WORDS: usize = 256;
+
+/// Identify Device word 0: fixed, non-removable disk
+const IDENTIFY_FIXED_DISK: u16 = 0x0040;
+
+/// Identify Device word 49: LBA supported
+const IDENTIFY_LBA: u16 = 0x0200;
+
+/// Identify Device word 53: words 54-58 are valid
+const IDENTIFY_CURRENT_VALID: u16 = 0x0001;
+
+/// Strings reported by Identify Device
+const SERIAL_NUMBER: &str = "EZPC0001";
+const FIRMWARE_REVISION: &str = "1.0";
+const MODEL_NUMBER: &str = "EZPC HARD DISK";
+
+// =============================================================================
+// Ide
+// =============================================================================
+
+/// Data transfer in progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transfer {
+    /// No data to move
+    None,
+    /// Identify data waiting to be read
+    Identify,
+    /// Read Sectors: the buffer holds sector `lba`, with `remaining` to go
+    /// after it
+    Read { lba: usize, remaining: usize },
+    /// Write Sectors: the buffer fills with sector `lba`, with `remaining`
+    /// to go after it
+    Write { lba: usize, remaining: usize },
+}
+
+/// ATA controller with up to two drives
+#[derive(Clone)]
+pub struct Ide {
+    /// IRQ line raised when a command completes
+    irq: u8,
+
+    /// Master and slave drives
+    drives: [Option<HardDisk>; 2],
+
+    /// Task file registers
+    error: u8,
+    sector_count: u8,
+    sector: u8,
+    cylinder: u16,
+    drive_head: u8,
+    status: u8,
+
+    /// Device control register
+    control: u8,
+
+    /// Sector or Identify data moving through the data port
+    buffer: Vec<u8>,
+    buffer_index: usize,
+    transfer: Transfer,
+
+    /// Command completed and the interrupt has not been acknowledged by
+    /// reading the status register
+    irq_pending: bool,
+}
+
+impl Ide {
+    /// Create a controller with no drives attached, raising `irq`
+    pub fn new(irq: u8) -> Self {
+        let mut ide = Self {
+            irq,
+            drives: [None, None],
+            error: 0,
+            sector_count: 0,
+            sector: 0,
+            cylinder: 0,
+            drive_head: 0,
+            status: 0,
+            control: 0,
+            buffer: Vec::new(),
+            buffer_index: 0,
+            transfer: Transfer::None,
+            irq_pending: false,
+        };
+        ide.controller_reset();
+        ide
+    }
+
+    /// Get the IRQ line raised when a command completes
+    pub fn irq(&self) -> u8 {
+        self.irq
+    }
+
+    /// Attach a drive (0 = master, 1 = slave)
+    ///
+    /// Returns the previously attached disk, if any.
+    pub fn insert_disk(&mut self, drive: u8, disk: HardDisk) -> Option<HardDisk> {
+        self.drives[drive as usize].replace(disk)
+    }
+
+    /// Get an attached drive
+    pub fn disk(&self, drive: u8) -> Option<&HardDisk> {
+        self.drives[drive as usize].as_ref()
+    }
+
+    /// Get an attached drive mutably, e.g. to save it
+    pub fn disk_mut(&mut self, drive: u8) -> Option<&mut HardDisk> {
+        self.drives[drive as usize].as_mut()
+    }
+
+    /// Read the alternate status register (0x3F6)
+    ///
+    /// Unlike the status register, this leaves a pending interrupt alone.
+    pub fn read_control(&self) -> u8 {
+        self.current_status()
+    }
+
+    /// Write the device control register (0x3F6)
+    pub fn write_control(&mut self, value: u8) {
+        if value & CONTROL_SRST != 0 {
+            self.controller_reset();
+        }
+        self.control = value;
+    }
+
+    /// Index of the drive selected in the drive/head register
+    fn drive(&self) -> usize {
+        (self.drive_head & DRIVE_HEAD_SLAVE != 0) as usize
+    }
+
+    /// Status of the selected drive; an absent drive reads as all zeros
+    fn current_status(&self) -> u8 {
+        if self.drives[self.drive()].is_some() {
+            self.status
+        } else {
+            0
+        }
+    }
+
+    /// Sector count from the task file (0 means 256)
+    fn block_count(&self) -> usize {
+        match self.sector_count {
+            0 => 256,
+            count => count as usize,
+        }
+    }
+
+    /// Validate the task file address for a run of sectors
+    ///
+    /// Returns the starting linear sector, or the error bits to abort with.
+    fn target(&self, count: usize) -> Result<usize, u8> {
+        let disk = self.drives[self.drive()].as_ref().ok_or(ERROR_ABRT)?;
+        let geometry = disk.geometry();
+        let lba = if self.drive_head & DRIVE_HEAD_LBA != 0 {
+            ((self.drive_head & DRIVE_HEAD_HEAD) as usize) << 24
+                | (self.cylinder as usize) << 8
+                | self.sector as usize
+        } else {
+            // CHS sector numbers count from 1
+            geometry
+                .chs_to_lba(
+                    self.cylinder,
+                    self.drive_head & DRIVE_HEAD_HEAD,
+                    self.sector.wrapping_sub(1),
+                )
+                .ok_or(ERROR_IDNF)?
+        };
+        if lba + count > geometry.total_sectors() {
+            return Err(ERROR_IDNF);
+        }
+        Ok(lba)
+    }
+
+    fn execute_command(&mut self, command: u8) {
+        #[cfg(debug_assertions)]
+        log_debug!(
+            "[IDE] Command {:02X} drive {} count {}",
+            command,
+            self.drive(),
+            self.sector_count
+        );
+
+        if self.drives[self.drive()].is_none() {
+            // Nothing answers for a missing drive
+            return;
+        }
+        self.error = 0;
+        self.transfer = Transfer::None;
+
+        match command {
+            CMD_IDENTIFY => {
+                let geometry = self.drives[self.drive()].as_ref().unwrap().geometry();
+                self.start_data_out(identify_data(geometry), Transfer::Identify);
+                self.irq_pending = true;
+            }
+            CMD_READ_SECTORS | CMD_READ_SECTORS_NO_RETRY => match self.target(self.block_count()) {
+                Ok(lba) => {
+                    let remaining = self.block_count() - 1;
+                    self.load_sector(lba, remaining);
+                }
+                Err(error) => self.abort(error),
+            },
+            CMD_WRITE_SECTORS | CMD_WRITE_SECTORS_NO_RETRY => {
+                match self.target(self.block_count()) {
+                    Ok(lba) => {
+                        // The first sector is requested without an interrupt
+                        let remaining = self.block_count() - 1;
+                        self.start_data_in(Transfer::Write { lba, remaining });
+                    }
+                    Err(error) => self.abort(error),
+                }
+            }
+            CMD_INITIALIZE_PARAMETERS => {
+                // Only the drive's own geometry is supported as a translation
+                let geometry = self.drives[self.drive()].as_ref().unwrap().geometry();
+                let heads = (self.drive_head & DRIVE_HEAD_HEAD) + 1;
+                if heads == geometry.heads && self.sector_count == geometry.sectors_per_track {
+                    self.complete();
+                } else {
+                    self.abort(ERROR_ABRT);
+                }
+            }
+            command if CMD_RECALIBRATE.contains(&command) => {
+                self.cylinder = 0;
+                self.complete();
+            }
+            command if CMD_SEEK.contains(&command) => match self.target(0) {
+                Ok(_) => self.complete(),
+                Err(error) => self.abort(error),
+            },
+            _ => {
+                log_warn!("[IDE] Unsupported command {:02X}", command);
+                self.abort(ERROR_ABRT);
+            }
+        }
+    }
+
+    /// Put sector `lba` in the buffer for the host to read
+    fn load_sector(&mut self, lba: usize, remaining: usize) {
+        let data = self.drives[self.drive()]
+            .as_ref()
+            .and_then(|disk| disk.sectors(lba, 1))
+            .unwrap_or(&[])
+            .to_vec();
+        self.start_data_out(data, Transfer::Read { lba, remaining });
+        self.irq_pending = true;
+    }
+
+    fn start_data_out(&mut self, data: Vec<u8>, transfer: Transfer) {
+        self.buffer = data;
+        self.buffer_index = 0;
+        self.transfer = transfer;
+        self.status = STATUS_DRDY | STATUS_DSC | STATUS_DRQ;
+    }
+
+    fn start_data_in(&mut self, transfer: Transfer) {
+        self.buffer.clear();
+        self.transfer = transfer;
+        self.status = STATUS_DRDY | STATUS_DSC | STATUS_DRQ;
+    }
+
+    /// Hand one byte of Identify or sector data to the host
+    fn next_data_byte(&mut self) -> u8 {
+        let Some(&byte) = self.buffer.get(self.buffer_index) else {
+            return 0xFF;
+        };
+        self.buffer_index += 1;
+        if self.buffer_index == self.buffer.len() {
+            match self.transfer {
+                Transfer::Read { lba, remaining } if remaining > 0 => {
+                    self.load_sector(lba + 1, remaining - 1);
+                }
+                _ => self.end_transfer(),
+            }
+        }
+        byte
+    }
+
+    /// Take one byte of sector data from the host
+    fn accept_data_byte(&mut self, value: u8) {
+        let Transfer::Write { lba, remaining } = self.transfer else {
+            return;
+        };
+        self.buffer.push(value);
+        if self.buffer.len() < HDD_SECTOR_SIZE {
+            return;
+        }
+
+        let drive = self.drive();
+        let data = core::mem::take(&mut self.buffer);
+        let written = self.drives[drive]
+            .as_mut()
+            .is_some_and(|disk| disk.write_sectors(lba, &data));
+        if !written {
+            // The image is write-protected
+            self.abort(ERROR_ABRT);
+        } else if remaining > 0 {
+            self.start_data_in(Transfer::Write {
+                lba: lba + 1,
+                remaining: remaining - 1,
+            });
+            self.irq_pending = true;
+        } else {
+            self.complete();
+        }
+    }
+
+    /// Finish the data phase, leaving the drive ready for the next command
+    fn end_transfer(&mut self) {
+        self.buffer.clear();
+        self.buffer_index = 0;
+        self.transfer = Transfer::None;
+        self.status = STATUS_DRDY | STATUS_DSC;
+    }
+
+    /// End the command successfully and raise the interrupt
+    fn complete(&mut self) {
+        self.end_transfer();
+        self.irq_pending = true;
+    }
+
+    /// End the command with an error and raise the interrupt
+    fn abort(&mut self, error: u8) {
+        self.end_transfer();
+        self.error = error;
+        self.status |= STATUS_ERR;
+        self.irq_pending = true;
+    }
+
+    /// Return the controller to its power-on state, keeping the drives
+    ///
+    /// The task file holds the reset signature of a hard disk, and the
+    /// error register reports passed diagnostics.
+    fn controller_reset(&mut self) {
+        self.error = DIAGNOSTIC_PASSED;
+        self.sector_count = 1;
+        self.sector = 1;
+        self.cylinder = 0;
+        self.drive_head = 0;
+        self.status = STATUS_DRDY | STATUS_DSC;
+        self.control = 0;
+        self.buffer.clear();
+        self.buffer_index = 0;
+        self.transfer = Transfer::None;
+        self.irq_pending = false;
+    }
+}
+
+impl Default for Ide {
+    fn default() -> Self {
+        Self::new(IDE_IRQ)
+    }
+}
+
+/// Build the Identify Device block for a drive
+fn identify_data(geometry: HddGeometry) -> Vec<u8> {
+    let mut words = [0u16; IDENTIFY_WORDS];
+    let total = geometry.total_sectors() as u32;
+
+    words[0] = IDENTIFY_FIXED_DISK;
+    words[1] = geometry.cylinders;
+    words[3] = geometry.heads as u16;
+    words[6] = geometry.sectors_per_track as u16;
+    put_string(&mut words[10..20], SERIAL_NUMBER);
+    put_string(&mut words[23..27], FIRMWARE_REVISION);
+    put_string(&mut words[27..47], MODEL_NUMBER);
+    words[49] = IDENTIFY_LBA;
+    words[53] = IDENTIFY_CURRENT_VALID;
+    words[54] = geometry.cylinders;
+    words[55] = geometry.heads as u16;
+    words[56] = geometry.sectors_per_track as u16;
+    words[57] = total as u16;
+    words[58] = (total >> 16) as u16;
+    words[60] = total as u16;
+    words[61] = (total >> 16) as u16;
+
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+/// Store an ATA string: space-padded, with the first character of each pair
+/// in the high byte
+fn put_string(words: &mut [u16], text: &str) {
+    let mut bytes = text.bytes().chain(core::iter::repeat(b' '));
+    for word in words {
+        let high = bytes.next().unwrap_or(b' ');
+        let low = bytes.next().unwrap_or(b' ');
+        *word = (high as u16) << 8 | low as u16;
+    }
+}
+
+// =============================================================================
+// IoDevice Implementation
+// =============================================================================
+
+impl IoDevice for Ide {
+    fn port_range(&self) -> RangeInclusive<u16> {
+        IDE_PORT_BASE..=IDE_PORT_END
+    }
+
+    fn io_width(&self) -> IoWidth {
+        IoWidth::Word
+    }
+
+    fn read_u8(&mut self, port: u16) -> u8 {
+        match port {
+            IDE_DATA => self.next_data_byte(),
+            IDE_ERROR => self.error,
+            IDE_SECTOR_COUNT => self.sector_count,
+            IDE_SECTOR => self.sector,
+            IDE_CYLINDER_LOW => self.cylinder as u8,
+            IDE_CYLINDER_HIGH => (self.cylinder >> 8) as u8,
+            IDE_DRIVE_HEAD => self.drive_head,
+            IDE_STATUS => {
+                self.irq_pending = false;
+                self.current_status()
+            }
+            _ => 0xFF,
+        }
+    }
+
+    fn write_u8(&mut self, port: u16, value: u8) {
+        match port {
+            IDE_DATA => self.accept_data_byte(value),
+            // No command here takes features
+            IDE_ERROR => {}
+            IDE_SECTOR_COUNT => self.sector_count = value,
+            IDE_SECTOR => self.sector = value,
+            IDE_CYLINDER_LOW => self.cylinder = (self.cylinder & 0xFF00) | value as u16,
+            IDE_CYLINDER_HIGH => {
+                self.cylinder = (self.cylinder & 0x00FF) | (value as u16) << 8;
+            }
+            IDE_DRIVE_HEAD => self.drive_head = value,
+            IDE_STATUS => self.execute_command(value),
+            _ => {}
+        }
+    }
+
+    fn read_u16(&mut self, port: u16) -> u16 {
+        if port == IDE_DATA {
+            let lo = self.next_data_byte() as u16;
+            let hi = self.next_data_byte() as u16;
+            lo | (hi << 8)
+        } else {
+            let lo = self.read_u8(port) as u16;
+            let hi = self.read_u8(port + 1) as u16;
+            lo | (hi << 8)
+        }
+    }
+
+    fn write_u16(&mut self, port: u16, value: u16) {
+        if port == IDE_DATA {
+            self.accept_data_byte(value as u8);
+            self.accept_data_byte((value >> 8) as u8);
+        } else {
+            self.write_u8(port, value as u8);
+            self.write_u8(port + 1, (value >> 8) as u8);
+        }
+    }
+
+    fn tick(&mut self, _cycles: u16, pic: &mut Pic) {
+        pic.set_irq_level(
+            self.irq,
+            self.irq_pending && self.control & CONTROL_NIEN == 0,
+        );
+    }
+
+    fn reset(&mut self) {
+        self.controller_reset();
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) {
+        if let Some(state) = state.downcast_ref::<Self>() {
+            *self = state.clone();
+        }
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4 cylinders, 2 heads, 4 sectors per track; each sector filled with its LBA
+    fn ide_with_pattern() -> Ide {
+        let geometry = HddGeometry::new(4, 2, 4);
+        let data = (0..geometry.total_size())
+            .map(|i| (i / HDD_SECTOR_SIZE) as u8)
+            .collect();
+        let mut ide = Ide::new(IDE_IRQ);
+        ide.insert_disk(0, HardDisk::new(data, geometry));
+        ide
+    }
+
+    fn read_sector(ide: &mut Ide) -> Vec<u8> {
+        (0..HDD_SECTOR_SIZE / 2)
+            .flat_map(|_| ide.read_u16(IDE_DATA).to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_identify_reports_geometry() {
+        let mut ide = ide_with_pattern();
+        ide.write_u8(IDE_STATUS, CMD_IDENTIFY);
+        assert_eq!(
+            ide.read_u8(IDE_STATUS),
+            STATUS_DRDY | STATUS_DSC | STATUS_DRQ
+        );
+
+        let words: Vec<u16> = (0..IDENTIFY_WORDS)
+            .map(|_| ide.read_u16(IDE_DATA))
+            .collect();
+        assert_eq!((words[1], words[3], words[6]), (4, 2, 4));
+        assert_eq!((words[60], words[61]), (32, 0));
+        // "EZ" in the first model word, first character high
+        assert_eq!(words[27], u16::from_be_bytes(*b"EZ"));
+        assert_eq!(ide.read_u8(IDE_STATUS), STATUS_DRDY | STATUS_DSC);
+    }
+
+    #[test]
+    fn test_chs_and_lba_reads() {
+        let mut ide = ide_with_pattern();
+
+        // 2 sectors from C1 H1 S2 (linear sector 13)
+        ide.write_u8(IDE_SECTOR_COUNT, 2);
+        ide.write_u8(IDE_SECTOR, 2);
+        ide.write_u8(IDE_CYLINDER_LOW, 1);
+        ide.write_u8(IDE_DRIVE_HEAD, 0xA1);
+        ide.write_u8(IDE_STATUS, CMD_READ_SECTORS);
+        assert!(ide.irq_pending);
+        assert!(read_sector(&mut ide).iter().all(|&b| b == 13));
+        assert!(ide.read_u8(IDE_STATUS) & STATUS_DRQ != 0);
+        assert!(read_sector(&mut ide).iter().all(|&b| b == 14));
+        assert_eq!(ide.read_u8(IDE_STATUS), STATUS_DRDY | STATUS_DSC);
+
+        // LBA 31 is the last sector
+        ide.write_u8(IDE_SECTOR_COUNT, 1);
+        ide.write_u8(IDE_SECTOR, 31);
+        ide.write_u8(IDE_CYLINDER_LOW, 0);
+        ide.write_u8(IDE_DRIVE_HEAD, 0xE0);
+        ide.write_u8(IDE_STATUS, CMD_READ_SECTORS);
+        assert!(read_sector(&mut ide).iter().all(|&b| b == 31));
+
+        // Two sectors from LBA 31 run past the end
+        ide.write_u8(IDE_SECTOR_COUNT, 2);
+        ide.write_u8(IDE_STATUS, CMD_READ_SECTORS);
+        assert_eq!(
+            ide.read_u8(IDE_STATUS),
+            STATUS_DRDY | STATUS_DSC | STATUS_ERR
+        );
+        assert_eq!(ide.read_u8(IDE_ERROR), ERROR_IDNF);
+    }
+
+    #[test]
+    fn test_write_sectors_interrupts_after_each_sector() {
+        let mut ide = ide_with_pattern();
+        ide.write_u8(IDE_SECTOR_COUNT, 2);
+        ide.write_u8(IDE_SECTOR, 8);
+        ide.write_u8(IDE_DRIVE_HEAD, 0xE0);
+        ide.write_u8(IDE_STATUS, CMD_WRITE_SECTORS);
+        assert!(!ide.irq_pending);
+        assert!(ide.read_u8(IDE_STATUS) & STATUS_DRQ != 0);
+
+        for _ in 0..HDD_SECTOR_SIZE / 2 {
+            ide.write_u16(IDE_DATA, 0xA5A5);
+        }
+        assert!(ide.irq_pending);
+        assert!(ide.read_u8(IDE_STATUS) & STATUS_DRQ != 0);
+        for _ in 0..HDD_SECTOR_SIZE / 2 {
+            ide.write_u16(IDE_DATA, 0x5A5A);
+        }
+        assert_eq!(ide.read_u8(IDE_STATUS), STATUS_DRDY | STATUS_DSC);
+
+        let disk = ide.disk(0).unwrap();
+        assert!(disk.is_dirty());
+        assert!(disk.sectors(8, 1).unwrap().iter().all(|&b| b == 0xA5));
+        assert!(disk.sectors(9, 1).unwrap().iter().all(|&b| b == 0x5A));
+        assert_eq!(disk.sectors(10, 1).unwrap()[0], 10);
+    }
+
+    #[test]
+    fn test_write_protected_disk_aborts() {
+        let mut ide = ide_with_pattern();
+        ide.disk_mut(0).unwrap().set_write_protected(true);
+        ide.write_u8(IDE_DRIVE_HEAD, 0xE0);
+        ide.write_u8(IDE_STATUS, CMD_WRITE_SECTORS);
+        for _ in 0..HDD_SECTOR_SIZE {
+            ide.write_u8(IDE_DATA, 0xFF);
+        }
+        assert!(ide.read_u8(IDE_STATUS) & STATUS_ERR != 0);
+        assert_eq!(ide.read_u8(IDE_ERROR), ERROR_ABRT);
+        assert_eq!(ide.disk(0).unwrap().sectors(1, 1).unwrap()[0], 1);
+    }
+
+    #[test]
+    fn test_missing_slave_and_soft_reset() {
+        let mut ide = ide_with_pattern();
+        ide.write_u8(IDE_DRIVE_HEAD, 0xB0);
+        assert_eq!(ide.read_u8(IDE_STATUS), 0);
+        ide.write_u8(IDE_STATUS, CMD_IDENTIFY);
+        assert!(!ide.irq_pending);
+
+        ide.write_control(CONTROL_SRST | CONTROL_NIEN);
+        ide.write_control(CONTROL_NIEN);
+        assert_eq!(ide.read_u8(IDE_DRIVE_HEAD), 0);
+        assert_eq!(ide.read_u8(IDE_ERROR), DIAGNOSTIC_PASSED);
+        assert_eq!(ide.read_control(), STATUS_DRDY | STATUS_DSC);
+
+        // nIEN keeps the completion interrupt off the PIC
+        let mut pic = Pic::new(0x08);
+        ide.write_u8(IDE_STATUS, CMD_IDENTIFY);
+        ide.tick(1, &mut pic);
+        assert_eq!(pic.raise_counts()[IDE_IRQ as usize], 0);
+        ide.write_control(0);
+        ide.tick(1, &mut pic);
+        assert_eq!(pic.raise_counts()[IDE_IRQ as usize], 1);
+    }
+}
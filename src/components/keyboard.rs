@@ -3,16 +3,31 @@
 //! The Keyboard is a simple data structure that buffers scancodes from the GUI.
 //! It does not implement IoDevice - all I/O is handled by the PPI.
 
-use std::collections::VecDeque;
+use alloc::collections::VecDeque;
+#[cfg(not(feature = "std"))]
+use alloc::rc::Rc;
+#[cfg(not(feature = "std"))]
+use core::cell::RefCell;
+#[cfg(feature = "std")]
 use std::sync::{Arc, RwLock};
 
+/// Scancode queue shared between the host input system and the keyboard
+///
+/// With `std` the host may push from another thread; without it there are
+/// no threads or locks, so the queue is only shared within one thread.
+#[cfg(feature = "std")]
+pub type ScancodeQueue = Arc<RwLock<VecDeque<u8>>>;
+#[cfg(not(feature = "std"))]
+pub type ScancodeQueue = Rc<RefCell<VecDeque<u8>>>;
+
 /// IBM PC Keyboard
 ///
 /// Simple scancode buffer that receives input from the GUI.
 /// The PPI pulls scancodes from this buffer during its tick().
+#[derive(Clone)]
 pub struct Keyboard {
     /// Shared queue of keyboard scancodes from GUI
-    scancode_queue: Arc<RwLock<VecDeque<u8>>>,
+    scancode_queue: ScancodeQueue,
 }
 
 impl Keyboard {
@@ -20,7 +35,7 @@ impl Keyboard {
     ///
     /// # Arguments
     /// * `scancode_queue` - Shared queue for receiving scancodes from GUI
-    pub fn new(scancode_queue: Arc<RwLock<VecDeque<u8>>>) -> Self {
+    pub fn new(scancode_queue: ScancodeQueue) -> Self {
         Self { scancode_queue }
     }
 
@@ -28,15 +43,12 @@ impl Keyboard {
     ///
     /// Returns the next available scancode, or None if queue is empty.
     pub fn pop_scancode(&mut self) -> Option<u8> {
-        self.scancode_queue.write().ok()?.pop_front()
+        self.with_queue(|queue| queue.pop_front()).flatten()
     }
 
     /// Check if there are scancodes available
     pub fn has_scancode(&self) -> bool {
-        self.scancode_queue
-            .read()
-            .map(|q| !q.is_empty())
-            .unwrap_or(false)
+        self.with_queue(|queue| !queue.is_empty()).unwrap_or(false)
     }
 
     /// Reset the keyboard
@@ -44,16 +56,40 @@ impl Keyboard {
     /// Clears the queue and pushes the 0xAA self-test success code.
     /// Called by PPI when keyboard reset sequence completes.
     pub fn reset(&mut self) {
-        if let Ok(mut queue) = self.scancode_queue.write() {
+        self.with_queue(|queue| {
             queue.clear();
             queue.push_back(0xAA); // Self-test passed
-        }
+        });
+    }
+
+    /// Acknowledge a reset command from the host
+    ///
+    /// Discards pending scancodes and queues the 0xFA ACK byte. The 0xAA
+    /// self-test code follows once the PPI completes the reset delay.
+    pub fn acknowledge_reset(&mut self) {
+        self.with_queue(|queue| {
+            queue.clear();
+            queue.push_back(0xFA); // ACK
+        });
     }
 
     /// Get the keyboard scancode queue for GUI integration
-    pub fn scancode_queue(&self) -> Arc<RwLock<VecDeque<u8>>> {
+    pub fn scancode_queue(&self) -> ScancodeQueue {
         self.scancode_queue.clone()
     }
+
+    /// Run `f` with the queue locked, or return None if the lock is poisoned
+    #[cfg(feature = "std")]
+    fn with_queue<R>(&self, f: impl FnOnce(&mut VecDeque<u8>) -> R) -> Option<R> {
+        let mut queue = self.scancode_queue.write().ok()?;
+        Some(f(&mut queue))
+    }
+
+    /// Run `f` with the queue borrowed
+    #[cfg(not(feature = "std"))]
+    fn with_queue<R>(&self, f: impl FnOnce(&mut VecDeque<u8>) -> R) -> Option<R> {
+        Some(f(&mut self.scancode_queue.borrow_mut()))
+    }
 }
 
 #[cfg(test)]
@@ -2,11 +2,60 @@
 //!
 //! The MDA provides 80x25 text mode with:
 //! - 4KB video RAM at 0xB0000-0xB0FFF
-//! - 9x14 pixel characters
+//! - 9x14 pixel characters (or 8x8 and others through the CRTC maximum
+//!   scanline register)
 //! - 720x350 display resolution
 //! - Monochrome green phosphor output
 
+/// CPU cycles per displayed frame (60 Hz at 4.77 MHz)
+const FRAME_CYCLES: u64 = 79_500;
+
+/// Character clocks per scanline, including horizontal retrace (CRTC R0 + 1)
+const HORIZONTAL_TOTAL: u64 = 97;
+
+/// Character clocks displayed per scanline (CRTC R1)
+const HORIZONTAL_DISPLAYED: u64 = 80;
+
+/// Scanlines per frame, including vertical retrace (26 rows of 14 + 6 adjust)
+const VERTICAL_TOTAL: u64 = 370;
+
+/// Scanlines displayed per frame (25 rows of 14)
+const VERTICAL_DISPLAYED: u64 = 350;
+
+/// Cycle within a frame at which vertical retrace begins
+const VRETRACE_START: u64 = FRAME_CYCLES * VERTICAL_DISPLAYED / VERTICAL_TOTAL;
+
+/// Height of the displayed area in scanlines
+const DISPLAY_HEIGHT: usize = 350;
+
+/// Number of 6845 CRTC registers
+const CRTC_REGISTERS: usize = 18;
+
+/// CRTC register holding the number of characters displayed per row
+const CRTC_HORIZONTAL_DISPLAYED: usize = 1;
+
+/// CRTC register holding the character height minus one
+const CRTC_MAX_SCANLINE: usize = 9;
+
+/// Offset of the CGA 8x8 font in the character ROM
+pub(crate) const FONT_8X8_OFFSET: usize = 0x1800;
+
+/// IBM MDA/CGA character ROM, included at compile time
+pub(crate) const FONT_ROM_DATA: &[u8] = include_bytes!("../../roms/MDA_CHAR.bin");
+
+/// Snapshot of the MDA registers for debugging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MdaState {
+    /// Mode control register (port 0x3B8)
+    pub mode_control: u8,
+    /// 6845 CRTC register selected through port 0x3B4
+    pub crtc_index: u8,
+    /// 6845 CRTC registers
+    pub crtc: [u8; CRTC_REGISTERS],
+}
+
 /// MDA (Monochrome Display Adapter)
+#[derive(Clone)]
 pub struct Mda {
     /// Video RAM (4KB for 80x25 text mode, 2 bytes per cell)
     /// Each cell: byte 0 = character code, byte 1 = attribute
@@ -19,11 +68,29 @@ pub struct Mda {
     /// (MDA refreshes at ~50-70Hz, we'll use 60Hz aligned with frame rate)
     update_threshold: u64,
 
-    /// Font ROM data (256 characters × 14 rows × 1 byte)
-    font_rom: [u8; 256 * 14],
+    /// Font ROM data (256 characters × 16 rows × 1 byte)
+    font_rom: [u8; 256 * 16],
+
+    /// 8x8 font from the same ROM, used for character heights of 8 or less
+    font_8x8: [u8; 256 * 8],
+
+    /// 6845 CRTC register selected through port 0x3B4
+    crtc_index: u8,
+
+    /// 6845 CRTC registers
+    crtc: [u8; CRTC_REGISTERS],
 
     /// Dirty flag - set when VRAM is written
     dirty: bool,
+
+    /// Mode control register (port 0x3B8)
+    mode_control: u8,
+
+    /// Set when the guest writes a different mode control value
+    mode_changed: bool,
+
+    /// Set when the beam enters vertical retrace
+    vretrace_started: bool,
 }
 
 impl Mda {
@@ -39,13 +106,26 @@ impl Mda {
             vram[i * 2 + 1] = 0x07; // Attribute: white on black
         }
 
+        // The BIOS programs 80 columns of 14-scanline character cells
+        let mut crtc = [0; CRTC_REGISTERS];
+        crtc[CRTC_HORIZONTAL_DISPLAYED] = HORIZONTAL_DISPLAYED as u8;
+        crtc[CRTC_MAX_SCANLINE] = 13;
+
+        let mut font_8x8 = [0u8; 256 * 8];
+        font_8x8.copy_from_slice(&FONT_ROM_DATA[FONT_8X8_OFFSET..FONT_8X8_OFFSET + 256 * 8]);
+
         Self {
             vram,
             cycle_count: 0,
-            // 60 Hz refresh at 4.77 MHz ~= 79,500 cycles per frame
-            update_threshold: 79_500,
+            update_threshold: FRAME_CYCLES,
             font_rom: Self::load_font_rom(),
+            font_8x8,
+            crtc_index: 0,
+            crtc,
             dirty: false,
+            mode_control: 0,
+            mode_changed: false,
+            vretrace_started: false,
         }
     }
 
@@ -61,16 +141,16 @@ impl Mda {
     ///   - Bytes 0x0808-0x080F: Char 1, scan lines 8-15
     ///   - ... (256 chars × 8 bytes = 2KB)
     ///
-    /// Characters are 8×16 pixels. MDA uses only the first 14 scan lines (0-13).
-    fn load_font_rom() -> [u8; 256 * 14] {
-        // Include the actual MDA character ROM at compile time
-        const ROM_DATA: &[u8] = include_bytes!("../../roms/MDA_CHAR.bin");
-
-        let mut font = [0u8; 256 * 14];
+    /// Characters are 8×16 pixels. The stock MDA character height uses only
+    /// the first 14 scan lines (0-13).
+    ///
+    /// The CGA 8x8 font follows at 0x1800.
+    fn load_font_rom() -> [u8; 256 * 16] {
+        let mut font = [0u8; 256 * 16];
 
         // Extract from two-bank character-major format
         for char_idx in 0..256 {
-            for scan_line in 0..14 {
+            for scan_line in 0..16 {
                 let rom_offset = if scan_line < 8 {
                     // Bank 0: char_idx * 8 bytes + scan_line
                     char_idx * 8 + scan_line
@@ -80,9 +160,9 @@ impl Mda {
                 };
 
                 // Our font is organized: char 0's all scanlines, char 1's all scanlines, etc.
-                let font_offset = char_idx * 14 + scan_line;
+                let font_offset = char_idx * 16 + scan_line;
 
-                font[font_offset] = ROM_DATA[rom_offset];
+                font[font_offset] = FONT_ROM_DATA[rom_offset];
             }
         }
 
@@ -102,10 +182,108 @@ impl Mda {
         self.dirty = true;
     }
 
+    /// Return the adapter to its power-on state
+    ///
+    /// Video RAM is not cleared, just like system RAM on a warm reset.
+    pub fn reset(&mut self) {
+        self.cycle_count = 0;
+        self.dirty = true;
+        self.mode_control = 0;
+        self.mode_changed = false;
+        self.vretrace_started = false;
+    }
+
+    /// Describe the adapter registers without side effects
+    pub fn describe(&self) -> MdaState {
+        MdaState {
+            mode_control: self.mode_control,
+            crtc_index: self.crtc_index,
+            crtc: self.crtc,
+        }
+    }
+
+    /// Get the current mode control register value
+    pub fn mode_control(&self) -> u8 {
+        self.mode_control
+    }
+
+    /// Character cell height in scanlines, from the CRTC maximum scanline register
+    pub fn char_height(&self) -> usize {
+        (self.crtc[CRTC_MAX_SCANLINE] as usize & 0x1F) + 1
+    }
+
+    /// Number of text rows that fit on screen at the current character height
+    ///
+    /// 25 with the standard 14-scanline font, 43 with an 8x8 font.
+    pub fn text_rows(&self) -> usize {
+        DISPLAY_HEIGHT / self.char_height()
+    }
+
+    /// Number of text columns per row, from the CRTC horizontal displayed register
+    ///
+    /// Capped at the 80 columns that fit on the 720-pixel display.
+    pub fn text_columns(&self) -> usize {
+        (self.crtc[CRTC_HORIZONTAL_DISPLAYED] as usize).min(HORIZONTAL_DISPLAYED as usize)
+    }
+
+    /// Get the (character, attribute) of a text cell
+    ///
+    /// Rows are `text_columns()` cells apart in video RAM, as the CRTC
+    /// address counter lays them out.
+    pub fn cell(&self, row: usize, col: usize) -> (u8, u8) {
+        let offset = ((row * self.text_columns() + col) * 2) as u16;
+        (
+            self.read_vram(offset),
+            self.read_vram(offset.wrapping_add(1)),
+        )
+    }
+
+    /// Take a pending mode change, returning the new mode control value
+    ///
+    /// Returns None if the mode has not changed since the last call.
+    pub fn take_mode_change(&mut self) -> Option<u8> {
+        if self.mode_changed {
+            self.mode_changed = false;
+            Some(self.mode_control)
+        } else {
+            None
+        }
+    }
+
+    /// Take a pending start of vertical retrace
+    ///
+    /// Returns true once per frame, after the beam has finished the last
+    /// displayed scanline.
+    pub fn take_vertical_retrace(&mut self) -> bool {
+        core::mem::take(&mut self.vretrace_started)
+    }
+
+    /// Check whether the beam is in vertical retrace
+    pub fn in_vertical_retrace(&self) -> bool {
+        self.cycle_count % FRAME_CYCLES >= VRETRACE_START
+    }
+
+    /// Check whether the beam is in horizontal retrace
+    pub fn in_horizontal_retrace(&self) -> bool {
+        // Position within the current scanline, in units of 1/FRAME_CYCLES lines
+        let line_phase = (self.cycle_count % FRAME_CYCLES) * VERTICAL_TOTAL % FRAME_CYCLES;
+        line_phase * HORIZONTAL_TOTAL >= FRAME_CYCLES * HORIZONTAL_DISPLAYED
+    }
+
+    /// Number of vertical retraces begun by the time `cycle_count` cycles have run
+    fn retraces_by(cycle_count: u64) -> u64 {
+        (cycle_count + FRAME_CYCLES - VRETRACE_START) / FRAME_CYCLES
+    }
+
     /// Update based on CPU cycles
     pub fn tick(&mut self, cycles: u16, _pic: &mut crate::components::pic::Pic) {
+        let before = self.cycle_count;
         self.cycle_count += cycles as u64;
 
+        if Self::retraces_by(self.cycle_count) > Self::retraces_by(before) {
+            self.vretrace_started = true;
+        }
+
         // TODO: Implement periodic framebuffer regeneration
         // For now, just accumulate cycles
     }
@@ -118,11 +296,11 @@ impl Mda {
                 // Bit 0: Horizontal retrace (1 = in retrace period)
                 // Bit 3: Vertical retrace (1 = in vertical retrace)
                 //
-                // Toggle both bits to simulate retrace periods.
-                // Horizontal retrace happens ~31.5kHz, vertical ~50-70Hz.
-                // Use different bit shifts to get appropriate frequencies.
-                let hretrace = ((self.cycle_count >> 7) & 1) as u8; // ~37kHz at 4.77MHz
-                let vretrace = ((self.cycle_count >> 14) & 1) as u8; // ~290Hz at 4.77MHz
+                // The beam position is derived from the cycle count, with
+                // one frame every FRAME_CYCLES so retrace lines up with the
+                // emulator's own frames.
+                let hretrace = self.in_horizontal_retrace() as u8;
+                let vretrace = self.in_vertical_retrace() as u8;
                 hretrace | (vretrace << 3)
             }
             _ => {
@@ -135,20 +313,23 @@ impl Mda {
     /// Write to MDA I/O port
     pub fn write_u8(&mut self, port: u16, value: u8) {
         match port {
-            0x3B8 => {
+            0x3B8 if value != self.mode_control => {
                 // Mode control register
-                // Not implemented yet
-                let _ = value;
+                // Bit 0: high resolution, bit 3: video enable, bit 5: blink
+                self.mode_control = value;
+                self.mode_changed = true;
             }
             0x3B4 => {
                 // CRTC index register
-                // Not implemented yet
-                let _ = value;
+                self.crtc_index = value & 0x1F;
             }
             0x3B5 => {
-                // CRTC data register
-                // Not implemented yet
-                let _ = value;
+                // CRTC data register; only the character height affects
+                // rendering so far
+                if let Some(reg) = self.crtc.get_mut(self.crtc_index as usize) {
+                    *reg = value;
+                    self.dirty = true;
+                }
             }
             _ => {
                 // Other ports ignored
@@ -158,18 +339,41 @@ impl Mda {
 
     /// Render the text mode display to an RGBA framebuffer
     ///
-    /// Converts the 80x25 text cells into 720x350 pixels (9x14 per character)
+    /// Converts the text cells into 720x350 pixels. Characters are 9 pixels
+    /// wide and `char_height()` scanlines tall, so a 14-scanline font shows
+    /// 80x25 and an 8x8 font 80x43. Scanlines below the last full row are
+    /// blanked.
     pub fn render_to_framebuffer(&self, framebuffer: &mut [u8]) {
-        for row in 0..25 {
+        let rows = self.text_rows();
+        for row in 0..rows {
             for col in 0..80 {
-                let cell_idx = row * 80 + col;
-                let char_code = self.vram[cell_idx * 2];
-                let attribute = self.vram[cell_idx * 2 + 1];
+                // Video RAM is only 4KB, so rows past it wrap like the
+                // adapter's address counter does
+                let cell_offset = ((row * 80 + col) * 2) as u16;
+                let char_code = self.read_vram(cell_offset);
+                let attribute = self.read_vram(cell_offset + 1);
 
-                // Render 9x14 character to framebuffer
                 self.render_char(framebuffer, col, row, char_code, attribute);
             }
         }
+
+        let blank_start = rows * self.char_height() * 720 * 4;
+        for pixel in framebuffer[blank_start..DISPLAY_HEIGHT * 720 * 4].chunks_exact_mut(4) {
+            pixel.copy_from_slice(&[0x00, 0x00, 0x00, 0xFF]);
+        }
+    }
+
+    /// Get one scanline of a character for the given character height
+    ///
+    /// Heights up to 8 use the 8x8 font, taller ones the MDA font. Lines
+    /// beyond the font are blank.
+    fn glyph_line(&self, char_code: u8, scan_line: usize, height: usize) -> u8 {
+        let char_code = char_code as usize;
+        match (height <= 8, scan_line) {
+            (true, 0..=7) => self.font_8x8[char_code * 8 + scan_line],
+            (false, 0..=15) => self.font_rom[char_code * 16 + scan_line],
+            _ => 0,
+        }
     }
 
     /// Render a single character to the framebuffer
@@ -187,9 +391,10 @@ impl Mda {
         let bg_intensity = 0x00; // Black background
 
         // Render each scan line of the character
-        for scan_line in 0..14 {
-            let font_byte = self.font_rom[char_code as usize * 14 + scan_line];
-            let y = row * 14 + scan_line;
+        let height = self.char_height();
+        for scan_line in 0..height {
+            let font_byte = self.glyph_line(char_code, scan_line, height);
+            let y = row * height + scan_line;
 
             // Render each pixel (9 pixels wide, 8 from font + 1 blank)
             for bit in 0..9 {
@@ -210,3 +415,42 @@ impl Mda {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fill the screen with full blocks and return the last lit scanline
+    fn last_lit_scanline(mda: &mut Mda) -> usize {
+        for cell in 0..2048u16 {
+            mda.write_vram(cell * 2, 0xDB);
+            mda.write_vram(cell * 2 + 1, 0x07);
+        }
+        let mut framebuffer = vec![0u8; 720 * DISPLAY_HEIGHT * 4];
+        mda.render_to_framebuffer(&mut framebuffer);
+        (0..DISPLAY_HEIGHT)
+            .rev()
+            .find(|&y| framebuffer[y * 720 * 4] != 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_default_font_shows_25_rows() {
+        let mut mda = Mda::new();
+        assert_eq!(mda.char_height(), 14);
+        assert_eq!(mda.text_rows(), 25);
+        assert_eq!(last_lit_scanline(&mut mda), 349);
+    }
+
+    #[test]
+    fn test_8x8_font_shows_43_rows() {
+        let mut mda = Mda::new();
+        mda.write_u8(0x3B4, 9); // Maximum scanline
+        mda.write_u8(0x3B5, 7);
+
+        assert_eq!(mda.char_height(), 8);
+        assert_eq!(mda.text_rows(), 43);
+        // 43 rows of 8 scanlines, the remaining 6 scanlines blank
+        assert_eq!(last_lit_scanline(&mut mda), 343);
+    }
+}
@@ -1,10 +1,20 @@
 //! IBM PC peripheral components
 
+pub mod cga;
+pub mod cmos;
 pub mod dma;
+pub mod expanded_memory;
 pub mod fdc;
 pub mod floppy;
+pub mod hdc;
+pub mod hdd;
+pub mod ide;
 pub mod keyboard;
 pub mod mda;
 pub mod pic;
 pub mod pit;
+pub mod post;
 pub mod ppi;
+pub mod rtc;
+pub mod speaker;
+pub mod uart;
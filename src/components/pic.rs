@@ -3,8 +3,8 @@
 //! The IBM PC uses a single 8259 PIC in edge-triggered mode to manage
 //! hardware interrupts from peripherals.
 
-use crate::io::IoDevice;
-use std::ops::RangeInclusive;
+use crate::io::{DeviceState, IoDevice};
+use core::ops::RangeInclusive;
 
 /// PIC I/O ports
 const PIC_COMMAND_PORT: u16 = 0x20;
@@ -26,12 +26,26 @@ enum InitState {
     WaitIcw4,
 }
 
+/// Snapshot of the PIC registers for debugging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PicState {
+    /// Interrupt Request Register
+    pub irr: u8,
+    /// In-Service Register
+    pub isr: u8,
+    /// Interrupt Mask Register
+    pub imr: u8,
+    /// Base interrupt vector (IRQ0)
+    pub vector_offset: u8,
+}
+
 /// Intel 8259 PIC state
 ///
 /// The 8259 manages 8 IRQ lines (IRQ0-IRQ7) and converts them into
 /// interrupt vectors for the CPU. The original IBM PC uses edge-triggered
 /// mode where interrupts are triggered on the rising edge (low-to-high transition)
 /// of the IRQ line.
+#[derive(Clone)]
 pub struct Pic {
     /// Interrupt Mask Register (IMR) - bit set = IRQ masked
     imr: u8,
@@ -64,6 +78,12 @@ pub struct Pic {
     /// Read register select (from OCW3)
     /// false = read IRR, true = read ISR
     read_isr: bool,
+
+    /// Number of times each IRQ line has been acknowledged (statistics only)
+    ack_counts: [u32; 8],
+
+    /// Number of rising edges seen on each IRQ line (statistics only)
+    raise_counts: [u64; 8],
 }
 
 impl Pic {
@@ -82,6 +102,8 @@ impl Pic {
             icw1_flags: 0,
             auto_eoi: false,
             read_isr: false,
+            ack_counts: [0; 8],
+            raise_counts: [0; 8],
         }
     }
 
@@ -111,20 +133,32 @@ impl Pic {
         if !prev_level && new_level {
             // Latch the interrupt request in IRR
             self.irr |= bit;
+            self.raise_counts[irq as usize] += 1;
         }
     }
 
     /// Check if interrupt output line should be active
     ///
-    /// Returns true if there are any unmasked pending interrupts that
-    /// are not currently being serviced.
+    /// Returns true if there is an unmasked pending interrupt with higher
+    /// priority than every interrupt currently being serviced. A handler
+    /// that re-enables interrupts can therefore only be preempted by a
+    /// higher-priority IRQ until it sends its EOI.
     pub fn intr_out(&self) -> bool {
-        // Pending interrupts that aren't masked
+        self.deliverable() != 0
+    }
+
+    /// Unmasked pending requests that outrank everything in service
+    fn deliverable(&self) -> u8 {
         let pending = self.irr & !self.imr;
-        pending != 0
+        if self.isr == 0 {
+            return pending;
+        }
+        // Fixed priority: only IRQs below the highest in-service one
+        let in_service = self.isr.trailing_zeros();
+        pending & ((1u8 << in_service) - 1)
     }
 
-    /// Interrupt Acknowledge - return the interrupt vector
+    /// Interrupt acknowledge (INTA cycle) - return the interrupt vector
     ///
     /// Called by the CPU when it's ready to service an interrupt.
     /// Returns the interrupt vector number for the highest priority
@@ -134,10 +168,11 @@ impl Pic {
     ///
     /// This also:
     /// - Clears the interrupt from IRR
-    /// - Sets the corresponding bit in ISR (marking it as in-service)
-    pub fn inta(&mut self) -> u8 {
+    /// - Sets the corresponding bit in ISR (marking it as in-service),
+    ///   unless auto-EOI mode was selected in ICW4
+    pub fn acknowledge(&mut self) -> u8 {
         // Find highest priority unmasked pending interrupt
-        let pending = self.irr & !self.imr;
+        let pending = self.deliverable();
 
         if pending == 0 {
             // No pending interrupts - return spurious interrupt vector
@@ -154,12 +189,41 @@ impl Pic {
         self.irr &= !bit;
 
         // Set in ISR (interrupt now being serviced)
-        self.isr |= bit;
+        if !self.auto_eoi {
+            self.isr |= bit;
+        }
+
+        self.ack_counts[irq as usize] = self.ack_counts[irq as usize].wrapping_add(1);
 
         // Return interrupt vector
         self.vector_offset + irq
     }
 
+    /// Get the number of times each IRQ line has been acknowledged
+    ///
+    /// Indexed by IRQ number. Spurious acknowledges are not counted.
+    pub fn ack_counts(&self) -> [u32; 8] {
+        self.ack_counts
+    }
+
+    /// Get the number of times each IRQ line has been raised
+    ///
+    /// Indexed by IRQ number. Every rising edge counts, whether or not the
+    /// line is masked or the request is ever acknowledged.
+    pub fn raise_counts(&self) -> [u64; 8] {
+        self.raise_counts
+    }
+
+    /// Describe the PIC registers without side effects
+    pub fn describe(&self) -> PicState {
+        PicState {
+            irr: self.irr,
+            isr: self.isr,
+            imr: self.imr,
+            vector_offset: self.vector_offset,
+        }
+    }
+
     /// Get the IMR (Interrupt Mask Register)
     pub fn get_imr(&self) -> u8 {
         self.imr
@@ -216,7 +280,7 @@ impl IoDevice for Pic {
                 self.imr
             }
             _ => {
-                println!("[PIC] Unhandled read from port 0x{:04X}", port);
+                log_warn!("[PIC] Unhandled read from port 0x{:04X}", port);
                 0xFF
             }
         }
@@ -229,7 +293,7 @@ impl IoDevice for Pic {
                 if (value & 0x10) != 0 {
                     // ICW1 - start initialization sequence
                     #[cfg(debug_assertions)]
-                    println!(
+                    log_debug!(
                         "[PIC] ICW1: 0x{:02X} (IC4={}, SNGL={})",
                         value,
                         (value & 0x01) != 0,
@@ -249,7 +313,7 @@ impl IoDevice for Pic {
                     if (value & 0x02) != 0 {
                         self.read_isr = (value & 0x01) != 0;
                         #[cfg(debug_assertions)]
-                        println!(
+                        log_debug!(
                             "[PIC] OCW3: read {} on next command port read",
                             if self.read_isr { "ISR" } else { "IRR" }
                         );
@@ -268,11 +332,11 @@ impl IoDevice for Pic {
                             let irq = value & 0x07;
                             self.isr &= !(1 << irq);
                             #[cfg(debug_assertions)]
-                            println!("[PIC] Specific EOI for IRQ{}", irq);
+                            log_debug!("[PIC] Specific EOI for IRQ{}", irq);
                         }
                         _ => {
                             #[cfg(debug_assertions)]
-                            println!("[PIC] OCW2 command: 0x{:02X} (type {})", value, eoi_type);
+                            log_debug!("[PIC] OCW2 command: 0x{:02X} (type {})", value, eoi_type);
                         }
                     }
                 }
@@ -284,7 +348,7 @@ impl IoDevice for Pic {
                         // ICW2 - interrupt vector offset (upper 5 bits)
                         self.vector_offset = value & 0xF8;
                         #[cfg(debug_assertions)]
-                        println!("[PIC] ICW2: vector offset = 0x{:02X}", self.vector_offset);
+                        log_debug!("[PIC] ICW2: vector offset = 0x{:02X}", self.vector_offset);
 
                         // Check if we need ICW3 (cascade mode)
                         if (self.icw1_flags & 0x02) == 0 {
@@ -297,27 +361,27 @@ impl IoDevice for Pic {
                             // Single mode, no ICW4
                             self.init_state = InitState::Ready;
                             #[cfg(debug_assertions)]
-                            println!("[PIC] Initialization complete (no ICW4)");
+                            log_debug!("[PIC] Initialization complete (no ICW4)");
                         }
                     }
                     InitState::WaitIcw3 => {
                         // ICW3 - cascade configuration (not used in IBM PC)
                         #[cfg(debug_assertions)]
-                        println!("[PIC] ICW3: 0x{:02X} (cascade config)", value);
+                        log_debug!("[PIC] ICW3: 0x{:02X} (cascade config)", value);
 
                         if (self.icw1_flags & 0x01) != 0 {
                             self.init_state = InitState::WaitIcw4;
                         } else {
                             self.init_state = InitState::Ready;
                             #[cfg(debug_assertions)]
-                            println!("[PIC] Initialization complete (no ICW4)");
+                            log_debug!("[PIC] Initialization complete (no ICW4)");
                         }
                     }
                     InitState::WaitIcw4 => {
                         // ICW4 - mode configuration
                         self.auto_eoi = (value & 0x02) != 0;
                         #[cfg(debug_assertions)]
-                        println!(
+                        log_debug!(
                             "[PIC] ICW4: 0x{:02X} (8086 mode={}, AEOI={})",
                             value,
                             (value & 0x01) != 0,
@@ -326,13 +390,13 @@ impl IoDevice for Pic {
 
                         self.init_state = InitState::Ready;
                         #[cfg(debug_assertions)]
-                        println!("[PIC] Initialization complete");
+                        log_debug!("[PIC] Initialization complete");
                     }
                     InitState::Ready => {
                         // Normal operation - write to IMR (OCW1)
                         #[cfg(debug_assertions)]
                         if self.imr != value {
-                            println!(
+                            log_debug!(
                                 "[PIC] IMR change: 0x{:02X} -> 0x{:02X} (IRQ6 {})",
                                 self.imr,
                                 value,
@@ -348,13 +412,23 @@ impl IoDevice for Pic {
                 }
             }
             _ => {
-                println!(
+                log_warn!(
                     "[PIC] Unhandled write to port 0x{:04X} = 0x{:02X}",
-                    port, value
+                    port,
+                    value
                 );
             }
         }
     }
+
+    fn reset(&mut self) {
+        // The vector offset is board wiring, not programmable power-on state
+        *self = Self::new(self.vector_offset);
+    }
+
+    fn snapshot(&self) -> Option<DeviceState> {
+        Some(DeviceState::Pic(self.describe()))
+    }
 }
 
 #[cfg(test)]
@@ -434,7 +508,7 @@ mod tests {
     }
 
     #[test]
-    fn test_inta_returns_vector() {
+    fn test_acknowledge_returns_vector() {
         let mut pic = Pic::new(0x08);
         pic.set_imr(0x00);
 
@@ -445,7 +519,7 @@ mod tests {
         assert!(pic.intr_out());
 
         // Acknowledge interrupt
-        let vector = pic.inta();
+        let vector = pic.acknowledge();
         assert_eq!(vector, 0x08 + 3); // 0x0B
 
         // Should be moved from IRR to ISR
@@ -468,21 +542,53 @@ mod tests {
         pic.set_irq_level(7, true);
 
         // IRQ2 should be serviced first (highest priority)
-        let vector = pic.inta();
+        let vector = pic.acknowledge();
         assert_eq!(vector, 0x08 + 2);
+        pic.eoi();
 
         // Then IRQ5
-        let vector = pic.inta();
+        let vector = pic.acknowledge();
         assert_eq!(vector, 0x08 + 5);
+        pic.eoi();
 
         // Then IRQ7
-        let vector = pic.inta();
+        let vector = pic.acknowledge();
         assert_eq!(vector, 0x08 + 7);
+        pic.eoi();
 
         // No more interrupts
         assert!(!pic.intr_out());
     }
 
+    #[test]
+    fn test_in_service_blocks_equal_and_lower_priority() {
+        let mut pic = Pic::new(0x08);
+        pic.set_imr(0x00);
+
+        // IRQ3 in service
+        pic.set_irq_level(3, true);
+        assert_eq!(pic.acknowledge(), 0x08 + 3);
+
+        // Lower priority (IRQ4) and the same line again wait for the EOI
+        pic.set_irq_level(4, true);
+        pic.set_irq_level(3, false);
+        pic.set_irq_level(3, true);
+        assert!(!pic.intr_out());
+
+        // Higher priority (IRQ1) preempts
+        pic.set_irq_level(1, true);
+        assert!(pic.intr_out());
+        assert_eq!(pic.acknowledge(), 0x08 + 1);
+        assert_eq!(pic.get_isr(), 0x0A);
+
+        // Unwinding: EOI for IRQ1, then IRQ3, then the rest are delivered
+        pic.eoi();
+        assert!(!pic.intr_out());
+        pic.eoi();
+        assert!(pic.intr_out());
+        assert_eq!(pic.acknowledge(), 0x08 + 3);
+    }
+
     #[test]
     fn test_eoi() {
         let mut pic = Pic::new(0x08);
@@ -491,7 +597,7 @@ mod tests {
         // Trigger and acknowledge IRQ1
         pic.set_irq_level(1, false);
         pic.set_irq_level(1, true);
-        let _vector = pic.inta();
+        let _vector = pic.acknowledge();
 
         assert_eq!(pic.get_isr(), 0x02); // Bit 1 set
 
@@ -504,7 +610,7 @@ mod tests {
     fn test_spurious_interrupt() {
         let mut pic = Pic::new(0x08);
         // No pending interrupts
-        let vector = pic.inta();
+        let vector = pic.acknowledge();
         // Should return spurious vector (offset + 7)
         assert_eq!(vector, 0x08 + 7);
     }
@@ -539,7 +645,7 @@ mod tests {
         // Trigger and acknowledge IRQ2
         pic.set_irq_level(2, false);
         pic.set_irq_level(2, true);
-        let _vector = pic.inta();
+        let _vector = pic.acknowledge();
 
         assert_eq!(pic.get_isr(), 0x04); // Bit 2 set
 
@@ -583,6 +689,25 @@ mod tests {
         assert!(pic.intr_out()); // Should now signal interrupt
     }
 
+    #[test]
+    fn test_auto_eoi_leaves_isr_clear() {
+        let mut pic = Pic::new(0x00);
+
+        // ICW1: single mode, ICW4 needed; ICW2: 0x08; ICW4: 8086 mode, auto EOI
+        pic.write_u8(PIC_COMMAND_PORT, 0x13);
+        pic.write_u8(PIC_DATA_PORT, 0x08);
+        pic.write_u8(PIC_DATA_PORT, 0x0B);
+        pic.write_u8(PIC_DATA_PORT, 0x00);
+
+        pic.set_irq_level(4, true);
+        assert_eq!(pic.acknowledge(), 0x08 + 4);
+        assert_eq!(pic.get_isr(), 0);
+
+        // Nothing in service, so a lower-priority request is delivered at once
+        pic.set_irq_level(6, true);
+        assert!(pic.intr_out());
+    }
+
     #[test]
     fn test_icw_initialization_sequence() {
         let mut pic = Pic::new(0x00); // Initial offset doesn't matter
@@ -610,7 +735,7 @@ mod tests {
         // Verify interrupt delivery with new vector offset
         pic.set_irq_level(2, false);
         pic.set_irq_level(2, true);
-        let vector = pic.inta();
+        let vector = pic.acknowledge();
         assert_eq!(vector, 0x08 + 2); // Should be 0x0A
     }
 
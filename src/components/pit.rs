@@ -7,10 +7,16 @@
 //! - Control Word (port 0x43): Write-only configuration register
 //!
 //! Input clock: 1.193182 MHz (14.31818 MHz crystal / 12)
+//!
+//! The PC and XT use the 8253. The AT's 8254 adds the read-back command,
+//! which latches the count and/or a status byte for several counters at
+//! once; software can tell the two apart by whether it works.
 
 use crate::components::pic::Pic;
-use crate::io::IoDevice;
-use std::ops::RangeInclusive;
+use crate::io::{DeviceState, IoDevice};
+use alloc::boxed::Box;
+use core::any::Any;
+use core::ops::RangeInclusive;
 
 /// PIT I/O port constants
 const PIT_COUNTER_0: u16 = 0x40;
@@ -20,11 +26,21 @@ const PIT_CONTROL: u16 = 0x43;
 
 /// PIT input clock frequency in Hz
 /// The original IBM PC uses a 14.31818 MHz crystal divided by 12
-const PIT_CLOCK_HZ: f64 = 1_193_182.0;
+pub(crate) const PIT_CLOCK_HZ: f64 = 1_193_182.0;
 
 /// How many CPU cycles (at 4.77 MHz) per PIT tick
 /// 4.77 MHz / 1.193182 MHz = ~4 cycles per PIT tick
-const CPU_CYCLES_PER_PIT_TICK: u16 = 4;
+const CPU_CYCLES_PER_PIT_TICK: u32 = 4;
+
+/// Which timer part is fitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PitModel {
+    /// Intel 8253, as in the IBM PC and XT
+    #[default]
+    I8253,
+    /// Intel 8254, as in the IBM AT, with the read-back command
+    I8254,
+}
 
 /// Counter access modes
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -46,7 +62,33 @@ enum CounterMode {
     Mode5, // Hardware triggered strobe
 }
 
+/// Snapshot of a single PIT counter for debugging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PitChannelState {
+    /// Current count
+    pub count: u16,
+    /// Reload value (0 means 65536)
+    pub reload: u16,
+    /// Operating mode (0-5)
+    pub mode: u8,
+    /// Latched count waiting to be read, if any
+    pub latch: Option<u16>,
+    /// Output pin level
+    pub output: bool,
+    /// Gate input level
+    pub gate: bool,
+    /// True until a count has been loaded after the last control word
+    pub null_count: bool,
+}
+
+/// Snapshot of all three PIT counters for debugging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PitState {
+    pub channels: [PitChannelState; 3],
+}
+
 /// State of a single PIT counter
+#[derive(Clone)]
 struct Counter {
     /// Current count value (decrements with each tick)
     count: u16,
@@ -77,8 +119,20 @@ struct Counter {
     /// Gate input state (for modes that use it)
     gate: bool,
 
+    /// Latched gate rising edge, consumed on the next PIT clock
+    ///
+    /// The edge is latched when the gate is raised so that a short pulse which
+    /// rises and falls between two tick() calls is still observed.
+    gate_triggered: bool,
+
+    /// Hardware-triggered modes (1 and 5) only count after a gate trigger
+    armed: bool,
+
     /// Null count flag (true if count hasn't been loaded yet)
     null_count: bool,
+
+    /// Status byte latched by a read-back command, returned before the count
+    status_latch: Option<u8>,
 }
 
 impl Counter {
@@ -93,7 +147,64 @@ impl Counter {
             byte_toggle: false,
             output: false,
             gate: true, // Counter 0 and 1 gate always high
+            gate_triggered: false,
+            armed: false,
             null_count: true,
+            status_latch: None,
+        }
+    }
+
+    /// Build the read-back status byte
+    ///
+    /// Bit 7: output pin, bit 6: null count, bits 5-4: access mode,
+    /// bits 3-1: operating mode, bit 0: BCD.
+    fn status(&self) -> u8 {
+        let access = match self.access_mode {
+            AccessMode::LatchCount => 0b00,
+            AccessMode::LowByteOnly => 0b01,
+            AccessMode::HighByteOnly => 0b10,
+            AccessMode::LowThenHigh => 0b11,
+        };
+        ((self.output as u8) << 7)
+            | ((self.null_count as u8) << 6)
+            | (access << 4)
+            | ((self.mode as u8) << 1)
+            | self.bcd as u8
+    }
+
+    /// Describe the counter without side effects
+    fn describe(&self) -> PitChannelState {
+        PitChannelState {
+            count: self.count,
+            reload: self.reload_value,
+            mode: self.mode as u8,
+            latch: self.latch,
+            output: self.output,
+            gate: self.gate,
+            null_count: self.null_count,
+        }
+    }
+
+    /// Set the gate input level, latching rising edges
+    fn set_gate(&mut self, level: bool) {
+        if level && !self.gate {
+            self.gate_triggered = true;
+        }
+        self.gate = level;
+    }
+
+    /// Apply mode-specific output state after a new count has been loaded
+    fn count_loaded(&mut self) {
+        self.null_count = false;
+        match self.mode {
+            // In Mode 0, output goes LOW when new count is loaded
+            CounterMode::Mode0 => self.output = false,
+            // Hardware-triggered modes wait (output HIGH) for a gate trigger
+            CounterMode::Mode1 | CounterMode::Mode5 => {
+                self.output = true;
+                self.armed = false;
+            }
+            _ => {}
         }
     }
 
@@ -105,22 +216,14 @@ impl Counter {
                 let count = if value == 0 { 0x100 } else { value as u16 };
                 self.reload_value = count;
                 self.count = count;
-                self.null_count = false;
-                // In Mode 0, output goes LOW when new count is loaded
-                if self.mode == CounterMode::Mode0 {
-                    self.output = false;
-                }
+                self.count_loaded();
             }
             AccessMode::HighByteOnly => {
                 // For 8-bit modes, 0 means 256 (0x100)
                 let count = if value == 0 { 0x100 } else { value as u16 };
                 self.reload_value = count;
                 self.count = count;
-                self.null_count = false;
-                // In Mode 0, output goes LOW when new count is loaded
-                if self.mode == CounterMode::Mode0 {
-                    self.output = false;
-                }
+                self.count_loaded();
             }
             AccessMode::LowThenHigh => {
                 if !self.byte_toggle {
@@ -134,11 +237,7 @@ impl Counter {
                     // The tick() method will handle reload_value == 0 specially
                     self.count = self.reload_value;
                     self.byte_toggle = false;
-                    self.null_count = false;
-                    // In Mode 0, output goes LOW when new count is loaded
-                    if self.mode == CounterMode::Mode0 {
-                        self.output = false;
-                    }
+                    self.count_loaded();
                 }
             }
             AccessMode::LatchCount => {
@@ -149,6 +248,10 @@ impl Counter {
 
     /// Read current count value (handles both byte modes and latching)
     fn read_count(&mut self) -> u8 {
+        if let Some(status) = self.status_latch.take() {
+            return status;
+        }
+
         let count_to_read = self.latch.unwrap_or(self.count);
 
         match self.access_mode {
@@ -176,12 +279,49 @@ impl Counter {
         }
     }
 
-    /// Decrement counter (returns true if interrupt should be generated)
+    /// Advance the counter by one PIT clock
+    /// Returns true if interrupt should be generated
     fn tick(&mut self) -> bool {
         if self.null_count {
             return false; // Counter not initialized
         }
 
+        let triggered = core::mem::take(&mut self.gate_triggered);
+
+        match self.mode {
+            CounterMode::Mode1 | CounterMode::Mode5 => {
+                // Hardware-triggered: a gate rising edge (re)loads the count,
+                // and counting continues regardless of the gate level
+                if triggered {
+                    self.count = self.reload_value;
+                    self.armed = true;
+                    if self.mode == CounterMode::Mode1 {
+                        self.output = false;
+                    }
+                    return false;
+                }
+                if !self.armed {
+                    return false;
+                }
+            }
+            CounterMode::Mode2 | CounterMode::Mode3 => {
+                // A gate rising edge restarts the count from the reload value
+                if triggered {
+                    self.count = self.reload_value;
+                    return false;
+                }
+                if !self.gate {
+                    return false;
+                }
+            }
+            CounterMode::Mode0 | CounterMode::Mode4 => {
+                // Gate low suspends counting
+                if !self.gate {
+                    return false;
+                }
+            }
+        }
+
         // Handle count of 0 specially - it represents 65536, so wrap to 0xFFFF
         // This happens either on initial load with count=0, or after reload
         if self.count == 0 {
@@ -206,6 +346,19 @@ impl Counter {
                     // Output already HIGH, no interrupt
                     false
                 }
+                CounterMode::Mode1 => {
+                    // Mode 1: Hardware Retriggerable One-Shot
+                    // Output returns HIGH at terminal count and the counter
+                    // waits for the next gate trigger
+                    self.armed = false;
+                    self.output = true;
+                    true
+                }
+                CounterMode::Mode5 => {
+                    // Mode 5: strobe at terminal count, then wait for a trigger
+                    self.armed = false;
+                    true
+                }
                 _ => {
                     // Other modes: reload and fire interrupt
                     // Note: reload_value of 0 means 65536, so we set count to 0
@@ -221,26 +374,41 @@ impl Counter {
 }
 
 /// Programmable Interval Timer
+#[derive(Clone)]
 pub struct Pit {
     /// The three counters
     counters: [Counter; 3],
 
     /// Accumulated CPU cycles (fractional tracking for PIT clock conversion)
-    cycle_accumulator: u16,
+    cycle_accumulator: u32,
 
     /// Track if counter 0 should raise IRQ0
     irq0_pending: bool,
+
+    /// Part being emulated
+    model: PitModel,
 }
 
 impl Pit {
     pub fn new() -> Self {
+        Self::with_model(PitModel::default())
+    }
+
+    /// Create a PIT of the given model
+    pub fn with_model(model: PitModel) -> Self {
         Self {
             counters: [Counter::new(), Counter::new(), Counter::new()],
             cycle_accumulator: 0,
             irq0_pending: false,
+            model,
         }
     }
 
+    /// Get the part being emulated
+    pub fn model(&self) -> PitModel {
+        self.model
+    }
+
     /// Parse and execute control word
     fn write_control(&mut self, value: u8) {
         let counter_select = (value >> 6) & 0x03;
@@ -248,9 +416,12 @@ impl Pit {
         let mode_bits = (value >> 1) & 0x07;
         let bcd = (value & 0x01) != 0;
 
-        // Read-back command (not implementing for minimal version)
         if counter_select == 0b11 {
-            unimplemented!("PIT read-back command not supported");
+            // The 8253 has no read-back command and ignores the write
+            if self.model == PitModel::I8254 {
+                self.read_back(value);
+            }
+            return;
         }
 
         // Check for BCD mode
@@ -293,13 +464,71 @@ impl Pit {
         counter.null_count = true; // Wait for count to be loaded
     }
 
+    /// Execute an 8254 read-back command
+    ///
+    /// Bits 3-1 select counters; bit 5 clear latches their counts and bit 4
+    /// clear latches their status. As with the latch command, a value that
+    /// is already latched and not yet read is kept.
+    fn read_back(&mut self, value: u8) {
+        let latch_count = value & 0x20 == 0;
+        let latch_status = value & 0x10 == 0;
+        for (i, counter) in self.counters.iter_mut().enumerate() {
+            if value & (0x02 << i) == 0 {
+                continue;
+            }
+            if latch_count && counter.latch.is_none() {
+                counter.latch = Some(counter.count);
+            }
+            if latch_status && counter.status_latch.is_none() {
+                counter.status_latch = Some(counter.status());
+            }
+        }
+    }
+
+    /// Set the gate input of a counter
+    ///
+    /// On the IBM PC, counters 0 and 1 have their gates tied high, and the
+    /// gate of counter 2 is driven by bit 0 of PPI port B. Rising edges are
+    /// latched, so a pulse shorter than the interval between tick() calls is
+    /// still seen by the counter on its next clock.
+    pub fn set_gate(&mut self, counter: usize, level: bool) {
+        self.counters[counter].set_gate(level);
+    }
+
+    /// Describe all counters without side effects
+    ///
+    /// Unlike reading the counter ports, this does not consume latches or
+    /// flip the low/high byte toggle.
+    pub fn describe(&self) -> PitState {
+        PitState {
+            channels: [
+                self.counters[0].describe(),
+                self.counters[1].describe(),
+                self.counters[2].describe(),
+            ],
+        }
+    }
+
+    /// Describe one counter without side effects
+    pub fn channel(&self, counter: usize) -> PitChannelState {
+        self.counters[counter].describe()
+    }
+
+    /// Get the output pin state of a counter
+    pub fn output(&self, counter: usize) -> bool {
+        self.counters[counter].output
+    }
+
     /// Update PIT state based on CPU cycles
-    /// Returns true if IRQ0 should be raised
-    fn tick_internal(&mut self, cpu_cycles: u16) -> bool {
+    ///
+    /// The counters are stepped one PIT clock at a time, so one-shots and gate
+    /// edges that start and finish within a large batch of cycles are still
+    /// observed. Returns the number of IRQ0 events that occurred in the batch.
+    fn tick_internal(&mut self, cpu_cycles: u16) -> u32 {
         // Accumulate CPU cycles and convert to PIT ticks
-        self.cycle_accumulator += cpu_cycles;
+        self.cycle_accumulator += cpu_cycles as u32;
 
-        let mut irq0_triggered = false;
+        let mut irq0_events = 0;
 
         // Process accumulated PIT ticks
         while self.cycle_accumulator >= CPU_CYCLES_PER_PIT_TICK {
@@ -310,7 +539,7 @@ impl Pit {
                 // Counter 0 wrapped - raise IRQ0
                 // In mode 2 (rate generator) or mode 3 (square wave),
                 // output pulse occurs on reload
-                irq0_triggered = true;
+                irq0_events += 1;
             }
 
             // Tick counter 1 (DRAM refresh) - we don't care about output
@@ -320,7 +549,7 @@ impl Pit {
             self.counters[2].tick();
         }
 
-        irq0_triggered
+        irq0_events
     }
 }
 
@@ -353,8 +582,13 @@ impl IoDevice for Pit {
     }
 
     fn tick(&mut self, cycles: u16, pic: &mut Pic) {
-        if self.tick_internal(cycles) {
+        if self.tick_internal(cycles) > 0 {
             // Counter 0 triggered - raise IRQ0
+            // If the line is still high from the previous batch, drop it first
+            // so the PIC sees a fresh rising edge
+            if self.irq0_pending {
+                pic.set_irq_level(0, false);
+            }
             pic.set_irq_level(0, true);
             self.irq0_pending = true;
         } else if self.irq0_pending {
@@ -364,6 +598,24 @@ impl IoDevice for Pit {
             self.irq0_pending = false;
         }
     }
+
+    fn reset(&mut self) {
+        *self = Self::with_model(self.model);
+    }
+
+    fn snapshot(&self) -> Option<DeviceState> {
+        Some(DeviceState::Pit(self.describe()))
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) {
+        if let Some(state) = state.downcast_ref::<Self>() {
+            *self = state.clone();
+        }
+    }
 }
 
 #[cfg(test)]
@@ -544,4 +796,136 @@ mod tests {
         assert!(pit.counters[0].tick()); // 1 -> 0, interrupt fires!
         assert!(pit.counters[0].output); // Output HIGH again
     }
+
+    #[test]
+    fn test_mode0_expires_within_large_batch() {
+        let mut pit = Pit::new();
+        pit.write_control(0b00010000); // Counter 0, low byte only, Mode 0
+        pit.write_u8(PIT_COUNTER_0, 0x0A);
+
+        // A single batch covering far more than 10 PIT clocks
+        assert_eq!(pit.tick_internal(60_000), 1);
+        assert!(pit.counters[0].output);
+
+        // Another large batch must not produce a second transition
+        assert_eq!(pit.tick_internal(60_000), 0);
+    }
+
+    #[test]
+    fn test_mode1_short_gate_pulse_within_batch() {
+        let mut pit = Pit::new();
+        pit.write_control(0b00010010); // Counter 0, low byte only, Mode 1
+        pit.write_u8(PIT_COUNTER_0, 0x08);
+        assert!(pit.counters[0].output);
+
+        // Gate pulse that rises and falls before the PIT is ticked
+        pit.set_gate(0, false);
+        pit.set_gate(0, true);
+        pit.set_gate(0, false);
+
+        // One-shot triggers, counts down and returns HIGH exactly once
+        assert_eq!(pit.tick_internal(40_000), 1);
+        assert!(pit.counters[0].output);
+        assert_eq!(pit.tick_internal(40_000), 0);
+    }
+
+    #[test]
+    fn test_mode1_waits_for_gate_trigger() {
+        let mut pit = Pit::new();
+        pit.write_control(0b00010010); // Counter 0, low byte only, Mode 1
+        pit.write_u8(PIT_COUNTER_0, 0x08);
+
+        // No gate edge: counter never starts
+        assert_eq!(pit.tick_internal(1_000), 0);
+        assert_eq!(pit.counters[0].count, 8);
+    }
+
+    #[test]
+    fn test_gate_low_suspends_mode2() {
+        let mut pit = Pit::new();
+        pit.write_control(0b00110100); // Counter 0, low+high, mode 2
+        pit.write_u8(PIT_COUNTER_0, 0x04);
+        pit.write_u8(PIT_COUNTER_0, 0x00);
+
+        pit.set_gate(0, false);
+        assert_eq!(pit.tick_internal(64), 0);
+        assert_eq!(pit.counters[0].count, 4);
+    }
+
+    #[test]
+    fn test_irq0_retriggers_across_batches() {
+        let mut pit = Pit::new();
+        let mut pic = Pic::new(0x08);
+        pic.set_imr(0x00);
+
+        pit.write_control(0b00110100);
+        pit.write_u8(PIT_COUNTER_0, 0x04);
+        pit.write_u8(PIT_COUNTER_0, 0x00);
+
+        // First batch raises IRQ0, acknowledge and EOI it
+        pit.tick(16, &mut pic);
+        assert_eq!(pic.acknowledge(), 0x08);
+        pic.eoi();
+
+        // Next batch also reaches terminal count: a fresh edge must be seen
+        pit.tick(16, &mut pic);
+        assert!(pic.intr_out());
+    }
+
+    #[test]
+    fn test_describe_does_not_disturb_read_sequence() {
+        let mut pit = Pit::new();
+        pit.write_control(0b00110100); // Counter 0, low+high, mode 2
+        pit.write_u8(PIT_COUNTER_0, 0x34);
+        pit.write_u8(PIT_COUNTER_0, 0x12);
+
+        // Latch and read only the low byte
+        pit.write_control(0b00000000);
+        assert_eq!(pit.read_u8(PIT_COUNTER_0), 0x34);
+
+        let state = pit.describe();
+        assert_eq!(state.channels[0].reload, 0x1234);
+        assert_eq!(state.channels[0].mode, 2);
+        assert_eq!(state.channels[0].latch, Some(0x1234));
+
+        // The high byte of the latched count is still next
+        assert_eq!(pit.read_u8(PIT_COUNTER_0), 0x12);
+        assert_eq!(pit.describe().channels[0].latch, None);
+    }
+
+    #[test]
+    fn test_read_back_only_on_8254() {
+        for model in [PitModel::I8253, PitModel::I8254] {
+            let mut pit = Pit::with_model(model);
+            pit.write_control(0b10110110); // Counter 2, low+high, mode 3
+            pit.write_u8(PIT_COUNTER_2, 0x34);
+            pit.write_u8(PIT_COUNTER_2, 0x12);
+
+            // Read-back: status only, counter 2
+            pit.write_u8(PIT_CONTROL, 0b11101000);
+
+            if model == PitModel::I8254 {
+                // Output low, count loaded, low+high, mode 3, binary
+                assert_eq!(pit.read_u8(PIT_COUNTER_2), 0b0011_0110);
+            }
+            assert_eq!(pit.read_u8(PIT_COUNTER_2), 0x34);
+            assert_eq!(pit.read_u8(PIT_COUNTER_2), 0x12);
+        }
+    }
+
+    #[test]
+    fn test_read_back_status_then_count() {
+        let mut pit = Pit::with_model(PitModel::I8254);
+        pit.write_control(0b00110100); // Counter 0, low+high, mode 2
+        pit.write_u8(PIT_COUNTER_0, 0x00);
+        pit.write_u8(PIT_COUNTER_0, 0x10);
+
+        // Latch count and status of counter 0, then let it run on
+        pit.write_u8(PIT_CONTROL, 0b11000010);
+        pit.tick_internal(40);
+
+        assert_eq!(pit.read_u8(PIT_COUNTER_0), 0b0011_0100);
+        assert_eq!(pit.read_u8(PIT_COUNTER_0), 0x00);
+        assert_eq!(pit.read_u8(PIT_COUNTER_0), 0x10);
+    }
 }
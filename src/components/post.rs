@@ -0,0 +1,92 @@
+//! POST code port (0x80)
+//!
+//! BIOSes write a progress byte to port 0x80 before each stage of the
+//! power-on self test. A diagnostic card shows the last value on a pair of
+//! seven-segment displays, so when the machine hangs the display names the
+//! stage that never finished. Nothing ever reads the port back.
+
+use crate::io::IoDevice;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use core::ops::RangeInclusive;
+
+/// Diagnostic port POST codes are written to
+pub const POST_CODE_PORT: u16 = 0x80;
+
+/// Maximum number of codes kept in the history
+///
+/// Older codes are dropped first, so a BIOS stuck in a retry loop can't
+/// grow the history without bound.
+pub const POST_HISTORY_LEN: usize = 1024;
+
+/// Callback invoked with each POST code as it is written
+pub type PostCodeSink = Box<dyn FnMut(u8)>;
+
+/// POST diagnostic card
+pub struct PostCard {
+    /// Codes in the order they were written, oldest first
+    history: VecDeque<u8>,
+
+    /// Host callback for live output
+    sink: Option<PostCodeSink>,
+}
+
+impl PostCard {
+    /// Create a POST card with an empty history and no sink
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::new(),
+            sink: None,
+        }
+    }
+
+    /// Set or clear the callback invoked for each code written
+    pub fn set_sink(&mut self, sink: Option<PostCodeSink>) {
+        self.sink = sink;
+    }
+
+    /// Get the most recently written code
+    pub fn last(&self) -> Option<u8> {
+        self.history.back().copied()
+    }
+
+    /// Get the codes written so far, oldest first
+    pub fn history(&self) -> &VecDeque<u8> {
+        &self.history
+    }
+
+    /// Forget all codes written so far
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+}
+
+impl Default for PostCard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IoDevice for PostCard {
+    fn read_u8(&mut self, _port: u16) -> u8 {
+        // Write-only port
+        0xFF
+    }
+
+    fn write_u8(&mut self, _port: u16, value: u8) {
+        if self.history.len() == POST_HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(value);
+        if let Some(sink) = self.sink.as_mut() {
+            sink(value);
+        }
+    }
+
+    fn port_range(&self) -> RangeInclusive<u16> {
+        POST_CODE_PORT..=POST_CODE_PORT
+    }
+
+    // The history deliberately survives reset: a BIOS that reboots itself
+    // part way through POST should leave a trail of both attempts.
+}
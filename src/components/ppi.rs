@@ -5,12 +5,13 @@
 //! - Port 0x61 (Port B): System control (speaker, keyboard clock, etc.)
 //! - Port 0x62 (Port C): System status bits
 
-use crate::components::keyboard::Keyboard;
+use crate::components::keyboard::{Keyboard, ScancodeQueue};
 use crate::components::pic::Pic;
-use crate::io::IoDevice;
-use std::collections::VecDeque;
-use std::ops::RangeInclusive;
-use std::sync::{Arc, RwLock};
+use crate::components::pit::Pit;
+use crate::io::{DeviceHandle, DeviceState, IoDevice};
+use alloc::boxed::Box;
+use core::any::Any;
+use core::ops::RangeInclusive;
 
 /// PPI I/O ports
 const PPI_PORT_A: u16 = 0x60; // Data port (DIP switches or keyboard)
@@ -42,10 +43,25 @@ enum KeyboardResetState {
 /// At 4.77 MHz, 100 cycles ≈ 21 microseconds.
 const KEYBOARD_RESET_DELAY_CYCLES: u32 = 100;
 
+/// Keyboard command: reset and run self-test
+const KEYBOARD_CMD_RESET: u8 = 0xFF;
+
+/// Snapshot of the PPI for debugging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PpiState {
+    /// Last value written to Port B (system control)
+    pub port_b: u8,
+    /// DIP switch configuration
+    pub dip_switches: u8,
+    /// Scancode latched for Port A, if any
+    pub latched_scancode: Option<u8>,
+}
+
 /// Intel 8255 PPI for IBM PC
 ///
 /// Handles keyboard data, DIP switches, and system control ports.
 /// Owns the Keyboard scancode buffer internally.
+#[derive(Clone)]
 pub struct Ppi {
     /// Keyboard scancode buffer (owned by PPI)
     keyboard: Keyboard,
@@ -68,11 +84,14 @@ pub struct Ppi {
     /// Cycles remaining before keyboard reset completes and 0xAA is sent
     /// When > 0, keyboard is performing BAT (Basic Assurance Test)
     reset_delay_cycles: u32,
+
+    /// PIT whose counter 2 gate is driven by bit 0 of Port B
+    timer: Option<DeviceHandle<Pit>>,
 }
 
 impl Ppi {
     /// Create a new PPI with default DIP switch configuration
-    pub fn new(scancode_queue: Arc<RwLock<VecDeque<u8>>>) -> Self {
+    pub fn new(scancode_queue: ScancodeQueue) -> Self {
         Self {
             keyboard: Keyboard::new(scancode_queue),
             latched_scancode: None,
@@ -81,11 +100,12 @@ impl Ppi {
             dip_switches: DIP_SWITCHES,
             reset_state: KeyboardResetState::Idle,
             reset_delay_cycles: 0,
+            timer: None,
         }
     }
 
     /// Create a new PPI with custom DIP switch configuration
-    pub fn with_dip_switches(scancode_queue: Arc<RwLock<VecDeque<u8>>>, dip_switches: u8) -> Self {
+    pub fn with_dip_switches(scancode_queue: ScancodeQueue, dip_switches: u8) -> Self {
         Self {
             keyboard: Keyboard::new(scancode_queue),
             latched_scancode: None,
@@ -94,11 +114,36 @@ impl Ppi {
             dip_switches,
             reset_state: KeyboardResetState::Idle,
             reset_delay_cycles: 0,
+            timer: None,
+        }
+    }
+
+    /// Describe the PPI without side effects
+    ///
+    /// Unlike reading Port A, this does not consume the latched scancode.
+    pub fn describe(&self) -> PpiState {
+        PpiState {
+            port_b: self.port_b_state,
+            dip_switches: self.dip_switches,
+            latched_scancode: self.latched_scancode,
         }
     }
 
+    /// Drive the gate of counter 2 of `pit` from bit 0 of Port B
+    ///
+    /// The gate is set to the current Port B value right away.
+    pub fn connect_timer(&mut self, pit: DeviceHandle<Pit>) {
+        pit.borrow_mut().set_gate(2, self.port_b_state & 0x01 != 0);
+        self.timer = Some(pit);
+    }
+
+    /// Get the last value written to port B (0x61)
+    pub fn port_b(&self) -> u8 {
+        self.port_b_state
+    }
+
     /// Get the keyboard scancode queue for GUI integration
-    pub fn scancode_queue(&self) -> Arc<RwLock<VecDeque<u8>>> {
+    pub fn scancode_queue(&self) -> ScancodeQueue {
         self.keyboard.scancode_queue()
     }
 }
@@ -132,10 +177,11 @@ impl IoDevice for Ppi {
             }
 
             PPI_PORT_B => {
-                // Read back Port B state
-                // Real hardware has some read-only status bits here
-                // For now just return 0x00 like the original implementation
-                0x00
+                // Port B is an output port, so reads return the latch. The
+                // BIOS selects the DIP switches with IN/OR 0x80/OUT and
+                // must get the other bits (keyboard clock, speaker) back
+                // unchanged.
+                self.port_b_state
             }
 
             PPI_PORT_C => {
@@ -154,9 +200,15 @@ impl IoDevice for Ppi {
 
     fn write_u8(&mut self, port: u16, value: u8) {
         match port {
-            PPI_PORT_A => {
-                // Port A is typically input-only on IBM PC
-                // Writes are ignored
+            PPI_PORT_A if value == KEYBOARD_CMD_RESET => {
+                // Port A is an input on the IBM PC, but some BIOSes send the
+                // AT-style reset command here. The keyboard ACKs it with 0xFA,
+                // then reports 0xAA after the same BAT delay as a clock reset.
+                // Other commands are ignored.
+                self.latched_scancode = None;
+                self.interrupt_pending = false;
+                self.keyboard.acknowledge_reset();
+                self.reset_delay_cycles = KEYBOARD_RESET_DELAY_CYCLES;
             }
 
             PPI_PORT_B => {
@@ -186,6 +238,11 @@ impl IoDevice for Ppi {
                     }
                 }
 
+                // Bit 0 is the gate of PIT counter 2
+                if let Some(pit) = &self.timer {
+                    pit.borrow_mut().set_gate(2, value & 0x01 != 0);
+                }
+
                 self.port_b_state = value;
             }
 
@@ -230,11 +287,42 @@ impl IoDevice for Ppi {
             }
         }
     }
+
+    fn reset(&mut self) {
+        // Keep the keyboard (and its host queue) and the DIP switch settings
+        self.latched_scancode = None;
+        self.interrupt_pending = false;
+        self.port_b_state = 0x00;
+        self.reset_state = KeyboardResetState::Idle;
+        self.reset_delay_cycles = 0;
+    }
+
+    fn snapshot(&self) -> Option<DeviceState> {
+        Some(DeviceState::Ppi(self.describe()))
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(self.clone()))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) {
+        if let Some(state) = state.downcast_ref::<Self>() {
+            // Keep our own keyboard, which is wired to this machine's host queue
+            self.latched_scancode = state.latched_scancode;
+            self.interrupt_pending = state.interrupt_pending;
+            self.port_b_state = state.port_b_state;
+            self.dip_switches = state.dip_switches;
+            self.reset_state = state.reset_state;
+            self.reset_delay_cycles = state.reset_delay_cycles;
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::collections::VecDeque;
+    use std::sync::{Arc, RwLock};
 
     #[test]
     fn test_ppi_new() {
@@ -347,7 +435,7 @@ mod tests {
         let _data = ppi.read_u8(PPI_PORT_A);
 
         // Acknowledge the interrupt
-        let _vector = pic.inta();
+        let _vector = pic.acknowledge();
         pic.eoi();
 
         // Next tick should lower IRQ line
@@ -388,6 +476,39 @@ mod tests {
         assert_eq!(ppi.read_u8(PPI_PORT_A), 0xAA);
     }
 
+    #[test]
+    fn test_reset_command_acks_then_passes_self_test() {
+        let queue = Arc::new(RwLock::new(VecDeque::new()));
+        let mut ppi = Ppi::new(queue.clone());
+        let mut pic = Pic::new(0x08);
+        pic.set_imr(0x00);
+
+        // A stale keystroke is discarded by the reset
+        queue.write().unwrap().push_back(0x1E);
+
+        ppi.write_u8(PPI_PORT_A, KEYBOARD_CMD_RESET);
+
+        // The ACK arrives immediately and raises IRQ1
+        ppi.tick(1, &mut pic);
+        assert_eq!(ppi.latched_scancode, Some(0xFA));
+        assert!(pic.intr_out());
+        assert_eq!(ppi.read_u8(PPI_PORT_A), 0xFA);
+
+        // IRQ1 drops after the read while the self-test runs
+        ppi.tick(1, &mut pic);
+        assert!(!ppi.interrupt_pending);
+        assert_eq!(ppi.latched_scancode, None);
+
+        // Self-test passed code follows the reset delay, raising IRQ1 again
+        ppi.tick(KEYBOARD_RESET_DELAY_CYCLES as u16, &mut pic);
+        assert_eq!(ppi.latched_scancode, Some(0xAA));
+        assert!(ppi.interrupt_pending);
+        assert_eq!(ppi.read_u8(PPI_PORT_A), 0xAA);
+
+        ppi.tick(1, &mut pic);
+        assert_eq!(ppi.latched_scancode, None);
+    }
+
     #[test]
     fn test_keyboard_reset_clears_pending() {
         let queue = Arc::new(RwLock::new(VecDeque::new()));
@@ -481,6 +602,38 @@ mod tests {
         assert_eq!(ppi.read_u8(PPI_PORT_B), 0x00);
     }
 
+    #[test]
+    fn test_port_b_reads_back_for_read_modify_write() {
+        let queue = Arc::new(RwLock::new(VecDeque::new()));
+        let mut ppi = Ppi::new(queue);
+
+        // Keyboard clock enabled, speaker gate on
+        ppi.write_u8(PPI_PORT_B, 0x41);
+        assert_eq!(ppi.read_u8(PPI_PORT_B), 0x41);
+
+        // IN AL, 61h; OR AL, 80h; OUT 61h, AL - keeps the keyboard clock high
+        let value = ppi.read_u8(PPI_PORT_B) | 0x80;
+        ppi.write_u8(PPI_PORT_B, value);
+        assert_eq!(ppi.read_u8(PPI_PORT_B), 0xC1);
+        assert_eq!(ppi.reset_state, KeyboardResetState::Idle);
+    }
+
+    #[test]
+    fn test_port_a_multiplexes_configured_switches_and_scancode() {
+        let queue = Arc::new(RwLock::new(VecDeque::new()));
+        let mut ppi = Ppi::with_dip_switches(queue.clone(), 0x6D);
+        let mut pic = Pic::new(0x08);
+
+        queue.write().unwrap().push_back(0x1C);
+        ppi.tick(1, &mut pic);
+
+        ppi.write_u8(PPI_PORT_B, 0xC0);
+        assert_eq!(ppi.read_u8(PPI_PORT_A), 0x6D);
+
+        ppi.write_u8(PPI_PORT_B, 0x40);
+        assert_eq!(ppi.read_u8(PPI_PORT_A), 0x1C);
+    }
+
     #[test]
     fn test_dip_mode_read_does_not_clear_scancode() {
         let queue = Arc::new(RwLock::new(VecDeque::new()));
@@ -0,0 +1,305 @@
+//! MC146818 real-time clock
+//!
+//! The clock lives in the bottom of CMOS RAM (see `cmos`):
+//! - 0x00, 0x02, 0x04: seconds, minutes, hours
+//! - 0x06-0x09: day of week, day of month, month, year
+//! - 0x0A-0x0D: status registers A-D
+//! - 0x32: century, by IBM AT convention
+//!
+//! Instead of counting by itself, the clock reads its time from a clock
+//! source: the host clock, or a fixed time in tests. Status register B picks
+//! BCD or binary fields and 12 or 24 hour mode. The periodic, alarm and
+//! update-ended interrupts need IRQ8 on the AT's second PIC, which the PC
+//! does not have, so they are never raised and register C always reads 0.
+
+use alloc::rc::Rc;
+
+/// Seconds register
+pub const RTC_SECONDS: u8 = 0x00;
+
+/// Minutes register
+pub const RTC_MINUTES: u8 = 0x02;
+
+/// Hours register
+pub const RTC_HOURS: u8 = 0x04;
+
+/// Day of week register (1 = Sunday)
+pub const RTC_WEEKDAY: u8 = 0x06;
+
+/// Day of month register
+pub const RTC_DAY: u8 = 0x07;
+
+/// Month register
+pub const RTC_MONTH: u8 = 0x08;
+
+/// Year within the century register
+pub const RTC_YEAR: u8 = 0x09;
+
+/// Status register A: update in progress, divider and rate select
+pub const RTC_STATUS_A: u8 = 0x0A;
+
+/// Status register B: mode and interrupt enables
+pub const RTC_STATUS_B: u8 = 0x0B;
+
+/// Status register C: interrupt flags
+pub const RTC_STATUS_C: u8 = 0x0C;
+
+/// Status register D: valid RAM and time
+pub const RTC_STATUS_D: u8 = 0x0D;
+
+/// Century register
+pub const RTC_CENTURY: u8 = 0x32;
+
+/// Register A bit 7: update in progress (read-only)
+const STATUS_A_UIP: u8 = 0x80;
+
+/// Register A at power-on: 32.768 kHz time base, 1024 Hz periodic rate
+const STATUS_A_DEFAULT: u8 = 0x26;
+
+/// Register B bit 1: 24-hour mode
+const STATUS_B_24_HOUR: u8 = 0x02;
+
+/// Register B bit 2: binary instead of BCD fields
+const STATUS_B_BINARY: u8 = 0x04;
+
+/// Register D bit 7: the battery is good, so RAM and time are valid
+const STATUS_D_VRT: u8 = 0x80;
+
+/// Hours register bit 7 in 12-hour mode: PM
+const HOURS_PM: u8 = 0x80;
+
+/// A calendar date and time of day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RtcTime {
+    /// Full year, e.g. 1987
+    pub year: u16,
+    /// Month (1-12)
+    pub month: u8,
+    /// Day of month (1-31)
+    pub day: u8,
+    /// Day of week (1 = Sunday, 7 = Saturday)
+    pub weekday: u8,
+    /// Hour (0-23)
+    pub hour: u8,
+    /// Minute (0-59)
+    pub minute: u8,
+    /// Second (0-59)
+    pub second: u8,
+}
+
+impl RtcTime {
+    /// Convert seconds since the Unix epoch to a UTC date and time
+    pub fn from_unix(seconds: u64) -> Self {
+        let days = seconds / 86400;
+        let secs_of_day = seconds % 86400;
+
+        // Civil-from-days, counting eras of 400 years from 0000-03-01
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as u64;
+
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            // 1970-01-01 was a Thursday
+            weekday: ((days + 4) % 7 + 1) as u8,
+            hour: (secs_of_day / 3600) as u8,
+            minute: (secs_of_day / 60 % 60) as u8,
+            second: (secs_of_day % 60) as u8,
+        }
+    }
+}
+
+/// Where the clock gets the current time from
+pub type ClockSource = Rc<dyn Fn() -> RtcTime>;
+
+/// Clock source following the host's system clock, in UTC
+#[cfg(feature = "std")]
+pub fn host_clock() -> ClockSource {
+    Rc::new(|| {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        RtcTime::from_unix(now)
+    })
+}
+
+/// MC146818 clock and status registers
+#[derive(Clone)]
+pub struct Rtc {
+    /// Source of the current time (None leaves the time registers as RAM)
+    clock: Option<ClockSource>,
+    /// Status register A, without the update-in-progress bit
+    status_a: u8,
+    /// Status register B
+    status_b: u8,
+}
+
+impl Rtc {
+    /// Create a clock in 24-hour BCD mode with no clock source
+    pub fn new() -> Self {
+        Self {
+            clock: None,
+            status_a: STATUS_A_DEFAULT,
+            status_b: STATUS_B_24_HOUR,
+        }
+    }
+
+    /// Set or clear the clock source
+    pub fn set_clock(&mut self, clock: Option<ClockSource>) {
+        self.clock = clock;
+    }
+
+    /// Check whether a clock source is set
+    pub fn has_clock(&self) -> bool {
+        self.clock.is_some()
+    }
+
+    /// Copy the status registers from another clock, keeping this one's source
+    pub fn restore_registers(&mut self, from: &Rtc) {
+        self.status_a = from.status_a;
+        self.status_b = from.status_b;
+    }
+
+    /// Read a register
+    ///
+    /// Returns None for the time registers when there is no clock source,
+    /// so they read back as ordinary CMOS RAM.
+    pub fn read(&self, index: u8) -> Option<u8> {
+        match index {
+            // An update never appears to be in progress, since the time is
+            // read in one go from the clock source
+            RTC_STATUS_A => Some(self.status_a & !STATUS_A_UIP),
+            RTC_STATUS_B => Some(self.status_b),
+            RTC_STATUS_C => Some(0),
+            RTC_STATUS_D => Some(STATUS_D_VRT),
+            _ => {
+                let now = (self.clock.as_ref()?)();
+                match index {
+                    RTC_SECONDS => Some(self.encode(now.second)),
+                    RTC_MINUTES => Some(self.encode(now.minute)),
+                    RTC_HOURS => Some(self.encode_hour(now.hour)),
+                    RTC_WEEKDAY => Some(self.encode(now.weekday)),
+                    RTC_DAY => Some(self.encode(now.day)),
+                    RTC_MONTH => Some(self.encode(now.month)),
+                    RTC_YEAR => Some(self.encode((now.year % 100) as u8)),
+                    RTC_CENTURY => Some(self.encode((now.year / 100) as u8)),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Write a register
+    ///
+    /// Returns false if the write should go to CMOS RAM instead. Writes to
+    /// the time registers are dropped while a clock source is set, so the
+    /// clock keeps following it.
+    pub fn write(&mut self, index: u8, value: u8) -> bool {
+        match index {
+            RTC_STATUS_A => self.status_a = value & !STATUS_A_UIP,
+            RTC_STATUS_B => self.status_b = value,
+            // C and D are read-only
+            RTC_STATUS_C | RTC_STATUS_D => {}
+            RTC_SECONDS | RTC_MINUTES | RTC_HOURS | RTC_WEEKDAY..=RTC_YEAR | RTC_CENTURY
+                if self.clock.is_some() => {}
+            _ => return false,
+        }
+        true
+    }
+
+    /// Encode a field in BCD or binary, as register B selects
+    fn encode(&self, value: u8) -> u8 {
+        if self.status_b & STATUS_B_BINARY != 0 {
+            value
+        } else {
+            ((value / 10) << 4) | (value % 10)
+        }
+    }
+
+    /// Encode the hour, converting to 12-hour time with a PM flag if selected
+    fn encode_hour(&self, hour: u8) -> u8 {
+        if self.status_b & STATUS_B_24_HOUR != 0 {
+            return self.encode(hour);
+        }
+        let pm = if hour >= 12 { HOURS_PM } else { 0 };
+        let hour = match hour % 12 {
+            0 => 12,
+            hour => hour,
+        };
+        self.encode(hour) | pm
+    }
+}
+
+impl Default for Rtc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed_clock(time: RtcTime) -> Option<ClockSource> {
+        Some(Rc::new(move || time))
+    }
+
+    /// 2023-11-14 22:13:20 UTC, a Tuesday
+    const TIME: u64 = 1_700_000_000;
+
+    #[test]
+    fn test_from_unix() {
+        assert_eq!(
+            RtcTime::from_unix(TIME),
+            RtcTime {
+                year: 2023,
+                month: 11,
+                day: 14,
+                weekday: 3,
+                hour: 22,
+                minute: 13,
+                second: 20,
+            }
+        );
+        assert_eq!(RtcTime::from_unix(0).weekday, 5);
+        // Leap day
+        let leap = RtcTime::from_unix(951_782_400);
+        assert_eq!((leap.year, leap.month, leap.day), (2000, 2, 29));
+    }
+
+    #[test]
+    fn test_binary_and_12_hour_modes() {
+        let mut rtc = Rtc::new();
+        rtc.set_clock(fixed_clock(RtcTime::from_unix(TIME)));
+        assert_eq!(rtc.read(RTC_YEAR), Some(0x23));
+        assert_eq!(rtc.read(RTC_CENTURY), Some(0x20));
+
+        rtc.write(RTC_STATUS_B, STATUS_B_BINARY | STATUS_B_24_HOUR);
+        assert_eq!(rtc.read(RTC_HOURS), Some(22));
+        assert_eq!(rtc.read(RTC_YEAR), Some(23));
+
+        // 12-hour BCD: 10 PM
+        rtc.write(RTC_STATUS_B, 0x00);
+        assert_eq!(rtc.read(RTC_HOURS), Some(HOURS_PM | 0x10));
+    }
+
+    #[test]
+    fn test_time_registers_are_ram_without_clock() {
+        let mut rtc = Rtc::new();
+        assert_eq!(rtc.read(RTC_SECONDS), None);
+        assert!(!rtc.write(RTC_SECONDS, 0x30));
+        assert_eq!(rtc.read(RTC_STATUS_D), Some(STATUS_D_VRT));
+
+        rtc.set_clock(fixed_clock(RtcTime::from_unix(TIME)));
+        assert!(rtc.write(RTC_SECONDS, 0x30));
+        assert_eq!(rtc.read(RTC_SECONDS), Some(0x20));
+    }
+}
@@ -0,0 +1,133 @@
+//! PC speaker
+//!
+//! The speaker is driven by PIT counter 2, gated by two bits of PPI port B
+//! (0x61):
+//! - Bit 0: counter 2 gate (the counter only runs while it is set)
+//! - Bit 1: speaker data enable (ANDed with the counter 2 output)
+//!
+//! Rather than following the counter output instruction by instruction, the
+//! speaker synthesizes a square wave at the frequency the counter is
+//! programmed for, so the host can pull samples at whatever rate its audio
+//! device runs.
+
+use crate::components::pit::{PitChannelState, PIT_CLOCK_HZ};
+
+/// Port B bit driving the counter 2 gate
+const GATE_ENABLE: u8 = 0x01;
+
+/// Port B bit connecting the counter 2 output to the speaker
+const DATA_ENABLE: u8 = 0x02;
+
+/// Counter mode that produces a square wave
+const SQUARE_WAVE_MODE: u8 = 3;
+
+/// Amplitude of the generated square wave
+const AMPLITUDE: i16 = 8192;
+
+/// PC speaker tone generator
+#[derive(Debug, Clone, Default)]
+pub struct Speaker {
+    /// Position within the current period of the tone (0.0-1.0)
+    phase: f64,
+}
+
+impl Speaker {
+    /// Create a silent speaker
+    pub fn new() -> Self {
+        Self { phase: 0.0 }
+    }
+
+    /// Frequency of the tone the speaker is playing, in Hz
+    ///
+    /// None when the speaker is silent: either port B bit is clear, or
+    /// counter 2 is not generating a square wave.
+    pub fn frequency(counter: &PitChannelState, port_b: u8) -> Option<f64> {
+        let enabled = port_b & (GATE_ENABLE | DATA_ENABLE) == GATE_ENABLE | DATA_ENABLE;
+        if !enabled || counter.mode != SQUARE_WAVE_MODE || counter.null_count {
+            return None;
+        }
+
+        // A reload value of 0 means 65536
+        let divisor = match counter.reload {
+            0 => 65536.0,
+            reload => reload as f64,
+        };
+        Some(PIT_CLOCK_HZ / divisor)
+    }
+
+    /// Produce the next sample for an audio device running at `rate` Hz
+    ///
+    /// `counter` is the state of PIT counter 2 and `port_b` the last value
+    /// written to port 0x61. Silence is 0; the tone starts each period high.
+    pub fn sample(&mut self, rate: u32, counter: &PitChannelState, port_b: u8) -> i16 {
+        let Some(frequency) = Self::frequency(counter, port_b) else {
+            self.phase = 0.0;
+            return 0;
+        };
+
+        let level = if self.phase < 0.5 {
+            AMPLITUDE
+        } else {
+            -AMPLITUDE
+        };
+        self.phase = (self.phase + frequency / rate as f64) % 1.0;
+        level
+    }
+
+    /// Fill a buffer with consecutive samples at `rate` Hz
+    pub fn fill(&mut self, rate: u32, counter: &PitChannelState, port_b: u8, buffer: &mut [i16]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.sample(rate, counter, port_b);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square_wave(reload: u16) -> PitChannelState {
+        PitChannelState {
+            count: reload,
+            reload,
+            mode: SQUARE_WAVE_MODE,
+            latch: None,
+            output: true,
+            gate: true,
+            null_count: false,
+        }
+    }
+
+    #[test]
+    fn test_silent_unless_both_port_b_bits_set() {
+        let mut speaker = Speaker::new();
+        let counter = square_wave(1193);
+        let mut buffer = [1i16; 64];
+
+        for port_b in [0x00, 0x01, 0x02, 0xFC] {
+            speaker.fill(48000, &counter, port_b, &mut buffer);
+            assert!(
+                buffer.iter().all(|&sample| sample == 0),
+                "port B {port_b:#04X}"
+            );
+        }
+
+        speaker.fill(48000, &counter, 0x03, &mut buffer);
+        assert!(buffer.iter().any(|&sample| sample != 0));
+    }
+
+    #[test]
+    fn test_reload_zero_is_lowest_tone() {
+        let frequency = Speaker::frequency(&square_wave(0), 0x03).unwrap();
+        assert!((frequency - PIT_CLOCK_HZ / 65536.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_other_modes_are_silent() {
+        let counter = PitChannelState {
+            mode: 2,
+            ..square_wave(1193)
+        };
+        assert_eq!(Speaker::frequency(&counter, 0x03), None);
+    }
+}
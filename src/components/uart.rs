@@ -0,0 +1,487 @@
+//! 16550-style UART (serial port)
+//!
+//! Eight registers from the base port (0x3F8 for COM1):
+//! - +0: receive buffer (read) / transmit holding (write); divisor low with DLAB
+//! - +1: interrupt enable; divisor high with DLAB
+//! - +2: interrupt identification (read) / FIFO control (write)
+//! - +3: line control (bit 7 is DLAB, the divisor latch access bit)
+//! - +4: modem control
+//! - +5: line status
+//! - +6: modem status
+//! - +7: scratch
+//!
+//! Transmitted bytes go straight to a host sink, so the transmitter is
+//! always empty. Received bytes are pulled from a host source whenever the
+//! receive buffer has room. Neither side is paced to the baud rate the
+//! divisor selects. The interrupt line reaches the PIC only while OUT2 is
+//! set in the modem control register, as on the PC's serial cards.
+
+use crate::components::pic::Pic;
+use crate::io::{DeviceState, IoDevice};
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use core::any::Any;
+use core::ops::RangeInclusive;
+
+/// Base port of COM1
+pub const COM1_BASE: u16 = 0x3F8;
+
+/// IRQ line of COM1
+pub const COM1_IRQ: u8 = 4;
+
+/// Clock the divisor latch divides down to the baud rate (1.8432 MHz / 16)
+pub const UART_BASE_BAUD: u32 = 115_200;
+
+/// Receive FIFO depth with FIFOs enabled
+const FIFO_DEPTH: usize = 16;
+
+/// Register offsets from the base port
+const REG_DATA: u16 = 0;
+const REG_IER: u16 = 1;
+const REG_IIR_FCR: u16 = 2;
+const REG_LCR: u16 = 3;
+const REG_MCR: u16 = 4;
+const REG_LSR: u16 = 5;
+const REG_MSR: u16 = 6;
+const REG_SCRATCH: u16 = 7;
+
+/// Interrupt enable bit 0: received data available
+const IER_RX_DATA: u8 = 0x01;
+
+/// Interrupt enable bit 1: transmit holding register empty
+const IER_THRE: u8 = 0x02;
+
+/// Interrupt identification: no interrupt pending
+const IIR_NONE: u8 = 0x01;
+
+/// Interrupt identification: transmit holding register empty
+const IIR_THRE: u8 = 0x02;
+
+/// Interrupt identification: received data available
+const IIR_RX_DATA: u8 = 0x04;
+
+/// Interrupt identification bits 7-6: FIFOs enabled
+const IIR_FIFO_ENABLED: u8 = 0xC0;
+
+/// FIFO control bit 0: enable FIFOs
+const FCR_ENABLE: u8 = 0x01;
+
+/// FIFO control bit 1: clear the receive FIFO
+const FCR_CLEAR_RX: u8 = 0x02;
+
+/// Line control bit 7: divisor latch access
+const LCR_DLAB: u8 = 0x80;
+
+/// Modem control bit 3: OUT2, which gates the interrupt line on the PC
+const MCR_OUT2: u8 = 0x08;
+
+/// Modem control bit 4: loopback
+const MCR_LOOPBACK: u8 = 0x10;
+
+/// Line status bit 0: data ready
+const LSR_DATA_READY: u8 = 0x01;
+
+/// Line status bits 5-6: transmit holding register and transmitter empty
+const LSR_TX_EMPTY: u8 = 0x60;
+
+/// Modem status with a connected peer: DCD, DSR and CTS asserted
+const MSR_CONNECTED: u8 = 0xB0;
+
+/// Callback invoked with each byte the guest transmits
+pub type SerialSink = Box<dyn FnMut(u8)>;
+
+/// Callback polled for the next byte to deliver to the guest
+pub type SerialSource = Box<dyn FnMut() -> Option<u8>>;
+
+/// Snapshot of the UART registers for debugging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UartState {
+    /// Divisor latch
+    pub divisor: u16,
+    /// Interrupt enable register
+    pub ier: u8,
+    /// Line control register
+    pub lcr: u8,
+    /// Modem control register
+    pub mcr: u8,
+    /// Scratch register
+    pub scratch: u8,
+    /// FIFOs enabled through the FIFO control register
+    pub fifo_enabled: bool,
+    /// Received bytes waiting to be read
+    pub rx_pending: usize,
+}
+
+/// Everything `save_state` captures; the host sink and source stay put
+#[derive(Clone)]
+struct SavedUart {
+    registers: UartState,
+    rx: VecDeque<u8>,
+    thre_pending: bool,
+}
+
+/// 16550 UART
+pub struct Uart {
+    /// First of the eight ports
+    base: u16,
+    /// PIC line the interrupt output drives
+    irq: u8,
+    /// Host side of the transmit line
+    sink: SerialSink,
+    /// Host side of the receive line
+    source: SerialSource,
+    /// Received bytes waiting to be read (at most one without FIFOs)
+    rx: VecDeque<u8>,
+    /// Divisor latch
+    divisor: u16,
+    /// Interrupt enable register
+    ier: u8,
+    /// Line control register
+    lcr: u8,
+    /// Modem control register
+    mcr: u8,
+    /// Scratch register
+    scratch: u8,
+    /// FIFOs enabled through the FIFO control register
+    fifo_enabled: bool,
+    /// Transmit holding register empty interrupt waiting to be acknowledged
+    thre_pending: bool,
+}
+
+impl Uart {
+    /// Create a UART at `base` on `irq`, connected to a host sink and source
+    pub fn new(base: u16, irq: u8, sink: SerialSink, source: SerialSource) -> Self {
+        Self {
+            base,
+            irq,
+            sink,
+            source,
+            rx: VecDeque::new(),
+            divisor: 0,
+            ier: 0,
+            lcr: 0,
+            mcr: 0,
+            scratch: 0,
+            fifo_enabled: false,
+            thre_pending: false,
+        }
+    }
+
+    /// Create COM1 (0x3F8, IRQ4)
+    pub fn com1(sink: SerialSink, source: SerialSource) -> Self {
+        Self::new(COM1_BASE, COM1_IRQ, sink, source)
+    }
+
+    /// Get the baud rate the divisor latch selects (None before it is set)
+    pub fn baud_rate(&self) -> Option<u32> {
+        match self.divisor {
+            0 => None,
+            divisor => Some(UART_BASE_BAUD / divisor as u32),
+        }
+    }
+
+    /// Get the line control register (word length, parity, stop bits)
+    pub fn line_control(&self) -> u8 {
+        self.lcr
+    }
+
+    /// Describe the UART registers without side effects
+    pub fn describe(&self) -> UartState {
+        UartState {
+            divisor: self.divisor,
+            ier: self.ier,
+            lcr: self.lcr,
+            mcr: self.mcr,
+            scratch: self.scratch,
+            fifo_enabled: self.fifo_enabled,
+            rx_pending: self.rx.len(),
+        }
+    }
+
+    /// Number of bytes the receive buffer holds
+    fn rx_capacity(&self) -> usize {
+        if self.fifo_enabled {
+            FIFO_DEPTH
+        } else {
+            1
+        }
+    }
+
+    /// Highest priority pending interrupt, as reported in bits 3-1 of IIR
+    fn pending_interrupt(&self) -> Option<u8> {
+        if self.ier & IER_RX_DATA != 0 && !self.rx.is_empty() {
+            Some(IIR_RX_DATA)
+        } else if self.ier & IER_THRE != 0 && self.thre_pending {
+            Some(IIR_THRE)
+        } else {
+            None
+        }
+    }
+
+    /// Send a byte out of the transmitter
+    fn transmit(&mut self, value: u8) {
+        if self.mcr & MCR_LOOPBACK != 0 {
+            // The transmitter is wired back into the receiver
+            if self.rx.len() < self.rx_capacity() {
+                self.rx.push_back(value);
+            }
+        } else {
+            (self.sink)(value);
+        }
+        self.thre_pending = true;
+    }
+}
+
+impl IoDevice for Uart {
+    fn read_u8(&mut self, port: u16) -> u8 {
+        let dlab = self.lcr & LCR_DLAB != 0;
+        match port - self.base {
+            REG_DATA if dlab => self.divisor as u8,
+            REG_DATA => self.rx.pop_front().unwrap_or(0),
+            REG_IER if dlab => (self.divisor >> 8) as u8,
+            REG_IER => self.ier,
+            REG_IIR_FCR => {
+                let fifo = if self.fifo_enabled {
+                    IIR_FIFO_ENABLED
+                } else {
+                    0
+                };
+                match self.pending_interrupt() {
+                    Some(id) => {
+                        // Reading IIR acknowledges a THRE interrupt
+                        if id == IIR_THRE {
+                            self.thre_pending = false;
+                        }
+                        id | fifo
+                    }
+                    None => IIR_NONE | fifo,
+                }
+            }
+            REG_LCR => self.lcr,
+            REG_MCR => self.mcr,
+            REG_LSR => {
+                LSR_TX_EMPTY
+                    | if self.rx.is_empty() {
+                        0
+                    } else {
+                        LSR_DATA_READY
+                    }
+            }
+            REG_MSR if self.mcr & MCR_LOOPBACK != 0 => {
+                // DTR, RTS, OUT1 and OUT2 loop back to DSR, CTS, RI and DCD
+                ((self.mcr & 0x01) << 5) | ((self.mcr & 0x02) << 3) | ((self.mcr & 0x0C) << 4)
+            }
+            REG_MSR => MSR_CONNECTED,
+            REG_SCRATCH => self.scratch,
+            _ => 0xFF,
+        }
+    }
+
+    fn write_u8(&mut self, port: u16, value: u8) {
+        let dlab = self.lcr & LCR_DLAB != 0;
+        match port - self.base {
+            REG_DATA if dlab => self.divisor = (self.divisor & 0xFF00) | value as u16,
+            REG_DATA => self.transmit(value),
+            REG_IER if dlab => self.divisor = (self.divisor & 0x00FF) | ((value as u16) << 8),
+            REG_IER => {
+                // Enabling the THRE interrupt with the transmitter empty
+                // raises it straight away
+                if value & IER_THRE != 0 && self.ier & IER_THRE == 0 {
+                    self.thre_pending = true;
+                }
+                self.ier = value & 0x0F;
+            }
+            REG_IIR_FCR => {
+                self.fifo_enabled = value & FCR_ENABLE != 0;
+                if value & FCR_CLEAR_RX != 0 {
+                    self.rx.clear();
+                }
+                self.rx.truncate(self.rx_capacity());
+            }
+            REG_LCR => self.lcr = value,
+            REG_MCR => self.mcr = value & 0x1F,
+            REG_SCRATCH => self.scratch = value,
+            // The line and modem status registers are read-only
+            _ => {}
+        }
+    }
+
+    fn port_range(&self) -> RangeInclusive<u16> {
+        self.base..=self.base + REG_SCRATCH
+    }
+
+    fn tick(&mut self, _cycles: u16, pic: &mut Pic) {
+        if self.mcr & MCR_LOOPBACK == 0 {
+            while self.rx.len() < self.rx_capacity() {
+                match (self.source)() {
+                    Some(byte) => self.rx.push_back(byte),
+                    None => break,
+                }
+            }
+        }
+
+        let level = self.mcr & MCR_OUT2 != 0 && self.pending_interrupt().is_some();
+        pic.set_irq_level(self.irq, level);
+    }
+
+    fn reset(&mut self) {
+        // Keep the host connections
+        self.rx.clear();
+        self.divisor = 0;
+        self.ier = 0;
+        self.lcr = 0;
+        self.mcr = 0;
+        self.scratch = 0;
+        self.fifo_enabled = false;
+        self.thre_pending = false;
+    }
+
+    fn snapshot(&self) -> Option<DeviceState> {
+        Some(DeviceState::Uart(self.describe()))
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        Some(Box::new(SavedUart {
+            registers: self.describe(),
+            rx: self.rx.clone(),
+            thre_pending: self.thre_pending,
+        }))
+    }
+
+    fn load_state(&mut self, state: &dyn Any) {
+        if let Some(state) = state.downcast_ref::<SavedUart>() {
+            // Keep our own host sink and source
+            let registers = state.registers;
+            self.divisor = registers.divisor;
+            self.ier = registers.ier;
+            self.lcr = registers.lcr;
+            self.mcr = registers.mcr;
+            self.scratch = registers.scratch;
+            self.fifo_enabled = registers.fifo_enabled;
+            self.rx = state.rx.clone();
+            self.thre_pending = state.thre_pending;
+        }
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::rc::Rc;
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    /// COM1 recording transmitted bytes and delivering `input`
+    fn com1(input: &[u8]) -> (Uart, Rc<RefCell<Vec<u8>>>) {
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let sink_sent = sent.clone();
+        let mut input: VecDeque<u8> = input.iter().copied().collect();
+        let uart = Uart::com1(
+            Box::new(move |byte| sink_sent.borrow_mut().push(byte)),
+            Box::new(move || input.pop_front()),
+        );
+        (uart, sent)
+    }
+
+    #[test]
+    fn test_thr_write_reaches_sink() {
+        let (mut uart, sent) = com1(&[]);
+        uart.write_u8(0x3F8, b'O');
+        uart.write_u8(0x3F8, b'K');
+        assert_eq!(*sent.borrow(), b"OK");
+        assert_eq!(uart.read_u8(0x3FD) & LSR_TX_EMPTY, LSR_TX_EMPTY);
+    }
+
+    #[test]
+    fn test_rbr_returns_source_data_with_data_ready() {
+        let (mut uart, _) = com1(b"hi");
+        let mut pic = Pic::new(0x08);
+        assert_eq!(uart.read_u8(0x3FD) & LSR_DATA_READY, 0);
+
+        uart.tick(1, &mut pic);
+        assert_eq!(uart.read_u8(0x3FD) & LSR_DATA_READY, LSR_DATA_READY);
+        assert_eq!(uart.read_u8(0x3F8), b'h');
+        assert_eq!(uart.read_u8(0x3FD) & LSR_DATA_READY, 0);
+
+        // Without FIFOs, the next byte arrives once the first is read
+        uart.tick(1, &mut pic);
+        assert_eq!(uart.read_u8(0x3F8), b'i');
+    }
+
+    #[test]
+    fn test_divisor_latch_sets_baud_rate() {
+        let (mut uart, sent) = com1(&[]);
+        uart.write_u8(0x3FB, 0x83); // DLAB, 8N1
+        uart.write_u8(0x3F8, 0x0C);
+        uart.write_u8(0x3F9, 0x00);
+        uart.write_u8(0x3FB, 0x03);
+
+        assert_eq!(uart.baud_rate(), Some(9600));
+        assert_eq!(uart.line_control(), 0x03);
+        // Divisor writes are not transmitted
+        assert!(sent.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_rx_interrupt_needs_out2() {
+        let (mut uart, _) = com1(b"x");
+        let mut pic = Pic::new(0x08);
+        uart.write_u8(0x3F9, IER_RX_DATA);
+        uart.tick(1, &mut pic);
+        assert_eq!(pic.get_irr() & 0x10, 0);
+        assert_eq!(uart.read_u8(0x3FA), IIR_RX_DATA);
+
+        uart.write_u8(0x3FC, MCR_OUT2);
+        uart.tick(1, &mut pic);
+        assert_eq!(pic.get_irr() & 0x10, 0x10);
+
+        uart.read_u8(0x3F8);
+        assert_eq!(uart.read_u8(0x3FA), IIR_NONE);
+    }
+
+    #[test]
+    fn test_save_state_round_trips_registers_and_fifo() {
+        let (mut uart, _) = com1(b"abc");
+        let mut pic = Pic::new(0x08);
+        uart.write_u8(0x3FB, 0x83); // DLAB, 8N1
+        uart.write_u8(0x3F8, 0x01);
+        uart.write_u8(0x3FB, 0x1B); // 8E1
+        uart.write_u8(0x3F9, IER_RX_DATA | IER_THRE);
+        uart.write_u8(0x3FA, FCR_ENABLE);
+        uart.write_u8(0x3FC, MCR_OUT2);
+        uart.write_u8(0x3FF, 0x42);
+        uart.tick(1, &mut pic);
+        let saved = uart.save_state().unwrap();
+        let described = uart.describe();
+        assert_eq!(described.rx_pending, 3);
+
+        // Restoring into a fresh port brings back registers and received data
+        let (mut restored, _) = com1(&[]);
+        restored.load_state(&*saved);
+        assert_eq!(restored.describe(), described);
+        assert_eq!(restored.baud_rate(), Some(UART_BASE_BAUD));
+        assert_eq!(restored.read_u8(0x3FA), IIR_RX_DATA | IIR_FIFO_ENABLED);
+        assert_eq!(restored.read_u8(0x3FF), 0x42);
+        assert_eq!(
+            [0x3F8, 0x3F8, 0x3F8].map(|port| restored.read_u8(port)),
+            *b"abc"
+        );
+        // With the receive buffer drained, the saved THRE interrupt is next
+        assert_eq!(restored.read_u8(0x3FA), IIR_THRE | IIR_FIFO_ENABLED);
+    }
+
+    #[test]
+    fn test_loopback_returns_transmitted_byte() {
+        let (mut uart, sent) = com1(&[]);
+        uart.write_u8(0x3FC, MCR_LOOPBACK | 0x01); // Loopback, DTR
+        uart.write_u8(0x3F8, 0x5A);
+
+        assert!(sent.borrow().is_empty());
+        assert_eq!(uart.read_u8(0x3F8), 0x5A);
+        assert_eq!(uart.read_u8(0x3FE), 0x20); // DSR
+    }
+}
@@ -0,0 +1,31 @@
+//! Coprocessor (8087) interface
+//!
+//! The 8088 hands ESC instructions (0xD8-0xDF) to an attached coprocessor.
+//! The CPU only decodes the ModR/M byte and effective address; what the
+//! instruction does is entirely up to the coprocessor. With nothing
+//! attached, ESC is a no-op and WAIT never stalls.
+
+use crate::cpu::decode::DecodedInstruction;
+use crate::cpu::Cpu;
+use crate::memory::MemoryBus;
+
+/// A numeric coprocessor attached to the CPU
+pub trait Coprocessor {
+    /// Execute an ESC instruction
+    ///
+    /// `instr.opcode` is the ESC opcode (0xD8-0xDF), `instr.src` is an
+    /// immediate holding the raw ModR/M byte, and `instr.dst` is the decoded
+    /// r/m operand (a register operand for mod=11, whose index selects ST(i)).
+    /// Segment overrides are already applied to memory operands.
+    fn execute(&mut self, instr: &DecodedInstruction, cpu: &mut Cpu, mem: &mut MemoryBus);
+
+    /// Whether the coprocessor is still working (drives the 8088 TEST pin)
+    ///
+    /// WAIT stalls while this returns true.
+    fn busy(&self) -> bool {
+        false
+    }
+
+    /// Reset to power-on state (called when the CPU is reset)
+    fn reset(&mut self) {}
+}
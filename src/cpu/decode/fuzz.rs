@@ -0,0 +1,80 @@
+//! Fuzzing entry point for the decoder
+//!
+//! `decode_one` takes arbitrary bytes, such as those of an untrusted ROM, and
+//! must never panic on them.
+
+use crate::cpu::decode::{CodeFetch, DecodedInstruction};
+use crate::cpu::tier1::handler_for;
+use crate::cpu::Cpu;
+
+/// Longest possible instruction: opcode, ModR/M, disp16, imm16
+const MAX_INSTRUCTION_LEN: usize = 6;
+
+/// Instruction bytes decoded from a buffer at linear address 0
+struct InstructionBytes([u8; MAX_INSTRUCTION_LEN]);
+
+impl CodeFetch for InstructionBytes {
+    fn code_byte(&self, addr: u32) -> u8 {
+        // No instruction is longer than the buffer, but read past it as
+        // unmapped memory anyway
+        self.0.get(addr as usize).copied().unwrap_or(0xFF)
+    }
+}
+
+/// Decode the first instruction in `bytes` as the CPU would execute it
+///
+/// Prefixes decode as one-byte instructions of their own, as in tier 1.
+/// Returns None if `bytes` ends before the instruction does, or if the
+/// instruction is invalid on the 8088. Nothing is executed: validity comes
+/// from the opcodes the 8088 dispatch table leaves undefined and from the
+/// ModR/M forms the group handlers reject.
+pub fn decode_one(bytes: &[u8]) -> Option<DecodedInstruction> {
+    let mut buffer = [0; MAX_INSTRUCTION_LEN];
+    let len = bytes.len().min(MAX_INSTRUCTION_LEN);
+    buffer[..len].copy_from_slice(&bytes[..len]);
+    let code = InstructionBytes(buffer);
+
+    let mut cpu = Cpu::for_decoding();
+    let opcode = cpu.fetch_u8(&code);
+    if !is_defined_opcode(opcode) {
+        return None;
+    }
+
+    let instr = cpu.decode_instruction_t1(&code, opcode, handler_for(cpu.model(), opcode));
+    if instr.length as usize > bytes.len() || !is_defined_form(opcode, buffer[1]) {
+        return None;
+    }
+    Some(instr)
+}
+
+/// Check whether the 8088 dispatch table has an instruction for `opcode`
+///
+/// These are the opcodes `tier1::dispatch` sends to `invalid_opcode`: the
+/// 80186+ additions, and the undocumented or unimplemented SALC, LOCK and
+/// INT1.
+fn is_defined_opcode(opcode: u8) -> bool {
+    !matches!(
+        opcode,
+        0x0F | 0x60..=0x6F | 0xC0 | 0xC1 | 0xC8 | 0xC9 | 0xD6 | 0xF0 | 0xF1
+    )
+}
+
+/// Check whether a ModR/M byte selects a defined form of `opcode`
+///
+/// Group opcodes with a reg field no handler implements are invalid, as are
+/// the pointer loads (LEA, LES, LDS and far CALL/JMP) given a register.
+fn is_defined_form(opcode: u8, modrm: u8) -> bool {
+    let reg = (modrm >> 3) & 0x07;
+    let register_direct = modrm >> 6 == 0b11;
+    match opcode {
+        0x8D | 0xC4 | 0xC5 => !register_direct,
+        0x8F => reg == 0,
+        0xFE => reg <= 1,
+        0xFF => match reg {
+            3 | 5 => !register_direct,
+            7 => false,
+            _ => true,
+        },
+        _ => true,
+    }
+}
@@ -116,8 +116,8 @@ impl DecodedInstruction {
 }
 
 // Manual Debug implementation since function pointers don't implement Debug
-impl std::fmt::Debug for DecodedInstruction {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for DecodedInstruction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("DecodedInstruction")
             .field("opcode", &format_args!("{:#04x}", self.opcode))
             .field("dst", &self.dst)
@@ -4,11 +4,32 @@
 //! - ModR/M byte parsing
 //! - Operand decoding
 //! - Instruction caching for tier 2 execution
+//! - A panic-free entry point for fuzzing (`decode_one`)
 
+pub mod fuzz;
 pub mod instruction;
 pub mod modrm;
 pub mod operands;
 
+use crate::memory::MemoryBus;
+
+pub use fuzz::decode_one;
 pub use instruction::DecodedInstruction;
 pub use modrm::{AddressingMode, ModRM};
 pub use operands::{Operand, OperandType};
+
+/// Source of instruction bytes for the decoder
+///
+/// Execution fetches from the `MemoryBus`; `decode_one` fetches from a
+/// plain byte buffer, so decoding needs no bus of its own.
+pub trait CodeFetch {
+    /// Read the instruction byte at a 20-bit linear address
+    fn code_byte(&self, addr: u32) -> u8;
+}
+
+impl CodeFetch for MemoryBus {
+    #[inline(always)]
+    fn code_byte(&self, addr: u32) -> u8 {
+        self.read_u8(addr)
+    }
+}
@@ -0,0 +1,580 @@
+//! 8088 disassembler
+//!
+//! Turns instruction bytes into Intel-syntax text for debugger output. This is
+//! independent of the execution decoder so it can describe any bytes, including
+//! opcodes the CPU does not implement yet. Opcodes that are not valid on the
+//! 8088 (80186+ additions) are shown as `DB` bytes.
+//!
+//! Each instruction can optionally be annotated with the flags it modifies,
+//! e.g. `ADD AX, BX ; affects CF,PF,AF,ZF,SF,OF`.
+
+use crate::cpu::Cpu;
+use crate::memory::{linear, MemoryBus};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Longest possible instruction: opcode, ModR/M, disp16, imm16
+const MAX_INSTRUCTION_LEN: usize = 6;
+
+/// Prefix bytes are unbounded on the 8088; stop reading after this many
+pub(crate) const MAX_PREFIXES: usize = 10;
+
+const REG8: [&str; 8] = ["AL", "CL", "DL", "BL", "AH", "CH", "DH", "BH"];
+const REG16: [&str; 8] = ["AX", "CX", "DX", "BX", "SP", "BP", "SI", "DI"];
+const SEG: [&str; 4] = ["ES", "CS", "SS", "DS"];
+const EA_BASE: [&str; 8] = ["BX+SI", "BX+DI", "BP+SI", "BP+DI", "SI", "DI", "BP", "BX"];
+
+const ALU: [&str; 8] = ["ADD", "OR", "ADC", "SBB", "AND", "SUB", "XOR", "CMP"];
+const SHIFT: [&str; 8] = ["ROL", "ROR", "RCL", "RCR", "SHL", "SHR", "SETMO", "SAR"];
+const GROUP3: [&str; 8] = ["TEST", "TEST", "NOT", "NEG", "MUL", "IMUL", "DIV", "IDIV"];
+const JCC: [&str; 16] = [
+    "JO", "JNO", "JB", "JNB", "JZ", "JNZ", "JBE", "JA", "JS", "JNS", "JP", "JNP", "JL", "JGE",
+    "JLE", "JG",
+];
+
+/// CF, PF, AF, ZF, SF and OF
+const ARITH_FLAGS: u16 = Cpu::CF | Cpu::PF | Cpu::AF | Cpu::ZF | Cpu::SF | Cpu::OF;
+
+/// Every flag POPF and IRET can load
+const ALL_FLAGS: u16 = ARITH_FLAGS | Cpu::TF | Cpu::IF | Cpu::DF;
+
+/// Flag names in annotation order
+const FLAG_NAMES: [(u16, &str); 9] = [
+    (Cpu::CF, "CF"),
+    (Cpu::PF, "PF"),
+    (Cpu::AF, "AF"),
+    (Cpu::ZF, "ZF"),
+    (Cpu::SF, "SF"),
+    (Cpu::OF, "OF"),
+    (Cpu::TF, "TF"),
+    (Cpu::IF, "IF"),
+    (Cpu::DF, "DF"),
+];
+
+/// One disassembled instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disassembly {
+    /// Length in bytes, including prefixes
+    pub length: usize,
+
+    /// Intel-syntax text, e.g. `MOV AX, [BX+SI+0x10]`
+    pub text: String,
+
+    /// Flags the instruction may modify (Cpu::CF etc.)
+    pub flags_affected: u16,
+}
+
+impl Disassembly {
+    /// Text with a `; affects ...` comment listing modified flags
+    ///
+    /// Instructions that leave the flags alone are not annotated.
+    pub fn annotated(&self) -> String {
+        if self.flags_affected == 0 {
+            self.text.clone()
+        } else {
+            format!(
+                "{} ; affects {}",
+                self.text,
+                flag_names(self.flags_affected)
+            )
+        }
+    }
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Format a flags mask as a comma-separated list, e.g. `CF,ZF`
+pub fn flag_names(flags: u16) -> String {
+    FLAG_NAMES
+        .iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Flags modified by an opcode
+///
+/// `reg` is the ModR/M reg field, which selects the operation for group
+/// opcodes (0x80-0x83, 0xD0-0xD3, 0xF6/0xF7, 0xFE/0xFF) and is ignored
+/// otherwise. Flags left undefined by the 8088 count as modified.
+pub fn flags_affected(opcode: u8, reg: u8) -> u16 {
+    let reg = reg & 0b111;
+    match opcode {
+        // ALU operations, including TEST and CMP
+        0x00..=0x3F if opcode & 0b111 < 6 => ARITH_FLAGS,
+        0x80..=0x85 | 0xA8 | 0xA9 => ARITH_FLAGS,
+
+        // Decimal adjust
+        0x27 | 0x2F | 0x37 | 0x3F | 0xD4 | 0xD5 => ARITH_FLAGS,
+
+        // INC/DEC leave CF alone
+        0x40..=0x4F => ARITH_FLAGS & !Cpu::CF,
+        0xFE | 0xFF if reg < 2 => ARITH_FLAGS & !Cpu::CF,
+
+        // Rotates only touch CF and OF; shifts set the arithmetic flags
+        0xD0..=0xD3 if reg < 4 => Cpu::CF | Cpu::OF,
+        0xD0..=0xD3 => ARITH_FLAGS,
+
+        // TEST, NOT, NEG, MUL, IMUL, DIV, IDIV (NOT changes nothing)
+        0xF6 | 0xF7 if reg == 2 => 0,
+        0xF6 | 0xF7 => ARITH_FLAGS,
+
+        // CMPS and SCAS compare; the other string operations do not
+        0xA6 | 0xA7 | 0xAE | 0xAF => ARITH_FLAGS,
+
+        // Flag transfer
+        0x9D | 0xCF => ALL_FLAGS,
+        0x9E => Cpu::SF | Cpu::ZF | Cpu::AF | Cpu::PF | Cpu::CF,
+
+        // Interrupts clear TF and IF on entry
+        0xCC..=0xCE => Cpu::TF | Cpu::IF,
+
+        // Flag control
+        0xF5 | 0xF8 | 0xF9 => Cpu::CF,
+        0xFA | 0xFB => Cpu::IF,
+        0xFC | 0xFD => Cpu::DF,
+
+        _ => 0,
+    }
+}
+
+/// Disassemble one instruction from `bytes`, located at offset `ip`
+///
+/// `ip` is only used to resolve relative jump targets. Returns None if
+/// `bytes` ends before the instruction does.
+pub fn disassemble(bytes: &[u8], ip: u16) -> Option<Disassembly> {
+    let mut reader = Reader { bytes, pos: 0 };
+
+    let mut segment = None;
+    let mut repeat = None;
+    let mut lock = false;
+    let opcode = loop {
+        let byte = reader.u8()?;
+        match byte {
+            0x26 | 0x2E | 0x36 | 0x3E => segment = Some(SEG[((byte >> 3) & 0b11) as usize]),
+            0xF2 => repeat = Some(0xF2),
+            0xF3 => repeat = Some(0xF3),
+            0xF0 => lock = true,
+            _ => break byte,
+        }
+        if reader.pos > MAX_PREFIXES {
+            return None;
+        }
+    };
+
+    let mut decoder = Decoder {
+        reader,
+        segment,
+        ip,
+        reg: 0,
+    };
+    let text = decoder.decode(opcode)?;
+
+    let mut prefix = String::new();
+    if lock {
+        prefix.push_str("LOCK ");
+    }
+    match (repeat, opcode) {
+        (Some(0xF3), 0xA6 | 0xA7 | 0xAE | 0xAF) => prefix.push_str("REPE "),
+        (Some(0xF3), _) => prefix.push_str("REP "),
+        (Some(_), _) => prefix.push_str("REPNE "),
+        (None, _) => {}
+    }
+
+    Some(Disassembly {
+        length: decoder.reader.pos,
+        text: prefix + &text,
+        flags_affected: flags_affected(opcode, decoder.reg),
+    })
+}
+
+/// Disassemble the instruction at `cs:ip`
+///
+/// Instruction bytes wrap around within the segment, like instruction fetch.
+pub fn disassemble_at(mem: &MemoryBus, cs: u16, ip: u16) -> Disassembly {
+    let mut bytes = [0u8; MAX_PREFIXES + MAX_INSTRUCTION_LEN];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = mem.peek_u8(linear(cs, ip.wrapping_add(i as u16)));
+    }
+
+    // The buffer holds the longest possible instruction, so decoding only
+    // fails on a run of prefixes longer than any real program uses
+    disassemble(&bytes, ip).unwrap_or_else(|| Disassembly {
+        length: 1,
+        text: format!("DB {:#04X}", bytes[0]),
+        flags_affected: 0,
+    })
+}
+
+/// Byte cursor over instruction bytes
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn u8(&mut self) -> Option<u8> {
+        let byte = *self.bytes.get(self.pos)?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        let lo = self.u8()? as u16;
+        let hi = self.u8()? as u16;
+        Some(lo | (hi << 8))
+    }
+}
+
+/// Operand size for r/m operands
+#[derive(Clone, Copy, PartialEq)]
+enum Size {
+    Byte,
+    Word,
+}
+
+/// Decoding state for one instruction after its prefixes
+struct Decoder<'a> {
+    reader: Reader<'a>,
+    segment: Option<&'static str>,
+    ip: u16,
+    /// ModR/M reg field of the last ModR/M byte read
+    reg: u8,
+}
+
+impl Decoder<'_> {
+    fn decode(&mut self, opcode: u8) -> Option<String> {
+        let size = if opcode & 1 == 0 {
+            Size::Byte
+        } else {
+            Size::Word
+        };
+
+        let text = match opcode {
+            // ALU operations in the standard six encodings
+            0x00..=0x3F if opcode & 0b111 < 6 => {
+                let op = ALU[(opcode >> 3) as usize];
+                match opcode & 0b111 {
+                    0 | 1 => {
+                        let (reg, rm) = self.modrm(size, false)?;
+                        format!("{} {}, {}", op, rm, reg)
+                    }
+                    2 | 3 => {
+                        let (reg, rm) = self.modrm(size, false)?;
+                        format!("{} {}, {}", op, reg, rm)
+                    }
+                    4 => format!("{} AL, {}", op, self.imm8()?),
+                    _ => format!("{} AX, {}", op, self.imm16()?),
+                }
+            }
+
+            0x06 | 0x0E | 0x16 | 0x1E => format!("PUSH {}", SEG[(opcode >> 3) as usize]),
+            0x07 | 0x17 | 0x1F => format!("POP {}", SEG[(opcode >> 3) as usize]),
+            0x27 => "DAA".to_string(),
+            0x2F => "DAS".to_string(),
+            0x37 => "AAA".to_string(),
+            0x3F => "AAS".to_string(),
+
+            0x40..=0x47 => format!("INC {}", REG16[(opcode & 7) as usize]),
+            0x48..=0x4F => format!("DEC {}", REG16[(opcode & 7) as usize]),
+            0x50..=0x57 => format!("PUSH {}", REG16[(opcode & 7) as usize]),
+            0x58..=0x5F => format!("POP {}", REG16[(opcode & 7) as usize]),
+
+            0x70..=0x7F => format!("{} {}", JCC[(opcode & 0xF) as usize], self.rel8()?),
+
+            0x80..=0x83 => {
+                let (_, rm) = self.modrm(size, true)?;
+                let imm = match opcode {
+                    0x81 => self.imm16()?,
+                    0x83 => format!("{:#06X}", self.reader.u8()? as i8 as i16 as u16),
+                    _ => self.imm8()?,
+                };
+                format!("{} {}, {}", ALU[self.reg as usize], rm, imm)
+            }
+
+            0x84..=0x87 => {
+                let op = if opcode < 0x86 { "TEST" } else { "XCHG" };
+                let (reg, rm) = self.modrm(size, false)?;
+                format!("{} {}, {}", op, rm, reg)
+            }
+            0x88 | 0x89 => {
+                let (reg, rm) = self.modrm(size, false)?;
+                format!("MOV {}, {}", rm, reg)
+            }
+            0x8A | 0x8B => {
+                let (reg, rm) = self.modrm(size, false)?;
+                format!("MOV {}, {}", reg, rm)
+            }
+            0x8C => {
+                let (_, rm) = self.modrm(Size::Word, false)?;
+                format!("MOV {}, {}", rm, SEG[(self.reg & 3) as usize])
+            }
+            0x8D => {
+                let (reg, rm) = self.modrm(Size::Word, false)?;
+                format!("LEA {}, {}", reg, rm)
+            }
+            0x8E => {
+                let (_, rm) = self.modrm(Size::Word, false)?;
+                format!("MOV {}, {}", SEG[(self.reg & 3) as usize], rm)
+            }
+            0x8F => {
+                let (_, rm) = self.modrm(Size::Word, true)?;
+                if self.reg != 0 {
+                    return Some(format!("DB {:#04X}", opcode));
+                }
+                format!("POP {}", rm)
+            }
+
+            0x90 => "NOP".to_string(),
+            0x91..=0x97 => format!("XCHG AX, {}", REG16[(opcode & 7) as usize]),
+            0x98 => "CBW".to_string(),
+            0x99 => "CWD".to_string(),
+            0x9A => format!("CALL {}", self.far_pointer()?),
+            0x9B => "WAIT".to_string(),
+            0x9C => "PUSHF".to_string(),
+            0x9D => "POPF".to_string(),
+            0x9E => "SAHF".to_string(),
+            0x9F => "LAHF".to_string(),
+
+            0xA0 => format!("MOV AL, {}", self.direct()?),
+            0xA1 => format!("MOV AX, {}", self.direct()?),
+            0xA2 => format!("MOV {}, AL", self.direct()?),
+            0xA3 => format!("MOV {}, AX", self.direct()?),
+            0xA4..=0xA7 | 0xAA..=0xAF => {
+                let op = match opcode & !1 {
+                    0xA4 => "MOVS",
+                    0xA6 => "CMPS",
+                    0xAA => "STOS",
+                    0xAC => "LODS",
+                    _ => "SCAS",
+                };
+                let suffix = if size == Size::Byte { "B" } else { "W" };
+                match self.segment {
+                    Some(seg) => format!("{}: {}{}", seg, op, suffix),
+                    None => format!("{}{}", op, suffix),
+                }
+            }
+            0xA8 => format!("TEST AL, {}", self.imm8()?),
+            0xA9 => format!("TEST AX, {}", self.imm16()?),
+
+            0xB0..=0xB7 => format!("MOV {}, {}", REG8[(opcode & 7) as usize], self.imm8()?),
+            0xB8..=0xBF => format!("MOV {}, {}", REG16[(opcode & 7) as usize], self.imm16()?),
+
+            0xC2 => format!("RET {}", self.imm16()?),
+            0xC3 => "RET".to_string(),
+            0xC4 | 0xC5 => {
+                let op = if opcode == 0xC4 { "LES" } else { "LDS" };
+                let (reg, rm) = self.modrm(Size::Word, false)?;
+                format!("{} {}, {}", op, reg, rm)
+            }
+            0xC6 => {
+                let (_, rm) = self.modrm(Size::Byte, true)?;
+                format!("MOV {}, {}", rm, self.imm8()?)
+            }
+            0xC7 => {
+                let (_, rm) = self.modrm(Size::Word, true)?;
+                format!("MOV {}, {}", rm, self.imm16()?)
+            }
+            0xCA => format!("RETF {}", self.imm16()?),
+            0xCB => "RETF".to_string(),
+            0xCC => "INT 3".to_string(),
+            0xCD => format!("INT {}", self.imm8()?),
+            0xCE => "INTO".to_string(),
+            0xCF => "IRET".to_string(),
+
+            0xD0..=0xD3 => {
+                let (_, rm) = self.modrm(size, true)?;
+                let count = if opcode < 0xD2 { "1" } else { "CL" };
+                format!("{} {}, {}", SHIFT[self.reg as usize], rm, count)
+            }
+            0xD4 => format!("AAM {}", self.imm8()?),
+            0xD5 => format!("AAD {}", self.imm8()?),
+            0xD7 => "XLAT".to_string(),
+            0xD8..=0xDF => {
+                let (_, rm) = self.modrm(Size::Word, false)?;
+                let esc = ((opcode & 7) << 3) | self.reg;
+                format!("ESC {:#04X}, {}", esc, rm)
+            }
+
+            0xE0 => format!("LOOPNZ {}", self.rel8()?),
+            0xE1 => format!("LOOPZ {}", self.rel8()?),
+            0xE2 => format!("LOOP {}", self.rel8()?),
+            0xE3 => format!("JCXZ {}", self.rel8()?),
+            0xE4 => format!("IN AL, {}", self.imm8()?),
+            0xE5 => format!("IN AX, {}", self.imm8()?),
+            0xE6 => format!("OUT {}, AL", self.imm8()?),
+            0xE7 => format!("OUT {}, AX", self.imm8()?),
+            0xE8 => format!("CALL {}", self.rel16()?),
+            0xE9 => format!("JMP {}", self.rel16()?),
+            0xEA => format!("JMP {}", self.far_pointer()?),
+            0xEB => format!("JMP {}", self.rel8()?),
+            0xEC => "IN AL, DX".to_string(),
+            0xED => "IN AX, DX".to_string(),
+            0xEE => "OUT DX, AL".to_string(),
+            0xEF => "OUT DX, AX".to_string(),
+
+            0xF4 => "HLT".to_string(),
+            0xF5 => "CMC".to_string(),
+            0xF6 | 0xF7 => {
+                let (_, rm) = self.modrm(size, true)?;
+                let op = GROUP3[self.reg as usize];
+                if self.reg < 2 {
+                    let imm = if size == Size::Byte {
+                        self.imm8()?
+                    } else {
+                        self.imm16()?
+                    };
+                    format!("{} {}, {}", op, rm, imm)
+                } else {
+                    format!("{} {}", op, rm)
+                }
+            }
+            0xF8 => "CLC".to_string(),
+            0xF9 => "STC".to_string(),
+            0xFA => "CLI".to_string(),
+            0xFB => "STI".to_string(),
+            0xFC => "CLD".to_string(),
+            0xFD => "STD".to_string(),
+            0xFE => {
+                let (_, rm) = self.modrm(Size::Byte, true)?;
+                match self.reg {
+                    0 => format!("INC {}", rm),
+                    1 => format!("DEC {}", rm),
+                    _ => format!("DB {:#04X}", opcode),
+                }
+            }
+            0xFF => {
+                let modrm = self.modrm_byte()?;
+                // CALL/JMP FAR load a segment:offset pointer from memory
+                let ptr = if self.reg == 3 || self.reg == 5 {
+                    "DWORD PTR "
+                } else {
+                    "WORD PTR "
+                };
+                let rm = self.rm(modrm, Size::Word, ptr)?;
+                match self.reg {
+                    0 => format!("INC {}", rm),
+                    1 => format!("DEC {}", rm),
+                    2 | 3 => format!("CALL {}", rm),
+                    4 | 5 => format!("JMP {}", rm),
+                    6 => format!("PUSH {}", rm),
+                    _ => format!("DB {:#04X}", opcode),
+                }
+            }
+
+            // 0x0F, 0x60-0x6F, 0xC0/0xC1, 0xC8/0xC9, 0xD6, 0xF1: not 8088 instructions
+            _ => format!("DB {:#04X}", opcode),
+        };
+
+        Some(text)
+    }
+
+    /// Read a ModR/M byte and return the formatted (reg, r/m) operands
+    ///
+    /// With `ptr` set, memory operands are qualified with BYTE/WORD PTR for
+    /// instructions where no register operand implies the size.
+    fn modrm(&mut self, size: Size, ptr: bool) -> Option<(String, String)> {
+        let modrm = self.modrm_byte()?;
+        let names = if size == Size::Byte { &REG8 } else { &REG16 };
+        let reg = names[self.reg as usize].to_string();
+
+        let ptr = match (ptr, size) {
+            (false, _) => "",
+            (true, Size::Byte) => "BYTE PTR ",
+            (true, Size::Word) => "WORD PTR ",
+        };
+        Some((reg, self.rm(modrm, size, ptr)?))
+    }
+
+    /// Read a ModR/M byte, remembering its reg field
+    fn modrm_byte(&mut self) -> Option<u8> {
+        let modrm = self.reader.u8()?;
+        self.reg = (modrm >> 3) & 0b111;
+        Some(modrm)
+    }
+
+    /// Format the r/m operand of `modrm`, reading any displacement
+    ///
+    /// `ptr` is prepended to memory operands (e.g. "WORD PTR ").
+    fn rm(&mut self, modrm: u8, size: Size, ptr: &str) -> Option<String> {
+        let mode = modrm >> 6;
+        let rm = modrm & 0b111;
+
+        if mode == 0b11 {
+            let names = if size == Size::Byte { &REG8 } else { &REG16 };
+            return Some(names[rm as usize].to_string());
+        }
+
+        let address = match (mode, rm) {
+            (0b00, 0b110) => format!("{:#06X}", self.reader.u16()?),
+            (0b00, _) => EA_BASE[rm as usize].to_string(),
+            (0b01, _) => {
+                let disp = self.reader.u8()? as i8;
+                if disp < 0 {
+                    format!("{}-{:#04X}", EA_BASE[rm as usize], disp.unsigned_abs())
+                } else {
+                    format!("{}+{:#04X}", EA_BASE[rm as usize], disp)
+                }
+            }
+            _ => format!("{}+{:#06X}", EA_BASE[rm as usize], self.reader.u16()?),
+        };
+
+        let mut operand = ptr.to_string();
+        if let Some(seg) = self.segment {
+            operand.push_str(seg);
+            operand.push(':');
+        }
+        operand.push('[');
+        operand.push_str(&address);
+        operand.push(']');
+
+        Some(operand)
+    }
+
+    /// Memory operand addressed by a 16-bit offset (MOV AL/AX moffs forms)
+    fn direct(&mut self) -> Option<String> {
+        let offset = self.reader.u16()?;
+        Some(match self.segment {
+            Some(seg) => format!("{}:[{:#06X}]", seg, offset),
+            None => format!("[{:#06X}]", offset),
+        })
+    }
+
+    fn imm8(&mut self) -> Option<String> {
+        Some(format!("{:#04X}", self.reader.u8()?))
+    }
+
+    fn imm16(&mut self) -> Option<String> {
+        Some(format!("{:#06X}", self.reader.u16()?))
+    }
+
+    fn far_pointer(&mut self) -> Option<String> {
+        let offset = self.reader.u16()?;
+        let segment = self.reader.u16()?;
+        Some(format!("{:04X}:{:04X}", segment, offset))
+    }
+
+    /// Jump target of a rel8 operand, relative to the end of the instruction
+    fn rel8(&mut self) -> Option<String> {
+        let rel = self.reader.u8()? as i8 as i16 as u16;
+        Some(self.target(rel))
+    }
+
+    /// Jump target of a rel16 operand, relative to the end of the instruction
+    fn rel16(&mut self) -> Option<String> {
+        let rel = self.reader.u16()?;
+        Some(self.target(rel))
+    }
+
+    fn target(&self, rel: u16) -> String {
+        let next_ip = self.ip.wrapping_add(self.reader.pos as u16);
+        format!("{:#06X}", next_ip.wrapping_add(rel))
+    }
+}
@@ -1,8 +1,9 @@
 //! Arithmetic instruction handlers (ADD, SUB, INC, DEC, etc.)
 
 use crate::cpu::decode::{DecodedInstruction, OperandType};
+use crate::cpu::execute::invalid_opcode;
 use crate::cpu::state::FlagOp;
-use crate::cpu::Cpu;
+use crate::cpu::{Cpu, ExceptionKind};
 use crate::memory::MemoryBus;
 
 /// ADD r/m, r - Add register to register/memory
@@ -777,6 +778,7 @@ pub fn daa(cpu: &mut Cpu, _mem: &mut MemoryBus, _instr: &DecodedInstruction) {
 /// Algorithm:
 /// 1. If ((AL & 0x0F) > 9) OR (AF == 1):
 ///    - AL = AL - 6
+///    - CF = old_CF OR borrow from (AL - 6)
 ///    - AF = 1
 /// 2. If (old_AL > 0x99) OR (old_CF == 1):
 ///    - AL = AL - 0x60
@@ -790,7 +792,10 @@ pub fn das(cpu: &mut Cpu, _mem: &mut MemoryBus, _instr: &DecodedInstruction) {
     let old_af = cpu.get_flag(Cpu::AF);
 
     // Step 1: Adjust low nibble if needed
+    // A borrow out of AL here (e.g. AL=0x03 with AF=1) also sets CF
+    let mut borrow = false;
     let new_af = if (al & 0x0F) > 9 || old_af {
+        borrow = al < 6;
         al = al.wrapping_sub(6);
         true
     } else {
@@ -802,7 +807,7 @@ pub fn das(cpu: &mut Cpu, _mem: &mut MemoryBus, _instr: &DecodedInstruction) {
         al = al.wrapping_sub(0x60);
         true
     } else {
-        false
+        borrow
     };
 
     // Write result back to AL
@@ -938,7 +943,8 @@ pub fn aam(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
 
     // Division by zero causes interrupt 0
     if base == 0 {
-        panic!("AAM: Division by zero");
+        divide_error(cpu, mem);
+        return;
     }
 
     let al = cpu.read_reg8(0); // Read AL
@@ -994,10 +1000,36 @@ pub fn group_fe(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction)
     match reg {
         0 => inc_rm(cpu, mem, instr), // INC r/m8
         1 => dec_rm(cpu, mem, instr), // DEC r/m8
-        _ => panic!("Invalid reg field {} for opcode 0xFE", reg),
+        _ => invalid_opcode(cpu, mem, instr),
     }
 }
 
+/// Cycles for IMUL r16, r/m16, imm on the 80186
+const IMUL_IMM_CYCLES: u16 = 22;
+
+/// IMUL r16, r/m16, imm - Three-operand signed multiply (80186+)
+/// Opcodes: 0x69 (imm16), 0x6B (imm8, sign-extended)
+///
+/// The low word of r/m16 * imm goes to r16; the high word is discarded.
+/// CF and OF are set if the product does not fit in 16 signed bits.
+pub fn imul_r_rm_imm(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
+    // The destination register is stored in the high byte of dst.value
+    let reg = (instr.dst.value >> 8) as u8;
+    let operand = cpu.read_operand(mem, &instr.dst) as i16;
+    let result = (operand as i32) * (instr.src.value as i16 as i32);
+
+    cpu.write_reg16(reg, result as u16);
+
+    // Emulator-defined SF, ZF, PF from the stored word
+    cpu.set_lazy_flags(result as u32 & 0xFFFF, FlagOp::And16);
+
+    let overflow = result != result as i16 as i32;
+    cpu.set_flag(Cpu::CF, overflow);
+    cpu.set_flag(Cpu::OF, overflow);
+
+    cpu.current_instruction_cycles += IMUL_IMM_CYCLES;
+}
+
 /// MUL r/m - Unsigned multiply
 /// Handles both byte (0xF6 /4) and word (0xF7 /4) variants
 ///
@@ -1005,7 +1037,9 @@ pub fn group_fe(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction)
 /// For word operation: AX * r/m16 → DX:AX
 ///
 /// Flags affected: CF, OF (set if upper half is non-zero)
-///                 SF, ZF, PF, AF are undefined
+///                 SF, ZF, PF are undefined on the 8088; this emulator
+///                 defines them from the low half of the result (AL or AX)
+///                 AF is undefined and left unchanged
 pub fn mul(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
     let operand_value = cpu.read_operand(mem, &instr.dst);
     let is_byte = instr.dst.op_type == OperandType::Reg8 || instr.dst.op_type == OperandType::Mem8;
@@ -1016,6 +1050,9 @@ pub fn mul(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
         let result = (al as u16) * (operand_value as u8 as u16);
         cpu.regs[0] = result; // Store full 16-bit result in AX
 
+        // Emulator-defined SF, ZF, PF from AL
+        cpu.set_lazy_flags(result as u32, FlagOp::And8);
+
         // CF and OF are set if AH is non-zero (upper 8 bits)
         let upper_half_nonzero = (result & 0xFF00) != 0;
         if upper_half_nonzero {
@@ -1033,6 +1070,9 @@ pub fn mul(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
         cpu.regs[0] = (result & 0xFFFF) as u16; // Store low word in AX
         cpu.regs[2] = (result >> 16) as u16; // Store high word in DX
 
+        // Emulator-defined SF, ZF, PF from AX
+        cpu.set_lazy_flags(result & 0xFFFF, FlagOp::And16);
+
         // CF and OF are set if DX is non-zero (upper 16 bits)
         let upper_half_nonzero = (result & 0xFFFF0000) != 0;
         if upper_half_nonzero {
@@ -1045,6 +1085,17 @@ pub fn mul(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
     }
 }
 
+/// Raise a divide error (INT 0)
+///
+/// The 8088 pushes the address of the instruction following the faulting
+/// one, so returning from the handler does not retry the division.
+fn divide_error(cpu: &mut Cpu, mem: &mut MemoryBus) {
+    if cpu.trap_exception(ExceptionKind::DivideError) {
+        return;
+    }
+    cpu.interrupt(mem, 0);
+}
+
 /// DIV r/m - Unsigned Divide
 /// Opcodes: 0xF6 /6 (8-bit), 0xF7 /6 (16-bit)
 ///
@@ -1067,7 +1118,8 @@ pub fn div(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
 
         // Check for divide by zero
         if divisor == 0 {
-            panic!("DIV: Division by zero");
+            divide_error(cpu, mem);
+            return;
         }
 
         let ax = cpu.regs[0]; // Read AX (dividend)
@@ -1076,7 +1128,8 @@ pub fn div(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
 
         // Check for quotient overflow (quotient must fit in AL)
         if quotient > 0xFF {
-            panic!("DIV: Quotient overflow (result doesn't fit in AL)");
+            divide_error(cpu, mem);
+            return;
         }
 
         // Store quotient in AL, remainder in AH
@@ -1089,7 +1142,8 @@ pub fn div(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
 
         // Check for divide by zero
         if divisor == 0 {
-            panic!("DIV: Division by zero");
+            divide_error(cpu, mem);
+            return;
         }
 
         let ax = cpu.regs[0]; // Low word
@@ -1101,7 +1155,8 @@ pub fn div(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
 
         // Check for quotient overflow (quotient must fit in AX)
         if quotient > 0xFFFF {
-            panic!("DIV: Quotient overflow (result doesn't fit in AX)");
+            divide_error(cpu, mem);
+            return;
         }
 
         // Store quotient in AX, remainder in DX
@@ -1165,6 +1220,9 @@ pub fn neg(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
 ///
 /// CF and OF are set if the result cannot be represented in the low half
 /// (i.e., sign extension of the low half doesn't equal the full result)
+///
+/// SF, ZF, PF are undefined on the 8088; this emulator defines them from the
+/// low half of the result (AL or AX). AF is undefined and left unchanged.
 pub fn imul(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
     let operand_value = cpu.read_operand(mem, &instr.dst);
     let is_byte = instr.dst.op_type == OperandType::Reg8 || instr.dst.op_type == OperandType::Mem8;
@@ -1176,6 +1234,9 @@ pub fn imul(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
         let result = (al as i16) * (operand as i16);
         cpu.regs[0] = result as u16; // Store full 16-bit result in AX
 
+        // Emulator-defined SF, ZF, PF from AL
+        cpu.set_lazy_flags(result as u16 as u32, FlagOp::And8);
+
         // CF and OF are set if the result cannot be represented in AL
         // (i.e., if sign-extending AL doesn't give the full AX result)
         let al_sign_extended = (result as u16 & 0xFF) as i8 as i16;
@@ -1191,6 +1252,9 @@ pub fn imul(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
         cpu.regs[0] = (result & 0xFFFF) as u16; // Store low word in AX
         cpu.regs[2] = ((result >> 16) & 0xFFFF) as u16; // Store high word in DX
 
+        // Emulator-defined SF, ZF, PF from AX
+        cpu.set_lazy_flags(result as u32 & 0xFFFF, FlagOp::And16);
+
         // CF and OF are set if the result cannot be represented in AX
         // (i.e., if sign-extending AX doesn't give the full DX:AX result)
         let ax_sign_extended = (result as u32 & 0xFFFF) as i16 as i32;
@@ -1224,16 +1288,20 @@ pub fn idiv(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
 
         // Check for divide by zero
         if divisor == 0 {
-            panic!("IDIV: Division by zero");
+            divide_error(cpu, mem);
+            return;
         }
 
-        let ax = cpu.regs[0] as i16; // Read AX as signed dividend
-        let quotient = ax / (divisor as i16);
-        let remainder = ax % (divisor as i16);
+        // Widen to i32 so -32768 / -1 reports overflow instead of wrapping
+        // Division truncates toward zero, so the remainder takes the dividend's sign
+        let ax = cpu.regs[0] as i16 as i32; // Read AX as signed dividend
+        let quotient = ax / (divisor as i32);
+        let remainder = ax % (divisor as i32);
 
         // Check for quotient overflow (quotient must fit in signed AL: -128 to 127)
         if quotient < -128 || quotient > 127 {
-            panic!("IDIV: Quotient overflow (result doesn't fit in AL)");
+            divide_error(cpu, mem);
+            return;
         }
 
         // Store quotient in AL, remainder in AH
@@ -1246,19 +1314,23 @@ pub fn idiv(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
 
         // Check for divide by zero
         if divisor == 0 {
-            panic!("IDIV: Division by zero");
+            divide_error(cpu, mem);
+            return;
         }
 
-        let ax = cpu.regs[0] as i16; // Low word (signed)
+        let ax = cpu.regs[0]; // Low word
         let dx = cpu.regs[2] as i16; // High word (signed)
-        let dividend = ((dx as i32) << 16) | (ax as u16 as i32);
+        let dividend = ((dx as i64) << 16) | (ax as i64);
 
-        let quotient = dividend / (divisor as i32);
-        let remainder = dividend % (divisor as i32);
+        // Widen to i64 so 0x80000000 / -1 reports overflow instead of wrapping
+        // Division truncates toward zero, so the remainder takes the dividend's sign
+        let quotient = dividend / (divisor as i64);
+        let remainder = dividend % (divisor as i64);
 
         // Check for quotient overflow (quotient must fit in signed AX: -32768 to 32767)
         if quotient < -32768 || quotient > 32767 {
-            panic!("IDIV: Quotient overflow (result doesn't fit in AX)");
+            divide_error(cpu, mem);
+            return;
         }
 
         // Store quotient in AX, remainder in DX
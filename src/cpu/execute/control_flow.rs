@@ -5,14 +5,18 @@
 //! 2. Apply appropriate cycle timing
 
 use crate::cpu::decode::DecodedInstruction;
-use crate::cpu::execute::{arithmetic, stack};
-use crate::cpu::Cpu;
+use crate::cpu::execute::{arithmetic, invalid_opcode, stack};
+use crate::cpu::{Cpu, ExceptionKind};
 use crate::memory::MemoryBus;
 
 /// Extra cycles when conditional jump is taken
 /// BASE_CYCLES has 4 (not taken), taken is 16, so we add 12
 const JCC_TAKEN_EXTRA_CYCLES: u16 = 12;
 
+/// Extra cycles when INTO interrupts
+/// BASE_CYCLES has 4 (OF clear), interrupting is 53, so we add 49
+const INTO_TAKEN_EXTRA_CYCLES: u16 = 49;
+
 /// Extra cycles when LOOP is taken
 /// BASE_CYCLES varies, handlers adjust as needed
 const LOOP_TAKEN_EXTRA_CYCLES: u16 = 12;
@@ -341,7 +345,8 @@ pub fn call_m16_16(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstructio
                 cpu.calculate_ea_from_operand(&instr.dst, base_index)
             }
         }
-        _ => panic!("CALL m16:16 requires memory operand"),
+        // CALL FAR with a register operand has no pointer to load
+        _ => return invalid_opcode(cpu, mem, instr),
     };
 
     // Get the actual segment (considering segment overrides)
@@ -381,7 +386,7 @@ pub fn group_ff(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction)
         4 => jmp_rm16(cpu, mem, instr),           // JMP r/m16 (near)
         5 => jmp_m16_16(cpu, mem, instr),         // JMP m16:16 (far)
         6 => stack::push_rm16(cpu, mem, instr),   // PUSH r/m16
-        _ => panic!("Invalid reg field {} for opcode 0xFF", reg),
+        _ => invalid_opcode(cpu, mem, instr),
     }
 }
 
@@ -413,7 +418,8 @@ pub fn jmp_m16_16(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction
                 cpu.calculate_ea_from_operand(&instr.dst, base_index)
             }
         }
-        _ => panic!("JMP m16:16 requires memory operand"),
+        // JMP FAR with a register operand has no pointer to load
+        _ => return invalid_opcode(cpu, mem, instr),
     };
 
     // Get the actual segment (considering segment overrides)
@@ -560,44 +566,6 @@ pub fn jcxz(cpu: &mut Cpu, _mem: &mut MemoryBus, instr: &DecodedInstruction) {
     }
 }
 
-/// Common interrupt entry sequence for both software and hardware interrupts
-///
-/// Stack operation: PUSH FLAGS, PUSH CS, PUSH IP, then load CS:IP from IVT
-/// The interrupt vector table (IVT) is located at 0000:0000
-/// Each entry is 4 bytes: offset (word) followed by segment (word)
-/// Vector for interrupt n is at address n*4
-pub(crate) fn enter_interrupt(cpu: &mut Cpu, mem: &mut MemoryBus, vector: u8) {
-    use super::stack::push_word;
-
-    // Push FLAGS register
-    let flags = cpu.get_flags();
-    push_word(cpu, mem, flags);
-
-    // Clear TF and IF flags (disable interrupts during handler)
-    cpu.set_flag(Cpu::TF, false);
-    cpu.set_flag(Cpu::IF, false);
-
-    // Push CS
-    let return_cs = cpu.read_seg(1); // CS
-    push_word(cpu, mem, return_cs);
-
-    // Push IP (return address)
-    let return_ip = cpu.ip;
-    push_word(cpu, mem, return_ip);
-
-    // Load new CS:IP from interrupt vector table
-    // IVT is at 0000:0000, each entry is 4 bytes
-    // Entry n is at address n*4: [offset_low, offset_high, segment_low, segment_high]
-    let ivt_addr = (vector as u32) * 4;
-    let new_ip = mem.read_u16(ivt_addr);
-    let new_cs = mem.read_u16(ivt_addr + 2);
-
-    // Set new CS:IP
-    cpu.write_seg(1, new_cs); // CS
-    cpu.ip = new_ip;
-    cpu.flush_prefetch_queue();
-}
-
 /// INT n - Software interrupt with 8-bit interrupt number
 /// Opcode: 0xCD
 ///
@@ -613,14 +581,15 @@ pub fn int_n(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
     {
         let cs = cpu.read_seg(1);
         let ip = cpu.ip;
-        println!(
+        log_debug!(
             "[INT] Software interrupt 0x{:02X} at {:04X}:{:04X}",
-            int_num, cs, ip
+            int_num,
+            cs,
+            ip
         );
     }
 
-    // Use common interrupt entry sequence
-    enter_interrupt(cpu, mem, int_num);
+    software_interrupt(cpu, mem, int_num);
 }
 
 /// INT3 - Breakpoint interrupt
@@ -633,14 +602,42 @@ pub fn int3(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
     {
         let cs = cpu.read_seg(1);
         let ip = cpu.ip;
-        println!(
+        log_debug!(
             "[INT] Software interrupt 0x03 (INT3/Breakpoint) at {:04X}:{:04X}",
-            cs, ip
+            cs,
+            ip
         );
     }
 
-    // Use common interrupt entry sequence with vector 3
-    enter_interrupt(cpu, mem, 3);
+    software_interrupt(cpu, mem, 3);
+}
+
+/// INTO - Interrupt on overflow
+/// Opcode: 0xCE (4 if OF=0, 53 if OF=1)
+///
+/// If OF=1 then INT 4
+pub fn into(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
+    if cpu.get_flag(Cpu::OF) {
+        software_interrupt(cpu, mem, 4);
+        cpu.current_instruction_cycles += INTO_TAKEN_EXTRA_CYCLES;
+    }
+}
+
+/// Enter a software interrupt, unless it is trapped as unhandled
+///
+/// A vector of 0000:0000 means nothing installed a handler, so with
+/// exceptions trapped the CPU stops at the INT instead of jumping to the IVT.
+fn software_interrupt(cpu: &mut Cpu, mem: &mut MemoryBus, vector: u8) {
+    let entry = vector as u32 * 4;
+    if cpu.trap_exceptions()
+        && mem.read_u16(entry) == 0
+        && mem.read_u16(entry + 2) == 0
+        && cpu.trap_exception(ExceptionKind::UnhandledInterrupt(vector))
+    {
+        return;
+    }
+
+    cpu.interrupt(mem, vector);
 }
 
 /// IRET - Return from interrupt
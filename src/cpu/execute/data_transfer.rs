@@ -1,6 +1,7 @@
 //! Data transfer instruction handlers (MOV, XCHG, etc.)
 
 use crate::cpu::decode::DecodedInstruction;
+use crate::cpu::execute::invalid_opcode;
 use crate::cpu::Cpu;
 use crate::memory::MemoryBus;
 
@@ -72,7 +73,7 @@ pub fn xchg_ax_r16(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstructio
 ///
 /// This instruction is commonly used for pointer arithmetic and address calculations.
 /// No flags are affected.
-pub fn lea(cpu: &mut Cpu, _mem: &mut MemoryBus, instr: &DecodedInstruction) {
+pub fn lea(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
     use crate::cpu::decode::OperandType;
 
     // LEA requires a memory operand as source
@@ -93,15 +94,15 @@ pub fn lea(cpu: &mut Cpu, _mem: &mut MemoryBus, instr: &DecodedInstruction) {
             };
 
             // Store the effective address (offset) in the destination register
-            cpu.write_operand(_mem, &instr.dst, ea);
+            cpu.write_operand(mem, &instr.dst, ea);
         }
         OperandType::Direct => {
             // Direct addressing: just use the offset directly
-            cpu.write_operand(_mem, &instr.dst, instr.src.value);
+            cpu.write_operand(mem, &instr.dst, instr.src.value);
         }
         _ => {
             // LEA with register operand is invalid (though some assemblers allow it)
-            panic!("LEA requires a memory operand");
+            invalid_opcode(cpu, mem, instr);
         }
     }
 }
@@ -126,11 +127,27 @@ pub fn mov_rm_sreg(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstructio
 /// This instruction is used to set up segment registers.
 /// No flags are affected.
 ///
-/// Note: On the 8088, loading CS via this instruction is not allowed.
-/// Some sources indicate the behavior is undefined if attempted.
+/// Intel documents CS as an invalid destination. Real 8088 silicon loads
+/// CS anyway (without flushing the prefetch queue), which no sane program
+/// relies on, so the emulator follows the documentation: the source operand
+/// is still read, but CS keeps its value and a warning is logged.
+///
+/// Loading SS holds off interrupts for one instruction, like POP SS.
 pub fn mov_sreg_rm(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
     let src_value = cpu.read_operand(mem, &instr.src);
+    if instr.dst.value == 1 {
+        log_warn!(
+            "[CPU] Ignoring MOV CS, {:#06X} at CS:IP = {:04X}:{:04X}",
+            src_value,
+            cpu.read_seg(1),
+            cpu.ip.wrapping_sub(instr.length as u16)
+        );
+        return;
+    }
     cpu.write_operand(mem, &instr.dst, src_value);
+    if instr.dst.value == 2 {
+        cpu.set_interrupt_delay();
+    }
 }
 
 /// MOV AL, moffs8 - Move byte at memory offset to AL
@@ -285,7 +302,8 @@ pub fn les(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
             cpu.write_seg(0, seg_value);
         }
         _ => {
-            panic!("LES requires a memory operand");
+            // LES with a register operand has no pointer to load
+            invalid_opcode(cpu, mem, instr);
         }
     }
 }
@@ -335,7 +353,8 @@ pub fn lds(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
             cpu.write_seg(3, seg_value);
         }
         _ => {
-            panic!("LDS requires a memory operand");
+            // LDS with a register operand has no pointer to load
+            invalid_opcode(cpu, mem, instr);
         }
     }
 }
@@ -0,0 +1,36 @@
+//! Coprocessor escape and synchronization handlers
+//!
+//! ESC (0xD8-0xDF) and WAIT (0x9B) are the 8088's side of the 8087
+//! interface. See `cpu::coprocessor` for the plug-in point.
+
+use crate::cpu::decode::DecodedInstruction;
+use crate::cpu::Cpu;
+use crate::memory::MemoryBus;
+
+/// ESC (0xD8-0xDF) - Escape to coprocessor
+///
+/// Routes the instruction to the attached coprocessor, if any.
+/// Without a coprocessor this is a no-op.
+pub fn esc(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
+    if let Some(mut coprocessor) = cpu.coprocessor.take() {
+        coprocessor.execute(instr, cpu, mem);
+        cpu.coprocessor = Some(coprocessor);
+    }
+}
+
+/// WAIT (0x9B) - Wait while the coprocessor is busy
+///
+/// Polls the TEST pin every 5 cycles. While the coprocessor is busy the
+/// instruction is re-executed, so hardware interrupts can still be taken
+/// between polls, as on the real 8088. Without a coprocessor it never stalls.
+pub fn wait(cpu: &mut Cpu, _mem: &mut MemoryBus, _instr: &DecodedInstruction) {
+    let busy = cpu
+        .coprocessor
+        .as_ref()
+        .is_some_and(|coprocessor| coprocessor.busy());
+
+    if busy {
+        cpu.ip = cpu.ip.wrapping_sub(1);
+        cpu.current_instruction_cycles += 5;
+    }
+}
@@ -103,10 +103,17 @@ pub fn pushf(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
 /// Pops FLAGS register from the stack.
 /// Stack operation: FLAGS = [SS:SP], SP += 2
 /// Takes 8 cycles on the 8088.
+///
+/// Like STI, popping IF from 0 to 1 delays interrupt recognition by one
+/// instruction.
 #[inline(always)]
 pub fn popf(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
+    let was_disabled = !cpu.get_flag(Cpu::IF);
     let flags = crate::cpu::execute::stack::pop_word(cpu, mem);
     cpu.set_flags(flags);
+    if was_disabled && cpu.get_flag(Cpu::IF) {
+        cpu.set_interrupt_delay();
+    }
 }
 
 /// Handler for SAHF (0x9E) - Store AH into Flags
@@ -1,14 +1,21 @@
 //! Basic instruction handlers and handler utilities
 
 use crate::cpu::decode::DecodedInstruction;
-use crate::cpu::Cpu;
+use crate::cpu::{Cpu, ExceptionKind};
 use crate::memory::MemoryBus;
 
 /// Handler for invalid/unimplemented opcodes
 ///
 /// This handler is called when an unknown or unimplemented opcode is
-/// encountered. It panics with information about the opcode and CPU state.
+/// encountered. After notifying the hook set with
+/// `Cpu::set_invalid_opcode_hook`, it panics with information about the
+/// opcode and CPU state, unless exceptions are trapped (see
+/// `Cpu::set_trap_exceptions`).
 pub fn invalid_opcode(cpu: &mut Cpu, _mem: &mut MemoryBus, instr: &DecodedInstruction) {
+    cpu.notify_invalid_opcode(instr.opcode);
+    if cpu.trap_exception(ExceptionKind::InvalidOpcode(instr.opcode)) {
+        return;
+    }
     panic!(
         "Invalid opcode: {:#04x} at CS:IP = {:04X}:{:04X}",
         instr.opcode,
@@ -1,6 +1,7 @@
 //! IO instruction handlers (IN/OUT)
 //!
-//! Cycle timing is handled by BASE_CYCLES table in timing.rs.
+//! Cycle timing is handled by BASE_CYCLES table in timing.rs, plus any
+//! IO delay configured on the bus for slow devices.
 
 use crate::cpu::decode::DecodedInstruction;
 use crate::cpu::Cpu;
@@ -11,6 +12,7 @@ use crate::memory::MemoryBus;
 pub fn in_al_imm8(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
     let port = instr.src.value as u16;
     let value = mem.io_read_u8(port);
+    cpu.current_instruction_cycles += mem.io_delay_u8(port);
     cpu.write_reg8(0, value as u8); // AL = reg 0
 }
 
@@ -19,6 +21,7 @@ pub fn in_al_imm8(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction
 pub fn in_ax_imm8(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
     let port = instr.src.value as u16;
     let value = mem.io_read_u16(port);
+    cpu.current_instruction_cycles += mem.io_delay_u16(port);
     cpu.write_reg16(0, value); // AX = reg 0
 }
 
@@ -28,6 +31,7 @@ pub fn out_imm8_al(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstructio
     let port = instr.dst.value as u16;
     let value = cpu.read_reg8(0); // AL = reg 0
     mem.io_write_u8(port, value);
+    cpu.current_instruction_cycles += mem.io_delay_u8(port);
 }
 
 /// OUT imm8, AX - Write AX to immediate port
@@ -36,6 +40,7 @@ pub fn out_imm8_ax(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstructio
     let port = instr.dst.value as u16;
     let value = cpu.read_reg16(0); // AX = reg 0
     mem.io_write_u16(port, value);
+    cpu.current_instruction_cycles += mem.io_delay_u16(port);
 }
 
 /// IN AL, DX - Read byte from DX port to AL
@@ -43,6 +48,7 @@ pub fn out_imm8_ax(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstructio
 pub fn in_al_dx(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
     let port = cpu.read_reg16(2); // DX = reg 2
     let value = mem.io_read_u8(port);
+    cpu.current_instruction_cycles += mem.io_delay_u8(port);
     cpu.write_reg8(0, value as u8); // AL = reg 0
 }
 
@@ -51,6 +57,7 @@ pub fn in_al_dx(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction)
 pub fn in_ax_dx(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
     let port = cpu.read_reg16(2); // DX = reg 2
     let value = mem.io_read_u16(port);
+    cpu.current_instruction_cycles += mem.io_delay_u16(port);
     cpu.write_reg16(0, value); // AX = reg 0
 }
 
@@ -60,6 +67,7 @@ pub fn out_dx_al(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction
     let port = cpu.read_reg16(2); // DX = reg 2
     let value = cpu.read_reg8(0); // AL = reg 0
     mem.io_write_u8(port, value);
+    cpu.current_instruction_cycles += mem.io_delay_u8(port);
 }
 
 /// OUT DX, AX - Write AX to DX port
@@ -68,4 +76,5 @@ pub fn out_dx_ax(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction
     let port = cpu.read_reg16(2); // DX = reg 2
     let value = cpu.read_reg16(0); // AX = reg 0
     mem.io_write_u16(port, value);
+    cpu.current_instruction_cycles += mem.io_delay_u16(port);
 }
@@ -2,7 +2,7 @@
 
 use crate::cpu::decode::{DecodedInstruction, OperandType};
 use crate::cpu::state::FlagOp;
-use crate::cpu::Cpu;
+use crate::cpu::{Cpu, CpuModel};
 use crate::memory::MemoryBus;
 
 /// ROL - Rotate Left
@@ -22,19 +22,11 @@ pub fn rol(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction, count
 
     let (result, new_cf) = if is_byte {
         let val = value as u8;
-        let count = count & 0x07; // Only low 3 bits for 8-bit
-        if count == 0 {
-            return;
-        }
         let result = val.rotate_left(count as u32);
         let cf = result & 1; // Rightmost bit after rotation
         (result as u16, cf != 0)
     } else {
         let val = value;
-        let count = count & 0x0F; // Only low 4 bits for 16-bit
-        if count == 0 {
-            return;
-        }
         let result = val.rotate_left(count as u32);
         let cf = result & 1; // Rightmost bit after rotation
         (result, cf != 0)
@@ -44,7 +36,7 @@ pub fn rol(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction, count
     cpu.set_flag(Cpu::CF, new_cf);
 
     // OF is only defined for count=1
-    if count & (if is_byte { 0x07 } else { 0x0F }) == 1 {
+    if count == 1 {
         let msb = if is_byte {
             (result & 0x80) != 0
         } else {
@@ -70,19 +62,11 @@ pub fn ror(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction, count
 
     let (result, new_cf) = if is_byte {
         let val = value as u8;
-        let count = count & 0x07;
-        if count == 0 {
-            return;
-        }
         let result = val.rotate_right(count as u32);
         let cf = (result & 0x80) != 0; // Leftmost bit after rotation
         (result as u16, cf)
     } else {
         let val = value;
-        let count = count & 0x0F;
-        if count == 0 {
-            return;
-        }
         let result = val.rotate_right(count as u32);
         let cf = (result & 0x8000) != 0; // Leftmost bit after rotation
         (result, cf)
@@ -92,7 +76,7 @@ pub fn ror(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction, count
     cpu.set_flag(Cpu::CF, new_cf);
 
     // OF is only defined for count=1
-    if count & (if is_byte { 0x07 } else { 0x0F }) == 1 {
+    if count == 1 {
         let msb = if is_byte {
             (result & 0x80) != 0
         } else {
@@ -122,14 +106,12 @@ pub fn rcl(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction, count
     let mut result = value;
 
     if is_byte {
-        let count = count & 0x1F; // Modulo 9 for 8-bit + CF
         for _ in 0..count {
             let new_cf = (result & 0x80) != 0;
             result = ((result << 1) & 0xFF) | (if cf { 1 } else { 0 });
             cf = new_cf;
         }
     } else {
-        let count = count & 0x1F; // Modulo 17 for 16-bit + CF
         for _ in 0..count {
             let new_cf = (result & 0x8000) != 0;
             result = ((result << 1) & 0xFFFF) | (if cf { 1 } else { 0 });
@@ -166,14 +148,12 @@ pub fn rcr(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction, count
     let mut result = value;
 
     if is_byte {
-        let count = count & 0x1F; // Modulo 9 for 8-bit + CF
         for _ in 0..count {
             let new_cf = (result & 1) != 0;
             result = (result >> 1) | (if cf { 0x80 } else { 0 });
             cf = new_cf;
         }
     } else {
-        let count = count & 0x1F; // Modulo 17 for 16-bit + CF
         for _ in 0..count {
             let new_cf = (result & 1) != 0;
             result = (result >> 1) | (if cf { 0x8000 } else { 0 });
@@ -218,34 +198,15 @@ pub fn shl(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction, count
 
     let (result, new_cf) = if is_byte {
         let val = value as u8;
-        let count = if count >= 8 { 8 } else { count };
-
-        let cf = if count > 0 {
-            // CF is the last bit shifted out
-            if count <= 8 {
-                (val >> (8 - count)) & 1 != 0
-            } else {
-                false
-            }
-        } else {
-            false
-        };
+
+        // CF is the last bit shifted out, a zero once the count passes 8
+        let cf = count <= 8 && (val >> (8 - count)) & 1 != 0;
 
         let result = if count >= 8 { 0 } else { (val << count) & 0xFF };
         ((result as u16), cf)
     } else {
         let val = value;
-        let count = if count >= 16 { 16 } else { count };
-
-        let cf = if count > 0 {
-            if count <= 16 {
-                (val >> (16 - count)) & 1 != 0
-            } else {
-                false
-            }
-        } else {
-            false
-        };
+        let cf = count <= 16 && (val >> (16 - count)) & 1 != 0;
 
         let result = if count >= 16 {
             0
@@ -302,25 +263,13 @@ pub fn shr(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction, count
 
     let (result, new_cf) = if is_byte {
         let val = value as u8;
-        let count = if count >= 8 { 8 } else { count };
-
-        let cf = if count > 0 && count <= 8 {
-            (val >> (count - 1)) & 1 != 0
-        } else {
-            false
-        };
+        let cf = count <= 8 && (val >> (count - 1)) & 1 != 0;
 
         let result = if count >= 8 { 0 } else { val >> count };
         (result as u16, cf)
     } else {
         let val = value;
-        let count = if count >= 16 { 16 } else { count };
-
-        let cf = if count > 0 && count <= 16 {
-            (val >> (count - 1)) & 1 != 0
-        } else {
-            false
-        };
+        let cf = count <= 16 && (val >> (count - 1)) & 1 != 0;
 
         let result = if count >= 16 { 0 } else { val >> count };
         (result, cf)
@@ -363,29 +312,21 @@ pub fn sar(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction, count
 
     let (result, new_cf) = if is_byte {
         let val = value as i8;
-        let count = if count >= 8 { 8 } else { count };
 
-        let cf = if count > 0 && count <= 8 {
-            ((val as u8) >> (count - 1)) & 1 != 0
-        } else {
-            false
-        };
+        // Past the width every bit shifted out is a copy of the sign
+        let count = count.min(8);
+        let cf = ((val as u8) >> (count - 1)) & 1 != 0;
 
         // Arithmetic shift preserves sign
-        let result = (val >> count) as u8;
+        let result = (val >> count.min(7)) as u8;
         (result as u16, cf)
     } else {
         let val = value as i16;
-        let count = if count >= 16 { 16 } else { count };
-
-        let cf = if count > 0 && count <= 16 {
-            ((val as u16) >> (count - 1)) & 1 != 0
-        } else {
-            false
-        };
+        let count = count.min(16);
+        let cf = ((val as u16) >> (count - 1)) & 1 != 0;
 
         // Arithmetic shift preserves sign
-        let result = (val >> count) as u16;
+        let result = (val >> count.min(15)) as u16;
         (result, cf)
     };
 
@@ -442,11 +383,25 @@ pub fn group_d1(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction)
     }
 }
 
+/// Shift count as the CPU model sees it
+///
+/// The 8088 uses all 8 bits of the count; the 80186 masks it to 5 bits.
+#[inline(always)]
+fn shift_count(cpu: &Cpu, count: u8) -> u8 {
+    match cpu.model() {
+        CpuModel::I8088 => count,
+        CpuModel::I80186 => count & 0x1F,
+    }
+}
+
 /// Group handler for 0xD2: Shift/rotate r/m8, CL
+///
+/// With CL=0 every operation is a no-op: the operand and all flags are left
+/// untouched (each handler returns before reading the operand).
 pub fn group_d2(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
     // The reg field of the ModR/M byte determines the operation
     let operation = instr.src.value as u8;
-    let count = cpu.read_reg8(1); // CL register
+    let count = shift_count(cpu, cpu.read_reg8(1)); // CL register
 
     match operation {
         0 => rol(cpu, mem, instr, count),
@@ -464,7 +419,7 @@ pub fn group_d2(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction)
 pub fn group_d3(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
     // The reg field of the ModR/M byte determines the operation
     let operation = instr.src.value as u8;
-    let count = cpu.read_reg8(1); // CL register
+    let count = shift_count(cpu, cpu.read_reg8(1)); // CL register
 
     match operation {
         0 => rol(cpu, mem, instr, count),
@@ -477,3 +432,37 @@ pub fn group_d3(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction)
         _ => unreachable!(),
     }
 }
+
+/// Cycles for shift/rotate by immediate on the 80186
+const SHIFT_IMM_CYCLES: u16 = 5;
+
+/// Group handler for 0xC0/0xC1: Shift/rotate r/m, imm8 (80186+)
+///
+/// The operation is in the low byte of src.value and the count in the high
+/// byte. The 80186 masks the count to 5 bits.
+fn group_shift_imm(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
+    let operation = instr.src.value as u8;
+    let count = shift_count(cpu, (instr.src.value >> 8) as u8);
+
+    match operation {
+        0 => rol(cpu, mem, instr, count),
+        1 => ror(cpu, mem, instr, count),
+        2 => rcl(cpu, mem, instr, count),
+        3 => rcr(cpu, mem, instr, count),
+        4 | 6 => shl(cpu, mem, instr, count), // SHL/SAL (same operation)
+        5 => shr(cpu, mem, instr, count),
+        7 => sar(cpu, mem, instr, count),
+        _ => unreachable!(),
+    }
+    cpu.current_instruction_cycles += SHIFT_IMM_CYCLES;
+}
+
+/// Group handler for 0xC0: Shift/rotate r/m8, imm8 (80186+)
+pub fn group_c0(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
+    group_shift_imm(cpu, mem, instr);
+}
+
+/// Group handler for 0xC1: Shift/rotate r/m16, imm8 (80186+)
+pub fn group_c1(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
+    group_shift_imm(cpu, mem, instr);
+}
@@ -1,6 +1,7 @@
 //! Stack operation handlers (PUSH, POP, etc.)
 
 use crate::cpu::decode::DecodedInstruction;
+use crate::cpu::execute::invalid_opcode;
 use crate::cpu::Cpu;
 use crate::memory::MemoryBus;
 
@@ -17,8 +18,10 @@ pub fn push_r16(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction)
 /// Part of opcode 0xFF (reg field = 110)
 ///
 /// Stack operation: SP -= 2, [SS:SP] = operand
+///
+/// Group 0xFF decodes the r/m operand into `dst`, like the other members.
 pub fn push_rm16(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
-    let value = cpu.read_operand(mem, &instr.src);
+    let value = cpu.read_operand(mem, &instr.dst);
     push_word(cpu, mem, value);
 }
 
@@ -40,6 +43,85 @@ pub fn pop_rm16(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction)
     cpu.write_operand(mem, &instr.dst, value);
 }
 
+/// Group handler for opcode 0x8F
+///
+/// Only reg=0 (POP r/m16) is defined. The other reg values are undefined on
+/// the 8088 and go to the invalid opcode handler like any other opcode the
+/// CPU does not implement.
+pub fn group_8f(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
+    // The reg field is stored in the value field of dst operand during group decoding
+    let reg = (instr.dst.value >> 8) as u8; // High byte stores the reg field
+
+    match reg {
+        0 => pop_rm16(cpu, mem, instr), // POP r/m16
+        _ => invalid_opcode(cpu, mem, instr),
+    }
+}
+
+/// Cycles for PUSH imm on the 80186
+const PUSH_IMM_CYCLES: u16 = 10;
+
+/// Cycles for ENTER with nesting level 0 on the 80186
+const ENTER_CYCLES: u16 = 15;
+
+/// Extra cycles per copied frame pointer for ENTER with nesting level > 0
+const ENTER_LEVEL_CYCLES: u16 = 16;
+
+/// Cycles for LEAVE on the 80186
+const LEAVE_CYCLES: u16 = 8;
+
+/// PUSH imm16 / PUSH imm8 (sign-extended) - 80186+
+/// Opcodes: 0x68, 0x6A
+///
+/// Stack operation: SP -= 2, [SS:SP] = immediate
+pub fn push_imm(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
+    push_word(cpu, mem, instr.src.value);
+    cpu.current_instruction_cycles += PUSH_IMM_CYCLES;
+}
+
+/// ENTER imm16, imm8 - Create a stack frame (80186+)
+/// Opcode: 0xC8
+///
+/// Pushes BP, copies `level - 1` frame pointers from the enclosing frame
+/// plus a pointer to the new one (for block-structured languages), points
+/// BP at the new frame and reserves `imm16` bytes of locals below it.
+/// The nesting level is taken modulo 32.
+pub fn enter(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
+    let frame_size = instr.src.value;
+    let level = instr.dst.value & 0x1F;
+
+    push_word(cpu, mem, cpu.read_reg16(5)); // BP
+    let frame_ptr = cpu.read_reg16(4); // SP
+
+    if level > 0 {
+        let ss = cpu.read_seg(2);
+        for _ in 1..level {
+            let bp = cpu.read_reg16(5).wrapping_sub(2);
+            cpu.write_reg16(5, bp);
+            let outer = cpu.read_mem16(mem, ss, bp);
+            push_word(cpu, mem, outer);
+        }
+        push_word(cpu, mem, frame_ptr);
+    }
+
+    cpu.write_reg16(5, frame_ptr); // BP
+    let sp = cpu.read_reg16(4).wrapping_sub(frame_size);
+    cpu.write_reg16(4, sp);
+
+    cpu.current_instruction_cycles += ENTER_CYCLES + level * ENTER_LEVEL_CYCLES;
+}
+
+/// LEAVE - Tear down a stack frame created by ENTER (80186+)
+/// Opcode: 0xC9
+///
+/// Stack operation: SP = BP, BP = POP()
+pub fn leave(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
+    cpu.write_reg16(4, cpu.read_reg16(5)); // SP = BP
+    let bp = pop_word(cpu, mem);
+    cpu.write_reg16(5, bp);
+    cpu.current_instruction_cycles += LEAVE_CYCLES;
+}
+
 /// Helper: Push a 16-bit value onto the stack
 /// The stack grows downward (SP decrements before write)
 #[inline(always)]
@@ -75,7 +157,13 @@ pub fn push_seg(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction)
 ///
 /// Stack operation: segment register = [SS:SP], SP += 2
 /// NOTE: POP CS (0x0F) is invalid on 8088
+///
+/// POP SS holds off interrupts for one instruction, so the SP load that
+/// follows it completes before anything is pushed on the new stack.
 pub fn pop_seg(cpu: &mut Cpu, mem: &mut MemoryBus, instr: &DecodedInstruction) {
     let value = pop_word(cpu, mem);
     cpu.write_operand(mem, &instr.dst, value);
+    if instr.dst.value == 2 {
+        cpu.set_interrupt_delay();
+    }
 }
@@ -4,6 +4,8 @@
 //! SI and DI registers based on the direction flag (DF).
 //!
 //! When combined with REP prefixes, these instructions repeat while CX != 0.
+//! A REP with CX=0 runs no iterations at all: SI, DI, CX, memory and flags
+//! are left alone and execution continues after the instruction.
 
 use crate::cpu::decode::DecodedInstruction;
 use crate::cpu::state::RepeatPrefix;
@@ -15,6 +17,10 @@ use crate::memory::MemoryBus;
 /// Stores the byte in AL to ES:DI, then increments or decrements DI
 /// based on the direction flag.
 pub fn stosb(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
+    if rep_exhausted(cpu) {
+        return;
+    }
+
     // Store AL to ES:DI
     let es = cpu.read_seg(0);
     let di = cpu.read_reg16(7);
@@ -38,6 +44,10 @@ pub fn stosb(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
 /// Stores the word in AX to ES:DI, then increments or decrements DI by 2
 /// based on the direction flag.
 pub fn stosw(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
+    if rep_exhausted(cpu) {
+        return;
+    }
+
     // Store AX to ES:DI
     let es = cpu.read_seg(0);
     let di = cpu.read_reg16(7);
@@ -60,7 +70,18 @@ pub fn stosw(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
 ///
 /// Copies a byte from DS:SI to ES:DI, then increments or decrements both
 /// SI and DI based on the direction flag.
+///
+/// Under REP each iteration reads its byte before writing it, and nothing
+/// is buffered between iterations. When the regions overlap this is not a
+/// memmove: copying forward (DF=0) onto a destination just above the source
+/// reads bytes that earlier iterations already overwrote, smearing the
+/// first bytes across the destination, exactly as on hardware. Software
+/// that shifts a buffer up sets DF=1 and copies from the top end instead.
 pub fn movsb(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
+    if rep_exhausted(cpu) {
+        return;
+    }
+
     // Read from DS:SI (or segment override)
     let ds = cpu
         .segment_override
@@ -91,7 +112,14 @@ pub fn movsb(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
 ///
 /// Copies a word from DS:SI to ES:DI, then increments or decrements both
 /// SI and DI by 2 based on the direction flag.
+///
+/// Overlap behaves as for `movsb`, but a word at a time: both bytes are
+/// read before either is written.
 pub fn movsw(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
+    if rep_exhausted(cpu) {
+        return;
+    }
+
     // Read from DS:SI (or segment override)
     let ds = cpu
         .segment_override
@@ -123,6 +151,10 @@ pub fn movsw(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
 /// Loads a byte from DS:SI into AL, then increments or decrements SI
 /// based on the direction flag.
 pub fn lodsb(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
+    if rep_exhausted(cpu) {
+        return;
+    }
+
     // Read from DS:SI (or segment override)
     let ds = cpu
         .segment_override
@@ -151,6 +183,10 @@ pub fn lodsb(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
 /// Loads a word from DS:SI into AX, then increments or decrements SI by 2
 /// based on the direction flag.
 pub fn lodsw(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
+    if rep_exhausted(cpu) {
+        return;
+    }
+
     // Read from DS:SI (or segment override)
     let ds = cpu
         .segment_override
@@ -179,6 +215,10 @@ pub fn lodsw(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
 /// Compares byte at DS:SI with byte at ES:DI by subtracting and setting flags,
 /// then increments or decrements SI and DI based on the direction flag.
 pub fn cmpsb(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
+    if rep_exhausted(cpu) {
+        return;
+    }
+
     // Read from DS:SI (or segment override)
     let ds = cpu
         .segment_override
@@ -215,6 +255,10 @@ pub fn cmpsb(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
 /// Compares word at DS:SI with word at ES:DI by subtracting and setting flags,
 /// then increments or decrements SI and DI by 2 based on the direction flag.
 pub fn cmpsw(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
+    if rep_exhausted(cpu) {
+        return;
+    }
+
     // Read from DS:SI (or segment override)
     let ds = cpu
         .segment_override
@@ -251,6 +295,10 @@ pub fn cmpsw(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
 /// Compares AL with byte at ES:DI by subtracting and setting flags,
 /// then increments or decrements DI based on the direction flag.
 pub fn scasb(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
+    if rep_exhausted(cpu) {
+        return;
+    }
+
     // Read AL
     let al = cpu.read_reg8(0);
 
@@ -281,6 +329,10 @@ pub fn scasb(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
 /// Compares AX with word at ES:DI by subtracting and setting flags,
 /// then increments or decrements DI by 2 based on the direction flag.
 pub fn scasw(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
+    if rep_exhausted(cpu) {
+        return;
+    }
+
     // Read AX
     let ax = cpu.read_reg16(0);
 
@@ -306,6 +358,14 @@ pub fn scasw(cpu: &mut Cpu, mem: &mut MemoryBus, _instr: &DecodedInstruction) {
     handle_rep_conditional(cpu);
 }
 
+/// Check for a REP prefix with CX already 0, which skips the operation
+///
+/// CX is tested before the first iteration, so without this the operation
+/// would run once and CX would wrap to 0xFFFF.
+fn rep_exhausted(cpu: &Cpu) -> bool {
+    cpu.repeat_prefix != RepeatPrefix::None && cpu.read_reg16(1) == 0
+}
+
 /// Helper function to handle REP prefix for string operations
 ///
 /// If a REP prefix is active:
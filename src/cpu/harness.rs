@@ -4,7 +4,17 @@
 //! a full emulator. Contains just CPU state and memory bus.
 
 use crate::cpu::Cpu;
-use crate::memory::MemoryBus;
+use crate::memory::{linear, MemoryBus};
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Default instruction budget before the watchdog fires
+pub const DEFAULT_WATCHDOG_LIMIT: u64 = 10_000_000;
+
+/// Number of recent CS:IP values reported when the watchdog fires
+const TRACE_LEN: usize = 16;
 
 /// Test harness for CPU instruction testing
 ///
@@ -16,6 +26,15 @@ pub struct CpuHarness {
 
     /// Memory bus
     pub mem: MemoryBus,
+
+    /// Maximum instructions before panicking (None disables the watchdog)
+    watchdog_limit: Option<u64>,
+
+    /// Instructions executed since the program was loaded or the CPU reset
+    instructions: u64,
+
+    /// CS:IP of the most recently executed instructions, oldest first
+    trace: VecDeque<(u16, u16)>,
 }
 
 impl CpuHarness {
@@ -24,6 +43,9 @@ impl CpuHarness {
         Self {
             cpu: Cpu::new(),
             mem: MemoryBus::new(),
+            watchdog_limit: Some(DEFAULT_WATCHDOG_LIMIT),
+            instructions: 0,
+            trace: VecDeque::with_capacity(TRACE_LEN),
         }
     }
 
@@ -34,7 +56,7 @@ impl CpuHarness {
     pub fn load_program(&mut self, code: &[u8], segment: u16) {
         // Load code into memory at segment:0
         for (i, &byte) in code.iter().enumerate() {
-            let addr = (segment as u32) * 16 + i as u32;
+            let addr = linear(segment, i as u16);
             self.mem.write_u8(addr, byte);
         }
 
@@ -44,12 +66,39 @@ impl CpuHarness {
 
         // Clear decode cache - loaded code may overwrite previously cached instructions
         self.cpu.decode_cache.clear();
+
+        self.restart_watchdog();
+    }
+
+    /// Set the instruction budget, or None to disable the watchdog
+    ///
+    /// Stepping past the budget panics with the current CS:IP and a trace of
+    /// recent instructions, so a runaway loop fails a test instead of hanging.
+    pub fn set_watchdog(&mut self, limit: Option<u64>) {
+        self.watchdog_limit = limit;
+    }
+
+    /// Instructions executed since the program was loaded or the CPU reset
+    pub fn instructions_executed(&self) -> u64 {
+        self.instructions
     }
 
     /// Execute one instruction
     ///
     /// Returns the number of cycles consumed by the instruction.
     pub fn step(&mut self) -> u16 {
+        if let Some(limit) = self.watchdog_limit {
+            if self.instructions >= limit {
+                self.watchdog_fired(limit);
+            }
+        }
+
+        if self.trace.len() == TRACE_LEN {
+            self.trace.pop_front();
+        }
+        self.trace.push_back((self.cpu.segments[1], self.cpu.ip));
+        self.instructions += 1;
+
         self.cpu.step(&mut self.mem)
     }
 
@@ -63,6 +112,27 @@ impl CpuHarness {
     /// Reset CPU to initial state
     pub fn reset(&mut self) {
         self.cpu.reset();
+        self.restart_watchdog();
+    }
+
+    fn restart_watchdog(&mut self) {
+        self.instructions = 0;
+        self.trace.clear();
+    }
+
+    fn watchdog_fired(&self, limit: u64) -> ! {
+        let trace: Vec<String> = self
+            .trace
+            .iter()
+            .map(|(cs, ip)| format!("{:04X}:{:04X}", cs, ip))
+            .collect();
+        panic!(
+            "watchdog: exceeded {} instructions at {:04X}:{:04X}; recent CS:IP: {}",
+            limit,
+            self.cpu.segments[1],
+            self.cpu.ip,
+            trace.join(" ")
+        );
     }
 }
 
@@ -5,13 +5,18 @@
 //! - Tier 2: Decode cache (warm path)
 //! - Tier 3: Compiled basic blocks (hot path)
 
+pub mod coprocessor;
 pub mod decode;
+pub mod disasm;
 pub mod execute;
 pub mod harness;
+pub mod registers;
 pub mod state;
 pub mod tier1;
 pub mod tier2;
 pub mod timing;
 
+pub use coprocessor::Coprocessor;
 pub use harness::CpuHarness;
-pub use state::Cpu;
+pub use registers::{Reg16, Reg8, Seg};
+pub use state::{Cpu, CpuModel, CpuState, Exception, ExceptionKind, InvalidOpcodeHook};
@@ -0,0 +1,89 @@
+//! Named register identifiers
+//!
+//! Each variant's discriminant is the register's encoding in ModR/M and
+//! opcode fields, so `reg as u8` can be passed to the numeric accessors.
+
+/// 8-bit general purpose register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Reg8 {
+    AL = 0,
+    CL = 1,
+    DL = 2,
+    BL = 3,
+    AH = 4,
+    CH = 5,
+    DH = 6,
+    BH = 7,
+}
+
+/// 16-bit general purpose register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Reg16 {
+    AX = 0,
+    CX = 1,
+    DX = 2,
+    BX = 3,
+    SP = 4,
+    BP = 5,
+    SI = 6,
+    DI = 7,
+}
+
+/// Segment register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Seg {
+    ES = 0,
+    CS = 1,
+    SS = 2,
+    DS = 3,
+}
+
+impl Reg8 {
+    /// Look up a register by its 3-bit encoding
+    pub fn from_index(index: u8) -> Option<Self> {
+        match index {
+            0 => Some(Self::AL),
+            1 => Some(Self::CL),
+            2 => Some(Self::DL),
+            3 => Some(Self::BL),
+            4 => Some(Self::AH),
+            5 => Some(Self::CH),
+            6 => Some(Self::DH),
+            7 => Some(Self::BH),
+            _ => None,
+        }
+    }
+}
+
+impl Reg16 {
+    /// Look up a register by its 3-bit encoding
+    pub fn from_index(index: u8) -> Option<Self> {
+        match index {
+            0 => Some(Self::AX),
+            1 => Some(Self::CX),
+            2 => Some(Self::DX),
+            3 => Some(Self::BX),
+            4 => Some(Self::SP),
+            5 => Some(Self::BP),
+            6 => Some(Self::SI),
+            7 => Some(Self::DI),
+            _ => None,
+        }
+    }
+}
+
+impl Seg {
+    /// Look up a segment register by its 2-bit encoding
+    pub fn from_index(index: u8) -> Option<Self> {
+        match index {
+            0 => Some(Self::ES),
+            1 => Some(Self::CS),
+            2 => Some(Self::SS),
+            3 => Some(Self::DS),
+            _ => None,
+        }
+    }
+}
@@ -8,8 +8,49 @@
 //! - Cycle counters
 //! - Prefetch queue
 
+use crate::cpu::coprocessor::Coprocessor;
+use crate::cpu::decode::CodeFetch;
+use crate::cpu::registers::{Reg16, Reg8, Seg};
 use crate::cpu::tier2::DecodeCache;
-use crate::memory::MemoryBus;
+use crate::memory::{linear, MemoryBus};
+use alloc::boxed::Box;
+
+/// Processor the CPU emulates
+///
+/// The 80186 adds a handful of instructions on top of the 8088 set (PUSH imm,
+/// IMUL imm, shift by imm, ENTER/LEAVE) and masks shift counts to 5 bits.
+/// Everything else, including timing, is modeled on the 8088.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CpuModel {
+    /// Intel 8088, as in the IBM PC
+    #[default]
+    I8088,
+    /// Intel 80186/80188 instruction set
+    I80186,
+}
+
+/// What went wrong when a trapped exception stopped the CPU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExceptionKind {
+    /// DIV or IDIV by zero, or a quotient too large for the destination
+    DivideError,
+    /// An opcode the emulated model does not implement
+    InvalidOpcode(u8),
+    /// A software interrupt whose vector is still 0000:0000
+    UnhandledInterrupt(u8),
+}
+
+/// An exception trapped with `Cpu::set_trap_exceptions`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Exception {
+    /// What happened
+    pub kind: ExceptionKind,
+    /// CS:IP of the faulting instruction, including any prefixes
+    pub at: (u16, u16),
+}
+
+/// Called with the opcode byte and CS:IP of each invalid opcode executed
+pub type InvalidOpcodeHook = Box<dyn FnMut(u8, (u16, u16))>;
 
 /// 8088 CPU state
 pub struct Cpu {
@@ -61,6 +102,12 @@ pub struct Cpu {
     /// Cycles spent filling prefetch queue
     prefetch_cycles: u16,
 
+    /// Linear address of the first byte in the prefetch queue
+    prefetch_addr: u32,
+
+    /// Fetch code through the prefetch queue (see `set_prefetch_queue`)
+    prefetch_enabled: bool,
+
     /// Segment override prefix (None or segment index 0-3 for ES/CS/SS/DS)
     pub segment_override: Option<u8>,
 
@@ -81,6 +128,68 @@ pub struct Cpu {
     /// Tier 2 decode cache
     /// Caches decoded instructions to skip decoding for frequently executed code
     pub decode_cache: DecodeCache,
+
+    /// CS:IP loaded by reset (F000:FFF0 unless overridden)
+    reset_vector: (u16, u16),
+
+    /// Attached coprocessor (receives ESC instructions)
+    pub(crate) coprocessor: Option<Box<dyn Coprocessor>>,
+
+    /// Instruction set being emulated
+    model: CpuModel,
+
+    /// IP of the first byte (including prefixes) of the current instruction
+    instruction_ip: u16,
+
+    /// Stop at faults instead of vectoring or panicking
+    trap_exceptions: bool,
+
+    /// Exception trapped by the last step, if any
+    exception: Option<Exception>,
+
+    /// Observer for invalid opcodes (see `set_invalid_opcode_hook`)
+    invalid_opcode_hook: Option<InvalidOpcodeHook>,
+}
+
+/// Generate `cpu.al()` / `cpu.set_al(value)` style accessors for named registers
+macro_rules! named_register_accessors {
+    ($read:ident, $write:ident, $kind:ident, $ty:ty: $($reg:ident => $get:ident, $set:ident;)*) => {
+        $(
+            #[doc = concat!("Read ", stringify!($reg))]
+            #[inline(always)]
+            pub fn $get(&self) -> $ty {
+                self.$read($kind::$reg)
+            }
+
+            #[doc = concat!("Write ", stringify!($reg))]
+            #[inline(always)]
+            pub fn $set(&mut self, value: $ty) {
+                self.$write($kind::$reg, value)
+            }
+        )*
+    };
+}
+
+/// Architectural CPU state captured at an instruction boundary
+///
+/// Flags are stored materialized, so restoring does not depend on the lazy
+/// flag evaluation state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuState {
+    /// General purpose registers (AX, CX, DX, BX, SP, BP, SI, DI)
+    pub regs: [u16; 8],
+    /// Segment registers (ES, CS, SS, DS)
+    pub segments: [u16; 4],
+    /// Instruction pointer
+    pub ip: u16,
+    /// Flags register
+    pub flags: u16,
+    /// CPU is halted waiting for an interrupt
+    pub halted: bool,
+    /// Interrupt recognition is delayed by one instruction (after STI)
+    pub delay_interrupt: bool,
+    /// Total CPU cycles executed
+    pub total_cycles: u64,
 }
 
 /// Repeat prefix type for string operations
@@ -121,11 +230,22 @@ pub enum FlagOp {
 impl Cpu {
     /// Create a new CPU with reset state
     pub fn new() -> Self {
+        Self::with_decode_cache(DecodeCache::new())
+    }
+
+    /// Create a CPU that only decodes instructions, as for `decode_one`
+    ///
+    /// Its decode cache has no room, so creating one allocates nothing.
+    pub(crate) fn for_decoding() -> Self {
+        Self::with_decode_cache(DecodeCache::with_capacity(0))
+    }
+
+    fn with_decode_cache(decode_cache: DecodeCache) -> Self {
         Self {
             regs: [0; 8],
             segments: [0; 4],
             ip: 0,
-            flags: 0,
+            flags: Self::FLAGS_ALWAYS_SET,
             last_result: 0,
             last_op: FlagOp::None,
             total_cycles: 0,
@@ -133,12 +253,21 @@ impl Cpu {
             prefetch_queue: [0; 4],
             prefetch_len: 0,
             prefetch_cycles: 0,
+            prefetch_addr: 0,
+            prefetch_enabled: false,
             segment_override: None,
             repeat_prefix: RepeatPrefix::None,
             repeat_ip: 0,
             delay_interrupt: false,
             halted: false,
-            decode_cache: DecodeCache::new(),
+            decode_cache,
+            reset_vector: (0xF000, 0xFFF0),
+            coprocessor: None,
+            model: CpuModel::I8088,
+            instruction_ip: 0,
+            trap_exceptions: false,
+            exception: None,
+            invalid_opcode_hook: None,
         }
     }
 
@@ -147,9 +276,9 @@ impl Cpu {
     pub fn reset(&mut self) {
         self.regs = [0; 8];
         self.segments = [0; 4];
-        self.segments[1] = 0xF000; // CS = 0xF000
-        self.ip = 0xFFF0; // IP = 0xFFF0
-        self.flags = 0x0002; // Bit 1 is always set on 8088
+        self.segments[1] = self.reset_vector.0; // CS = 0xF000 by default
+        self.ip = self.reset_vector.1; // IP = 0xFFF0 by default
+        self.flags = Self::FLAGS_ALWAYS_SET;
         self.last_result = 0;
         self.last_op = FlagOp::None;
         self.total_cycles = 0;
@@ -161,6 +290,167 @@ impl Cpu {
         self.repeat_prefix = RepeatPrefix::None;
         self.repeat_ip = 0;
         self.halted = false;
+        self.exception = None;
+        self.decode_cache.clear();
+        if let Some(coprocessor) = self.coprocessor.as_mut() {
+            coprocessor.reset();
+        }
+    }
+
+    /// Reset CPU and start execution at CS:IP instead of F000:FFF0
+    ///
+    /// The new address is kept as the reset vector, so later resets return
+    /// to it as well. Useful for firmware or boot sectors loaded in RAM.
+    pub fn reset_to(&mut self, cs: u16, ip: u16) {
+        self.set_reset_vector(cs, ip);
+        self.reset();
+    }
+
+    /// Override the CS:IP that `reset()` starts execution at
+    pub fn set_reset_vector(&mut self, cs: u16, ip: u16) {
+        self.reset_vector = (cs, ip);
+    }
+
+    /// Get the CS:IP that `reset()` starts execution at
+    pub fn reset_vector(&self) -> (u16, u16) {
+        self.reset_vector
+    }
+
+    /// Select the instruction set to emulate
+    ///
+    /// Clears the decode cache, since the same bytes decode differently on
+    /// each model. The model survives `reset()`.
+    pub fn set_model(&mut self, model: CpuModel) {
+        self.model = model;
+        self.decode_cache.clear();
+    }
+
+    /// Get the instruction set being emulated
+    pub fn model(&self) -> CpuModel {
+        self.model
+    }
+
+    /// Stop at faults instead of handling them
+    ///
+    /// While enabled, a divide error, an invalid opcode or a software
+    /// interrupt through an unset vector leaves CS:IP at the faulting
+    /// instruction, with nothing pushed, and is reported by
+    /// `take_exception`. Stepping again retries the instruction. Off by
+    /// default: faults vector through the IVT and invalid opcodes panic.
+    pub fn set_trap_exceptions(&mut self, enabled: bool) {
+        self.trap_exceptions = enabled;
+        self.exception = None;
+    }
+
+    /// Check whether faults are trapped
+    pub fn trap_exceptions(&self) -> bool {
+        self.trap_exceptions
+    }
+
+    /// Take the exception trapped by the last step, if any
+    pub fn take_exception(&mut self) -> Option<Exception> {
+        self.exception.take()
+    }
+
+    /// Trap a fault raised by the current instruction
+    ///
+    /// Returns false if trapping is disabled, in which case the caller
+    /// handles the fault as the hardware would.
+    pub(crate) fn trap_exception(&mut self, kind: ExceptionKind) -> bool {
+        if !self.trap_exceptions {
+            return false;
+        }
+        let at = (self.segments[1], self.instruction_ip);
+        self.ip = self.instruction_ip;
+        self.flush_prefetch_queue();
+        self.exception = Some(Exception { kind, at });
+        true
+    }
+
+    /// Set or clear a callback invoked for every invalid opcode executed
+    ///
+    /// The callback sees the opcode byte and the CS:IP of the instruction
+    /// (including any prefixes) before the fault is trapped or raised, so
+    /// it observes invalid opcodes whatever the exception policy.
+    pub fn set_invalid_opcode_hook(&mut self, hook: Option<InvalidOpcodeHook>) {
+        self.invalid_opcode_hook = hook;
+    }
+
+    /// Report an invalid opcode to the hook, if one is set
+    pub(crate) fn notify_invalid_opcode(&mut self, opcode: u8) {
+        let at = (self.segments[1], self.instruction_ip);
+        if let Some(hook) = self.invalid_opcode_hook.as_mut() {
+            hook(opcode, at);
+        }
+    }
+
+    /// Model the 4-byte prefetch queue when fetching instructions
+    ///
+    /// With the queue modeled, a store to code the BIU has already queued
+    /// does not affect execution until the queue is flushed by a jump, as on
+    /// real hardware. Instructions are decoded on every step rather than
+    /// taken from the decode cache, so this is slower. Off by default.
+    pub fn set_prefetch_queue(&mut self, enabled: bool) {
+        self.prefetch_enabled = enabled;
+        self.flush_prefetch_queue();
+    }
+
+    /// Check if the prefetch queue is modeled
+    pub fn prefetch_queue_enabled(&self) -> bool {
+        self.prefetch_enabled
+    }
+
+    /// Attach a coprocessor to receive ESC instructions
+    ///
+    /// Returns the previously attached coprocessor, if any.
+    pub fn attach_coprocessor(
+        &mut self,
+        coprocessor: Box<dyn Coprocessor>,
+    ) -> Option<Box<dyn Coprocessor>> {
+        self.coprocessor.replace(coprocessor)
+    }
+
+    /// Detach the coprocessor; ESC becomes a no-op again
+    pub fn detach_coprocessor(&mut self) -> Option<Box<dyn Coprocessor>> {
+        self.coprocessor.take()
+    }
+
+    /// Check if a coprocessor is attached
+    pub fn has_coprocessor(&self) -> bool {
+        self.coprocessor.is_some()
+    }
+
+    /// Capture the architectural state between instructions
+    pub fn save_state(&self) -> CpuState {
+        CpuState {
+            regs: self.regs,
+            segments: self.segments,
+            ip: self.ip,
+            flags: self.compute_flags(),
+            halted: self.halted,
+            delay_interrupt: self.delay_interrupt,
+            total_cycles: self.total_cycles,
+        }
+    }
+
+    /// Restore state captured by `save_state`
+    ///
+    /// The prefetch queue and decode cache are flushed, since memory may have
+    /// changed along with the registers.
+    pub fn load_state(&mut self, state: &CpuState) {
+        self.regs = state.regs;
+        self.segments = state.segments;
+        self.ip = state.ip;
+        self.set_flags(state.flags);
+        self.halted = state.halted;
+        self.delay_interrupt = state.delay_interrupt;
+        self.total_cycles = state.total_cycles;
+        self.current_instruction_cycles = 0;
+        self.prefetch_queue = [0; 4];
+        self.prefetch_len = 0;
+        self.prefetch_cycles = 0;
+        self.segment_override = None;
+        self.repeat_prefix = RepeatPrefix::None;
         self.decode_cache.clear();
     }
 
@@ -222,19 +512,77 @@ impl Cpu {
         self.segments[(seg & 0b11) as usize] = value;
     }
 
-    // === Memory Access Methods ===
+    /// Read an 8-bit register by name
+    #[inline(always)]
+    pub fn reg8(&self, reg: Reg8) -> u8 {
+        self.read_reg8(reg as u8)
+    }
+
+    /// Write an 8-bit register by name
+    #[inline(always)]
+    pub fn set_reg8(&mut self, reg: Reg8, value: u8) {
+        self.write_reg8(reg as u8, value)
+    }
+
+    /// Read a 16-bit register by name
+    #[inline(always)]
+    pub fn reg16(&self, reg: Reg16) -> u16 {
+        self.read_reg16(reg as u8)
+    }
+
+    /// Write a 16-bit register by name
+    #[inline(always)]
+    pub fn set_reg16(&mut self, reg: Reg16, value: u16) {
+        self.write_reg16(reg as u8, value)
+    }
+
+    /// Read a segment register by name
+    #[inline(always)]
+    pub fn seg(&self, seg: Seg) -> u16 {
+        self.read_seg(seg as u8)
+    }
 
-    /// Compute physical address from segment:offset
-    /// Physical address = (segment << 4) + offset
+    /// Write a segment register by name
     #[inline(always)]
-    pub fn compute_address(segment: u16, offset: u16) -> u32 {
-        ((segment as u32) << 4) + (offset as u32)
+    pub fn set_seg(&mut self, seg: Seg, value: u16) {
+        self.write_seg(seg as u8, value)
     }
 
+    named_register_accessors!(reg8, set_reg8, Reg8, u8:
+        AL => al, set_al;
+        CL => cl, set_cl;
+        DL => dl, set_dl;
+        BL => bl, set_bl;
+        AH => ah, set_ah;
+        CH => ch, set_ch;
+        DH => dh, set_dh;
+        BH => bh, set_bh;
+    );
+
+    named_register_accessors!(reg16, set_reg16, Reg16, u16:
+        AX => ax, set_ax;
+        CX => cx, set_cx;
+        DX => dx, set_dx;
+        BX => bx, set_bx;
+        SP => sp, set_sp;
+        BP => bp, set_bp;
+        SI => si, set_si;
+        DI => di, set_di;
+    );
+
+    named_register_accessors!(seg, set_seg, Seg, u16:
+        ES => es, set_es;
+        CS => cs, set_cs;
+        SS => ss, set_ss;
+        DS => ds, set_ds;
+    );
+
+    // === Memory Access Methods ===
+
     /// Read a byte from memory using segment:offset addressing
     #[inline(always)]
     pub fn read_mem8(&self, mem: &MemoryBus, segment: u16, offset: u16) -> u8 {
-        let addr = Self::compute_address(segment, offset);
+        let addr = linear(segment, offset);
         mem.read_u8(addr)
     }
 
@@ -245,7 +593,7 @@ impl Cpu {
     /// the written address could include this byte (8088 max instruction length is ~6 bytes).
     #[inline(always)]
     pub fn write_mem8(&mut self, mem: &mut MemoryBus, segment: u16, offset: u16, value: u8) {
-        let addr = Self::compute_address(segment, offset);
+        let addr = linear(segment, offset);
         mem.write_u8(addr, value);
         // Invalidate decode cache - must invalidate any instruction that could include this byte
         // An instruction starting up to 6 bytes before could include this byte
@@ -254,9 +602,17 @@ impl Cpu {
     }
 
     /// Read a word from memory using segment:offset addressing
+    ///
+    /// A word at offset 0xFFFF wraps within the segment: the high byte is read
+    /// from offset 0x0000, as on the 8088.
     #[inline(always)]
     pub fn read_mem16(&self, mem: &MemoryBus, segment: u16, offset: u16) -> u16 {
-        let addr = Self::compute_address(segment, offset);
+        if offset == 0xFFFF {
+            let lo = self.read_mem8(mem, segment, offset) as u16;
+            let hi = self.read_mem8(mem, segment, 0) as u16;
+            return lo | (hi << 8);
+        }
+        let addr = linear(segment, offset);
         mem.read_u16(addr)
     }
 
@@ -265,9 +621,16 @@ impl Cpu {
     /// Also invalidates the decode cache at nearby addresses to support self-modifying code.
     /// We invalidate addresses [addr-6, addr+1] because an instruction up to 6 bytes before
     /// the written address could include these bytes.
+    ///
+    /// Like reads, a word at offset 0xFFFF wraps within the segment.
     #[inline(always)]
     pub fn write_mem16(&mut self, mem: &mut MemoryBus, segment: u16, offset: u16, value: u16) {
-        let addr = Self::compute_address(segment, offset);
+        if offset == 0xFFFF {
+            self.write_mem8(mem, segment, offset, value as u8);
+            self.write_mem8(mem, segment, 0, (value >> 8) as u8);
+            return;
+        }
+        let addr = linear(segment, offset);
         mem.write_u16(addr, value);
         // Invalidate decode cache - must invalidate any instruction that could include these bytes
         // An instruction starting up to 6 bytes before could include the first written byte
@@ -288,6 +651,20 @@ impl Cpu {
     pub const DF: u16 = 1 << 10; // Direction
     pub const OF: u16 = 1 << 11; // Overflow
 
+    /// FLAGS bits that always read as 1 on the 8088 (bit 1 and bits 12-15)
+    pub const FLAGS_ALWAYS_SET: u16 = 0xF002;
+
+    /// FLAGS bits the 8088 actually stores; bits 3 and 5 always read as 0
+    pub const FLAGS_STORED: u16 = Self::CF
+        | Self::PF
+        | Self::AF
+        | Self::ZF
+        | Self::SF
+        | Self::TF
+        | Self::IF
+        | Self::DF
+        | Self::OF;
+
     /// Set lazy flag state after an operation
     #[inline(always)]
     pub fn set_lazy_flags(&mut self, result: u32, op: FlagOp) {
@@ -298,14 +675,14 @@ impl Cpu {
     /// Compute flags from lazy state
     /// OF, AF, and control flags (DF, IF, TF) are set eagerly and preserved from self.flags
     /// Other flags (CF, ZF, SF, PF) are computed lazily from last_result and last_op
-    fn compute_flags(&self) -> u16 {
-        let mut flags = 0b0010; // Bit 1 always set on 8088
+    pub(crate) fn compute_flags(&self) -> u16 {
+        let mut flags = Self::FLAGS_ALWAYS_SET;
 
         // Preserve OF, AF, and control flags (DF, IF, TF) which are set eagerly
         flags |= self.flags & (Self::OF | Self::AF | Self::DF | Self::IF | Self::TF);
 
         match self.last_op {
-            FlagOp::None => return self.flags | 0b0010, // Ensure bit 1 is set
+            FlagOp::None => return self.flags | Self::FLAGS_ALWAYS_SET,
 
             FlagOp::Add8
             | FlagOp::Adc8
@@ -399,9 +776,13 @@ impl Cpu {
     }
 
     /// Set the flags register directly
+    ///
+    /// Reserved bits are forced to the values the 8088 reads back, whatever
+    /// `flags` holds, so a FLAGS image popped by POPF or IRET round-trips
+    /// exactly through PUSHF.
     #[inline(always)]
     pub fn set_flags(&mut self, flags: u16) {
-        self.flags = flags | 0b0010; // Bit 1 always set
+        self.flags = (flags & Self::FLAGS_STORED) | Self::FLAGS_ALWAYS_SET;
         self.last_op = FlagOp::None;
     }
 
@@ -687,16 +1068,22 @@ impl Cpu {
     // === Instruction Decoding Methods ===
 
     /// Fetch a byte from CS:IP and advance IP
+    ///
+    /// Comes from the prefetch queue when it is modeled and holds CS:IP.
     #[inline(always)]
-    pub fn fetch_u8(&mut self, mem: &MemoryBus) -> u8 {
-        let byte = self.read_mem8(mem, self.segments[1], self.ip);
+    pub fn fetch_u8<M: CodeFetch + ?Sized>(&mut self, mem: &M) -> u8 {
+        let byte = if self.prefetch_enabled {
+            self.fetch_queued_u8(mem)
+        } else {
+            mem.code_byte(linear(self.segments[1], self.ip))
+        };
         self.ip = self.ip.wrapping_add(1);
         byte
     }
 
     /// Fetch a word from CS:IP and advance IP (little-endian)
     #[inline(always)]
-    pub fn fetch_u16(&mut self, mem: &MemoryBus) -> u16 {
+    pub fn fetch_u16<M: CodeFetch + ?Sized>(&mut self, mem: &M) -> u16 {
         let low = self.fetch_u8(mem) as u16;
         let high = self.fetch_u8(mem) as u16;
         (high << 8) | low
@@ -704,19 +1091,19 @@ impl Cpu {
 
     /// Fetch a signed byte from CS:IP and advance IP
     #[inline(always)]
-    pub fn fetch_i8(&mut self, mem: &MemoryBus) -> i8 {
+    pub fn fetch_i8<M: CodeFetch + ?Sized>(&mut self, mem: &M) -> i8 {
         self.fetch_u8(mem) as i8
     }
 
     /// Fetch a signed word from CS:IP and advance IP (little-endian)
     #[inline(always)]
-    pub fn fetch_i16(&mut self, mem: &MemoryBus) -> i16 {
+    pub fn fetch_i16<M: CodeFetch + ?Sized>(&mut self, mem: &M) -> i16 {
         self.fetch_u16(mem) as i16
     }
 
     /// Decode a ModR/M byte from CS:IP
     /// Returns the decoded ModR/M with any displacement/address loaded
-    pub fn decode_modrm(&mut self, mem: &MemoryBus) -> crate::cpu::decode::ModRM {
+    pub fn decode_modrm<M: CodeFetch + ?Sized>(&mut self, mem: &M) -> crate::cpu::decode::ModRM {
         use crate::cpu::decode::{AddressingMode, ModRM};
 
         let modrm_byte = self.fetch_u8(mem);
@@ -1028,6 +1415,41 @@ impl Cpu {
         self.prefetch_cycles = 0;
     }
 
+    /// Take the byte at CS:IP from the prefetch queue
+    ///
+    /// If the queue does not start at CS:IP (IP was changed without a
+    /// flush, e.g. by a debugger), it is discarded and the byte is read from
+    /// memory instead.
+    fn fetch_queued_u8<M: CodeFetch + ?Sized>(&mut self, mem: &M) -> u8 {
+        let addr = linear(self.segments[1], self.ip);
+        if self.prefetch_len == 0 || self.prefetch_addr != addr {
+            self.prefetch_len = 0;
+            self.prefetch_addr = addr.wrapping_add(1);
+            return mem.code_byte(addr);
+        }
+        let byte = self.prefetch_queue[0];
+        self.prefetch_queue.copy_within(1.., 0);
+        self.prefetch_len -= 1;
+        self.prefetch_addr = addr.wrapping_add(1);
+        byte
+    }
+
+    /// Top up the prefetch queue with the bytes following CS:IP
+    ///
+    /// Called once an instruction is decoded and before it executes, so the
+    /// bytes it stores to code already in the queue are not seen.
+    fn fill_prefetch_queue(&mut self, mem: &MemoryBus) {
+        let cs = self.segments[1];
+        if self.prefetch_len == 0 {
+            self.prefetch_addr = linear(cs, self.ip);
+        }
+        while (self.prefetch_len as usize) < self.prefetch_queue.len() {
+            let offset = self.ip.wrapping_add(self.prefetch_len as u16);
+            self.prefetch_queue[self.prefetch_len as usize] = self.read_mem8(mem, cs, offset);
+            self.prefetch_len += 1;
+        }
+    }
+
     // === Execution Methods ===
 
     /// Execute one instruction (tier 1/2 execution)
@@ -1044,7 +1466,7 @@ impl Cpu {
     ///
     /// Returns the number of CPU cycles consumed.
     pub fn step(&mut self, mem: &mut MemoryBus) -> u16 {
-        use crate::cpu::tier1::DISPATCH_TABLE;
+        use crate::cpu::tier1::handler_for;
         use crate::cpu::timing::SEGMENT_OVERRIDE_CYCLES;
 
         // If CPU is halted, skip instruction execution but check for interrupts
@@ -1062,66 +1484,75 @@ impl Cpu {
         self.repeat_prefix = RepeatPrefix::None;
 
         let cs = self.read_seg(1);
+        self.instruction_ip = self.ip;
 
         // Track if segment override was used (for timing penalty)
         let mut had_segment_override = false;
 
-        // Execute instruction, looping while prefix handlers set state
+        // Execute instruction, looping while prefix handlers set state.
+        // A prefix and the instruction it modifies are atomic: interrupts are
+        // only checked once a non-prefix opcode has executed.
         loop {
-            // Remember if we had prefix state set before this instruction
-            let had_seg_override = self.segment_override;
-            let had_repeat_prefix = self.repeat_prefix;
-
             // Compute physical address for cache lookup
-            let instr_addr = Self::compute_address(cs, self.ip);
+            let instr_addr = linear(cs, self.ip);
 
             // Check decode cache first (tier 2)
             // Skip cache if segment override is active - the override gets baked into
             // operands at decode time, so cached instructions with/without overrides
             // would be incompatible.
-            let instr = if self.segment_override.is_none() {
-                if let Some(entry) = self.decode_cache.get(instr_addr) {
+            let instr = if self.segment_override.is_none() && !self.prefetch_enabled {
+                // Instruction bytes wrap within CS, like instruction fetch
+                let ip = self.ip;
+                let code_byte = |i: u16| mem.peek_u8(linear(cs, ip.wrapping_add(i)));
+
+                if let Some(entry) = self.decode_cache.get_checked(instr_addr, code_byte) {
                     // Cache hit: use cached instruction, advance IP by instruction length
                     let instr = entry.instruction.clone();
                     self.ip = self.ip.wrapping_add(instr.length as u16);
                     instr
                 } else {
                     // Cache miss: decode with tier 1 and cache the result
-                    let opcode = self.read_mem8(mem, cs, self.ip);
-                    self.ip = self.ip.wrapping_add(1);
+                    let opcode = self.fetch_u8(mem);
 
-                    let handler = DISPATCH_TABLE[opcode as usize];
+                    let handler = handler_for(self.model, opcode);
                     let instr = self.decode_instruction_t1(mem, opcode, handler);
 
-                    // Cache the decoded instruction
-                    self.decode_cache.insert(instr_addr, instr.clone());
+                    // Cache the decoded instruction with the bytes it came from
+                    let mut bytes = [0u8; 6];
+                    for (i, byte) in bytes.iter_mut().enumerate() {
+                        *byte = code_byte(i as u16);
+                    }
+                    let len = (instr.length as usize).min(bytes.len());
+                    self.decode_cache
+                        .insert(instr_addr, instr.clone(), &bytes[..len]);
                     instr
                 }
             } else {
-                // Segment override active - always use tier 1 decode, don't cache
-                let opcode = self.read_mem8(mem, cs, self.ip);
-                self.ip = self.ip.wrapping_add(1);
+                // Segment override active or prefetch queue modeled - always
+                // use tier 1 decode, don't cache
+                let opcode = self.fetch_u8(mem);
 
-                let handler = DISPATCH_TABLE[opcode as usize];
+                let handler = handler_for(self.model, opcode);
                 self.decode_instruction_t1(mem, opcode, handler)
             };
 
+            if self.prefetch_enabled {
+                self.fill_prefetch_queue(mem);
+            }
+
             // Apply base cycles and EA cycles from decoded instruction
             self.current_instruction_cycles += instr.total_cycles() as u16;
 
             // Execute the instruction (handler may add extra cycles for variable timing)
             instr.execute(self, mem);
 
-            // If a prefix was set by this instruction, continue to fetch next byte
-            // Otherwise, we executed the actual instruction and we're done
-            if self.segment_override == had_seg_override && self.repeat_prefix == had_repeat_prefix
-            {
-                break;
-            }
-
-            // Track that we processed a segment override prefix
-            if self.segment_override != had_seg_override {
-                had_segment_override = true;
+            // If this was a prefix, continue to fetch the next byte. Check the
+            // opcode rather than prefix state, so a repeated prefix (ES: ES:)
+            // is not mistaken for a complete instruction.
+            match instr.opcode {
+                0x26 | 0x2E | 0x36 | 0x3E => had_segment_override = true,
+                0xF2 | 0xF3 => {}
+                _ => break,
             }
         }
 
@@ -1130,8 +1561,15 @@ impl Cpu {
             self.current_instruction_cycles += SEGMENT_OVERRIDE_CYCLES as u16;
         }
 
-        // After instruction execution, check for hardware interrupts
-        self.check_interrupts(mem);
+        // Prefixes last exactly one instruction, whether it completed or faulted
+        self.segment_override = None;
+        self.repeat_prefix = RepeatPrefix::None;
+
+        // After instruction execution, check for hardware interrupts. A
+        // trapped fault stops the CPU at the faulting instruction instead.
+        if self.exception.is_none() {
+            self.check_interrupts(mem);
+        }
 
         // Accumulate instruction cycles into total cycles
         self.total_cycles += self.current_instruction_cycles as u64;
@@ -1150,8 +1588,6 @@ impl Cpu {
     ///
     /// Note: After STI, interrupt recognition is delayed by one instruction
     fn check_interrupts(&mut self, mem: &mut MemoryBus) {
-        use crate::cpu::execute::control_flow::enter_interrupt;
-
         // If interrupt recognition is delayed (after STI), skip this check
         // and clear the delay flag for next instruction
         if self.delay_interrupt {
@@ -1159,7 +1595,7 @@ impl Cpu {
             {
                 // Only log if there's actually a pending interrupt we're delaying
                 if mem.pic().intr_out() {
-                    println!("[CPU] check_interrupts: delay_interrupt set, skipping (PIC has pending IRQ)");
+                    log_debug!("[CPU] check_interrupts: delay_interrupt set, skipping (PIC has pending IRQ)");
                 }
             }
             self.delay_interrupt = false;
@@ -1176,17 +1612,14 @@ impl Cpu {
             return;
         }
 
-        // Clear halt flag - interrupt wakes CPU from halt
-        self.halted = false;
-
         // Acknowledge interrupt and get vector number
-        let vector = mem.pic_mut().inta();
+        let vector = mem.pic_mut().acknowledge();
 
         #[cfg(debug_assertions)]
         {
             let cs = self.read_seg(1);
             let ip = self.ip;
-            println!(
+            log_debug!(
                 "[INT] Hardware interrupt 0x{:02X} (IRQ{}) at {:04X}:{:04X}",
                 vector,
                 vector.wrapping_sub(0x08),
@@ -1195,8 +1628,41 @@ impl Cpu {
             );
         }
 
-        // Use common interrupt entry sequence
-        enter_interrupt(self, mem, vector);
+        self.interrupt(mem, vector);
+    }
+
+    /// Enter the handler for interrupt `vector`
+    ///
+    /// The entry sequence shared by INT, divide errors and hardware
+    /// interrupts: push FLAGS, clear TF and IF, push CS and IP, then load
+    /// CS:IP from the IVT entry at `vector * 4`. Host code can call it
+    /// between instructions to inject an interrupt, which also wakes a
+    /// halted CPU. Unlike INT, a missing handler is never trapped.
+    pub fn interrupt(&mut self, mem: &mut MemoryBus, vector: u8) {
+        use crate::cpu::execute::stack::push_word;
+
+        // Prefixes apply to the interrupted instruction only, never the handler
+        self.segment_override = None;
+        self.repeat_prefix = RepeatPrefix::None;
+        self.halted = false;
+
+        let flags = self.get_flags();
+        push_word(self, mem, flags);
+        self.set_flag(Self::TF, false);
+        self.set_flag(Self::IF, false);
+
+        let return_cs = self.read_seg(1); // CS
+        push_word(self, mem, return_cs);
+        let return_ip = self.ip;
+        push_word(self, mem, return_ip);
+
+        // Each IVT entry is an offset word followed by a segment word
+        let ivt_addr = (vector as u32) * 4;
+        let new_ip = mem.read_u16(ivt_addr);
+        let new_cs = mem.read_u16(ivt_addr + 2);
+        self.write_seg(1, new_cs); // CS
+        self.ip = new_ip;
+        self.flush_prefetch_queue();
     }
 
     /// Set the interrupt delay flag (called by STI)
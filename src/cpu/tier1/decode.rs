@@ -6,10 +6,11 @@
 
 use crate::cpu::decode::instruction::DecodedInstruction;
 use crate::cpu::decode::operands::Operand;
+use crate::cpu::decode::CodeFetch;
 use crate::cpu::timing::{
     calculate_memory_timing, calculate_total_ea_cycles, BASE_CYCLES, WORD_TRANSFER_PENALTY,
 };
-use crate::cpu::Cpu;
+use crate::cpu::{Cpu, CpuModel};
 use crate::memory::MemoryBus;
 
 impl Cpu {
@@ -17,9 +18,9 @@ impl Cpu {
     ///
     /// This is called during tier 1 execution to decode operands on-the-fly.
     /// The handler is already selected from the dispatch table.
-    pub fn decode_instruction_t1(
+    pub fn decode_instruction_t1<M: CodeFetch + ?Sized>(
         &mut self,
-        mem: &MemoryBus,
+        mem: &M,
         opcode: u8,
         handler: fn(&mut Cpu, &mut MemoryBus, &DecodedInstruction),
     ) -> DecodedInstruction {
@@ -586,6 +587,12 @@ impl Cpu {
                 instr = instr.with_src(Operand::imm8(int_num)).with_length(2);
             }
 
+            // INTO (0xCE) - no operands
+            0xCE => {
+                // No operands needed
+                instr = instr.with_length(1);
+            }
+
             // IRET (0xCF) - no operands
             0xCF => {
                 // No operands needed
@@ -689,6 +696,19 @@ impl Cpu {
                 }
             }
 
+            // Group 0x8F: POP r/m16 (reg=0 only)
+            0x8F => {
+                let modrm = self.fetch_u8(mem);
+                let reg = (modrm >> 3) & 0x07;
+                let (rm_operand, extra_len) = self.decode_rm_from_modrm_byte(mem, modrm, false);
+
+                // Store reg field in high byte of dst.value for group_8f to use
+                let mut dst_with_reg = rm_operand;
+                dst_with_reg.value = (dst_with_reg.value & 0xFF) | ((reg as u16) << 8);
+
+                instr = instr.with_dst(dst_with_reg).with_length(1 + 1 + extra_len);
+            }
+
             // Group 0xFE: INC/DEC r/m8
             0xFE => {
                 let modrm = self.fetch_u8(mem);
@@ -814,6 +834,78 @@ impl Cpu {
                 instr = instr.with_src(Operand::imm8(base)).with_length(2);
             }
 
+            // ESC - Escape to coprocessor (0xD8-0xDF)
+            0xD8..=0xDF => {
+                // The coprocessor decodes the instruction itself, so pass the
+                // raw ModR/M byte along with the decoded r/m operand
+                let modrm = self.fetch_u8(mem);
+                let (rm_operand, extra_len) = self.decode_rm_from_modrm_byte(mem, modrm, false);
+                instr = instr
+                    .with_dst(rm_operand)
+                    .with_src(Operand::imm8(modrm))
+                    .with_length(1 + 1 + extra_len);
+            }
+
+            // 80186: PUSH imm16 (0x68)
+            0x68 if self.model() == CpuModel::I80186 => {
+                let imm = self.fetch_u16(mem);
+                instr = instr.with_src(Operand::imm16(imm)).with_length(3);
+            }
+
+            // 80186: PUSH imm8, sign-extended (0x6A)
+            0x6A if self.model() == CpuModel::I80186 => {
+                let imm = self.fetch_u8(mem) as i8 as i16 as u16;
+                instr = instr.with_src(Operand::imm16(imm)).with_length(2);
+            }
+
+            // 80186: IMUL r16, r/m16, imm16 (0x69) / imm8 sign-extended (0x6B)
+            0x69 | 0x6B if self.model() == CpuModel::I80186 => {
+                let modrm = self.fetch_u8(mem);
+                let reg = (modrm >> 3) & 0x07;
+                let (rm_operand, extra_len) = self.decode_rm_from_modrm_byte(mem, modrm, false);
+                let (imm, imm_len) = if opcode == 0x69 {
+                    (self.fetch_u16(mem), 2)
+                } else {
+                    (self.fetch_u8(mem) as i8 as i16 as u16, 1)
+                };
+
+                // Store destination register in high byte of dst.value
+                let mut dst_with_reg = rm_operand;
+                dst_with_reg.value = (dst_with_reg.value & 0xFF) | ((reg as u16) << 8);
+
+                instr = instr
+                    .with_dst(dst_with_reg)
+                    .with_src(Operand::imm16(imm))
+                    .with_length(1 + 1 + extra_len + imm_len);
+            }
+
+            // 80186: Shift/rotate r/m8 (0xC0) or r/m16 (0xC1) by imm8
+            0xC0 | 0xC1 if self.model() == CpuModel::I80186 => {
+                let modrm = self.fetch_u8(mem);
+                let reg = (modrm >> 3) & 0x07; // Operation type
+                let (rm_operand, extra_len) =
+                    self.decode_rm_from_modrm_byte(mem, modrm, opcode == 0xC0);
+                let count = self.fetch_u8(mem);
+
+                // Operation type in the low byte of src.value, count in the high byte
+                instr = instr
+                    .with_dst(rm_operand)
+                    .with_src(Operand::imm16(((count as u16) << 8) | reg as u16))
+                    .with_length(1 + 1 + extra_len + 1);
+            }
+
+            // 80186: ENTER imm16, imm8 (0xC8)
+            0xC8 if self.model() == CpuModel::I80186 => {
+                let frame_size = self.fetch_u16(mem);
+                let level = self.fetch_u8(mem);
+                instr = instr
+                    .with_dst(Operand::imm8(level))
+                    .with_src(Operand::imm16(frame_size))
+                    .with_length(4);
+            }
+
+            // 80186: LEAVE (0xC9) has no operands
+
             // Default case for unimplemented/invalid opcodes
             _ => {
                 // No operands, length is just 1
@@ -845,7 +937,11 @@ impl Cpu {
     ///
     /// Returns the two operands decoded from ModR/M and the total length
     /// of the ModR/M byte + displacement
-    fn decode_modrm_operands(&mut self, mem: &MemoryBus, is_byte: bool) -> (Operand, Operand, u8) {
+    fn decode_modrm_operands<M: CodeFetch + ?Sized>(
+        &mut self,
+        mem: &M,
+        is_byte: bool,
+    ) -> (Operand, Operand, u8) {
         let modrm = self.fetch_u8(mem);
         let reg = (modrm >> 3) & 0x07;
 
@@ -864,9 +960,9 @@ impl Cpu {
     /// Helper: Decode the r/m operand from ModR/M byte
     ///
     /// Returns (operand, displacement_length)
-    fn decode_rm_from_modrm_byte(
+    fn decode_rm_from_modrm_byte<M: CodeFetch + ?Sized>(
         &mut self,
-        mem: &MemoryBus,
+        mem: &M,
         modrm: u8,
         is_byte: bool,
     ) -> (Operand, u8) {
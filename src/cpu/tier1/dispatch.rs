@@ -4,11 +4,20 @@
 
 use crate::cpu::decode::instruction::InstructionHandler;
 use crate::cpu::execute::*;
+use crate::cpu::CpuModel;
 
 /// Dispatch table with 256 entries, one for each possible opcode
 ///
 /// Each entry is a function pointer that handles the opcode.
 /// Unimplemented opcodes point to the invalid_opcode handler.
+///
+/// The table covers the 8088 only. Opcodes the 80186 added (PUSHA/POPA,
+/// BOUND, PUSH/IMUL imm, INS/OUTS, shift by imm, ENTER/LEAVE) also go to
+/// invalid_opcode; `handler_for` swaps in the ones implemented for
+/// `CpuModel::I80186`. Real 8088s decode most of them as aliases of other
+/// instructions (0x60-0x6F repeat the Jcc block, for example), but no program
+/// written for the 8088 uses those aliases and executing them would hide the
+/// fact that the program expects a newer CPU.
 pub static DISPATCH_TABLE: [InstructionHandler; 256] = [
     // 0x00-0x0F: ADD, OR, ADC, SBB, AND, SUB, XOR, CMP, and segment prefixes
     arithmetic::add_rm_r,    // 0x00: ADD r/m8, r8
@@ -162,7 +171,7 @@ pub static DISPATCH_TABLE: [InstructionHandler; 256] = [
     data_transfer::mov_rm_sreg, // 0x8C: MOV r/m16, Sreg
     data_transfer::lea,         // 0x8D: LEA r16, m - Load Effective Address
     data_transfer::mov_sreg_rm, // 0x8E: MOV Sreg, r/m16
-    invalid_opcode,             // 0x8F: POP r/m16 (group, not implemented yet)
+    stack::group_8f,            // 0x8F: POP r/m16 (group)
     // 0x90-0x9F: XCHG, CBW, CWD, CALL, WAIT, PUSHF, POPF, SAHF, LAHF
     nop,                        // 0x90: NOP (XCHG AX, AX)
     data_transfer::xchg_ax_r16, // 0x91: XCHG AX, CX
@@ -175,7 +184,7 @@ pub static DISPATCH_TABLE: [InstructionHandler; 256] = [
     data_transfer::cbw,         // 0x98: CBW - Convert Byte to Word
     data_transfer::cwd,         // 0x99: CWD - Convert Word to Doubleword
     control_flow::call_far,     // 0x9A: CALL far
-    invalid_opcode,             // 0x9B: WAIT (not implemented yet)
+    esc::wait,                  // 0x9B: WAIT
     flags::pushf,               // 0x9C: PUSHF - Push FLAGS register
     flags::popf,                // 0x9D: POPF - Pop FLAGS register
     flags::sahf,                // 0x9E: SAHF - Store AH into Flags
@@ -229,7 +238,7 @@ pub static DISPATCH_TABLE: [InstructionHandler; 256] = [
     control_flow::ret_far,      // 0xCB: RETF
     control_flow::int3,         // 0xCC: INT 3
     control_flow::int_n,        // 0xCD: INT imm8
-    invalid_opcode,             // 0xCE: INTO (not implemented yet)
+    control_flow::into,         // 0xCE: INTO
     control_flow::iret,         // 0xCF: IRET
     // 0xD0-0xDF: Shifts and rotates
     shift::group_d0,     // 0xD0: Shift r/m8, 1 (group: ROL/ROR/RCL/RCR/SHL/SHR/SAR)
@@ -240,14 +249,14 @@ pub static DISPATCH_TABLE: [InstructionHandler; 256] = [
     arithmetic::aad,     // 0xD5: AAD imm8
     invalid_opcode,      // 0xD6: SALC (undocumented, not implemented)
     data_transfer::xlat, // 0xD7: XLAT - Table lookup translation
-    invalid_opcode,      // 0xD8: ESC (FPU, not implemented)
-    invalid_opcode,      // 0xD9: ESC (FPU, not implemented)
-    invalid_opcode,      // 0xDA: ESC (FPU, not implemented)
-    invalid_opcode,      // 0xDB: ESC (FPU, not implemented)
-    invalid_opcode,      // 0xDC: ESC (FPU, not implemented)
-    invalid_opcode,      // 0xDD: ESC (FPU, not implemented)
-    invalid_opcode,      // 0xDE: ESC (FPU, not implemented)
-    invalid_opcode,      // 0xDF: ESC (FPU, not implemented)
+    esc::esc,            // 0xD8: ESC (coprocessor)
+    esc::esc,            // 0xD9: ESC (coprocessor)
+    esc::esc,            // 0xDA: ESC (coprocessor)
+    esc::esc,            // 0xDB: ESC (coprocessor)
+    esc::esc,            // 0xDC: ESC (coprocessor)
+    esc::esc,            // 0xDD: ESC (coprocessor)
+    esc::esc,            // 0xDE: ESC (coprocessor)
+    esc::esc,            // 0xDF: ESC (coprocessor)
     // 0xE0-0xEF: LOOP, IN, OUT, CALL, JMP
     control_flow::loopne,    // 0xE0: LOOPNE/LOOPNZ
     control_flow::loope,     // 0xE1: LOOPE/LOOPZ
@@ -283,3 +292,26 @@ pub static DISPATCH_TABLE: [InstructionHandler; 256] = [
     arithmetic::group_fe,   // 0xFE: INC/DEC r/m8 (group)
     control_flow::group_ff, // 0xFF: INC/DEC/CALL/JMP/PUSH r/m16 (group)
 ];
+
+/// Look up the handler for an opcode on the given CPU model
+///
+/// PUSHA/POPA, BOUND and INS/OUTS are not implemented and stay invalid on
+/// the 80186 too.
+#[inline(always)]
+pub fn handler_for(model: CpuModel, opcode: u8) -> InstructionHandler {
+    if model == CpuModel::I80186 {
+        let handler: Option<InstructionHandler> = match opcode {
+            0x68 | 0x6A => Some(stack::push_imm), // PUSH imm16 / imm8
+            0x69 | 0x6B => Some(arithmetic::imul_r_rm_imm), // IMUL r16, r/m16, imm
+            0xC0 => Some(shift::group_c0),        // Shift r/m8, imm8
+            0xC1 => Some(shift::group_c1),        // Shift r/m16, imm8
+            0xC8 => Some(stack::enter),           // ENTER imm16, imm8
+            0xC9 => Some(stack::leave),           // LEAVE
+            _ => None,
+        };
+        if let Some(handler) = handler {
+            return handler;
+        }
+    }
+    DISPATCH_TABLE[opcode as usize]
+}
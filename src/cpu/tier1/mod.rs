@@ -8,4 +8,4 @@
 pub mod decode;
 pub mod dispatch;
 
-pub use dispatch::DISPATCH_TABLE;
+pub use dispatch::{handler_for, DISPATCH_TABLE};
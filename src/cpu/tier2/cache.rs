@@ -2,20 +2,45 @@
 //!
 //! Caches decoded instructions indexed by their physical address (linear address from CS:IP).
 //! This allows skipping the decode phase for frequently executed code, particularly in loops.
-
-use std::collections::HashMap;
+//!
+//! Keying by the 20-bit physical address means the same offset in different
+//! segments never shares an entry, while aliases of one physical address
+//! (0000:1234 and 0123:0004) do. Each entry also keeps the bytes it was
+//! decoded from, which lookups compare against memory to catch code changed
+//! behind the cache's back.
 
 use crate::cpu::decode::instruction::DecodedInstruction;
 
+/// Address-to-entry map; without `std` there is no HashMap, so use a BTreeMap
+#[cfg(feature = "std")]
+type EntryMap = std::collections::HashMap<u32, CacheEntry>;
+#[cfg(not(feature = "std"))]
+type EntryMap = alloc::collections::BTreeMap<u32, CacheEntry>;
+
+#[cfg(feature = "std")]
+fn entry_map(capacity: usize) -> EntryMap {
+    EntryMap::with_capacity(capacity)
+}
+
+#[cfg(not(feature = "std"))]
+fn entry_map(_capacity: usize) -> EntryMap {
+    EntryMap::new()
+}
+
 /// Default maximum number of entries in the decode cache
 /// When this limit is reached, the cache is cleared entirely
 const DEFAULT_MAX_ENTRIES: usize = 8192;
 
+/// Instruction bytes kept per entry: the longest 8088 instruction
+const CACHED_BYTES: usize = 6;
+
 /// A cached instruction entry with execution count tracking
 #[derive(Clone)]
 pub struct CacheEntry {
     /// The decoded instruction
     pub instruction: DecodedInstruction,
+    /// The bytes the instruction was decoded from (the first `CACHED_BYTES`)
+    bytes: [u8; CACHED_BYTES],
     /// Number of times this instruction has been executed from cache
     /// Used for tier 3 hot path detection
     pub hit_count: u32,
@@ -23,13 +48,28 @@ pub struct CacheEntry {
 
 impl CacheEntry {
     /// Create a new cache entry for a decoded instruction
-    pub fn new(instruction: DecodedInstruction) -> Self {
+    ///
+    /// `bytes` are the instruction bytes it was decoded from.
+    pub fn new(instruction: DecodedInstruction, bytes: &[u8]) -> Self {
+        let mut saved = [0; CACHED_BYTES];
+        let len = bytes.len().min(CACHED_BYTES);
+        saved[..len].copy_from_slice(&bytes[..len]);
         Self {
             instruction,
+            bytes: saved,
             hit_count: 0,
         }
     }
 
+    /// Check the instruction bytes against memory
+    ///
+    /// `fetch(i)` returns byte `i` of the instruction as it is now.
+    #[inline(always)]
+    pub fn matches(&self, fetch: impl Fn(u16) -> u8) -> bool {
+        let len = (self.instruction.length as usize).min(CACHED_BYTES);
+        (0..len).all(|i| self.bytes[i] == fetch(i as u16))
+    }
+
     /// Increment hit count and return the new value
     #[inline(always)]
     pub fn record_hit(&mut self) -> u32 {
@@ -44,7 +84,7 @@ impl CacheEntry {
 /// When the cache reaches its maximum capacity, it is cleared entirely (simple eviction).
 pub struct DecodeCache {
     /// Map from physical address to cached instruction
-    entries: HashMap<u32, CacheEntry>,
+    entries: EntryMap,
     /// Maximum number of entries before clearing
     max_entries: usize,
     /// Total cache hits (for statistics)
@@ -57,7 +97,7 @@ impl DecodeCache {
     /// Create a new decode cache with default capacity
     pub fn new() -> Self {
         Self {
-            entries: HashMap::with_capacity(DEFAULT_MAX_ENTRIES),
+            entries: entry_map(DEFAULT_MAX_ENTRIES),
             max_entries: DEFAULT_MAX_ENTRIES,
             total_hits: 0,
             total_misses: 0,
@@ -67,7 +107,7 @@ impl DecodeCache {
     /// Create a new decode cache with custom capacity
     pub fn with_capacity(max_entries: usize) -> Self {
         Self {
-            entries: HashMap::with_capacity(max_entries),
+            entries: entry_map(max_entries),
             max_entries,
             total_hits: 0,
             total_misses: 0,
@@ -91,12 +131,32 @@ impl DecodeCache {
         }
     }
 
+    /// Look up a cached instruction, checking its bytes are still current
+    ///
+    /// `fetch(i)` returns byte `i` of the instruction at `addr` as memory
+    /// holds it now. An entry whose bytes have changed (a write that range
+    /// invalidation missed) is dropped and counted as a miss.
+    #[inline(always)]
+    pub fn get_checked(&mut self, addr: u32, fetch: impl Fn(u16) -> u8) -> Option<&CacheEntry> {
+        let Some(entry) = self.entries.get(&addr) else {
+            self.total_misses += 1;
+            return None;
+        };
+        if !entry.matches(fetch) {
+            self.entries.remove(&addr);
+            self.total_misses += 1;
+            return None;
+        }
+        self.get(addr)
+    }
+
     /// Insert a decoded instruction into the cache
     ///
+    /// `bytes` are the instruction bytes it was decoded from.
     /// If the cache is at capacity, it is cleared before inserting.
     /// Returns true if the cache was cleared, false otherwise.
     #[inline(always)]
-    pub fn insert(&mut self, addr: u32, instruction: DecodedInstruction) -> bool {
+    pub fn insert(&mut self, addr: u32, instruction: DecodedInstruction, bytes: &[u8]) -> bool {
         let mut cleared = false;
 
         // Check if we need to clear the cache
@@ -105,7 +165,8 @@ impl DecodeCache {
             cleared = true;
         }
 
-        self.entries.insert(addr, CacheEntry::new(instruction));
+        self.entries
+            .insert(addr, CacheEntry::new(instruction, bytes));
         cleared
     }
 
@@ -47,10 +47,10 @@ pub static BASE_CYCLES: [u8; 256] = [
     4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4,
     // 0x80-0x8F: Group arithmetic, TEST, XCHG, MOV, LEA
     4, 4, 4, 4, 5, 5, 4, 4, // Groups 0x80-83, TEST, XCHG
-    2, 2, 2, 2, 2, 2, 2, 0, // MOV r/m,r and r,r/m, MOV sreg, LEA, POP r/m
+    2, 2, 2, 2, 2, 2, 2, 12, // MOV r/m,r and r,r/m, MOV sreg, LEA, POP r/m
     // 0x90-0x9F: NOP, XCHG AX, CBW, CWD, CALL far, WAIT, PUSHF, POPF, SAHF, LAHF
     3, 3, 3, 3, 3, 3, 3, 3, // NOP, XCHG AX,r16
-    2, 5, 36, 0, 14, 12, 4, 4, // CBW, CWD, CALL far, WAIT, PUSHF, POPF, SAHF, LAHF
+    2, 5, 36, 3, 14, 12, 4, 4, // CBW, CWD, CALL far, WAIT, PUSHF, POPF, SAHF, LAHF
     // 0xA0-0xAF: MOV moffs, string ops
     14, 14, 14, 14, 18, 26, 22, 30, // MOV moffs (14), MOVSB/W, CMPSB/W
     4, 4, 11, 15, 12, 16, 15, 19, // TEST acc,imm, STOSB/W, LODSB/W, SCASB/W
@@ -59,10 +59,10 @@ pub static BASE_CYCLES: [u8; 256] = [
     4, 4, 4, 4, 4, 4, 4, 4, // MOV r16, imm16
     // 0xC0-0xCF: Shifts (invalid), RET, LES, LDS, MOV r/m,imm, INT, IRET
     0, 0, 24, 20, 24, 24, 4, 4, // Invalid, RET imm, RET, LES, LDS, MOV r/m,imm
-    0, 0, 33, 34, 52, 51, 0, 44, // Invalid, RETF imm, RETF, INT 3, INT n, INTO, IRET
+    0, 0, 33, 34, 52, 51, 4, 44, // Invalid, RETF imm, RETF, INT 3, INT n, INTO, IRET
     // 0xD0-0xDF: Shifts, AAM, AAD, XLAT, ESC (FPU)
     2, 2, 8, 8, 83, 60, 0, 11, // Shift by 1, Shift by CL, AAM, AAD, SALC, XLAT
-    0, 0, 0, 0, 0, 0, 0, 0, // ESC (FPU) - not implemented
+    2, 2, 2, 2, 2, 2, 2, 2, // ESC (coprocessor), register forms
     // 0xE0-0xEF: LOOP, IN, OUT, CALL, JMP
     // LOOP family uses not-taken timing as base (like Jcc), handlers add extra for taken
     5, 5, 5, 6, 10, 14, 10, 14, // LOOPNE, LOOPE, LOOP, JCXZ, IN imm, OUT imm
@@ -246,6 +246,11 @@ pub fn calculate_memory_timing(opcode: u8, dst: &Operand, src: &Operand) -> (u8,
         // MOV sreg, r/m16 (0x8E) - memory source = read
         0x8E if src_is_mem => MEMORY_READ_EXTRA_CYCLES,
 
+        // POP r/m16 (0x8F) - stack read plus memory write
+        // Intel: 25+EA for memory vs 12 for register; the word penalty for
+        // the memory write is added separately, leaving +9
+        0x8F if dst_is_mem => 9,
+
         // MOV r/m, imm (0xC6, 0xC7) - memory destination = simple write
         // Intel: 10+EA for mem,imm vs 4 for reg,imm = +6
         0xC6 | 0xC7 if dst_is_mem => 6,
@@ -295,6 +300,10 @@ pub fn calculate_memory_timing(opcode: u8, dst: &Operand, src: &Operand) -> (u8,
         // These have complex timing based on operation, but base needs adjustment
         0xF6 | 0xF7 if dst_is_mem => MEMORY_READ_EXTRA_CYCLES,
 
+        // ESC (0xD8-0xDF) - the 8088 reads the memory operand for the 8087
+        // Intel: 8+EA for memory vs 2 for register = +6
+        0xD8..=0xDF if dst_is_mem => 6,
+
         // Default: no extra cycles
         _ => 0,
     };
@@ -0,0 +1,50 @@
+//! Execution breakpoints
+//!
+//! Shared by the GDB stub and the `Machine` host API, so neither needs the
+//! other to stop at an address.
+
+use crate::cpu::Cpu;
+use crate::memory::linear;
+
+/// Called with the CS:IP of a breakpoint when execution reaches it
+pub type BreakpointHook = Box<dyn FnMut(u16, u16)>;
+
+/// A set of execution breakpoints at linear addresses (seg*16 + offset)
+#[derive(Debug, Clone, Default)]
+pub struct Breakpoints {
+    addrs: Vec<u32>,
+}
+
+impl Breakpoints {
+    /// Create an empty breakpoint set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a breakpoint at a linear address
+    pub fn insert(&mut self, addr: u32) {
+        if !self.addrs.contains(&addr) {
+            self.addrs.push(addr);
+        }
+    }
+
+    /// Remove the breakpoint at a linear address, if any
+    pub fn remove(&mut self, addr: u32) {
+        self.addrs.retain(|&a| a != addr);
+    }
+
+    /// Check for a breakpoint at a linear address
+    pub fn contains(&self, addr: u32) -> bool {
+        self.addrs.contains(&addr)
+    }
+
+    /// Check if there are no breakpoints
+    pub fn is_empty(&self) -> bool {
+        self.addrs.is_empty()
+    }
+
+    /// Check if the CPU is about to execute an instruction at a breakpoint
+    pub fn hit(&self, cpu: &Cpu) -> bool {
+        self.contains(linear(cpu.segments[1], cpu.ip))
+    }
+}
@@ -3,6 +3,7 @@
 //! Implements the core GDB commands for debugging the emulated CPU.
 
 use super::GdbDebugger;
+use crate::cpu::disasm;
 use crate::cpu::Cpu;
 use crate::memory::MemoryBus;
 
@@ -55,7 +56,7 @@ pub fn handle_command(
         'H' => "OK".to_string(), // We don't have threads
 
         // Query commands
-        'q' => handle_query(cmd),
+        'q' => handle_query(cmd, cpu, mem, debugger),
 
         // Insert breakpoint: Z0,<addr>,<kind>
         'Z' => insert_breakpoint(debugger, cmd),
@@ -259,7 +260,7 @@ fn write_memory(mem: &mut MemoryBus, cmd: &str) -> String {
     // Parse command: M<addr>,<len>:<hex-bytes>
     let parts: Vec<&str> = cmd[1..].split(&[',' as char, ':' as char][..]).collect();
     if parts.len() != 3 {
-        eprintln!(
+        log_warn!(
             "GDB: Memory write parse error - expected 3 parts, got {}",
             parts.len()
         );
@@ -269,7 +270,7 @@ fn write_memory(mem: &mut MemoryBus, cmd: &str) -> String {
     let addr = match u32::from_str_radix(parts[0], 16) {
         Ok(a) => a,
         Err(_) => {
-            eprintln!("GDB: Memory write - invalid address: {}", parts[0]);
+            log_warn!("GDB: Memory write - invalid address: {}", parts[0]);
             return "E01".to_string();
         }
     };
@@ -277,13 +278,13 @@ fn write_memory(mem: &mut MemoryBus, cmd: &str) -> String {
     let len = match usize::from_str_radix(parts[1], 16) {
         Ok(l) => l,
         Err(_) => {
-            eprintln!("GDB: Memory write - invalid length: {}", parts[1]);
+            log_warn!("GDB: Memory write - invalid length: {}", parts[1]);
             return "E01".to_string();
         }
     };
 
     let data = parts[2];
-    eprintln!("GDB: Writing {} bytes to address 0x{:08x}", len, addr);
+    log_debug!("GDB: Writing {} bytes to address 0x{:08x}", len, addr);
 
     // Write bytes to memory
     for i in 0..len {
@@ -291,7 +292,7 @@ fn write_memory(mem: &mut MemoryBus, cmd: &str) -> String {
         let byte = match u8::from_str_radix(hex_byte, 16) {
             Ok(b) => b,
             Err(_) => {
-                eprintln!("GDB: Memory write - invalid byte data at offset {}", i);
+                log_warn!("GDB: Memory write - invalid byte data at offset {}", i);
                 return "E01".to_string();
             }
         };
@@ -300,13 +301,16 @@ fn write_memory(mem: &mut MemoryBus, cmd: &str) -> String {
         mem.write_u8(byte_addr, byte);
     }
 
-    eprintln!("GDB: Memory write successful");
+    log_debug!("GDB: Memory write successful");
     "OK".to_string()
 }
 
 /// Handle query commands (qXXX)
-fn handle_query(cmd: &str) -> String {
-    if cmd.starts_with("qSupported") {
+fn handle_query(cmd: &str, cpu: &Cpu, mem: &mut MemoryBus, debugger: &mut GdbDebugger) -> String {
+    if let Some(hex) = cmd.strip_prefix("qRcmd,") {
+        // Monitor command from the GDB console
+        monitor_command(hex, cpu, mem, debugger)
+    } else if cmd.starts_with("qSupported") {
         // Report our capabilities
         "PacketSize=4096".to_string()
     } else if cmd == "qAttached" {
@@ -336,13 +340,108 @@ fn handle_query(cmd: &str) -> String {
     }
 }
 
+/// Monitor command: qRcmd,<hex-encoded command>
+///
+/// Output is returned hex-encoded, which GDB prints on its console.
+fn monitor_command(
+    hex: &str,
+    cpu: &Cpu,
+    mem: &mut MemoryBus,
+    debugger: &mut GdbDebugger,
+) -> String {
+    let bytes: Result<Vec<u8>, _> = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2).unwrap_or(""), 16))
+        .collect();
+    let command = match bytes.map(String::from_utf8) {
+        Ok(Ok(command)) => command,
+        _ => return "E01".to_string(),
+    };
+
+    let mut args = command.split_whitespace();
+    let output = match args.next().unwrap_or("") {
+        "memmap" => mem
+            .memory_map()
+            .iter()
+            .map(|region| format!("{}\n", region))
+            .collect(),
+        "disasm" => disasm_listing(args.collect::<Vec<_>>(), cpu, mem, debugger),
+        "irqstats" => irq_stats(mem),
+        "flags" => match args.next() {
+            Some("on") => {
+                debugger.set_annotate_flags(true);
+                "Flag annotations on\n".to_string()
+            }
+            Some("off") => {
+                debugger.set_annotate_flags(false);
+                "Flag annotations off\n".to_string()
+            }
+            _ => "Usage: flags on|off\n".to_string(),
+        },
+        other => format!("Unknown monitor command: {}\n", other),
+    };
+
+    output.bytes().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Per-line interrupt counts for `monitor irqstats`
+fn irq_stats(mem: &MemoryBus) -> String {
+    let raised = mem.pic().raise_counts();
+    let acked = mem.pic().ack_counts();
+
+    let mut table = "IRQ      raised       acked\n".to_string();
+    for irq in 0..8 {
+        table.push_str(&format!(
+            "{:>3}  {:>10}  {:>10}\n",
+            irq, raised[irq], acked[irq]
+        ));
+    }
+    table
+}
+
+/// Disassemble for `monitor disasm [SEG:OFF] [COUNT]`
+///
+/// Starts at CS:IP by default and lists 10 instructions.
+fn disasm_listing(args: Vec<&str>, cpu: &Cpu, mem: &MemoryBus, debugger: &GdbDebugger) -> String {
+    const USAGE: &str = "Usage: disasm [SEG:OFF] [COUNT]\n";
+
+    let (mut cs, mut ip) = (cpu.segments[1], cpu.ip);
+    let mut count = 10;
+    for arg in args {
+        if let Some((seg, off)) = arg.split_once(':') {
+            match (u16::from_str_radix(seg, 16), u16::from_str_radix(off, 16)) {
+                (Ok(seg), Ok(off)) => (cs, ip) = (seg, off),
+                _ => return USAGE.to_string(),
+            }
+        } else {
+            match arg.parse() {
+                Ok(n) => count = n,
+                Err(_) => return USAGE.to_string(),
+            }
+        }
+    }
+
+    let mut listing = String::new();
+    for _ in 0..count {
+        let instr = disasm::disassemble_at(mem, cs, ip);
+        let text = if debugger.annotate_flags() {
+            instr.annotated()
+        } else {
+            instr.text
+        };
+        listing.push_str(&format!("{:04X}:{:04X}  {}\n", cs, ip, text));
+        ip = ip.wrapping_add(instr.length as u16);
+    }
+    listing
+}
+
 /// Insert breakpoint: Z0,<addr>,<kind>
 fn insert_breakpoint(debugger: &mut GdbDebugger, cmd: &str) -> String {
     // Parse: Z0,<addr>,<kind>
     // Skip "Z0," (3 chars) to get "addr,kind"
     let parts: Vec<&str> = cmd[3..].split(',').collect();
     if parts.len() < 2 {
-        eprintln!(
+        log_warn!(
             "GDB: Breakpoint parse error - expected at least 2 parts, got {}",
             parts.len()
         );
@@ -352,15 +451,16 @@ fn insert_breakpoint(debugger: &mut GdbDebugger, cmd: &str) -> String {
     let addr = match u32::from_str_radix(parts[0], 16) {
         Ok(a) => a,
         Err(e) => {
-            eprintln!(
+            log_warn!(
                 "GDB: Breakpoint parse error - invalid address '{}': {:?}",
-                parts[0], e
+                parts[0],
+                e
             );
             return "E01".to_string();
         }
     };
 
-    eprintln!("GDB: Setting breakpoint at linear address 0x{:08x}", addr);
+    log_debug!("GDB: Setting breakpoint at linear address 0x{:08x}", addr);
     debugger.add_breakpoint(addr);
     "OK".to_string()
 }
@@ -371,7 +471,7 @@ fn remove_breakpoint(debugger: &mut GdbDebugger, cmd: &str) -> String {
     // Skip "z0," (3 chars) to get "addr,kind"
     let parts: Vec<&str> = cmd[3..].split(',').collect();
     if parts.len() < 2 {
-        eprintln!(
+        log_warn!(
             "GDB: Remove breakpoint parse error - expected at least 2 parts, got {}",
             parts.len()
         );
@@ -381,15 +481,16 @@ fn remove_breakpoint(debugger: &mut GdbDebugger, cmd: &str) -> String {
     let addr = match u32::from_str_radix(parts[0], 16) {
         Ok(a) => a,
         Err(e) => {
-            eprintln!(
+            log_warn!(
                 "GDB: Remove breakpoint parse error - invalid address '{}': {:?}",
-                parts[0], e
+                parts[0],
+                e
             );
             return "E01".to_string();
         }
     };
 
-    eprintln!("GDB: Removing breakpoint at linear address 0x{:08x}", addr);
+    log_debug!("GDB: Removing breakpoint at linear address 0x{:08x}", addr);
     debugger.remove_breakpoint(addr);
     "OK".to_string()
 }
@@ -3,11 +3,15 @@
 //! Implements the GDB Remote Serial Protocol over a Unix socket.
 //! Uses non-blocking I/O with a helper thread to avoid blocking emulation.
 
+mod breakpoints;
 mod commands;
 mod protocol;
 mod socket;
 
+pub use breakpoints::{BreakpointHook, Breakpoints};
+
 use crate::cpu::Cpu;
+use crate::logging::{self, LogLevel};
 use crate::memory::MemoryBus;
 use protocol::format_packet;
 use std::collections::VecDeque;
@@ -43,10 +47,13 @@ pub struct GdbDebugger {
     state: DebugState,
 
     /// Breakpoints (linear addresses: seg*16 + offset)
-    breakpoints: Vec<u32>,
+    breakpoints: Breakpoints,
 
     /// Statistics
     packets_processed: usize,
+
+    /// Annotate `monitor disasm` output with the flags each instruction affects
+    annotate_flags: bool,
 }
 
 impl GdbDebugger {
@@ -69,16 +76,39 @@ impl GdbDebugger {
             interrupt_requested,
             _socket_thread: socket_thread,
             state: DebugState::Paused, // Start paused, waiting for GDB
-            breakpoints: Vec::new(),
+            breakpoints: Breakpoints::new(),
             packets_processed: 0,
+            annotate_flags: true,
         }
     }
 
+    /// Set the log level; packet tracing is only shown at `LogLevel::Trace`
+    ///
+    /// Logging is process-wide, so this also affects the emulator core.
+    pub fn set_log_level(&mut self, level: LogLevel) {
+        logging::set_level(level);
+    }
+
+    /// Get the current log level
+    pub fn log_level(&self) -> LogLevel {
+        logging::level()
+    }
+
     /// Check if emulation is paused
     pub fn is_paused(&self) -> bool {
         self.state == DebugState::Paused
     }
 
+    /// Enable or disable flag annotations in `monitor disasm` output
+    pub fn set_annotate_flags(&mut self, enabled: bool) {
+        self.annotate_flags = enabled;
+    }
+
+    /// Check whether `monitor disasm` output is annotated with flags
+    pub fn annotate_flags(&self) -> bool {
+        self.annotate_flags
+    }
+
     /// Check if in single-step mode
     pub fn is_single_stepping(&self) -> bool {
         self.state == DebugState::SingleStep
@@ -101,20 +131,17 @@ impl GdbDebugger {
 
     /// Add breakpoint at linear address
     pub fn add_breakpoint(&mut self, addr: u32) {
-        if !self.breakpoints.contains(&addr) {
-            self.breakpoints.push(addr);
-        }
+        self.breakpoints.insert(addr);
     }
 
     /// Remove breakpoint at linear address
     pub fn remove_breakpoint(&mut self, addr: u32) {
-        self.breakpoints.retain(|&a| a != addr);
+        self.breakpoints.remove(addr);
     }
 
     /// Check if current IP matches a breakpoint
     pub fn check_breakpoint(&self, cpu: &Cpu) -> bool {
-        let linear_addr = (cpu.segments[1] as u32 * 16) + cpu.ip as u32;
-        self.breakpoints.contains(&linear_addr)
+        self.breakpoints.hit(cpu)
     }
 
     /// Send a packet to GDB client
@@ -143,7 +170,7 @@ impl GdbDebugger {
 
             self.packets_processed += 1;
 
-            eprintln!("GDB: Received command: {}", packet);
+            log_trace!("GDB: Received command: {}", packet);
 
             // Check if this is a deferred-response command (s, c)
             let deferred = packet.starts_with('s') || packet.starts_with('c');
@@ -153,15 +180,15 @@ impl GdbDebugger {
 
             // Send response
             if !response.is_empty() {
-                eprintln!("GDB: Sending response: {}", response);
+                log_trace!("GDB: Sending response: {}", response);
                 self.send_packet(&response);
             } else if !deferred {
                 // Empty response for unsupported commands (but not for s/c)
-                eprintln!("GDB: Empty response (not supported)");
+                log_trace!("GDB: Empty response (not supported)");
                 self.send_packet("");
             } else {
                 // Deferred response (s/c) - will send S05 later
-                eprintln!("GDB: Deferred response (will send halt reason after execution)");
+                log_trace!("GDB: Deferred response (will send halt reason after execution)");
             }
         }
     }
@@ -29,18 +29,18 @@ pub fn spawn_socket_listener(
         let listener = match UnixListener::bind(&socket_path) {
             Ok(l) => l,
             Err(e) => {
-                eprintln!("Failed to bind GDB socket at {}: {}", socket_path, e);
+                log_error!("Failed to bind GDB socket at {}: {}", socket_path, e);
                 return;
             }
         };
 
-        println!("GDB server listening on {}", socket_path);
+        log_info!("GDB server listening on {}", socket_path);
 
         // Accept connections (one at a time)
         loop {
             match listener.accept() {
                 Ok((stream, _addr)) => {
-                    println!("GDB client connected");
+                    log_info!("GDB client connected");
 
                     // Handle this connection
                     if let Err(e) = handle_connection(
@@ -49,13 +49,13 @@ pub fn spawn_socket_listener(
                         outgoing.clone(),
                         interrupt_requested.clone(),
                     ) {
-                        eprintln!("GDB connection error: {}", e);
+                        log_error!("GDB connection error: {}", e);
                     }
 
-                    println!("GDB client disconnected");
+                    log_info!("GDB client disconnected");
                 }
                 Err(e) => {
-                    eprintln!("GDB accept error: {}", e);
+                    log_error!("GDB accept error: {}", e);
                     thread::sleep(Duration::from_millis(100));
                 }
             }
@@ -116,7 +116,7 @@ fn handle_connection(
                     read_buffer.remove(0);
                     // Set interrupt flag
                     *interrupt_requested.write().unwrap() = true;
-                    eprintln!("GDB: Interrupt signal received (0x03)");
+                    log_debug!("GDB: Interrupt signal received (0x03)");
                 }
             }
             Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
@@ -3,12 +3,12 @@
 //! This module manages the overall emulator state, including CPU, memory,
 //! and rendering components.
 
+use crate::components::cmos::Cmos;
 use crate::components::floppy::FloppyDisk;
-use crate::components::pit::Pit;
-use crate::components::ppi::Ppi;
-use crate::cpu::Cpu;
+use crate::components::rtc;
 use crate::debugger::GdbDebugger;
-use crate::memory::MemoryBus;
+use crate::io::DeviceHandle;
+use crate::machine::{Machine, MachineConfig, MachineEvent};
 use std::collections::VecDeque;
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
@@ -20,13 +20,10 @@ use graphics::FramebufferRenderer;
 
 /// Main emulator state
 pub struct EmulatorState {
-    cpu: Cpu,
-    memory: MemoryBus,
+    machine: Machine,
     renderer: FramebufferRenderer,
     last_frame_time: Instant,
     target_frame_duration: Duration,
-    /// Keyboard scancode queue (shared with windowing system)
-    scancode_queue: Arc<RwLock<VecDeque<u8>>>,
     /// Optional GDB debugger
     debugger: Option<GdbDebugger>,
 }
@@ -61,44 +58,58 @@ impl EmulatorState {
         floppy_a: Option<FloppyDisk>,
         floppy_b: Option<FloppyDisk>,
     ) -> Self {
-        let mut memory = MemoryBus::new();
-
-        // Load ROM if provided
-        if let Some(rom) = rom_data {
-            memory.load_rom(&rom);
-        }
+        // A second disk image implies a second drive
+        let config = MachineConfig {
+            floppy_drives: if floppy_b.is_some() { 2 } else { 1 },
+            ..MachineConfig::default()
+        };
+        let mut state = Self::with_config(
+            device,
+            queue,
+            surface_format,
+            rom_data,
+            gdb_socket_path,
+            config,
+        );
 
         // Insert floppy disks into FDC
         if let Some(disk) = floppy_a {
-            memory.insert_floppy(0, disk);
+            state.machine.insert_floppy(0, disk);
         }
         if let Some(disk) = floppy_b {
-            memory.insert_floppy(1, disk);
+            state.machine.insert_floppy(1, disk);
         }
 
-        // Create keyboard queue and register PPI (which owns the keyboard)
-        let scancode_queue = Arc::new(RwLock::new(VecDeque::new()));
-        let ppi = Ppi::new(scancode_queue.clone());
-        memory.register_io_device(Box::new(ppi));
+        state
+    }
 
-        // Create and register PIT
-        let pit = Pit::new();
-        memory.register_io_device(Box::new(pit));
+    /// Create a new emulator state for a hardware configuration
+    ///
+    /// The configuration picks the video adapter and the equipment word the
+    /// ROM sees. Insert disks through `machine_mut` afterwards.
+    pub fn with_config(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface_format: wgpu::TextureFormat,
+        rom_data: Option<Vec<u8>>,
+        gdb_socket_path: Option<&str>,
+        config: MachineConfig,
+    ) -> Self {
+        let mut machine = Machine::with_config(config);
 
-        // Create and reset CPU to initialize reset vector (CS=0xF000, IP=0xFFF0)
-        let mut cpu = Cpu::new();
-        cpu.reset();
+        // Load ROM if provided
+        if let Some(rom) = rom_data {
+            machine.load_rom(&rom);
+        }
 
         // Create debugger if socket path provided
         let debugger = gdb_socket_path.map(|path| GdbDebugger::new(path));
 
         Self {
-            cpu,
-            memory,
+            machine,
             renderer: FramebufferRenderer::new(device, queue, surface_format),
             last_frame_time: Instant::now(),
             target_frame_duration: Duration::from_micros(16667), // 60 FPS (~16.67ms)
-            scancode_queue,
             debugger,
         }
     }
@@ -107,16 +118,34 @@ impl EmulatorState {
     ///
     /// The windowing system can use this to push scancodes when keys are pressed.
     pub fn scancode_queue(&self) -> Arc<RwLock<VecDeque<u8>>> {
-        self.scancode_queue.clone()
+        self.machine.scancode_queue()
+    }
+
+    /// Get a reference to the underlying machine
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    /// Get a mutable reference to the underlying machine
+    pub fn machine_mut(&mut self) -> &mut Machine {
+        &mut self.machine
+    }
+
+    /// Attach CMOS RAM at ports 0x70/0x71, with its clock following the host
+    pub fn attach_cmos(&mut self, mut cmos: Cmos) -> DeviceHandle<Cmos> {
+        cmos.set_clock(Some(rtc::host_clock()));
+        self.machine.attach_device(cmos)
     }
 
     /// Update emulator state for one frame
-    pub fn update(&mut self) {
+    ///
+    /// Returns the events produced by the machine during the frame.
+    pub fn update(&mut self) -> Vec<MachineEvent> {
         let elapsed = self.last_frame_time.elapsed();
 
         // Process GDB commands if debugger enabled
         if let Some(ref mut debugger) = self.debugger {
-            debugger.process_commands(&mut self.cpu, &mut self.memory);
+            debugger.process_commands(&mut self.machine.cpu, &mut self.machine.memory);
 
             // If paused, don't execute instructions
             if debugger.is_paused() {
@@ -125,48 +154,38 @@ impl EmulatorState {
                     std::thread::sleep(self.target_frame_duration - elapsed);
                 }
                 self.last_frame_time = Instant::now();
-                return;
+                return Vec::new();
             }
         }
 
-        // IBM 5150 runs at 4.77 MHz, targeting 60 FPS
-        // 4,770,000 cycles/sec / 60 frames/sec = 79,500 cycles per frame
-        const CYCLES_PER_FRAME: u64 = 79_500;
-
-        // Run CPU until we've executed enough cycles for this frame
-        let target_cycles = self.cpu.total_cycles + CYCLES_PER_FRAME;
-
-        while self.cpu.total_cycles < target_cycles {
-            let cycles = self.cpu.step(&mut self.memory);
-            self.memory.tick(cycles);
-
-            // Process FDC DMA transfers
-            // In real hardware, DMA happens during CPU wait states.
-            // We process transfers after each instruction.
-            while self.memory.fdc_dma_tick().is_some() {
-                // Continue transferring until no more data or terminal count
+        // Run CPU until we've executed enough cycles for this frame,
+        // checking for breakpoints and single-step after each instruction
+        let debugger = &mut self.debugger;
+        let events = self.machine.run_frame_until(|cpu| {
+            let Some(debugger) = debugger.as_mut() else {
+                return false;
+            };
+
+            // Check for interrupt request (Ctrl-C from GDB)
+            if debugger.check_interrupt() {
+                return true;
             }
 
-            // Check for breakpoints and single-step after each instruction
-            if let Some(ref mut debugger) = self.debugger {
-                // Check for interrupt request (Ctrl-C from GDB)
-                if debugger.check_interrupt() {
-                    break;
-                }
-
-                if debugger.check_breakpoint(&self.cpu) {
-                    debugger.pause();
-                    debugger.send_halt_reason();
-                    break;
-                }
+            if debugger.check_breakpoint(cpu) {
+                debugger.pause();
+                debugger.send_halt_reason();
+                return true;
+            }
 
-                // Handle single-step mode
-                if debugger.is_single_stepping() {
-                    debugger.finish_single_step();
-                    break;
-                }
+            // Handle single-step mode. The machine has already ticked the
+            // peripherals for this instruction, so timers keep running.
+            if debugger.is_single_stepping() {
+                debugger.finish_single_step();
+                return true;
             }
-        }
+
+            false
+        });
 
         // Sleep if we're under the frame budget
         if elapsed < self.target_frame_duration {
@@ -174,6 +193,13 @@ impl EmulatorState {
         }
 
         self.last_frame_time = Instant::now();
+
+        events
+    }
+
+    /// Fill an audio buffer with PC speaker samples at `rate` Hz
+    pub fn speaker_samples(&mut self, rate: u32, buffer: &mut [i16]) {
+        self.machine.speaker_samples(rate, buffer);
     }
 
     /// Render current frame to surface
@@ -181,8 +207,8 @@ impl EmulatorState {
         // Get mutable access to framebuffer
         let framebuffer = self.renderer.framebuffer_mut();
 
-        // Let MDA render its text mode to the framebuffer
-        self.memory.mda().render_to_framebuffer(framebuffer);
+        // Let the active video adapter render to the framebuffer
+        self.machine.render_to_framebuffer(framebuffer);
 
         // Render framebuffer to surface
         self.renderer.render(surface_texture);
@@ -4,7 +4,27 @@
 //! IN/OUT instructions. Peripherals implement the IoDevice trait and register
 //! with the MemoryBus.
 
-use std::ops::RangeInclusive;
+use crate::components::cga::CgaState;
+use crate::components::dma::DmaState;
+use crate::components::mda::MdaState;
+use crate::components::pic::PicState;
+use crate::components::pit::PitState;
+use crate::components::ppi::PpiState;
+use crate::components::uart::UartState;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use core::any::Any;
+use core::cell::RefCell;
+use core::ops::RangeInclusive;
+
+/// Width of a device's data bus connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoWidth {
+    /// 8-bit device: word IO is split into byte accesses at `port` and `port + 1`
+    Byte,
+    /// 16-bit device: word IO arrives as a single `read_u16`/`write_u16`
+    Word,
+}
 
 /// Trait for IO peripheral devices
 pub trait IoDevice {
@@ -17,6 +37,33 @@ pub trait IoDevice {
     /// Get the port range this device handles (inclusive)
     fn port_range(&self) -> RangeInclusive<u16>;
 
+    /// Width of the device's data bus connection
+    ///
+    /// Default implementation returns `IoWidth::Byte`, like every card on the
+    /// 8-bit XT bus.
+    fn io_width(&self) -> IoWidth {
+        IoWidth::Byte
+    }
+
+    /// Read a word from a port within this device's range
+    ///
+    /// Only called for `IoWidth::Word` devices. Default implementation reads
+    /// the two bytes separately.
+    fn read_u16(&mut self, port: u16) -> u16 {
+        let lo = self.read_u8(port) as u16;
+        let hi = self.read_u8(port.wrapping_add(1)) as u16;
+        lo | (hi << 8)
+    }
+
+    /// Write a word to a port within this device's range
+    ///
+    /// Only called for `IoWidth::Word` devices. Default implementation writes
+    /// the two bytes separately.
+    fn write_u16(&mut self, port: u16, value: u16) {
+        self.write_u8(port, value as u8);
+        self.write_u8(port.wrapping_add(1), (value >> 8) as u8);
+    }
+
     /// Update device state based on CPU cycles
     ///
     /// Called after each CPU instruction with the number of cycles consumed.
@@ -25,4 +72,103 @@ pub trait IoDevice {
     fn tick(&mut self, _cycles: u16, _pic: &mut crate::components::pic::Pic) {
         // Default: do nothing
     }
+
+    /// Return the device to its power-on state
+    ///
+    /// Called when the machine is reset. Attached media (disk images, host
+    /// queues) are not part of device state and should be preserved.
+    /// Default implementation does nothing - stateless devices need not override.
+    fn reset(&mut self) {
+        // Default: do nothing
+    }
+
+    /// Snapshot the device's internal state for debugging
+    ///
+    /// Must not have side effects: unlike reading ports, this never advances
+    /// read sequences, clears latches or acknowledges anything.
+    /// Default implementation returns None - devices opt in by overriding.
+    fn snapshot(&self) -> Option<DeviceState> {
+        None
+    }
+
+    /// Capture the device's complete internal state for save/restore
+    ///
+    /// Unlike `snapshot`, the result is opaque and only meaningful to
+    /// `load_state` on the same device type.
+    /// Default implementation returns None - stateless devices need not override.
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        None
+    }
+
+    /// Restore state captured by `save_state`
+    ///
+    /// Connections to the host (e.g. input queues) are kept as they are.
+    /// State captured from a different device type is ignored.
+    fn load_state(&mut self, _state: &dyn Any) {
+        // Default: do nothing
+    }
+}
+
+/// Structured, side-effect-free view of a device's state
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceState {
+    Dma(DmaState),
+    Pic(PicState),
+    Pit(PitState),
+    Ppi(PpiState),
+    Mda(MdaState),
+    Cga(CgaState),
+    Uart(UartState),
+}
+
+/// Shared handle to an attached IO device
+///
+/// The bus dispatches IN/OUT and ticks through one clone of the handle, while
+/// host code keeps another to inspect or poke the device directly.
+pub type DeviceHandle<D> = Rc<RefCell<D>>;
+
+impl<D: IoDevice> IoDevice for Rc<RefCell<D>> {
+    fn read_u8(&mut self, port: u16) -> u8 {
+        self.borrow_mut().read_u8(port)
+    }
+
+    fn write_u8(&mut self, port: u16, value: u8) {
+        self.borrow_mut().write_u8(port, value)
+    }
+
+    fn port_range(&self) -> RangeInclusive<u16> {
+        self.borrow().port_range()
+    }
+
+    fn io_width(&self) -> IoWidth {
+        self.borrow().io_width()
+    }
+
+    fn read_u16(&mut self, port: u16) -> u16 {
+        self.borrow_mut().read_u16(port)
+    }
+
+    fn write_u16(&mut self, port: u16, value: u16) {
+        self.borrow_mut().write_u16(port, value)
+    }
+
+    fn tick(&mut self, cycles: u16, pic: &mut crate::components::pic::Pic) {
+        self.borrow_mut().tick(cycles, pic)
+    }
+
+    fn reset(&mut self) {
+        self.borrow_mut().reset()
+    }
+
+    fn snapshot(&self) -> Option<DeviceState> {
+        self.borrow().snapshot()
+    }
+
+    fn save_state(&self) -> Option<Box<dyn Any>> {
+        self.borrow().save_state()
+    }
+
+    fn load_state(&mut self, state: &dyn Any) {
+        self.borrow_mut().load_state(state)
+    }
 }
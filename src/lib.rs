@@ -1,10 +1,28 @@
 //! EZPC - IBM PC Emulator with 8088 CPU Core
 //!
 //! A high-performance, cycle-accurate emulator using a three-tier execution system.
+//!
+//! The CPU, memory bus and components only need `core` and `alloc`. Building
+//! with `--no-default-features` drops the `std` feature and everything that
+//! depends on the host: the windowed frontend, the GDB stub, `Machine` and
+//! disk image files.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[macro_use]
+pub mod logging;
 
 pub mod components;
 pub mod cpu;
+#[cfg(feature = "std")]
 pub mod debugger;
+#[cfg(feature = "std")]
 pub mod emulator;
 pub mod io;
+#[cfg(feature = "std")]
+pub mod machine;
 pub mod memory;
+pub mod rom_builder;
+pub mod snapshot;
@@ -0,0 +1,198 @@
+//! Lightweight leveled logging for the emulator core
+//!
+//! Messages go through the `log_error!` .. `log_trace!` macros, which skip
+//! formatting entirely when the level is filtered out. The level and the
+//! output sink are process-wide, since devices deep inside the memory bus
+//! have no handle back to the Machine that owns them.
+//!
+//! The default sink writes to stderr; tests and embedders can install their
+//! own with `set_sink`. Without the `std` feature there is no sink and
+//! messages are discarded.
+
+use core::fmt;
+use core::sync::atomic::{AtomicU8, Ordering};
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+
+/// Message severity, from most to least important
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    /// Something failed (e.g. the GDB socket could not be bound)
+    Error = 0,
+    /// Unexpected guest or client behavior (e.g. unhandled ports, bad packets)
+    Warn = 1,
+    /// Lifecycle events (e.g. GDB client connected)
+    Info = 2,
+    /// Device state changes (e.g. PIC initialization, FDC commands)
+    Debug = 3,
+    /// Per-operation tracing (e.g. every IO access, every GDB packet)
+    Trace = 4,
+}
+
+impl LogLevel {
+    /// Parse a level name such as "warn" or "TRACE"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "error" => Some(Self::Error),
+            "warn" => Some(Self::Warn),
+            "info" => Some(Self::Info),
+            "debug" => Some(Self::Debug),
+            "trace" => Some(Self::Trace),
+            _ => None,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::Error,
+            1 => Self::Warn,
+            2 => Self::Info,
+            3 => Self::Debug,
+            _ => Self::Trace,
+        }
+    }
+}
+
+/// Output sink for log messages
+#[cfg(feature = "std")]
+pub type LogSink = Box<dyn Fn(LogLevel, &str) + Send>;
+
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+#[cfg(feature = "std")]
+static SINK: Mutex<Option<LogSink>> = Mutex::new(None);
+
+/// Set the most verbose level that is still emitted
+///
+/// The level is global to the process: it applies to every Machine and
+/// debugger, and to tests running in parallel.
+pub fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Get the process-wide log level
+pub fn level() -> LogLevel {
+    LogLevel::from_u8(LEVEL.load(Ordering::Relaxed))
+}
+
+/// Check whether messages at `level` are emitted
+#[inline(always)]
+pub fn enabled(level: LogLevel) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Replace the output sink (e.g. to capture messages in a test)
+#[cfg(feature = "std")]
+pub fn set_sink(sink: LogSink) {
+    *SINK.lock().unwrap_or_else(|e| e.into_inner()) = Some(sink);
+}
+
+/// Restore the default stderr sink
+#[cfg(feature = "std")]
+pub fn reset_sink() {
+    *SINK.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Emit a message; use the macros instead so filtered messages are not formatted
+#[cfg(feature = "std")]
+pub fn write(level: LogLevel, args: fmt::Arguments) {
+    let sink = SINK.lock().unwrap_or_else(|e| e.into_inner());
+    match sink.as_ref() {
+        Some(sink) => sink(level, &args.to_string()),
+        None => eprintln!("{}", args),
+    }
+}
+
+/// Emit a message; without `std` there is nowhere to send it
+#[cfg(not(feature = "std"))]
+pub fn write(_level: LogLevel, _args: fmt::Arguments) {}
+
+/// Log a message at the given level
+#[macro_export]
+macro_rules! log_at {
+    ($level:expr, $($arg:tt)+) => {
+        if $crate::logging::enabled($level) {
+            $crate::logging::write($level, format_args!($($arg)+));
+        }
+    };
+}
+
+/// Log an error
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)+) => { $crate::log_at!($crate::logging::LogLevel::Error, $($arg)+) };
+}
+
+/// Log a warning
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)+) => { $crate::log_at!($crate::logging::LogLevel::Warn, $($arg)+) };
+}
+
+/// Log an informational message
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)+) => { $crate::log_at!($crate::logging::LogLevel::Info, $($arg)+) };
+}
+
+/// Log a debug message
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)+) => { $crate::log_at!($crate::logging::LogLevel::Debug, $($arg)+) };
+}
+
+/// Log a trace message
+#[macro_export]
+macro_rules! log_trace {
+    ($($arg:tt)+) => { $crate::log_at!($crate::logging::LogLevel::Trace, $($arg)+) };
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_error_level_suppresses_packet_trace() {
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let sink_captured = captured.clone();
+        set_sink(Box::new(move |level, message| {
+            // Other tests may log concurrently; keep only this test's messages
+            if message.contains("[logging-test]") {
+                sink_captured
+                    .lock()
+                    .unwrap()
+                    .push((level, message.to_string()));
+            }
+        }));
+        let previous = level();
+        set_level(LogLevel::Error);
+
+        log_trace!("[logging-test] GDB: Received command: {}", "qSupported");
+        log_debug!("[logging-test] GDB: Setting breakpoint");
+        log_error!("[logging-test] Failed to bind GDB socket");
+
+        set_level(previous);
+        reset_sink();
+
+        let captured = captured.lock().unwrap();
+        assert_eq!(
+            *captured,
+            vec![(
+                LogLevel::Error,
+                "[logging-test] Failed to bind GDB socket".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_level_ordering_and_names() {
+        assert!(LogLevel::Error < LogLevel::Trace);
+        assert_eq!(LogLevel::from_name("TRACE"), Some(LogLevel::Trace));
+        assert_eq!(LogLevel::from_name("warn"), Some(LogLevel::Warn));
+        assert_eq!(LogLevel::from_name("loud"), None);
+    }
+}
@@ -0,0 +1,1718 @@
+//! Headless IBM PC machine
+//!
+//! Wires the CPU, memory bus and standard peripherals together without any
+//! windowing or rendering. The windowed emulator drives a Machine once per
+//! frame; tests and tools can drive one directly.
+
+use crate::components::cga::{self, Cga};
+use crate::components::expanded_memory::ExpandedMemory;
+use crate::components::floppy::FloppyDisk;
+use crate::components::hdc::{Hdc, HDC_IRQ};
+use crate::components::hdd::{HardDisk, HddGeometry};
+use crate::components::ide::Ide;
+use crate::components::pit::{Pit, PitModel};
+use crate::components::post::{PostCard, PostCodeSink};
+use crate::components::ppi::Ppi;
+use crate::components::speaker::Speaker;
+use crate::cpu::{disasm, Cpu, Exception, InvalidOpcodeHook};
+use crate::debugger::{BreakpointHook, Breakpoints};
+use crate::io::{DeviceHandle, DeviceState, IoDevice};
+use crate::logging::{self, LogLevel};
+use crate::memory::{linear, MemRegion, MemoryBus};
+use crate::snapshot::{InputEvent, InputLog, Snapshot};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// CPU clock of the IBM 5150
+pub const BASE_CLOCK_HZ: u64 = 4_770_000;
+
+/// CPU clock with the turbo switch on (twice the stock speed)
+pub const TURBO_CLOCK_HZ: u64 = 2 * BASE_CLOCK_HZ;
+
+/// Video frames per second the machine is run at
+pub const FRAMES_PER_SECOND: u64 = 60;
+
+/// CPU cycles per video frame at the stock clock
+///
+/// IBM 5150 runs at 4.77 MHz, targeting 60 FPS:
+/// 4,770,000 cycles/sec / 60 frames/sec = 79,500 cycles per frame
+pub const CYCLES_PER_FRAME: u64 = BASE_CLOCK_HZ / FRAMES_PER_SECOND;
+
+/// Instructions retired between checks of the instruction rate limiter
+const THROTTLE_INTERVAL: u64 = 1000;
+
+/// How far the rate limiter may fall behind before it stops catching up
+///
+/// Beyond this the machine was idle (halted, paused, or not being run), and
+/// the limiter measures from now instead of running flat out to make up
+/// the gap.
+const THROTTLE_SLACK: Duration = Duration::from_millis(20);
+
+/// Instructions disassembled by `dump_state`
+const DUMP_INSTRUCTIONS: usize = 4;
+
+/// Linear address of the equipment flags word in the BIOS Data Area (0040:0010)
+pub const BDA_EQUIPMENT_WORD: u32 = 0x410;
+
+/// Video adapter reported in the equipment word (bits 5-4)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoAdapter {
+    /// EGA/VGA or other adapter with its own BIOS
+    Ega,
+    /// CGA, booting into 40x25 color text
+    Cga40,
+    /// CGA, booting into 80x25 color text
+    Cga80,
+    /// Monochrome Display Adapter, 80x25
+    Mda,
+}
+
+impl VideoAdapter {
+    /// Parse an adapter name: "mda", "cga" (80 columns) or "cga40"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "mda" => Some(Self::Mda),
+            "cga" | "cga80" => Some(Self::Cga80),
+            "cga40" => Some(Self::Cga40),
+            _ => None,
+        }
+    }
+}
+
+/// Hardware installed in a machine
+///
+/// Determines the equipment flags word the guest sees through the DIP
+/// switches, the BIOS Data Area and INT 11h.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MachineConfig {
+    /// Primary video adapter
+    pub video: VideoAdapter,
+    /// Number of floppy drives (0-4)
+    pub floppy_drives: u8,
+    /// Number of serial ports (0-7)
+    pub serial_ports: u8,
+    /// Number of parallel ports (0-3)
+    pub parallel_ports: u8,
+    /// Game port installed
+    pub game_port: bool,
+    /// 8087 coprocessor installed
+    pub fpu: bool,
+    /// Timer part; only the 8254 answers the read-back command
+    pub pit: PitModel,
+    /// Memory above 1MB in KB, as reported by INT 15h AH=88h
+    ///
+    /// The 8088 cannot address it, so nothing backs it; this is only for
+    /// software that sizes extended memory before deciding whether to use it.
+    pub extended_memory_kb: u16,
+}
+
+impl MachineConfig {
+    /// Compute the BIOS equipment flags word
+    ///
+    /// Bit 0: floppy drives present
+    /// Bit 1: 8087 installed
+    /// Bits 3-2: planar RAM (always 11 = 64K)
+    /// Bits 5-4: initial video mode
+    /// Bits 7-6: number of floppy drives - 1
+    /// Bits 11-9: number of serial ports
+    /// Bit 12: game port installed
+    /// Bits 15-14: number of parallel ports
+    pub fn equipment_word(&self) -> u16 {
+        let mut word = 0b1100;
+
+        if self.floppy_drives > 0 {
+            word |= 0x0001;
+            word |= ((self.floppy_drives.min(4) - 1) as u16) << 6;
+        }
+        if self.fpu {
+            word |= 0x0002;
+        }
+        word |= match self.video {
+            VideoAdapter::Ega => 0b00,
+            VideoAdapter::Cga40 => 0b01,
+            VideoAdapter::Cga80 => 0b10,
+            VideoAdapter::Mda => 0b11,
+        } << 4;
+        word |= (self.serial_ports.min(7) as u16) << 9;
+        if self.game_port {
+            word |= 0x1000;
+        }
+        word |= (self.parallel_ports.min(3) as u16) << 14;
+
+        word
+    }
+
+    /// SW1 DIP switch settings read through PPI port A
+    ///
+    /// The switches mirror the low byte of the equipment word.
+    pub fn dip_switches(&self) -> u8 {
+        self.equipment_word() as u8
+    }
+}
+
+impl Default for MachineConfig {
+    /// A stock 5150: MDA, one floppy drive, no ports or coprocessor
+    fn default() -> Self {
+        Self {
+            video: VideoAdapter::Mda,
+            floppy_drives: 1,
+            serial_ports: 0,
+            parallel_ports: 0,
+            game_port: false,
+            fpu: false,
+            pit: PitModel::I8253,
+            extended_memory_kb: 0,
+        }
+    }
+}
+
+/// Something an embedder may want to react to after running a frame
+#[derive(Debug, Clone, PartialEq)]
+pub enum MachineEvent {
+    /// The CPU halted with interrupts disabled and can never resume
+    Halted,
+
+    /// Hardware interrupts acknowledged during the frame, indexed by IRQ line
+    IrqSummary([u32; 8]),
+
+    /// The guest wrote a new value to the video mode control register
+    VideoModeChanged(u8),
+
+    /// The display entered vertical retrace; present the frame now to stay
+    /// in sync with guest code that waits for retrace
+    VerticalRetrace,
+
+    /// A RET popped a different address than its CALL pushed (only reported
+    /// with the call stack check enabled)
+    ReturnMismatch(ReturnMismatch),
+
+    /// Execution reached a breakpoint set with `Machine::set_breakpoint`; the
+    /// instruction at this CS:IP has not run yet
+    Breakpoint((u16, u16)),
+
+    /// The guest wrote a value watched with
+    /// `Machine::breakpoint_on_memory_value`, given as (address, value); the
+    /// writing instruction has completed
+    Watchpoint((u32, u8)),
+
+    /// The CPU faulted with pause on exception enabled; the machine stays
+    /// paused until `resume`
+    Exception(Exception),
+}
+
+/// Why `Machine::run_until_halt` returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HaltOutcome {
+    /// The CPU halted
+    Halted,
+    /// The cycle cap was reached first
+    CycleLimit,
+}
+
+/// Result of `Machine::run_until_halt`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HaltReport {
+    /// Whether the CPU halted or ran out of cycles
+    pub outcome: HaltOutcome,
+    /// CPU cycles executed
+    pub cycles: u64,
+    /// CS:IP where execution stopped
+    pub at: (u16, u16),
+}
+
+/// Called with a trapped exception and the `Machine::dump_state` report
+/// taken at the faulting instruction
+pub type ExceptionHook = Box<dyn FnMut(&Exception, &str)>;
+
+/// A RET that returned somewhere other than the matching CALL's next instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReturnMismatch {
+    /// CS:IP of the RET instruction
+    pub at: (u16, u16),
+    /// CS:IP the matching CALL would return to
+    pub expected: (u16, u16),
+    /// CS:IP the RET popped
+    pub actual: (u16, u16),
+}
+
+/// CALL or RET about to execute, as seen by the call stack check
+enum StackTransfer {
+    /// Return address the CALL pushes
+    Call((u16, u16)),
+    /// Return address the RET pops
+    Ret((u16, u16)),
+}
+
+/// Rewind point kept by time travel
+struct Checkpoint {
+    /// Frame the snapshot was taken at the start of
+    frame: u64,
+    /// Machine state at the start of `frame`
+    snapshot: Snapshot,
+    /// Input injected since the snapshot
+    log: InputLog,
+    /// Cycle count at the start of `frame` and of each frame run after it
+    frame_starts: Vec<u64>,
+}
+
+/// Ring of periodic snapshots for `Machine::rewind`
+struct TimeTravel {
+    /// Frames between checkpoints
+    interval: u64,
+    /// Maximum number of checkpoints kept
+    depth: usize,
+    /// Checkpoints, oldest first
+    checkpoints: VecDeque<Checkpoint>,
+}
+
+/// An IBM PC: CPU, memory bus and the standard set of peripherals
+pub struct Machine {
+    /// CPU state
+    pub cpu: Cpu,
+
+    /// Memory bus (RAM, ROM and hardwired peripherals)
+    pub memory: MemoryBus,
+
+    /// Keyboard scancode queue (shared with the host input system)
+    scancode_queue: Arc<RwLock<VecDeque<u8>>>,
+
+    /// Installed hardware
+    config: MachineConfig,
+
+    /// Input delivered through `inject` (None unless recording)
+    recording: Option<InputLog>,
+
+    /// CGA display rendered at its native size, before scaling to the frame
+    cga_display: Vec<u8>,
+
+    /// POST diagnostic port (0x80)
+    post_card: DeviceHandle<PostCard>,
+
+    /// Timer, whose counter 2 drives the speaker
+    pit: DeviceHandle<Pit>,
+
+    /// Keyboard and system control port, whose port B gates the speaker
+    ppi: DeviceHandle<Ppi>,
+
+    /// Tone generator fed by PIT counter 2 and PPI port B
+    speaker: Speaker,
+
+    /// CPU clock in Hz
+    clock_hz: u64,
+
+    /// Fraction of a peripheral cycle left over from scaling CPU cycles
+    /// (in units of 1/clock_hz)
+    bus_cycle_remainder: u64,
+
+    /// Instructions executed so far (halted steps don't count)
+    instructions_retired: u64,
+
+    /// Target instruction rate in MIPS (None for unbounded)
+    target_mips: Option<f64>,
+
+    /// Wall time and retirement count the rate limiter measures from
+    throttle_start: (Instant, u64),
+
+    /// Return addresses of outstanding CALLs (None unless checking)
+    call_stack: Option<Vec<(u16, u16)>>,
+
+    /// Mismatch found by the last step, reported by `run_frame_until`
+    return_mismatch: Option<ReturnMismatch>,
+
+    /// Host-set execution breakpoints
+    breakpoints: Breakpoints,
+
+    /// Called when a breakpoint is hit
+    breakpoint_hook: Option<BreakpointHook>,
+
+    /// Exception the machine is paused on (pause on exception mode)
+    paused_on: Option<Exception>,
+
+    /// Called when pausing on an exception
+    exception_hook: Option<ExceptionHook>,
+
+    /// Frames started by `run_frame_until` (paused frames don't count)
+    frames_run: u64,
+
+    /// Rewind history (None unless time travel is enabled)
+    time_travel: Option<TimeTravel>,
+
+    /// (cycle, IRQ line) pulses waiting to be delivered, earliest first
+    scheduled_irqs: Vec<(u64, u8)>,
+}
+
+impl Machine {
+    /// Create a new machine with the PPI (keyboard) and PIT registered
+    ///
+    /// The CPU is reset, so execution starts at the reset vector F000:FFF0.
+    pub fn new() -> Self {
+        Self::with_config(MachineConfig::default())
+    }
+
+    /// Create a new machine with the given hardware configuration
+    ///
+    /// The DIP switches and the BDA equipment word are set from `config`.
+    pub fn with_config(config: MachineConfig) -> Self {
+        let mut memory = MemoryBus::new();
+
+        // Create keyboard queue and register PPI (which owns the keyboard)
+        let scancode_queue = Arc::new(RwLock::new(VecDeque::new()));
+        let ppi = Ppi::with_dip_switches(scancode_queue.clone(), config.dip_switches());
+        let ppi = memory.attach_device(ppi);
+
+        // Create and register PIT, with counter 2 gated by PPI port B
+        let pit = memory.attach_device(Pit::with_model(config.pit));
+        ppi.borrow_mut().connect_timer(pit.clone());
+
+        let post_card = memory.attach_device(PostCard::new());
+
+        if matches!(config.video, VideoAdapter::Cga40 | VideoAdapter::Cga80) {
+            memory.install_cga(Cga::new());
+        }
+
+        // Create and reset CPU to initialize reset vector (CS=0xF000, IP=0xFFF0)
+        let mut cpu = Cpu::new();
+        cpu.reset();
+
+        memory.write_u16(BDA_EQUIPMENT_WORD, config.equipment_word());
+
+        Self {
+            cpu,
+            memory,
+            scancode_queue,
+            config,
+            recording: None,
+            cga_display: Vec::new(),
+            post_card,
+            pit,
+            ppi,
+            speaker: Speaker::new(),
+            clock_hz: BASE_CLOCK_HZ,
+            bus_cycle_remainder: 0,
+            instructions_retired: 0,
+            target_mips: None,
+            throttle_start: (Instant::now(), 0),
+            call_stack: None,
+            return_mismatch: None,
+            breakpoints: Breakpoints::new(),
+            breakpoint_hook: None,
+            paused_on: None,
+            exception_hook: None,
+            frames_run: 0,
+            time_travel: None,
+            scheduled_irqs: Vec::new(),
+        }
+    }
+
+    /// Get the hardware configuration
+    pub fn config(&self) -> MachineConfig {
+        self.config
+    }
+
+    /// Load BIOS ROM data at the end of the ROM space
+    pub fn load_rom(&mut self, rom_data: &[u8]) {
+        self.memory.load_rom(rom_data);
+    }
+
+    /// Insert a floppy disk into a drive (0 = A:, 1 = B:)
+    pub fn insert_floppy(&mut self, drive: u8, disk: FloppyDisk) {
+        self.memory.insert_floppy(drive, disk);
+    }
+
+    /// Get a reference to the keyboard scancode queue
+    ///
+    /// The host input system pushes scancodes here when keys are pressed.
+    pub fn scancode_queue(&self) -> Arc<RwLock<VecDeque<u8>>> {
+        self.scancode_queue.clone()
+    }
+
+    /// Attach an IO device and return a handle the host can keep
+    ///
+    /// The bus dispatches IN/OUT and ticks to the device, while the returned
+    /// handle gives host code shared access to it (e.g. setting a joystick
+    /// axis or injecting serial input).
+    pub fn attach_device<D: IoDevice + 'static>(&mut self, device: D) -> DeviceHandle<D> {
+        self.memory.attach_device(device)
+    }
+
+    /// Install an EMS board (see `MemoryBus::install_expanded_memory`)
+    pub fn install_expanded_memory(&mut self, ems: ExpandedMemory) -> DeviceHandle<ExpandedMemory> {
+        self.memory.install_expanded_memory(ems)
+    }
+
+    /// Attach a fixed disk image as drive 0 of an XT disk controller
+    ///
+    /// The controller sits at ports 0x320-0x323 on IRQ5 and DMA channel 3.
+    /// Booting from it still needs the controller's BIOS extension ROM (or
+    /// a BIOS with fixed disk support built in). Returns a handle for
+    /// reading the image back after the guest has written to it.
+    ///
+    /// Panics if an IDE controller already uses IRQ5.
+    pub fn attach_hdd(&mut self, image: Vec<u8>, geometry: HddGeometry) -> DeviceHandle<Hdc> {
+        assert!(
+            self.memory
+                .ide()
+                .is_none_or(|ide| ide.borrow().irq() != HDC_IRQ),
+            "the IDE controller already uses IRQ{}",
+            HDC_IRQ
+        );
+        let mut hdc = Hdc::new();
+        hdc.insert_disk(0, HardDisk::new(image, geometry));
+        self.memory.install_hdc(hdc)
+    }
+
+    /// Attach a hard disk as the master drive of an IDE controller on `irq`
+    ///
+    /// The controller sits at ports 0x1F0-0x1F7 and 0x3F6. The AT's IRQ14
+    /// needs a second PIC, so it raises one of IRQ0-7 instead; `IDE_IRQ`
+    /// (IRQ5) is free unless an XT controller is attached. Like
+    /// `attach_hdd`, booting from it needs a BIOS that knows about the
+    /// drive. Returns a handle for saving the image on exit.
+    ///
+    /// Panics if `irq` is above 7 or is already used by an XT controller.
+    pub fn attach_ide(&mut self, disk: HardDisk, irq: u8) -> DeviceHandle<Ide> {
+        assert!(irq < 8, "IRQ{} needs a second PIC", irq);
+        assert!(
+            self.memory.hdc().is_none() || irq != HDC_IRQ,
+            "the XT disk controller already uses IRQ{}",
+            irq
+        );
+        let mut ide = Ide::new(irq);
+        ide.insert_disk(0, disk);
+        self.memory.install_ide(ide)
+    }
+
+    /// Charge extra cycles on every IN/OUT, modeling slow ISA cards
+    ///
+    /// Added on top of each instruction's documented timing. A word access
+    /// to an 8-bit device is two bus accesses and pays the delay twice.
+    pub fn set_io_delay(&mut self, cycles: u16) {
+        self.memory.set_io_delay(cycles);
+    }
+
+    /// Override the IO delay for one device's ports
+    pub fn set_port_io_delay(&mut self, ports: RangeInclusive<u16>, cycles: u16) {
+        self.memory.set_port_io_delay(ports, cycles);
+    }
+
+    /// Snapshot the state of all devices for debugging
+    ///
+    /// Side-effect free, so it is safe to call from a debugger UI at any time.
+    pub fn devices_state(&self) -> Vec<DeviceState> {
+        self.memory.devices_state()
+    }
+
+    /// Fill an audio buffer with PC speaker samples at `rate` Hz
+    ///
+    /// The tone follows PIT counter 2 and the gate and data bits of port
+    /// 0x61 as they are when called, so a host audio callback can keep
+    /// pulling samples while frames run.
+    pub fn speaker_samples(&mut self, rate: u32, buffer: &mut [i16]) {
+        let counter = self.pit.borrow().channel(2);
+        let port_b = self.ppi.borrow().port_b();
+        self.speaker.fill(rate, &counter, port_b, buffer);
+    }
+
+    /// Describe what is mapped where in the address space
+    ///
+    /// Reflects the regions the memory bus actually decodes, which may be
+    /// fewer than the configuration asks for (e.g. there is no EGA window).
+    pub fn memory_map(&self) -> Vec<MemRegion> {
+        self.memory.memory_map()
+    }
+
+    /// Describe the machine state in a human-readable report
+    ///
+    /// Covers registers, decoded flags, the next few instructions at CS:IP,
+    /// the PIC and PIT registers and the video mode, e.g. for pasting into a
+    /// bug report. Has no side effects.
+    pub fn dump_state(&self) -> String {
+        let cpu = &self.cpu;
+        let [ax, cx, dx, bx, sp, bp, si, di] = cpu.regs;
+        let [es, cs, ss, ds] = cpu.segments;
+        let flags = cpu.compute_flags();
+        let mut out = String::new();
+
+        // Writing to a String cannot fail
+        let _ = writeln!(
+            out,
+            "AX={:04X} BX={:04X} CX={:04X} DX={:04X}",
+            ax, bx, cx, dx
+        );
+        let _ = writeln!(
+            out,
+            "SI={:04X} DI={:04X} BP={:04X} SP={:04X}",
+            si, di, bp, sp
+        );
+        let _ = writeln!(
+            out,
+            "CS={:04X} DS={:04X} ES={:04X} SS={:04X}",
+            cs, ds, es, ss
+        );
+        let _ = writeln!(
+            out,
+            "CS:IP={:04X}:{:04X} SS:SP={:04X}:{:04X}",
+            cs, cpu.ip, ss, sp
+        );
+        let _ = writeln!(out, "FLAGS={:04X} [{}]", flags, disasm::flag_names(flags));
+        if cpu.halted {
+            let _ = writeln!(out, "CPU halted");
+        }
+        let _ = writeln!(out, "Cycles={}", cpu.total_cycles);
+
+        let _ = writeln!(out, "Next instructions:");
+        let mut ip = cpu.ip;
+        for _ in 0..DUMP_INSTRUCTIONS {
+            let insn = disasm::disassemble_at(&self.memory, cs, ip);
+            let _ = writeln!(out, "  {:04X}:{:04X}  {}", cs, ip, insn.annotated());
+            ip = ip.wrapping_add(insn.length as u16);
+        }
+
+        let pic = self.memory.pic().describe();
+        let _ = writeln!(
+            out,
+            "PIC: IRR={:02X} ISR={:02X} IMR={:02X} base={:02X}",
+            pic.irr, pic.isr, pic.imr, pic.vector_offset
+        );
+
+        for (i, ch) in self.pit.borrow().describe().channels.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "PIT{}: mode={} count={:04X} reload={:04X} out={} gate={}",
+                i, ch.mode, ch.count, ch.reload, ch.output as u8, ch.gate as u8
+            );
+        }
+
+        let mode_control = match self.active_cga() {
+            Some(cga) => cga.borrow().mode_control(),
+            None => self.memory.mda().mode_control(),
+        };
+        let _ = writeln!(
+            out,
+            "Video: {:?} mode control={:02X}",
+            self.active_video(),
+            mode_control
+        );
+
+        out
+    }
+
+    /// Copy code or data into memory at a linear address
+    ///
+    /// Useful for dropping small handler ROMs or test programs into RAM
+    /// without a full BIOS. Writes to unmapped or ROM addresses are ignored.
+    pub fn load_at(&mut self, addr: u32, data: &[u8]) {
+        for (i, &byte) in data.iter().enumerate() {
+            self.memory.write_u8(addr + i as u32, byte);
+        }
+        self.cpu.decode_cache.clear();
+    }
+
+    /// Point interrupt vector table entries at handler code
+    ///
+    /// Each entry is `(vector, cs, ip)`. The IVT lives at linear address 0,
+    /// with each vector occupying 4 bytes: IP (low word) then CS (high word).
+    pub fn install_handlers(&mut self, handlers: &[(u8, u16, u16)]) {
+        for &(vector, cs, ip) in handlers {
+            let entry = vector as u32 * 4;
+            self.memory.write_u16(entry, ip);
+            self.memory.write_u16(entry + 2, cs);
+        }
+    }
+
+    /// Install an INT 11h handler that returns the BDA equipment word in AX
+    ///
+    /// For running without a BIOS ROM. The handler code is copied to `cs:ip`.
+    pub fn install_equipment_service(&mut self, cs: u16, ip: u16) {
+        // PUSH DS; MOV AX, 0x40; MOV DS, AX; MOV AX, [0x10]; POP DS; IRET
+        let handler = [
+            0x1E, 0xB8, 0x40, 0x00, 0x8E, 0xD8, 0xA1, 0x10, 0x00, 0x1F, 0xCF,
+        ];
+        self.load_at(linear(cs, ip), &handler);
+        self.install_handlers(&[(0x11, cs, ip)]);
+    }
+
+    /// Install an INT 15h system services handler
+    ///
+    /// For running without a BIOS ROM. The handler code is copied to `cs:ip`
+    /// and answers two functions, returning with CF clear:
+    ///
+    /// - AH=88h: extended memory size in KB in AX, from the configuration
+    /// - AH=86h: wait CX:DX microseconds, with interrupts enabled
+    ///
+    /// Any other function returns AH=86h with CF set, as the XT BIOS does.
+    /// The wait counts PIT counter 0 ticks, so the counter must be running
+    /// with a full 65536 reload in low-then-high access mode, as the BIOS
+    /// leaves it.
+    pub fn install_system_services(&mut self, cs: u16, ip: u16) {
+        let [ext_low, ext_high] = self.config.extended_memory_kb.to_le_bytes();
+        let handler = [
+            0xFB, // STI
+            0x80, 0xFC, 0x88, // CMP AH, 0x88
+            0x74, 0x0B, // JE ext
+            0x80, 0xFC, 0x86, // CMP AH, 0x86
+            0x74, 0x0D, // JE wait
+            0xB4, 0x86, // MOV AH, 0x86
+            0xF9, // STC
+            0xCA, 0x02, 0x00, // RETF 2
+            // ext:
+            0xB8, ext_low, ext_high, // MOV AX, extended_memory_kb
+            0xF8,     // CLC
+            0xCA, 0x02, 0x00, // RETF 2
+            // wait:
+            0x50, 0x53, 0x51, 0x52, 0x56, 0x57, 0x55, // PUSH AX..BP
+            0x89, 0xCE, // MOV SI, CX
+            0x89, 0xD7, // MOV DI, DX
+            // PIT ticks = us * 1.2, rounding the 1.193 MHz clock up
+            0xBB, 0x05, 0x00, // MOV BX, 5
+            0x89, 0xC8, // MOV AX, CX
+            0x31, 0xD2, // XOR DX, DX
+            0xF7, 0xF3, // DIV BX
+            0x89, 0xC1, // MOV CX, AX
+            0x89, 0xF8, // MOV AX, DI
+            0xF7, 0xF3, // DIV BX
+            0x01, 0xC7, // ADD DI, AX
+            0x11, 0xCE, // ADC SI, CX
+            0xE8, 0x23, 0x00, // CALL read
+            0x89, 0xC5, // MOV BP, AX
+            // loop: subtract ticks elapsed since the last read from SI:DI
+            0xE8, 0x1E, 0x00, // CALL read
+            0x89, 0xEB, // MOV BX, BP
+            0x29, 0xC3, // SUB BX, AX
+            0x89, 0xC5, // MOV BP, AX
+            0x29, 0xDF, // SUB DI, BX
+            0x83, 0xDE, 0x00, // SBB SI, 0
+            0x72, 0x06, // JC done
+            0x89, 0xF0, // MOV AX, SI
+            0x09, 0xF8, // OR AX, DI
+            0x75, 0xEA, // JNZ loop
+            // done:
+            0x5D, 0x5F, 0x5E, 0x5A, 0x59, 0x5B, 0x58, // POP BP..AX
+            0xF8, // CLC
+            0xCA, 0x02, 0x00, // RETF 2
+            // read: latch counter 0 and return it in AX
+            0xB0, 0x00, // MOV AL, 0x00
+            0xE6, 0x43, // OUT 0x43, AL
+            0xE4, 0x40, // IN AL, 0x40
+            0x88, 0xC4, // MOV AH, AL
+            0xE4, 0x40, // IN AL, 0x40
+            0x86, 0xC4, // XCHG AL, AH
+            0xC3, // RET
+        ];
+        self.load_at(linear(cs, ip), &handler);
+        self.install_handlers(&[(0x15, cs, ip)]);
+    }
+
+    /// Install an INT 13h handler for fixed disks on the XT controller
+    ///
+    /// For running without a BIOS ROM (or the controller's own ROM). The
+    /// handler code is copied to `cs:ip` and serves drives 80h and 81h:
+    ///
+    /// - AH=00h: reset, always succeeds
+    /// - AH=02h/03h: read/write AL sectors at CHS CH/CL/DH to or from ES:BX
+    ///
+    /// Data moves through the controller's data port rather than DMA. On
+    /// success AH=0, AL is the sector count and CF is clear. A failed
+    /// transfer returns AH=04h (sector not found) with CF set, without
+    /// asking the controller for details; any other function or drive
+    /// number returns AH=01h with CF set.
+    pub fn install_disk_service(&mut self, cs: u16, ip: u16) {
+        let handler = [
+            0x80, 0xFA, 0x80, // CMP DL, 0x80
+            0x72, 0x18, // JB bad
+            0x80, 0xFA, 0x81, // CMP DL, 0x81
+            0x77, 0x13, // JA bad
+            0x80, 0xFC, 0x02, // CMP AH, 0x02
+            0x74, 0x14, // JE transfer
+            0x80, 0xFC, 0x03, // CMP AH, 0x03
+            0x74, 0x0F, // JE transfer
+            0x80, 0xFC, 0x00, // CMP AH, 0x00
+            0x75, 0x04, // JNE bad
+            0xF8, // CLC
+            0xCA, 0x02, 0x00, // RETF 2
+            // bad:
+            0xB4, 0x01, // MOV AH, 0x01
+            0xF9, // STC
+            0xCA, 0x02, 0x00, // RETF 2
+            // transfer:
+            0x53, 0x51, 0x52, 0x57, 0x56, // PUSH BX, CX, DX, DI, SI
+            0x89, 0xDF, // MOV DI, BX
+            0x89, 0xC3, // MOV BX, AX
+            0x89, 0xD6, // MOV SI, DX
+            0xBA, 0x23, 0x03, // MOV DX, 0x323
+            0xB0, 0x00, // MOV AL, 0
+            0xEE, // OUT DX, AL (no DMA or IRQ)
+            0x4A, // DEC DX
+            0xEE, // OUT DX, AL (select)
+            0x4A, 0x4A, // DEC DX; DEC DX
+            // Command block
+            0xB0, 0x08, // MOV AL, 0x08 (read)
+            0x80, 0xFF, 0x02, // CMP BH, 0x02
+            0x74, 0x02, // JE +2
+            0xB0, 0x0A, // MOV AL, 0x0A (write)
+            0xEE, // OUT DX, AL
+            0x89, 0xF0, // MOV AX, SI
+            0xA8, 0x01, // TEST AL, 0x01
+            0x88, 0xE0, // MOV AL, AH
+            0x74, 0x02, // JZ +2
+            0x0C, 0x20, // OR AL, 0x20
+            0xEE, // OUT DX, AL (drive and head)
+            0x88, 0xC8, // MOV AL, CL
+            0xFE, 0xC8, // DEC AL
+            0xEE, // OUT DX, AL (cylinder high bits, sector from 0)
+            0x88, 0xE8, // MOV AL, CH
+            0xEE, // OUT DX, AL (cylinder)
+            0x88, 0xD8, // MOV AL, BL
+            0xEE, // OUT DX, AL (count)
+            0xB0, 0x05, // MOV AL, 0x05
+            0xEE, // OUT DX, AL (control)
+            // Data phase: CX = count * 512 bytes
+            0x88, 0xDD, // MOV CH, BL
+            0xB1, 0x00, // MOV CL, 0
+            0xD1, 0xE1, // SHL CX, 1
+            0x42, // INC DX
+            0x80, 0xFF, 0x02, // CMP BH, 0x02
+            0x75, 0x0D, // JNE write
+            // read: stop early if the controller skips to the status phase
+            0xEC, // IN AL, DX
+            0xA8, 0x04, // TEST AL, 0x04
+            0x75, 0x16, // JNZ done
+            0x4A, // DEC DX
+            0xEC, // IN AL, DX
+            0xAA, // STOSB
+            0x42, // INC DX
+            0xE2, 0xF5, // LOOP read
+            0xEB, 0x0E, // JMP done
+            // write:
+            0xEC, // IN AL, DX
+            0xA8, 0x04, // TEST AL, 0x04
+            0x75, 0x09, // JNZ done
+            0x4A, // DEC DX
+            0x26, 0x8A, 0x05, // MOV AL, ES:[DI]
+            0x47, // INC DI
+            0xEE, // OUT DX, AL
+            0x42, // INC DX
+            0xE2, 0xF2, // LOOP write
+            // done: read the completion byte
+            0x4A, // DEC DX
+            0xEC, // IN AL, DX
+            0xA8, 0x02, // TEST AL, 0x02
+            0x89, 0xD8, // MOV AX, BX
+            0xB4, 0x00, // MOV AH, 0
+            0x74, 0x06, // JZ ok
+            0xB8, 0x00, 0x04, // MOV AX, 0x0400
+            0xF9, // STC
+            0xEB, 0x01, // JMP out
+            // ok:
+            0xF8, // CLC
+            // out:
+            0x5E, 0x5F, 0x5A, 0x59, 0x5B, // POP SI, DI, DX, CX, BX
+            0xCA, 0x02, 0x00, // RETF 2
+        ];
+        self.load_at(linear(cs, ip), &handler);
+        self.install_handlers(&[(0x13, cs, ip)]);
+    }
+
+    /// Enable or disable CGA "snow" simulation
+    ///
+    /// On a real CGA, CPU writes to video RAM during active display steal
+    /// the adapter's fetch and show up as garbage pixels for a frame (see
+    /// `Cga::set_snow`). Does nothing without a CGA installed.
+    pub fn set_cga_snow(&mut self, enabled: bool) {
+        if let Some(cga) = self.memory.cga() {
+            cga.borrow_mut().set_snow(enabled);
+        }
+    }
+
+    /// Check whether CGA snow simulation is enabled
+    pub fn cga_snow(&self) -> bool {
+        self.memory.cga().is_some_and(|cga| cga.borrow().snow())
+    }
+
+    /// Get the video adapter the guest is using
+    ///
+    /// Read from the equipment word in the BIOS Data Area, which the ROM may
+    /// change from the configured adapter (e.g. to switch displays). A CGA
+    /// that is not installed, or an EGA, falls back to the MDA.
+    pub fn active_video(&self) -> VideoAdapter {
+        let word = self.memory.peek_u8(BDA_EQUIPMENT_WORD);
+        match (word >> 4) & 0b11 {
+            0b01 if self.memory.cga().is_some() => VideoAdapter::Cga40,
+            0b10 if self.memory.cga().is_some() => VideoAdapter::Cga80,
+            _ => VideoAdapter::Mda,
+        }
+    }
+
+    /// Get the installed CGA if it is the active adapter
+    fn active_cga(&self) -> Option<&DeviceHandle<Cga>> {
+        self.memory
+            .cga()
+            .filter(|_| self.active_video() != VideoAdapter::Mda)
+    }
+
+    /// Render the active adapter's display to a 720x350 RGBA framebuffer
+    ///
+    /// The CGA's 640x200 display is scaled up to fill the frame.
+    pub fn render_to_framebuffer(&mut self, framebuffer: &mut [u8]) {
+        let Some(cga) = self.active_cga().cloned() else {
+            self.memory.mda().render_to_framebuffer(framebuffer);
+            return;
+        };
+
+        let display = &mut self.cga_display;
+        display.resize(cga::DISPLAY_WIDTH * cga::DISPLAY_HEIGHT * 4, 0);
+        cga.borrow().render_to_framebuffer(display);
+        for y in 0..350 {
+            let src_y = y * cga::DISPLAY_HEIGHT / 350;
+            for x in 0..720 {
+                let src = (src_y * cga::DISPLAY_WIDTH + x * cga::DISPLAY_WIDTH / 720) * 4;
+                let dst = (y * 720 + x) * 4;
+                framebuffer[dst..dst + 4].copy_from_slice(&display[src..src + 4]);
+            }
+        }
+    }
+
+    /// Get the (character, attribute) at a cell of the active adapter's text screen
+    pub fn screen_cell(&self, row: usize, col: usize) -> (u8, u8) {
+        match self.active_cga() {
+            Some(cga) => cga.borrow().cell(row, col),
+            None => self.memory.mda().cell(row, col),
+        }
+    }
+
+    /// Read the text screen as a string, one line per row
+    ///
+    /// Covers every row and column currently displayed. Characters outside
+    /// printable ASCII (including NUL and the graphics characters) become
+    /// spaces, and trailing spaces are trimmed from each row, so tests can
+    /// compare against plain text.
+    pub fn screen_text(&self) -> String {
+        let (rows, columns) = match self.active_cga() {
+            Some(cga) => {
+                let cga = cga.borrow();
+                (cga.text_rows(), cga.text_columns())
+            }
+            None => {
+                let mda = self.memory.mda();
+                (mda.text_rows(), mda.text_columns())
+            }
+        };
+
+        let mut text = String::new();
+        for row in 0..rows {
+            let line: String = (0..columns)
+                .map(|col| match self.screen_cell(row, col).0 {
+                    ch @ 0x20..=0x7E => ch as char,
+                    _ => ' ',
+                })
+                .collect();
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Set or clear a callback invoked with each POST code the guest writes
+    ///
+    /// Codes are recorded in the history whether or not a sink is set.
+    pub fn set_post_code_sink(&mut self, sink: Option<PostCodeSink>) {
+        self.post_card.borrow_mut().set_sink(sink);
+    }
+
+    /// Get the last code written to the POST diagnostic port 0x80
+    pub fn last_post_code(&self) -> Option<u8> {
+        self.post_card.borrow().last()
+    }
+
+    /// Get every code written to port 0x80, oldest first
+    pub fn post_code_history(&self) -> Vec<u8> {
+        self.post_card.borrow().history().iter().copied().collect()
+    }
+
+    /// Change the CPU clock, e.g. to model a turbo XT
+    ///
+    /// Takes effect from the next frame: `run_frame` executes
+    /// `clock_hz / FRAMES_PER_SECOND` cycles. Timers, video and the other
+    /// peripherals keep running off their own crystal, so they see the same
+    /// number of cycles per frame at any CPU clock.
+    pub fn set_clock_hz(&mut self, clock_hz: u64) {
+        assert!(clock_hz > 0, "CPU clock must be non-zero");
+        self.clock_hz = clock_hz;
+        self.bus_cycle_remainder = 0;
+    }
+
+    /// Get the CPU clock in Hz
+    pub fn clock_hz(&self) -> u64 {
+        self.clock_hz
+    }
+
+    /// Get the number of CPU cycles `run_frame` executes at the current clock
+    pub fn cycles_per_frame(&self) -> u64 {
+        self.clock_hz / FRAMES_PER_SECOND
+    }
+
+    /// Switch between the stock clock and `TURBO_CLOCK_HZ`
+    pub fn set_turbo(&mut self, enabled: bool) {
+        self.set_clock_hz(if enabled {
+            TURBO_CLOCK_HZ
+        } else {
+            BASE_CLOCK_HZ
+        });
+    }
+
+    /// Check whether the CPU is running faster than stock
+    pub fn turbo(&self) -> bool {
+        self.clock_hz > BASE_CLOCK_HZ
+    }
+
+    /// Limit execution to a number of million instructions per second
+    ///
+    /// `None` runs as fast as the host allows. With a target set, `step`
+    /// sleeps as needed to keep the retirement rate at the target, whatever
+    /// pace `run_frame` is called at. Independent of `set_clock_hz`, which
+    /// scales emulated time rather than wall time.
+    pub fn set_target_mips(&mut self, mips: Option<f64>) {
+        self.target_mips = mips.filter(|mips| *mips > 0.0);
+        self.restart_throttle();
+    }
+
+    /// Get the instruction rate target in MIPS, if any
+    pub fn target_mips(&self) -> Option<f64> {
+        self.target_mips
+    }
+
+    /// Get the number of instructions executed so far
+    pub fn instructions_retired(&self) -> u64 {
+        self.instructions_retired
+    }
+
+    /// Set the most verbose log level emitted by the emulator core
+    ///
+    /// Logging is process-wide, so this also affects other machines.
+    pub fn set_log_level(&mut self, level: LogLevel) {
+        logging::set_level(level);
+    }
+
+    /// Get the current log level
+    pub fn log_level(&self) -> LogLevel {
+        logging::level()
+    }
+
+    /// Enable or disable the shadow call stack check
+    ///
+    /// While enabled, every CALL records the address it will return to and
+    /// every RET is compared against the most recent record. A mismatch is
+    /// logged as a warning and ends the current frame with a
+    /// `MachineEvent::ReturnMismatch`. Off by default, since some code
+    /// legitimately rewrites return addresses or returns with JMP.
+    pub fn enable_call_stack_check(&mut self, enabled: bool) {
+        self.call_stack = enabled.then(Vec::new);
+        self.return_mismatch = None;
+    }
+
+    /// Check whether the shadow call stack check is enabled
+    pub fn call_stack_check_enabled(&self) -> bool {
+        self.call_stack.is_some()
+    }
+
+    /// Stop before executing the instruction at a linear address
+    ///
+    /// `run_frame_until` ends the frame with a `MachineEvent::Breakpoint`
+    /// when the next instruction is at a breakpoint, after calling the hook
+    /// set with `set_breakpoint_hook`. Running again executes the instruction
+    /// and carries on.
+    pub fn set_breakpoint(&mut self, addr: u32) {
+        self.breakpoints.insert(addr);
+    }
+
+    /// Stop before executing the instruction at CS:IP
+    pub fn set_breakpoint_at(&mut self, cs: u16, ip: u16) {
+        self.set_breakpoint(linear(cs, ip));
+    }
+
+    /// Remove the breakpoint at a linear address, if any
+    pub fn clear_breakpoint(&mut self, addr: u32) {
+        self.breakpoints.remove(addr);
+    }
+
+    /// Remove the breakpoint at CS:IP, if any
+    pub fn clear_breakpoint_at(&mut self, cs: u16, ip: u16) {
+        self.clear_breakpoint(linear(cs, ip));
+    }
+
+    /// Set or clear a callback invoked with the CS:IP of each breakpoint hit
+    pub fn set_breakpoint_hook(&mut self, hook: Option<BreakpointHook>) {
+        self.breakpoint_hook = hook;
+    }
+
+    /// Stop when the guest writes `value` to the byte at linear `addr`
+    ///
+    /// Other values written there go unnoticed, which makes this the way to
+    /// catch the moment a flag variable flips. `run_frame_until` ends the
+    /// frame with a `MachineEvent::Watchpoint` after the instruction that
+    /// made the write.
+    pub fn breakpoint_on_memory_value(&mut self, addr: u32, value: u8) {
+        self.memory.watch_value(addr, value);
+    }
+
+    /// Remove every value watchpoint at a linear address
+    pub fn clear_memory_value_breakpoint(&mut self, addr: u32) {
+        self.memory.unwatch_value(addr);
+    }
+
+    /// Pause on CPU faults instead of handling them
+    ///
+    /// While enabled, a divide error, an invalid opcode or a software
+    /// interrupt with no handler installed stops the CPU at the faulting
+    /// instruction (see `Cpu::set_trap_exceptions`). `run_frame_until` then
+    /// passes a `dump_state` report to the hook set with `set_exception_hook`,
+    /// reports `MachineEvent::Exception` and runs nothing further until
+    /// `resume`.
+    pub fn set_pause_on_exception(&mut self, enabled: bool) {
+        self.cpu.set_trap_exceptions(enabled);
+        if !enabled {
+            self.paused_on = None;
+        }
+    }
+
+    /// Check whether the machine pauses on CPU faults
+    pub fn pause_on_exception(&self) -> bool {
+        self.cpu.trap_exceptions()
+    }
+
+    /// Set or clear a callback invoked for every invalid opcode the guest hits
+    ///
+    /// The callback gets the opcode byte and CS:IP before the fault is
+    /// handled, whether or not the machine pauses on exceptions. Useful for
+    /// finding mis-assembled code or instructions from a later CPU model.
+    pub fn on_invalid_opcode(&mut self, hook: Option<InvalidOpcodeHook>) {
+        self.cpu.set_invalid_opcode_hook(hook);
+    }
+
+    /// Set or clear the callback invoked when pausing on an exception
+    pub fn set_exception_hook(&mut self, hook: Option<ExceptionHook>) {
+        self.exception_hook = hook;
+    }
+
+    /// Get the exception the machine is paused on, if any
+    pub fn paused_on(&self) -> Option<Exception> {
+        self.paused_on
+    }
+
+    /// Continue after pausing on an exception
+    ///
+    /// Execution retries the faulting instruction, so fix up the state
+    /// first (or disable pause on exception to let the guest handle it).
+    pub fn resume(&mut self) {
+        self.paused_on = None;
+    }
+
+    /// Count how many times each IRQ line has been raised
+    ///
+    /// Indexed by IRQ number, for spotting interrupt storms. Only the XT's
+    /// single 8259 is emulated, so lines 8-15 always read 0. Counts restart
+    /// when the machine is reset.
+    pub fn irq_stats(&self) -> [u64; 16] {
+        let mut stats = [0; 16];
+        stats[..8].copy_from_slice(&self.memory.pic().raise_counts());
+        stats
+    }
+
+    /// Reset the CPU and every peripheral to power-on state
+    ///
+    /// RAM, ROM and inserted disks are preserved, like pressing the reset
+    /// button rather than cycling power.
+    pub fn reset(&mut self) {
+        self.cpu.reset();
+        self.memory.reset_devices();
+        // The PIT resets after the PPI, so drive the gate from the cleared port
+        let port_b = self.ppi.borrow().port_b();
+        self.pit.borrow_mut().set_gate(2, port_b & 0x01 != 0);
+        self.paused_on = None;
+        if let Some(call_stack) = self.call_stack.as_mut() {
+            call_stack.clear();
+        }
+        self.restart_throttle();
+    }
+
+    /// Capture the complete machine state
+    ///
+    /// ROM, the hardware configuration and attached host connections are not
+    /// included; restore into a machine set up the same way.
+    pub fn snapshot(&self) -> Snapshot {
+        let scancodes = self
+            .scancode_queue
+            .read()
+            .map(|queue| queue.iter().copied().collect())
+            .unwrap_or_default();
+
+        Snapshot {
+            cpu: self.cpu.save_state(),
+            bus: self.memory.save_state(),
+            scancodes,
+            devices: self.devices_state(),
+        }
+    }
+
+    /// Restore state captured by `snapshot`
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.cpu.load_state(&snapshot.cpu);
+        self.memory.load_state(&snapshot.bus);
+        if let Ok(mut queue) = self.scancode_queue.write() {
+            queue.clear();
+            queue.extend(snapshot.scancodes.iter().copied());
+        }
+        if let Some(call_stack) = self.call_stack.as_mut() {
+            call_stack.clear();
+        }
+        self.restart_throttle();
+    }
+
+    /// Deliver host input to the machine, recording it if a recording is active
+    ///
+    /// An IRQ on a line the PIC doesn't have (8 or above) is logged and
+    /// dropped without being recorded.
+    pub fn inject(&mut self, event: InputEvent) {
+        if let InputEvent::Irq { line, .. } = event {
+            if !Self::check_irq_line(line) {
+                return;
+            }
+        }
+        if let Some(log) = self.recording.as_mut() {
+            log.push(self.cpu.total_cycles, event);
+        }
+        if let Some(checkpoint) = self
+            .time_travel
+            .as_mut()
+            .and_then(|time_travel| time_travel.checkpoints.back_mut())
+        {
+            checkpoint.log.push(self.cpu.total_cycles, event);
+        }
+        self.apply_input(event);
+    }
+
+    /// Start recording input delivered through `inject`
+    ///
+    /// Take a snapshot at the same time to be able to replay the recording.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(InputLog::new());
+    }
+
+    /// Stop recording and return the log, or None if not recording
+    pub fn stop_recording(&mut self) -> Option<InputLog> {
+        let mut log = self.recording.take()?;
+        log.end_cycle = self.cpu.total_cycles;
+        Some(log)
+    }
+
+    /// Restore `snapshot` and re-run up to the end of `log`
+    ///
+    /// Each event is delivered before the first instruction that starts at or
+    /// after its recorded cycle, which is exactly when `inject` delivered it
+    /// during recording, so the run is reproduced exactly.
+    pub fn replay(&mut self, snapshot: &Snapshot, log: &InputLog) {
+        self.restore(snapshot);
+
+        let mut events = log.events.iter().peekable();
+        loop {
+            while let Some(&&(cycle, event)) = events.peek() {
+                if cycle > self.cpu.total_cycles {
+                    break;
+                }
+                self.apply_input(event);
+                events.next();
+            }
+
+            if self.cpu.total_cycles >= log.end_cycle {
+                break;
+            }
+            self.step();
+        }
+    }
+
+    /// Keep a snapshot every `interval` frames so the machine can be rewound
+    ///
+    /// Up to `depth` snapshots are kept, the oldest being dropped first, so
+    /// history reaches back roughly `interval * depth` frames. Each snapshot
+    /// holds a full copy of RAM, so keep the depth modest. Input delivered
+    /// through `inject` is logged alongside for `rewind_exact`. Enabling again
+    /// discards the existing history.
+    pub fn enable_time_travel(&mut self, interval: u64, depth: usize) {
+        assert!(
+            interval > 0 && depth > 0,
+            "time travel needs a non-zero interval and depth"
+        );
+        self.time_travel = Some(TimeTravel {
+            interval,
+            depth,
+            checkpoints: VecDeque::new(),
+        });
+    }
+
+    /// Stop keeping snapshots and drop the rewind history
+    pub fn disable_time_travel(&mut self) {
+        self.time_travel = None;
+    }
+
+    /// Get the number of frames `run_frame_until` has started
+    pub fn frame_count(&self) -> u64 {
+        self.frames_run
+    }
+
+    /// Go back at least `frames` frames, to the nearest snapshot
+    ///
+    /// Restores the newest snapshot taken at or before the frame `frames`
+    /// ago and returns the frame it was taken at, which becomes the current
+    /// frame. Returns None, leaving the machine as it is, if time travel is
+    /// disabled or the history doesn't reach back that far. History after
+    /// the restored point is discarded.
+    pub fn rewind(&mut self, frames: u64) -> Option<u64> {
+        let target = self.frames_run.checked_sub(frames)?;
+        let checkpoint = self.take_checkpoint(target)?;
+        self.restore(&checkpoint.snapshot);
+        self.paused_on = None;
+        self.frames_run = checkpoint.frame;
+        Some(checkpoint.frame)
+    }
+
+    /// Go back exactly `frames` frames
+    ///
+    /// Like `rewind`, then replays the logged input forward from the
+    /// snapshot up to the start of the target frame. Returns that frame.
+    pub fn rewind_exact(&mut self, frames: u64) -> Option<u64> {
+        let target = self.frames_run.checked_sub(frames)?;
+        let mut checkpoint = self.take_checkpoint(target)?;
+
+        // The current frame hasn't started yet, so it has no start cycle:
+        // rewinding to it replays up to where the machine already is
+        let elapsed = (target - checkpoint.frame) as usize;
+        let end_cycle = checkpoint
+            .frame_starts
+            .get(elapsed)
+            .copied()
+            .unwrap_or(self.cpu.total_cycles);
+        checkpoint
+            .log
+            .events
+            .retain(|&(cycle, _)| cycle <= end_cycle);
+        checkpoint.log.end_cycle = end_cycle;
+        self.replay(&checkpoint.snapshot, &checkpoint.log);
+        self.paused_on = None;
+        self.frames_run = target;
+
+        // Keep the checkpoint unless the next frame would take it again
+        if elapsed > 0 {
+            checkpoint.frame_starts.truncate(elapsed);
+            if let Some(time_travel) = self.time_travel.as_mut() {
+                time_travel.checkpoints.push_back(checkpoint);
+            }
+        }
+        Some(target)
+    }
+
+    /// Remove the newest checkpoint at or before `frame` and everything after it
+    fn take_checkpoint(&mut self, frame: u64) -> Option<Checkpoint> {
+        let checkpoints = &mut self.time_travel.as_mut()?.checkpoints;
+        let index = checkpoints
+            .iter()
+            .rposition(|checkpoint| checkpoint.frame <= frame)?;
+        let checkpoint = checkpoints.remove(index);
+        checkpoints.truncate(index);
+        checkpoint
+    }
+
+    /// Note the start of a frame, taking a snapshot if one is due
+    fn record_frame_start(&mut self) {
+        let Some(mut time_travel) = self.time_travel.take() else {
+            return;
+        };
+
+        if self.frames_run.is_multiple_of(time_travel.interval) {
+            if time_travel.checkpoints.len() == time_travel.depth {
+                time_travel.checkpoints.pop_front();
+            }
+            time_travel.checkpoints.push_back(Checkpoint {
+                frame: self.frames_run,
+                snapshot: self.snapshot(),
+                log: InputLog::new(),
+                frame_starts: Vec::new(),
+            });
+        }
+        if let Some(checkpoint) = time_travel.checkpoints.back_mut() {
+            checkpoint.frame_starts.push(self.cpu.total_cycles);
+        }
+
+        self.time_travel = Some(time_travel);
+    }
+
+    fn apply_input(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::Scancode(code) => {
+                if let Ok(mut queue) = self.scancode_queue.write() {
+                    queue.push_back(code);
+                }
+            }
+            InputEvent::Irq { line, level } => {
+                // Hand-built logs can still carry a bad line
+                if Self::check_irq_line(line) {
+                    self.memory.pic_mut().set_irq_level(line, level);
+                }
+            }
+        }
+    }
+
+    /// Run one frame's worth of cycles and report what happened
+    ///
+    /// This is the entry point for embedder-driven main loops: call it once per
+    /// host frame, then react to the returned events.
+    pub fn run_frame(&mut self) -> Vec<MachineEvent> {
+        self.run_frame_until(|_| false)
+    }
+
+    /// Run one frame's worth of cycles, stopping early if `stop` returns true
+    ///
+    /// `stop` is called after every instruction, e.g. to check debugger
+    /// breakpoints. The frame also ends early if the CPU halts with interrupts
+    /// disabled.
+    ///
+    /// Peripherals tick after every instruction, so stopping after each one
+    /// (as a debugger single-step does) still advances timers and delivers
+    /// interrupts between steps.
+    pub fn run_frame_until<F: FnMut(&Cpu) -> bool>(&mut self, mut stop: F) -> Vec<MachineEvent> {
+        let mut events = Vec::new();
+        if self.paused_on.is_some() {
+            return events;
+        }
+        self.record_frame_start();
+        self.frames_run += 1;
+        let acks_before = self.memory.pic().ack_counts();
+        let target_cycles = self.cpu.total_cycles + self.cycles_per_frame();
+
+        while self.cpu.total_cycles < target_cycles {
+            self.step();
+
+            let (mode, retrace) = match self.active_cga() {
+                Some(cga) => {
+                    let mut cga = cga.borrow_mut();
+                    (cga.take_mode_change(), cga.take_vertical_retrace())
+                }
+                None => {
+                    let mda = self.memory.mda_mut();
+                    (mda.take_mode_change(), mda.take_vertical_retrace())
+                }
+            };
+
+            if let Some(mode) = mode {
+                events.push(MachineEvent::VideoModeChanged(mode));
+            }
+
+            if retrace {
+                events.push(MachineEvent::VerticalRetrace);
+            }
+
+            if let Some(exception) = self.cpu.take_exception() {
+                self.pause_for(exception);
+                events.push(MachineEvent::Exception(exception));
+                break;
+            }
+
+            if let Some(mismatch) = self.return_mismatch.take() {
+                events.push(MachineEvent::ReturnMismatch(mismatch));
+                break;
+            }
+
+            if self.cpu.halted && !self.cpu.get_flag(Cpu::IF) {
+                events.push(MachineEvent::Halted);
+                break;
+            }
+
+            if !self.breakpoints.is_empty() && self.breakpoints.hit(&self.cpu) {
+                let at = (self.cpu.segments[1], self.cpu.ip);
+                if let Some(hook) = self.breakpoint_hook.as_mut() {
+                    hook(at.0, at.1);
+                }
+                events.push(MachineEvent::Breakpoint(at));
+                break;
+            }
+
+            if let Some(hit) = self.memory.take_value_watch_hit() {
+                events.push(MachineEvent::Watchpoint(hit));
+                break;
+            }
+
+            if stop(&self.cpu) {
+                break;
+            }
+        }
+
+        let acks_after = self.memory.pic().ack_counts();
+        let mut irqs = [0u32; 8];
+        for (irq, count) in irqs.iter_mut().enumerate() {
+            *count = acks_after[irq].wrapping_sub(acks_before[irq]);
+        }
+        if irqs.iter().any(|&count| count != 0) {
+            events.push(MachineEvent::IrqSummary(irqs));
+        }
+
+        events
+    }
+
+    /// Run until the CPU halts or `max_cycles` have been executed
+    ///
+    /// Meant for test ROMs that end in HLT. With `hlt_terminal` set, any HLT
+    /// ends the run. Otherwise a HLT with interrupts enabled keeps waiting
+    /// for an interrupt, such as a timer tick, to wake it, and only a HLT
+    /// with interrupts disabled ends the run. The final register state is
+    /// left in `cpu`.
+    pub fn run_until_halt(&mut self, max_cycles: u64, hlt_terminal: bool) -> HaltReport {
+        let start = self.cpu.total_cycles;
+        let outcome = loop {
+            if self.cpu.halted && (hlt_terminal || !self.cpu.get_flag(Cpu::IF)) {
+                break HaltOutcome::Halted;
+            }
+            if self.cpu.total_cycles - start >= max_cycles {
+                break HaltOutcome::CycleLimit;
+            }
+            self.step();
+        };
+        HaltReport {
+            outcome,
+            cycles: self.cpu.total_cycles - start,
+            at: (self.cpu.segments[1], self.cpu.ip),
+        }
+    }
+
+    /// Raise IRQ `line` once `cpu.total_cycles` reaches `at_cycle`
+    ///
+    /// The PIC sees a rising edge before the first instruction that starts
+    /// at or after `at_cycle`, the same point `replay` delivers logged input,
+    /// so the interrupt lands at the same instruction on every run. It is
+    /// then taken as usual once that instruction completes, if unmasked and
+    /// interrupts are enabled. Works with every way of running the machine.
+    /// A line the PIC doesn't have (8 or above) is logged and ignored.
+    pub fn schedule_irq(&mut self, line: u8, at_cycle: u64) {
+        if !Self::check_irq_line(line) {
+            return;
+        }
+        let index = self
+            .scheduled_irqs
+            .partition_point(|&(cycle, _)| cycle <= at_cycle);
+        self.scheduled_irqs.insert(index, (at_cycle, line));
+    }
+
+    /// Drop all IRQs scheduled with `schedule_irq` that have not fired yet
+    pub fn clear_scheduled_irqs(&mut self) {
+        self.scheduled_irqs.clear();
+    }
+
+    /// Run for at least `cycles` CPU cycles
+    ///
+    /// Stops at the first instruction boundary at or past the target, and
+    /// returns the number of cycles actually run.
+    pub fn run_cycles(&mut self, cycles: u64) -> u64 {
+        let start = self.cpu.total_cycles;
+        while self.cpu.total_cycles - start < cycles {
+            self.step();
+        }
+        self.cpu.total_cycles - start
+    }
+
+    /// Check that `line` is one of the PIC's IRQ lines, warning if not
+    fn check_irq_line(line: u8) -> bool {
+        if line >= 8 {
+            log_warn!("[MACHINE] Ignoring IRQ{}: the PIC only has IRQ0-7", line);
+            return false;
+        }
+        true
+    }
+
+    /// Pulse every scheduled IRQ that is due
+    fn deliver_scheduled_irqs(&mut self) {
+        let due = self
+            .scheduled_irqs
+            .partition_point(|&(cycle, _)| cycle <= self.cpu.total_cycles);
+        for (_, line) in self.scheduled_irqs.drain(..due) {
+            // Drop the line first in case a device is holding it high, and
+            // leave it low so the device's next edge is still seen
+            let pic = self.memory.pic_mut();
+            pic.set_irq_level(line, false);
+            pic.set_irq_level(line, true);
+            pic.set_irq_level(line, false);
+        }
+    }
+
+    /// Execute one instruction and advance peripherals
+    ///
+    /// Returns the number of CPU cycles consumed.
+    pub fn step(&mut self) -> u16 {
+        if !self.scheduled_irqs.is_empty() {
+            self.deliver_scheduled_irqs();
+        }
+        if self.call_stack.is_some() {
+            self.check_call_stack();
+        }
+
+        let was_halted = self.cpu.halted;
+        let cycles = self.cpu.step(&mut self.memory);
+        if !was_halted {
+            self.instructions_retired += 1;
+            if self.instructions_retired.is_multiple_of(THROTTLE_INTERVAL) {
+                self.throttle();
+            }
+        }
+        let bus_cycles = self.bus_cycles(cycles);
+        self.memory.tick(bus_cycles);
+
+        // Process FDC DMA transfers
+        // In real hardware, DMA happens during CPU wait states.
+        // We process transfers after each instruction.
+        while self.memory.fdc_dma_tick().is_some() {
+            // Continue transferring until no more data or terminal count
+        }
+        while self.memory.hdc_dma_tick().is_some() {}
+
+        cycles
+    }
+
+    /// Pause on a trapped exception and hand the context to the hook
+    fn pause_for(&mut self, exception: Exception) {
+        log_warn!(
+            "[MACHINE] Paused on {:?} at {:04X}:{:04X}",
+            exception.kind,
+            exception.at.0,
+            exception.at.1
+        );
+        self.paused_on = Some(exception);
+        if let Some(mut hook) = self.exception_hook.take() {
+            hook(&exception, &self.dump_state());
+            self.exception_hook = Some(hook);
+        }
+    }
+
+    /// Sleep until wall time catches up with the target instruction rate
+    fn throttle(&mut self) {
+        let Some(mips) = self.target_mips else {
+            return;
+        };
+        let (start, start_count) = self.throttle_start;
+        let retired = self.instructions_retired - start_count;
+        let due = Duration::from_secs_f64(retired as f64 / (mips * 1_000_000.0));
+        let elapsed = start.elapsed();
+        if let Some(ahead) = due.checked_sub(elapsed) {
+            std::thread::sleep(ahead);
+        } else if elapsed - due > THROTTLE_SLACK {
+            self.restart_throttle();
+        }
+    }
+
+    /// Measure the instruction rate from the current time and count
+    fn restart_throttle(&mut self) {
+        self.throttle_start = (Instant::now(), self.instructions_retired);
+    }
+
+    /// Update the shadow call stack for the instruction about to execute
+    fn check_call_stack(&mut self) {
+        let Some(transfer) = self.next_stack_transfer() else {
+            return;
+        };
+        let Some(call_stack) = self.call_stack.as_mut() else {
+            return;
+        };
+
+        match transfer {
+            StackTransfer::Call(ret) => call_stack.push(ret),
+            StackTransfer::Ret(actual) => {
+                // A RET with no recorded CALL belongs to code that was already
+                // running when the check was enabled
+                let Some(expected) = call_stack.pop() else {
+                    return;
+                };
+                if actual != expected {
+                    let at = (self.cpu.segments[1], self.cpu.ip);
+                    log_warn!(
+                        "[MACHINE] RET at {:04X}:{:04X} returned to {:04X}:{:04X}, expected {:04X}:{:04X}",
+                        at.0,
+                        at.1,
+                        actual.0,
+                        actual.1,
+                        expected.0,
+                        expected.1
+                    );
+                    self.return_mismatch = Some(ReturnMismatch {
+                        at,
+                        expected,
+                        actual,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Classify the instruction at CS:IP as a CALL or RET, if it is one
+    fn next_stack_transfer(&self) -> Option<StackTransfer> {
+        if self.cpu.halted {
+            return None;
+        }
+
+        let cs = self.cpu.segments[1];
+        let ip = self.cpu.ip;
+        // Peek rather than read so the check stays out of the memory profile
+        let fetch = |offset: u16| self.memory.peek_u8(linear(cs, ip.wrapping_add(offset)));
+
+        // Skip segment override, REP and LOCK prefixes, giving up on a run
+        // longer than the disassembler decodes
+        let mut offset = 0;
+        while matches!(
+            fetch(offset),
+            0x26 | 0x2E | 0x36 | 0x3E | 0xF0 | 0xF2 | 0xF3
+        ) {
+            offset += 1;
+            if offset as usize > disasm::MAX_PREFIXES {
+                return None;
+            }
+        }
+
+        let is_call = match fetch(offset) {
+            0xE8 | 0x9A => true,
+            0xFF => matches!((fetch(offset + 1) >> 3) & 0x07, 2 | 3),
+            _ => false,
+        };
+        if is_call {
+            let length = disasm::disassemble_at(&self.memory, cs, ip).length as u16;
+            return Some(StackTransfer::Call((cs, ip.wrapping_add(length))));
+        }
+
+        let ss = self.cpu.segments[2];
+        let sp = self.cpu.regs[4];
+        let stack_word = |offset: u16| {
+            u16::from_le_bytes([
+                self.memory.peek_u8(linear(ss, sp.wrapping_add(offset))),
+                self.memory.peek_u8(linear(ss, sp.wrapping_add(offset + 1))),
+            ])
+        };
+        match fetch(offset) {
+            0xC2 | 0xC3 => Some(StackTransfer::Ret((cs, stack_word(0)))),
+            0xCA | 0xCB => Some(StackTransfer::Ret((stack_word(2), stack_word(0)))),
+            _ => None,
+        }
+    }
+
+    /// Convert CPU cycles to cycles of the stock 4.77 MHz clock peripherals run on
+    fn bus_cycles(&mut self, cycles: u16) -> u16 {
+        if self.clock_hz == BASE_CLOCK_HZ {
+            return cycles;
+        }
+        let scaled = cycles as u64 * BASE_CLOCK_HZ + self.bus_cycle_remainder;
+        self.bus_cycle_remainder = scaled % self.clock_hz;
+        (scaled / self.clock_hz).min(u16::MAX as u64) as u16
+    }
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -2,16 +2,31 @@
 //!
 //! Main entry point for the emulator application.
 
-use ezpc::components::floppy::FloppyDisk;
+use ezpc::components::cmos::Cmos;
+use ezpc::components::floppy::{DiskGeometry, FloppyDisk};
+use ezpc::components::hdd::{HardDisk, HddGeometry};
+use ezpc::components::ide::{Ide, IDE_IRQ};
 use ezpc::emulator::scancode::physical_key_to_scancode;
 use ezpc::emulator::EmulatorState;
+use ezpc::io::DeviceHandle;
+use ezpc::logging::{self, LogLevel};
+use ezpc::machine::{MachineConfig, VideoAdapter};
 use std::path::Path;
 use std::sync::Arc;
 use winit::application::ApplicationHandler;
 use winit::event::{ElementState, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::{Window, WindowId};
 
+/// Parse a hexadecimal "segment:offset" address such as "0000:7C00"
+fn parse_seg_off(text: &str) -> Option<(u16, u16)> {
+    let (segment, offset) = text.split_once(':')?;
+    let segment = u16::from_str_radix(segment, 16).ok()?;
+    let offset = u16::from_str_radix(offset, 16).ok()?;
+    Some((segment, offset))
+}
+
 /// Application state for winit event loop
 struct App {
     window: Option<Arc<Window>>,
@@ -21,14 +36,25 @@ struct App {
     gdb_socket_path: Option<String>,
     floppy_a: Option<FloppyDisk>,
     floppy_b: Option<FloppyDisk>,
+    entry: Option<(u16, u16)>,
+    cmos: Option<Cmos>,
+    cmos_handle: Option<DeviceHandle<Cmos>>,
+    hdd: Option<HardDisk>,
+    hdd_handle: Option<DeviceHandle<Ide>>,
+    video: VideoAdapter,
 }
 
 impl App {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         rom_data: Option<Vec<u8>>,
         gdb_socket_path: Option<String>,
         floppy_a: Option<FloppyDisk>,
         floppy_b: Option<FloppyDisk>,
+        entry: Option<(u16, u16)>,
+        cmos: Option<Cmos>,
+        hdd: Option<HardDisk>,
+        video: VideoAdapter,
     ) -> Self {
         Self {
             window: None,
@@ -38,6 +64,38 @@ impl App {
             gdb_socket_path,
             floppy_a,
             floppy_b,
+            entry,
+            cmos,
+            cmos_handle: None,
+            hdd,
+            hdd_handle: None,
+            video,
+        }
+    }
+
+    /// Write the CMOS back to its file if the guest changed it
+    fn flush_cmos(&self) {
+        if let Some(cmos) = &self.cmos_handle {
+            let mut cmos = cmos.borrow_mut();
+            if cmos.is_dirty() && cmos.path().is_some() {
+                if let Err(e) = cmos.save() {
+                    eprintln!("Failed to save CMOS: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Write the hard disk back to its image if it is writable and changed
+    fn flush_hdd(&self) {
+        if let Some(ide) = &self.hdd_handle {
+            let mut ide = ide.borrow_mut();
+            if let Some(disk) = ide.disk_mut(0) {
+                if disk.is_dirty() && !disk.is_write_protected() {
+                    if let Err(e) = disk.save() {
+                        eprintln!("Failed to save hard disk: {}", e);
+                    }
+                }
+            }
         }
     }
 }
@@ -109,15 +167,39 @@ impl ApplicationHandler for App {
         surface.configure(&device, &config);
 
         // Create emulator state with ROM data, GDB socket, and floppy disks
-        let emulator = EmulatorState::with_floppies(
+        // A second disk image implies a second drive
+        let config = MachineConfig {
+            video: self.video,
+            floppy_drives: if self.floppy_b.is_some() { 2 } else { 1 },
+            ..MachineConfig::default()
+        };
+        let mut emulator = EmulatorState::with_config(
             device,
             queue,
             surface_format,
             self.rom_data.take(),
             self.gdb_socket_path.as_deref(),
-            self.floppy_a.take(),
-            self.floppy_b.take(),
+            config,
         );
+        if let Some(disk) = self.floppy_a.take() {
+            emulator.machine_mut().insert_floppy(0, disk);
+        }
+        if let Some(disk) = self.floppy_b.take() {
+            emulator.machine_mut().insert_floppy(1, disk);
+        }
+
+        // Start somewhere other than F000:FFF0 if requested
+        if let Some((cs, ip)) = self.entry {
+            emulator.machine_mut().cpu.reset_to(cs, ip);
+        }
+
+        // Without --cmos the settings are lost on exit, but the clock still runs
+        let cmos = self.cmos.take().unwrap_or_default();
+        self.cmos_handle = Some(emulator.attach_cmos(cmos));
+
+        if let Some(disk) = self.hdd.take() {
+            self.hdd_handle = Some(emulator.machine_mut().attach_ide(disk, IDE_IRQ));
+        }
 
         // Store state
         self.window = Some(window);
@@ -138,11 +220,30 @@ impl ApplicationHandler for App {
     ) {
         match event {
             WindowEvent::CloseRequested => {
+                self.flush_cmos();
+                self.flush_hdd();
                 event_loop.exit();
             }
             WindowEvent::KeyboardInput {
                 event: key_event, ..
             } => {
+                // F11 isn't on the PC keyboard, so it's free for the turbo switch
+                if key_event.physical_key == PhysicalKey::Code(KeyCode::F11) {
+                    if key_event.state == ElementState::Pressed && !key_event.repeat {
+                        if let Some(emulator) = &mut self.emulator {
+                            let machine = emulator.machine_mut();
+                            let turbo = !machine.turbo();
+                            machine.set_turbo(turbo);
+                            println!(
+                                "Turbo {} ({} Hz)",
+                                if turbo { "on" } else { "off" },
+                                machine.clock_hz()
+                            );
+                        }
+                    }
+                    return;
+                }
+
                 // Convert winit key to IBM PC scancode
                 if let Some(make_code) = physical_key_to_scancode(key_event.physical_key) {
                     let scancode = match key_event.state {
@@ -158,6 +259,9 @@ impl ApplicationHandler for App {
                 }
             }
             WindowEvent::RedrawRequested => {
+                // Save whatever the guest changed during the last frame
+                self.flush_cmos();
+
                 if let (Some(emulator), Some(surface), Some(window)) =
                     (&mut self.emulator, &self.surface, &self.window)
                 {
@@ -205,6 +309,13 @@ fn main() {
     let mut floppy_a_path: Option<String> = None;
     let mut floppy_b_path: Option<String> = None;
     let mut writable = false;
+    let mut create: Option<(String, String)> = None;
+    let mut entry: Option<(u16, u16)> = None;
+    let mut geometry_override: Option<DiskGeometry> = None;
+    let mut cmos_path: Option<String> = None;
+    let mut hdd_path: Option<String> = None;
+    let mut hdd_geometry: Option<HddGeometry> = None;
+    let mut video = VideoAdapter::Mda;
 
     // Simple argument parser
     let mut i = 1;
@@ -239,6 +350,99 @@ fn main() {
                     std::process::exit(1);
                 }
             }
+            "--hdd" => {
+                if i + 1 < args.len() {
+                    hdd_path = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("Error: --hdd requires a disk image path");
+                    std::process::exit(1);
+                }
+            }
+            "--hdd-geometry" => {
+                // Next argument is an explicit C:H:S for the hard disk image
+                match args.get(i + 1).and_then(|arg| HddGeometry::from_chs(arg)) {
+                    Some(geometry) => {
+                        hdd_geometry = Some(geometry);
+                        i += 2;
+                    }
+                    None => {
+                        eprintln!(
+                            "Error: --hdd-geometry requires cylinders:heads:sectors like 615:4:17"
+                        );
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--create" => {
+                // Next two arguments are the geometry and the output path
+                if i + 2 < args.len() {
+                    create = Some((args[i + 1].clone(), args[i + 2].clone()));
+                    i += 3;
+                } else {
+                    eprintln!("Error: --create requires a geometry and a path");
+                    eprintln!("Usage: {} --create <GEOMETRY> <PATH>", args[0]);
+                    std::process::exit(1);
+                }
+            }
+            "--geometry" => {
+                // Next argument is an explicit C:H:S for the disk images
+                match args.get(i + 1).and_then(|arg| DiskGeometry::from_chs(arg)) {
+                    Some(geometry) => {
+                        geometry_override = Some(geometry);
+                        i += 2;
+                    }
+                    None => {
+                        eprintln!(
+                            "Error: --geometry requires cylinders:heads:sectors like 80:2:10"
+                        );
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--entry" => {
+                // Next argument is the start address as hex seg:off
+                match args.get(i + 1).and_then(|arg| parse_seg_off(arg)) {
+                    Some(address) => {
+                        entry = Some(address);
+                        i += 2;
+                    }
+                    None => {
+                        eprintln!("Error: --entry requires a hex address like 0000:7C00");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--log-level" => match args.get(i + 1).and_then(|arg| LogLevel::from_name(arg)) {
+                Some(level) => {
+                    logging::set_level(level);
+                    i += 2;
+                }
+                None => {
+                    eprintln!("Error: --log-level requires error, warn, info, debug or trace");
+                    std::process::exit(1);
+                }
+            },
+            "--video" => match args.get(i + 1).and_then(|arg| VideoAdapter::from_name(arg)) {
+                Some(adapter) => {
+                    video = adapter;
+                    i += 2;
+                }
+                None => {
+                    eprintln!("Error: --video requires mda, cga or cga40");
+                    std::process::exit(1);
+                }
+            },
+            "--cmos" => {
+                // Next argument is the CMOS RAM file
+                if i + 1 < args.len() {
+                    cmos_path = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("Error: --cmos requires a file path");
+                    std::process::exit(1);
+                }
+            }
             "-w" | "--writable" => {
                 writable = true;
                 i += 1;
@@ -251,20 +455,41 @@ fn main() {
                 println!("Options:");
                 println!("  -a, --floppy-a <PATH>  Disk image for drive A: (first floppy)");
                 println!("  -b, --floppy-b <PATH>  Disk image for drive B: (second floppy)");
+                println!("  --hdd <PATH>           Hard disk image on the IDE controller");
+                println!("  --hdd-geometry <C:H:S> Force the geometry of the hard disk image");
                 println!(
                     "  -w, --writable         Allow writes to disk images (default: read-only)"
                 );
                 println!("  --gdb <socket-path>    Enable GDB remote debugging on Unix socket");
+                println!("  --create <GEOM> <PATH> Create a blank formatted disk image and exit");
+                println!("  --geometry <C:H:S>     Force the geometry of the disk images");
+                println!(
+                    "  --entry <SEG:OFF>      Start execution at SEG:OFF instead of F000:FFF0"
+                );
+                println!("  --log-level <LEVEL>    error, warn, info (default), debug or trace");
+                println!("  --cmos <PATH>          Keep CMOS RAM in PATH (created if missing)");
+                println!("  --video <ADAPTER>      mda (default), cga or cga40 (40-column boot)");
                 println!("  --help, -h             Show this help message");
                 println!();
+                println!("Press F11 while running to toggle turbo (9.54 MHz CPU clock)");
+                println!();
                 println!("Supported disk formats: raw sector images (.img)");
                 println!("  160KB (40x1x8), 180KB (40x1x9), 320KB (40x2x8), 360KB (40x2x9)");
                 println!("  720KB (80x2x9), 1.2MB (80x2x15), 1.44MB (80x2x18)");
+                println!(
+                    "  Sizes within one sector of these snap to them unless --geometry is given"
+                );
+                println!("  --create accepts: 160k, 180k, 320k, 360k, 720k, 1.2m, 1.44m, 2.88m");
+                println!(
+                    "  Hard disk images get 16 heads and 63 sectors per track (10MB: 306x4x17)"
+                );
+                println!("  unless --hdd-geometry is given (up to 16 heads and 63 sectors)");
                 println!();
                 println!("Examples:");
                 println!("  {} bios.rom", args[0]);
                 println!("  {} -a dos.img bios.rom", args[0]);
                 println!("  {} -a boot.img -w --gdb /tmp/ezpc.sock bios.rom", args[0]);
+                println!("  {} --create 1.44m blank.img", args[0]);
                 std::process::exit(0);
             }
             arg if arg.starts_with('-') => {
@@ -280,6 +505,34 @@ fn main() {
         }
     }
 
+    // Create a blank disk image and exit
+    if let Some((geometry_name, path)) = create {
+        let geometry = match DiskGeometry::from_name(&geometry_name) {
+            Some(geometry) => geometry,
+            None => {
+                eprintln!("Error: Unknown disk geometry '{}'", geometry_name);
+                eprintln!("Use --help for supported geometries");
+                std::process::exit(1);
+            }
+        };
+
+        let mut disk = FloppyDisk::create_blank(geometry, true);
+        if let Err(e) = disk.save_as(Path::new(&path)) {
+            eprintln!("Failed to create disk image '{}': {}", path, e);
+            std::process::exit(1);
+        }
+
+        println!(
+            "Created {} ({}x{}x{}, {} bytes)",
+            path,
+            geometry.cylinders,
+            geometry.heads,
+            geometry.sectors_per_track,
+            geometry.total_size()
+        );
+        std::process::exit(0);
+    }
+
     // Load ROM file if provided
     let rom_data = if let Some(path) = rom_path {
         match std::fs::read(&path) {
@@ -299,7 +552,7 @@ fn main() {
 
     // Load floppy disk images
     let floppy_a = if let Some(ref path) = floppy_a_path {
-        match FloppyDisk::from_file(Path::new(path)) {
+        match FloppyDisk::from_file_with_geometry(Path::new(path), geometry_override) {
             Ok(mut disk) => {
                 let geometry = disk.geometry();
                 println!(
@@ -328,7 +581,7 @@ fn main() {
     };
 
     let floppy_b = if let Some(ref path) = floppy_b_path {
-        match FloppyDisk::from_file(Path::new(path)) {
+        match FloppyDisk::from_file_with_geometry(Path::new(path), geometry_override) {
             Ok(mut disk) => {
                 let geometry = disk.geometry();
                 println!(
@@ -356,6 +609,54 @@ fn main() {
         None
     };
 
+    let hdd = if let Some(ref path) = hdd_path {
+        match HardDisk::from_file_with_geometry(Path::new(path), hdd_geometry) {
+            Ok(mut disk) => {
+                let geometry = disk.geometry();
+                println!(
+                    "Hard disk: {} ({}x{}x{}, {} bytes)",
+                    path,
+                    geometry.cylinders,
+                    geometry.heads,
+                    geometry.sectors_per_track,
+                    geometry.total_size()
+                );
+                if writable {
+                    disk.set_write_protected(false);
+                    println!("  (writable)");
+                } else {
+                    println!("  (read-only)");
+                }
+                Some(disk)
+            }
+            Err(e) => {
+                eprintln!("Failed to load hard disk '{}': {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
+    // Load CMOS RAM, or start blank if the file doesn't exist yet
+    let cmos = if let Some(ref path) = cmos_path {
+        match Cmos::from_file(Path::new(path)) {
+            Ok(cmos) => {
+                println!("CMOS: {}", path);
+                if !cmos.checksum_valid() {
+                    println!("  (checksum mismatch)");
+                }
+                Some(cmos)
+            }
+            Err(e) => {
+                eprintln!("Failed to load CMOS '{}': {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
     // Print GDB info if enabled
     if let Some(ref socket) = gdb_socket_path {
         println!("GDB remote debugging enabled on: {}", socket);
@@ -367,7 +668,16 @@ fn main() {
     event_loop.set_control_flow(ControlFlow::Poll);
 
     // Create and run app
-    let mut app = App::new(rom_data, gdb_socket_path, floppy_a, floppy_b);
+    let mut app = App::new(
+        rom_data,
+        gdb_socket_path,
+        floppy_a,
+        floppy_b,
+        entry,
+        cmos,
+        hdd,
+        video,
+    );
     event_loop
         .run_app(&mut app)
         .expect("Failed to run event loop");
@@ -3,14 +3,28 @@
 //! The IBM PC memory layout:
 //! - 0x00000-0x9FFFF: RAM (up to 640KB, we start with 64KB)
 //! - 0xA0000-0xBFFFF: Video memory (not implemented yet)
-//! - 0xC0000-0xFFFFF: ROM and BIOS
+//! - 0xC0000-0xEFFFF: Optional EMS page frame (see `install_expanded_memory`)
+//! - 0xC0000-0xFFFFF: ROM and BIOS, optionally with a writable flash region
 
+use crate::components::cga::{Cga, CGA_VRAM_SIZE};
 use crate::components::dma::{Dma, DmaCapable, DmaDirection};
+use crate::components::expanded_memory::{ExpandedMemory, EMS_PAGE_SIZE, EMS_PHYSICAL_PAGES};
 use crate::components::fdc::Fdc;
 use crate::components::floppy::FloppyDisk;
+use crate::components::hdc::{Hdc, HDC_DMA_CHANNEL};
+use crate::components::ide::{Ide, IDE_CONTROL_PORT};
 use crate::components::mda::Mda;
 use crate::components::pic::Pic;
-use crate::io::IoDevice;
+use crate::io::{DeviceHandle, DeviceState, IoDevice, IoWidth};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::cell::{Cell, RefCell};
+use core::fmt;
+use core::ops::{Range, RangeInclusive};
 
 /// DMA I/O ports (hardwired for performance)
 const DMA_CTRL_BASE: u16 = 0x00;
@@ -28,6 +42,13 @@ const PIC_PORT_END: u16 = 0x21;
 const MDA_VRAM_BASE: u32 = 0xB0000;
 const MDA_VRAM_END: u32 = 0xB0FFF;
 
+/// CGA memory range (16KB of video RAM, decoded twice)
+const CGA_VRAM_BASE: u32 = 0xB8000;
+const CGA_VRAM_END: u32 = 0xBFFFF;
+
+/// ROM space (last 64KB of the address space)
+const ROM_BASE: u32 = 0xF0000;
+
 /// MDA I/O ports (hardwired for performance)
 const MDA_PORT_BASE: u16 = 0x3B0;
 const MDA_PORT_END: u16 = 0x3BF;
@@ -36,6 +57,140 @@ const MDA_PORT_END: u16 = 0x3BF;
 const FDC_PORT_BASE: u16 = 0x3F0;
 const FDC_PORT_END: u16 = 0x3F7;
 
+/// Translate a real-mode segment:offset pair into a 20-bit linear address
+///
+/// Addresses past 1MB (e.g. FFFF:0010) wrap around to the bottom of memory,
+/// as they do on the 8088's 20 address lines.
+#[inline(always)]
+pub fn linear(segment: u16, offset: u16) -> u32 {
+    (((segment as u32) << 4) + offset as u32) & 0xFFFFF
+}
+
+/// Size of a memory profiling page (4KB)
+pub const PROFILE_PAGE_SIZE: u32 = 0x1000;
+
+/// Number of profiling pages covering the 1MB address space
+const PROFILE_PAGES: usize = 0x100000 / PROFILE_PAGE_SIZE as usize;
+
+/// Access counts for one 4KB page of the address space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageStats {
+    /// Linear address of the start of the page
+    pub base: u32,
+    /// Byte reads from the page
+    pub reads: u64,
+    /// Byte writes to the page
+    pub writes: u64,
+}
+
+/// Per-page access counters, only allocated while profiling is enabled
+///
+/// Counters use `Cell` because reads go through `&self`.
+struct MemProfile {
+    reads: [Cell<u64>; PROFILE_PAGES],
+    writes: [Cell<u64>; PROFILE_PAGES],
+}
+
+impl MemProfile {
+    fn new() -> Self {
+        Self {
+            reads: core::array::from_fn(|_| Cell::new(0)),
+            writes: core::array::from_fn(|_| Cell::new(0)),
+        }
+    }
+
+    #[inline(always)]
+    fn page(addr: u32) -> usize {
+        ((addr & 0xFFFFF) / PROFILE_PAGE_SIZE) as usize
+    }
+
+    #[inline(always)]
+    fn record_read(&self, addr: u32) {
+        let count = &self.reads[Self::page(addr)];
+        count.set(count.get() + 1);
+    }
+
+    #[inline(always)]
+    fn record_write(&self, addr: u32) {
+        let count = &self.writes[Self::page(addr)];
+        count.set(count.get() + 1);
+    }
+}
+
+/// What backs a region of the address space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemRegionKind {
+    /// Read/write system memory
+    Ram,
+    /// Read-only memory; guest writes are ignored
+    Rom,
+    /// Writable region of ROM space (see `MemoryBus::set_flash_region`)
+    Flash,
+    /// Video adapter memory
+    Video,
+    /// EMS page frame, a window onto expanded memory
+    PageFrame,
+}
+
+/// A mapped range of the 1MB physical address space
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemRegion {
+    /// First linear address of the region
+    pub start: u32,
+    /// Last linear address of the region (inclusive)
+    pub end: u32,
+    /// What backs the region
+    pub kind: MemRegionKind,
+    /// Human-readable description
+    pub name: &'static str,
+}
+
+impl fmt::Display for MemRegion {
+    /// Format as e.g. `F0000-FFFFF  ROM    64K  BIOS ROM`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            MemRegionKind::Ram => "RAM",
+            MemRegionKind::Rom => "ROM",
+            MemRegionKind::Flash => "Flash",
+            MemRegionKind::Video => "Video",
+            MemRegionKind::PageFrame => "EMS",
+        };
+        let size = self.end - self.start + 1;
+        let size = if size.is_multiple_of(1024) {
+            format!("{}K", size / 1024)
+        } else {
+            format!("{}B", size)
+        };
+        write!(
+            f,
+            "{:05X}-{:05X}  {:<5}  {:>4}  {}",
+            self.start, self.end, kind, size, self.name
+        )
+    }
+}
+
+/// Saved contents of RAM and the state of every peripheral
+///
+/// ROM is not included, since the guest cannot change it, apart from the flash
+/// region if one is set. Registered IO devices are matched up by registration
+/// order when restoring.
+pub struct BusState {
+    ram: Box<[u8; 65536]>,
+    flash: Option<(Range<u32>, Vec<u8>)>,
+    dma: Dma,
+    pic: Pic,
+    mda: Mda,
+    fdc: Fdc,
+    io_devices: Vec<Option<Box<dyn Any>>>,
+}
+
+impl BusState {
+    /// Get the saved RAM contents
+    pub fn ram(&self) -> &[u8] {
+        &self.ram[..]
+    }
+}
+
 /// Memory bus for the IBM PC
 pub struct MemoryBus {
     /// RAM - starting with 64KB
@@ -44,6 +199,9 @@ pub struct MemoryBus {
     /// ROM - BIOS and extension ROMs (64KB space)
     rom: [u8; 65536],
 
+    /// Linear addresses within ROM space that accept writes
+    flash: Option<Range<u32>>,
+
     /// 8237 DMA Controller
     /// Hardwired at ports 0x00-0x0F and 0x81-0x83, 0x87 for performance
     dma: Dma,
@@ -62,6 +220,33 @@ pub struct MemoryBus {
 
     /// Registered IO devices for IN/OUT instructions
     io_devices: Vec<Box<dyn IoDevice>>,
+
+    /// Extra cycles charged for every IO bus access
+    io_delay: u16,
+
+    /// Port ranges whose accesses cost a different number of extra cycles
+    io_delay_overrides: Vec<(RangeInclusive<u16>, u16)>,
+
+    /// EMS board serving its page frame (also registered for its ports)
+    expanded_memory: Option<DeviceHandle<ExpandedMemory>>,
+
+    /// Fixed disk controller fed by DMA channel 3 (also registered for its ports)
+    hdc: Option<DeviceHandle<Hdc>>,
+
+    /// IDE controller answering at 0x3F6 (also registered for its ports)
+    ide: Option<DeviceHandle<Ide>>,
+
+    /// CGA serving its video RAM (also registered for its ports)
+    cga: Option<DeviceHandle<Cga>>,
+
+    /// Memory access counters (None unless profiling is enabled)
+    profile: Option<Box<MemProfile>>,
+
+    /// (address, value) pairs that trip when that value is written there
+    value_watches: Vec<(u32, u8)>,
+
+    /// First value watch tripped since the last `take_value_watch_hit`
+    value_watch_hit: Option<(u32, u8)>,
 }
 
 impl MemoryBus {
@@ -70,11 +255,21 @@ impl MemoryBus {
         Self {
             ram: [0; 65536],
             rom: [0; 65536],
+            flash: None,
             dma: Dma::new(),
             pic: Pic::new(0x08), // IRQ0-7 map to INT 0x08-0x0F
             mda: Mda::new(),
             fdc: Fdc::new(),
             io_devices: Vec::new(),
+            io_delay: 0,
+            io_delay_overrides: Vec::new(),
+            expanded_memory: None,
+            hdc: None,
+            ide: None,
+            cga: None,
+            profile: None,
+            value_watches: Vec::new(),
+            value_watch_hit: None,
         }
     }
 
@@ -100,6 +295,31 @@ impl MemoryBus {
         self.rom[offset..].copy_from_slice(rom_data);
     }
 
+    /// Make part of ROM space writable, like a flashable BIOS area
+    ///
+    /// Writes to linear addresses in `range` store into ROM; the rest of ROM
+    /// keeps ignoring writes. Replaces any previous flash region. Panics if
+    /// the range is not inside ROM space (0xF0000-0xFFFFF).
+    pub fn set_flash_region(&mut self, range: Range<u32>) {
+        assert!(
+            range.start >= ROM_BASE && range.end <= ROM_BASE + self.rom.len() as u32,
+            "flash region {:05X}-{:05X} is outside ROM space",
+            range.start,
+            range.end
+        );
+        self.flash = Some(range);
+    }
+
+    /// Make all of ROM read-only again
+    pub fn clear_flash_region(&mut self) {
+        self.flash = None;
+    }
+
+    /// Get the writable region of ROM space, if any
+    pub fn flash_region(&self) -> Option<Range<u32>> {
+        self.flash.clone()
+    }
+
     /// Insert a floppy disk into a drive
     ///
     /// Drive 0 = A:, Drive 1 = B:, etc.
@@ -107,9 +327,96 @@ impl MemoryBus {
         self.fdc.insert_disk(drive, disk);
     }
 
+    /// Describe the mapped regions of the address space, in address order
+    ///
+    /// Addresses outside every region are unmapped: reads return 0xFF and
+    /// writes are ignored.
+    pub fn memory_map(&self) -> Vec<MemRegion> {
+        let mut regions = vec![
+            MemRegion {
+                start: 0x00000,
+                end: self.ram.len() as u32 - 1,
+                kind: MemRegionKind::Ram,
+                name: "Conventional RAM",
+            },
+            MemRegion {
+                start: MDA_VRAM_BASE,
+                end: MDA_VRAM_END,
+                kind: MemRegionKind::Video,
+                name: "MDA video RAM",
+            },
+        ];
+
+        if self.cga.is_some() {
+            regions.push(MemRegion {
+                start: CGA_VRAM_BASE,
+                end: CGA_VRAM_END,
+                kind: MemRegionKind::Video,
+                name: "CGA video RAM",
+            });
+        }
+
+        if let Some(ems) = &self.expanded_memory {
+            let start = ems.borrow().frame_base();
+            regions.push(MemRegion {
+                start,
+                end: start + (EMS_PHYSICAL_PAGES * EMS_PAGE_SIZE) as u32 - 1,
+                kind: MemRegionKind::PageFrame,
+                name: "EMS page frame",
+            });
+        }
+
+        let rom_end = ROM_BASE + self.rom.len() as u32;
+        let Some(flash) = self.flash.clone().filter(|flash| !flash.is_empty()) else {
+            regions.push(MemRegion {
+                start: ROM_BASE,
+                end: rom_end - 1,
+                kind: MemRegionKind::Rom,
+                name: "BIOS ROM",
+            });
+            return regions;
+        };
+
+        if flash.start > ROM_BASE {
+            regions.push(MemRegion {
+                start: ROM_BASE,
+                end: flash.start - 1,
+                kind: MemRegionKind::Rom,
+                name: "BIOS ROM",
+            });
+        }
+        regions.push(MemRegion {
+            start: flash.start,
+            end: flash.end - 1,
+            kind: MemRegionKind::Flash,
+            name: "Flash ROM",
+        });
+        if flash.end < rom_end {
+            regions.push(MemRegion {
+                start: flash.end,
+                end: rom_end - 1,
+                kind: MemRegionKind::Rom,
+                name: "BIOS ROM",
+            });
+        }
+        regions
+    }
+
     /// Read a byte from memory
     #[inline(always)]
     pub fn read_u8(&self, addr: u32) -> u8 {
+        if let Some(profile) = &self.profile {
+            profile.record_read(addr);
+        }
+        self.peek_u8(addr)
+    }
+
+    /// Read a byte without counting it as a guest access
+    ///
+    /// For emulator bookkeeping such as the call stack check, which the
+    /// memory profile should not see.
+    #[inline(always)]
+    pub fn peek_u8(&self, addr: u32) -> u8 {
         if addr < 0x10000 {
             // RAM (first 64KB)
             self.ram[addr as usize]
@@ -117,9 +424,20 @@ impl MemoryBus {
             // MDA video RAM (0xB0000-0xB0FFF)
             let offset = (addr - MDA_VRAM_BASE) as u16;
             self.mda.read_vram(offset)
-        } else if addr >= 0xF0000 {
+        } else if let Some(cga) = self
+            .cga
+            .as_ref()
+            .filter(|_| (CGA_VRAM_BASE..=CGA_VRAM_END).contains(&addr))
+        {
+            // CGA video RAM (0xB8000-0xBBFFF, mirrored up to 0xBFFFF)
+            let offset = ((addr - CGA_VRAM_BASE) as usize % CGA_VRAM_SIZE) as u16;
+            cga.borrow().read_vram(offset)
+        } else if addr >= ROM_BASE {
             // ROM/BIOS area (last 64KB)
-            self.rom[(addr - 0xF0000) as usize]
+            self.rom[(addr - ROM_BASE) as usize]
+        } else if let Some(ems) = &self.expanded_memory {
+            // EMS page frame; unmapped pages float like unmapped memory
+            ems.borrow().read_frame(addr).unwrap_or(0xFF)
         } else {
             // Unmapped memory returns 0xFF
             0xFF
@@ -129,6 +447,13 @@ impl MemoryBus {
     /// Write a byte to memory
     #[inline(always)]
     pub fn write_u8(&mut self, addr: u32, value: u8) {
+        if let Some(profile) = &self.profile {
+            profile.record_write(addr);
+        }
+        if !self.value_watches.is_empty() {
+            self.check_value_watches(addr, value);
+        }
+
         if addr < 0x10000 {
             // RAM (first 64KB)
             self.ram[addr as usize] = value;
@@ -136,8 +461,53 @@ impl MemoryBus {
             // MDA video RAM (0xB0000-0xB0FFF)
             let offset = (addr - MDA_VRAM_BASE) as u16;
             self.mda.write_vram(offset, value);
+        } else if let Some(cga) = self
+            .cga
+            .as_ref()
+            .filter(|_| (CGA_VRAM_BASE..=CGA_VRAM_END).contains(&addr))
+        {
+            // CGA video RAM (0xB8000-0xBBFFF, mirrored up to 0xBFFFF)
+            let offset = ((addr - CGA_VRAM_BASE) as usize % CGA_VRAM_SIZE) as u16;
+            cga.borrow_mut().write_vram(offset, value);
+        } else if self
+            .flash
+            .as_ref()
+            .is_some_and(|flash| flash.contains(&addr))
+        {
+            // Flashable part of ROM
+            self.rom[(addr - ROM_BASE) as usize] = value;
+        } else if let Some(ems) = &self.expanded_memory {
+            // EMS page frame (ROM addresses are outside it)
+            ems.borrow_mut().write_frame(addr, value);
+        }
+        // Other ROM writes are ignored
+    }
+
+    /// Trip when the guest writes `value` to the byte at `addr`
+    ///
+    /// Writes of any other value, and host writes through `load`, are not
+    /// reported. An address can be watched for several values at once.
+    pub fn watch_value(&mut self, addr: u32, value: u8) {
+        if !self.value_watches.contains(&(addr, value)) {
+            self.value_watches.push((addr, value));
+        }
+    }
+
+    /// Stop watching `addr` for any value
+    pub fn unwatch_value(&mut self, addr: u32) {
+        self.value_watches.retain(|&(watched, _)| watched != addr);
+    }
+
+    /// Take the (address, value) of the first value watch tripped since the
+    /// last call
+    pub fn take_value_watch_hit(&mut self) -> Option<(u32, u8)> {
+        self.value_watch_hit.take()
+    }
+
+    fn check_value_watches(&mut self, addr: u32, value: u8) {
+        if self.value_watch_hit.is_none() && self.value_watches.contains(&(addr, value)) {
+            self.value_watch_hit = Some((addr, value));
         }
-        // ROM writes are ignored
     }
 
     /// Read a word (little-endian) from memory
@@ -161,11 +531,197 @@ impl MemoryBus {
         self.ram[offset..end].copy_from_slice(&data[..end - offset]);
     }
 
+    /// Turn per-page memory access counting on or off
+    ///
+    /// Counts byte accesses made through `read_u8`/`write_u8` (so a word
+    /// access counts twice, matching the 8088's 8-bit bus). Enabling always
+    /// starts from zero; disabling discards the counters.
+    pub fn enable_mem_profiling(&mut self, enabled: bool) {
+        self.profile = if enabled {
+            Some(Box::new(MemProfile::new()))
+        } else {
+            None
+        };
+    }
+
+    /// Get the `top_n` most accessed pages, busiest first
+    ///
+    /// Pages are ranked by reads + writes; untouched pages are omitted.
+    /// Returns an empty list if profiling is disabled.
+    pub fn mem_hotpages(&self, top_n: usize) -> Vec<PageStats> {
+        let Some(profile) = &self.profile else {
+            return Vec::new();
+        };
+
+        let mut pages: Vec<PageStats> = (0..PROFILE_PAGES)
+            .map(|page| PageStats {
+                base: page as u32 * PROFILE_PAGE_SIZE,
+                reads: profile.reads[page].get(),
+                writes: profile.writes[page].get(),
+            })
+            .filter(|stats| stats.reads + stats.writes > 0)
+            .collect();
+        pages.sort_by_key(|stats| core::cmp::Reverse(stats.reads + stats.writes));
+        pages.truncate(top_n);
+        pages
+    }
+
+    /// Get the total (reads, writes) counted since profiling was enabled
+    pub fn mem_access_totals(&self) -> Option<(u64, u64)> {
+        self.profile.as_ref().map(|profile| {
+            let reads = profile.reads.iter().map(Cell::get).sum();
+            let writes = profile.writes.iter().map(Cell::get).sum();
+            (reads, writes)
+        })
+    }
+
     /// Register an IO peripheral device
     pub fn register_io_device(&mut self, device: Box<dyn IoDevice>) {
         self.io_devices.push(device);
     }
 
+    /// Attach an IO peripheral device, keeping a handle to it
+    ///
+    /// Unlike `register_io_device`, the caller retains shared access to the
+    /// device through the returned handle, e.g. to set a joystick axis from
+    /// host code while the bus keeps dispatching IN/OUT to it.
+    pub fn attach_device<D: IoDevice + 'static>(&mut self, device: D) -> DeviceHandle<D> {
+        let handle = Rc::new(RefCell::new(device));
+        self.io_devices.push(Box::new(handle.clone()));
+        handle
+    }
+
+    /// Install an EMS board, serving its page frame and page registers
+    ///
+    /// Returns a handle for mapping pages or inspecting the backing store
+    /// from host code. Replaces the page frame of any earlier board, though
+    /// its ports stay registered.
+    pub fn install_expanded_memory(&mut self, ems: ExpandedMemory) -> DeviceHandle<ExpandedMemory> {
+        let handle = self.attach_device(ems);
+        self.expanded_memory = Some(handle.clone());
+        handle
+    }
+
+    /// Install a fixed disk controller, connecting it to DMA channel 3
+    ///
+    /// Returns a handle for attaching drives or reading back the images from
+    /// host code.
+    pub fn install_hdc(&mut self, hdc: Hdc) -> DeviceHandle<Hdc> {
+        let handle = self.attach_device(hdc);
+        self.hdc = Some(handle.clone());
+        handle
+    }
+
+    /// Get the installed fixed disk controller, if any
+    pub fn hdc(&self) -> Option<&DeviceHandle<Hdc>> {
+        self.hdc.as_ref()
+    }
+
+    /// Install an IDE controller at 0x1F0-0x1F7
+    ///
+    /// Also routes 0x3F6 (alternate status and device control) to it, which
+    /// the FDC otherwise decodes. Returns a handle for attaching drives or
+    /// saving the images from host code.
+    pub fn install_ide(&mut self, ide: Ide) -> DeviceHandle<Ide> {
+        let handle = self.attach_device(ide);
+        self.ide = Some(handle.clone());
+        handle
+    }
+
+    /// Get the installed IDE controller, if any
+    pub fn ide(&self) -> Option<&DeviceHandle<Ide>> {
+        self.ide.as_ref()
+    }
+
+    /// Install a CGA, serving its video RAM at 0xB8000 and ports at 0x3D0-0x3DF
+    ///
+    /// The MDA stays in place, as on a PC with both adapters fitted.
+    /// Returns a handle for rendering the display from host code.
+    pub fn install_cga(&mut self, cga: Cga) -> DeviceHandle<Cga> {
+        let handle = self.attach_device(cga);
+        self.cga = Some(handle.clone());
+        handle
+    }
+
+    /// Get the installed CGA, if any
+    pub fn cga(&self) -> Option<&DeviceHandle<Cga>> {
+        self.cga.as_ref()
+    }
+
+    /// Reset all peripherals to their power-on state
+    ///
+    /// RAM, ROM and inserted media are preserved.
+    pub fn reset_devices(&mut self) {
+        self.dma.reset();
+        self.pic.reset();
+        self.mda.reset();
+        self.fdc.reset();
+        for device in self.io_devices.iter_mut() {
+            device.reset();
+        }
+    }
+
+    /// Capture RAM and the complete state of every peripheral
+    pub fn save_state(&self) -> BusState {
+        BusState {
+            ram: Box::new(self.ram),
+            flash: self.flash.clone().map(|flash| {
+                let bytes = self.rom
+                    [(flash.start - ROM_BASE) as usize..(flash.end - ROM_BASE) as usize]
+                    .to_vec();
+                (flash, bytes)
+            }),
+            dma: self.dma.clone(),
+            pic: self.pic.clone(),
+            mda: self.mda.clone(),
+            fdc: self.fdc.clone(),
+            io_devices: self
+                .io_devices
+                .iter()
+                .map(|device| device.save_state())
+                .collect(),
+        }
+    }
+
+    /// Restore RAM and peripheral state captured by `save_state`
+    ///
+    /// The bus must have the same IO devices registered, in the same order,
+    /// as the bus the state was captured from.
+    pub fn load_state(&mut self, state: &BusState) {
+        self.ram = *state.ram;
+        if let Some((flash, bytes)) = &state.flash {
+            let start = (flash.start - ROM_BASE) as usize;
+            self.rom[start..start + bytes.len()].copy_from_slice(bytes);
+        }
+        self.dma = state.dma.clone();
+        self.pic = state.pic.clone();
+        self.mda = state.mda.clone();
+        self.fdc = state.fdc.clone();
+        for (device, saved) in self.io_devices.iter_mut().zip(&state.io_devices) {
+            if let Some(saved) = saved {
+                device.load_state(&**saved);
+            }
+        }
+    }
+
+    /// Snapshot the state of every device that can describe itself
+    ///
+    /// Hardwired devices come first, followed by registered IO devices in
+    /// registration order. Has no side effects on any device.
+    pub fn devices_state(&self) -> Vec<DeviceState> {
+        let mut states = vec![
+            DeviceState::Dma(self.dma.describe()),
+            DeviceState::Pic(self.pic.describe()),
+            DeviceState::Mda(self.mda.describe()),
+        ];
+        states.extend(
+            self.io_devices
+                .iter()
+                .filter_map(|device| device.snapshot()),
+        );
+        states
+    }
+
     /// Get a reference to the PIC (Programmable Interrupt Controller)
     pub fn pic(&self) -> &Pic {
         &self.pic
@@ -275,6 +831,62 @@ impl MemoryBus {
         }
     }
 
+    /// Set the extra cycles charged for each IO bus access
+    ///
+    /// Models the wait states an ISA card inserts on IN/OUT. Ports with an
+    /// override from `set_port_io_delay` keep their own cost.
+    pub fn set_io_delay(&mut self, cycles: u16) {
+        self.io_delay = cycles;
+    }
+
+    /// Get the extra cycles charged for an IO bus access without an override
+    pub fn io_delay(&self) -> u16 {
+        self.io_delay
+    }
+
+    /// Charge a different number of extra cycles for accesses to `ports`
+    ///
+    /// Replaces any override for the same range; where ranges overlap, the
+    /// most recently set one wins.
+    pub fn set_port_io_delay(&mut self, ports: RangeInclusive<u16>, cycles: u16) {
+        self.io_delay_overrides.retain(|(range, _)| *range != ports);
+        self.io_delay_overrides.push((ports, cycles));
+    }
+
+    /// Remove all per-port IO delay overrides
+    pub fn clear_port_io_delays(&mut self) {
+        self.io_delay_overrides.clear();
+    }
+
+    /// Extra cycles for one byte access to `port`
+    #[inline(always)]
+    pub fn io_delay_u8(&self, port: u16) -> u16 {
+        self.io_delay_overrides
+            .iter()
+            .rev()
+            .find(|(range, _)| range.contains(&port))
+            .map_or(self.io_delay, |&(_, cycles)| cycles)
+    }
+
+    /// Extra cycles for a word access to `port`
+    ///
+    /// A 16-bit device is charged once; anything else is charged for both
+    /// byte accesses, matching how `io_read_u16` dispatches.
+    #[inline(always)]
+    pub fn io_delay_u16(&self, port: u16) -> u16 {
+        let next = port.wrapping_add(1);
+        let word_device = port != 0xFFFF
+            && self.io_devices.iter().any(|device| {
+                let range = device.port_range();
+                device.io_width() == IoWidth::Word && range.contains(&port) && range.contains(&next)
+            });
+        if word_device {
+            self.io_delay_u8(port)
+        } else {
+            self.io_delay_u8(port) + self.io_delay_u8(next)
+        }
+    }
+
     /// Read a byte from an IO port
     #[inline(always)]
     pub fn io_read_u8(&mut self, port: u16) -> u8 {
@@ -287,7 +899,7 @@ impl MemoryBus {
         {
             let value = self.dma.read_u8(port);
             #[cfg(debug_assertions)]
-            println!("[IO] IN  port 0x{:04X} -> 0x{:02X}", port, value);
+            log_trace!("[IO] IN  port 0x{:04X} -> 0x{:02X}", port, value);
             return value;
         }
 
@@ -295,7 +907,7 @@ impl MemoryBus {
         if port >= PIC_PORT_BASE && port <= PIC_PORT_END {
             let value = self.pic.read_u8(port);
             #[cfg(debug_assertions)]
-            println!("[IO] IN  port 0x{:04X} -> 0x{:02X}", port, value);
+            log_trace!("[IO] IN  port 0x{:04X} -> 0x{:02X}", port, value);
             return value;
         }
 
@@ -303,7 +915,15 @@ impl MemoryBus {
         if port >= MDA_PORT_BASE && port <= MDA_PORT_END {
             let value = self.mda.read_u8(port);
             #[cfg(debug_assertions)]
-            println!("[IO] IN  port 0x{:04X} -> 0x{:02X}", port, value);
+            log_trace!("[IO] IN  port 0x{:04X} -> 0x{:02X}", port, value);
+            return value;
+        }
+
+        // An installed IDE controller takes 0x3F6 from the FDC
+        if let Some(ide) = self.ide.as_ref().filter(|_| port == IDE_CONTROL_PORT) {
+            let value = ide.borrow().read_control();
+            #[cfg(debug_assertions)]
+            log_trace!("[IO] IN  port 0x{:04X} -> 0x{:02X}", port, value);
             return value;
         }
 
@@ -311,7 +931,7 @@ impl MemoryBus {
         if port >= FDC_PORT_BASE && port <= FDC_PORT_END {
             let value = self.fdc.read_u8(port);
             #[cfg(debug_assertions)]
-            println!("[IO] IN  port 0x{:04X} -> 0x{:02X}", port, value);
+            log_trace!("[IO] IN  port 0x{:04X} -> 0x{:02X}", port, value);
             return value;
         }
 
@@ -320,12 +940,11 @@ impl MemoryBus {
             if device.port_range().contains(&port) {
                 let value = device.read_u8(port);
                 #[cfg(debug_assertions)]
-                println!("[IO] IN  port 0x{:04X} -> 0x{:02X}", port, value);
+                log_trace!("[IO] IN  port 0x{:04X} -> 0x{:02X}", port, value);
                 return value;
             }
         }
-        #[cfg(debug_assertions)]
-        println!("[IO] IN  port 0x{:04X} -> 0xFF (unmapped)", port);
+        log_debug!("[IO] IN  port 0x{:04X} -> 0xFF (unmapped)", port);
         0xFF // Unmapped port returns 0xFF
     }
 
@@ -333,7 +952,7 @@ impl MemoryBus {
     #[inline(always)]
     pub fn io_write_u8(&mut self, port: u16, value: u8) {
         #[cfg(debug_assertions)]
-        println!("[IO] OUT port 0x{:04X} <- 0x{:02X}", port, value);
+        log_trace!("[IO] OUT port 0x{:04X} <- 0x{:02X}", port, value);
 
         // DMA is hardwired for performance (ports 0x00-0x0F and page registers)
         if (port >= DMA_CTRL_BASE && port <= DMA_CTRL_END)
@@ -358,6 +977,12 @@ impl MemoryBus {
             return;
         }
 
+        // An installed IDE controller takes 0x3F6 from the FDC
+        if let Some(ide) = self.ide.as_ref().filter(|_| port == IDE_CONTROL_PORT) {
+            ide.borrow_mut().write_control(value);
+            return;
+        }
+
         // FDC is hardwired for DMA coordination
         if port >= FDC_PORT_BASE && port <= FDC_PORT_END {
             self.fdc.write_u8(port, value);
@@ -372,21 +997,51 @@ impl MemoryBus {
             }
         }
         // Writes to unmapped ports are ignored
+        log_debug!("[IO] OUT port 0x{:04X} <- 0x{:02X} (unmapped)", port, value);
     }
 
     /// Read a word (little-endian) from an IO port
+    ///
+    /// A 16-bit device covering both ports gets a single word read; anything
+    /// else sees two byte reads, as on the 8-bit XT bus.
     #[inline(always)]
     pub fn io_read_u16(&mut self, port: u16) -> u16 {
+        if let Some(device) = self.word_io_device(port) {
+            let value = device.read_u16(port);
+            #[cfg(debug_assertions)]
+            log_trace!("[IO] IN  port 0x{:04X} -> 0x{:04X}", port, value);
+            return value;
+        }
+
         let lo = self.io_read_u8(port) as u16;
-        let hi = self.io_read_u8(port + 1) as u16;
+        let hi = self.io_read_u8(port.wrapping_add(1)) as u16;
         lo | (hi << 8)
     }
 
     /// Write a word (little-endian) to an IO port
+    ///
+    /// A 16-bit device covering both ports gets a single word write; anything
+    /// else sees two byte writes, as on the 8-bit XT bus.
     #[inline(always)]
     pub fn io_write_u16(&mut self, port: u16, value: u16) {
+        if let Some(device) = self.word_io_device(port) {
+            #[cfg(debug_assertions)]
+            log_trace!("[IO] OUT port 0x{:04X} <- 0x{:04X}", port, value);
+            device.write_u16(port, value);
+            return;
+        }
+
         self.io_write_u8(port, value as u8);
-        self.io_write_u8(port + 1, (value >> 8) as u8);
+        self.io_write_u8(port.wrapping_add(1), (value >> 8) as u8);
+    }
+
+    /// Find a registered 16-bit device that decodes both `port` and `port + 1`
+    fn word_io_device(&mut self, port: u16) -> Option<&mut Box<dyn IoDevice>> {
+        let next = port.checked_add(1)?;
+        self.io_devices.iter_mut().find(|device| {
+            let range = device.port_range();
+            device.io_width() == IoWidth::Word && range.contains(&port) && range.contains(&next)
+        })
     }
 
     /// Update peripherals based on CPU cycles
@@ -476,4 +1131,15 @@ impl MemoryBus {
             DmaDirection::Invalid => None,
         }
     }
+
+    /// Process fixed disk DMA transfers
+    ///
+    /// Like `fdc_dma_tick`, for the controller installed with `install_hdc`.
+    /// Returns None when no controller is installed.
+    pub fn hdc_dma_tick(&mut self) -> Option<bool> {
+        let hdc = self.hdc.clone()?;
+        let mut hdc = hdc.borrow_mut();
+        self.dma.set_dreq(HDC_DMA_CHANNEL, hdc.dma_dreq());
+        self.dma_transfer_byte(HDC_DMA_CHANNEL, &mut *hdc)
+    }
 }
@@ -0,0 +1,210 @@
+//! BIOS-like ROM images for tests
+//!
+//! Integration tests that boot a Machine need a ROM with code at known
+//! offsets, a reset vector and usually a few interrupt handlers. Writing that
+//! as one big byte array means counting offsets by hand; RomBuilder lets the
+//! test place labeled blocks of machine code and refer to them by name.
+//!
+//! The image covers the whole BIOS window (F000:0000-F000:FFFF), so offsets
+//! within the image are IPs in segment F000.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Segment the BIOS window starts at
+pub const ROM_SEGMENT: u16 = 0xF000;
+
+/// Size of the BIOS window in bytes
+pub const ROM_SIZE: usize = 0x10000;
+
+/// Offset of the reset vector within the BIOS window
+pub const RESET_VECTOR_OFFSET: u16 = 0xFFF0;
+
+/// Value unused ROM bytes read as (like an unprogrammed EPROM)
+const FILL_BYTE: u8 = 0xFF;
+
+/// Builds a ROM image from labeled blocks of machine code and data
+///
+/// Blocks are placed one after another from offset 0 unless moved with
+/// `org`. Label references are resolved when the image is built, so code
+/// may refer to labels placed after it.
+pub struct RomBuilder {
+    /// ROM contents (always ROM_SIZE bytes)
+    image: Vec<u8>,
+
+    /// Offset the next block is placed at
+    cursor: usize,
+
+    /// Offsets of defined labels
+    labels: BTreeMap<String, u16>,
+
+    /// Words to patch with a label's offset at build time
+    fixups: Vec<(usize, String)>,
+
+    /// Label the reset vector jumps to
+    reset: Option<String>,
+
+    /// Interrupt vectors to point at labels during reset
+    vectors: Vec<(u8, String)>,
+}
+
+impl RomBuilder {
+    /// Create an empty ROM image
+    pub fn new() -> Self {
+        Self {
+            image: vec![FILL_BYTE; ROM_SIZE],
+            cursor: 0,
+            labels: BTreeMap::new(),
+            fixups: Vec::new(),
+            reset: None,
+            vectors: Vec::new(),
+        }
+    }
+
+    /// Move the placement cursor to an offset in the ROM
+    pub fn org(&mut self, offset: u16) -> &mut Self {
+        self.cursor = offset as usize;
+        self
+    }
+
+    /// Define a label at the current placement offset
+    ///
+    /// Panics if the label is already defined.
+    pub fn label(&mut self, name: &str) -> &mut Self {
+        let previous = self.labels.insert(name.to_string(), self.cursor as u16);
+        assert!(previous.is_none(), "label '{}' defined twice", name);
+        self
+    }
+
+    /// Place raw bytes at the current placement offset
+    ///
+    /// Panics if they would run past the end of the ROM.
+    pub fn bytes(&mut self, data: &[u8]) -> &mut Self {
+        let end = self.cursor + data.len();
+        assert!(
+            end <= ROM_SIZE,
+            "ROM overflow: {} bytes at {:#06X}",
+            data.len(),
+            self.cursor
+        );
+        self.image[self.cursor..end].copy_from_slice(data);
+        self.cursor = end;
+        self
+    }
+
+    /// Define a label and place code or data at it
+    pub fn code(&mut self, name: &str, data: &[u8]) -> &mut Self {
+        self.label(name).bytes(data)
+    }
+
+    /// Place a 16-bit word holding a label's offset
+    ///
+    /// Use it to finish an instruction whose immediate or displacement is a
+    /// label, e.g. `.bytes(&[0xBB]).word_ref("table")` for MOV BX, table.
+    pub fn word_ref(&mut self, name: &str) -> &mut Self {
+        self.fixups.push((self.cursor, name.to_string()));
+        self.bytes(&[0, 0])
+    }
+
+    /// Make the reset vector jump to a label
+    pub fn reset_vector(&mut self, name: &str) -> &mut Self {
+        self.reset = Some(name.to_string());
+        self
+    }
+
+    /// Point an interrupt vector at a label
+    ///
+    /// The IVT lives in RAM, so the entries are written by a stub that runs
+    /// at reset before jumping to the reset label. The stub leaves AX and DS
+    /// set to 0.
+    pub fn interrupt(&mut self, vector: u8, name: &str) -> &mut Self {
+        self.vectors.push((vector, name.to_string()));
+        self
+    }
+
+    /// Get the offset of a defined label
+    pub fn offset_of(&self, name: &str) -> Option<u16> {
+        self.labels.get(name).copied()
+    }
+
+    /// Resolve labels and produce the ROM image
+    ///
+    /// The result is sized for `MemoryBus::load_rom`/`Machine::load_rom`.
+    /// Panics if a referenced label is undefined or the reset stub does not
+    /// fit before the reset vector.
+    pub fn build(&self) -> Vec<u8> {
+        let mut image = self.image.clone();
+
+        for (at, name) in &self.fixups {
+            let offset = self.resolve(name);
+            image[*at..*at + 2].copy_from_slice(&offset.to_le_bytes());
+        }
+
+        let mut entry = self.reset.as_deref().map(|name| self.resolve(name));
+
+        if !self.vectors.is_empty() {
+            let stub = self.reset_stub(entry);
+            let start = self.cursor;
+            assert!(
+                start + stub.len() <= RESET_VECTOR_OFFSET as usize,
+                "no room for the IVT setup stub at {:#06X}",
+                start
+            );
+            image[start..start + stub.len()].copy_from_slice(&stub);
+            entry = Some(start as u16);
+        }
+
+        if let Some(entry) = entry {
+            // JMP FAR F000:entry
+            let at = RESET_VECTOR_OFFSET as usize;
+            image[at] = 0xEA;
+            image[at + 1..at + 3].copy_from_slice(&entry.to_le_bytes());
+            image[at + 3..at + 5].copy_from_slice(&ROM_SEGMENT.to_le_bytes());
+        }
+
+        image
+    }
+
+    /// Generate code that fills the IVT entries, then continues at `entry`
+    fn reset_stub(&self, entry: Option<u16>) -> Vec<u8> {
+        // XOR AX, AX; MOV DS, AX
+        let mut stub = vec![0x31, 0xC0, 0x8E, 0xD8];
+
+        for (vector, name) in &self.vectors {
+            let slot = *vector as u16 * 4;
+            let offset = self.resolve(name);
+            // MOV WORD [slot], offset; MOV WORD [slot + 2], ROM_SEGMENT
+            stub.extend_from_slice(&[0xC7, 0x06]);
+            stub.extend_from_slice(&slot.to_le_bytes());
+            stub.extend_from_slice(&offset.to_le_bytes());
+            stub.extend_from_slice(&[0xC7, 0x06]);
+            stub.extend_from_slice(&(slot + 2).to_le_bytes());
+            stub.extend_from_slice(&ROM_SEGMENT.to_le_bytes());
+        }
+
+        match entry {
+            Some(entry) => {
+                // JMP FAR F000:entry
+                stub.push(0xEA);
+                stub.extend_from_slice(&entry.to_le_bytes());
+                stub.extend_from_slice(&ROM_SEGMENT.to_le_bytes());
+            }
+            None => stub.push(0xF4), // HLT
+        }
+
+        stub
+    }
+
+    fn resolve(&self, name: &str) -> u16 {
+        self.offset_of(name)
+            .unwrap_or_else(|| panic!("undefined ROM label '{}'", name))
+    }
+}
+
+impl Default for RomBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,225 @@
+//! Machine snapshots and input logs for deterministic replay
+//!
+//! Emulation is deterministic apart from host input, so a snapshot plus the
+//! input delivered after it (stamped with the CPU cycle it arrived at) is
+//! enough to reproduce a run exactly. See `Machine::replay`.
+
+use crate::cpu::CpuState;
+use crate::io::DeviceState;
+use crate::memory::BusState;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Complete machine state at an instruction boundary
+pub struct Snapshot {
+    /// CPU registers, flags and cycle count
+    pub cpu: CpuState,
+
+    /// RAM and peripheral state
+    pub bus: BusState,
+
+    /// Scancodes queued by the host but not yet taken by the keyboard
+    pub scancodes: Vec<u8>,
+
+    /// Debugging view of every device, for comparing snapshots
+    pub devices: Vec<DeviceState>,
+}
+
+impl Snapshot {
+    /// Compare against a later (or diverging) snapshot
+    ///
+    /// Lists the registers, RAM ranges and devices whose state differs.
+    /// The cycle count and host scancode queue are not compared.
+    pub fn diff(&self, other: &Snapshot) -> SnapshotDiff {
+        let mut registers = Vec::new();
+        for (name, before, after) in register_values(&self.cpu)
+            .into_iter()
+            .zip(register_values(&other.cpu))
+            .map(|((name, before), (_, after))| (name, before, after))
+        {
+            if before != after {
+                registers.push(RegisterChange {
+                    name,
+                    before,
+                    after,
+                });
+            }
+        }
+
+        let mut memory: Vec<MemoryChange> = Vec::new();
+        let (ram_before, ram_after) = (self.bus.ram(), other.bus.ram());
+        for (addr, (&before, &after)) in ram_before.iter().zip(ram_after).enumerate() {
+            if before == after {
+                continue;
+            }
+            match memory.last_mut() {
+                Some(range) if range.end() == addr as u32 => {
+                    range.before.push(before);
+                    range.after.push(after);
+                }
+                _ => memory.push(MemoryChange {
+                    start: addr as u32,
+                    before: vec![before],
+                    after: vec![after],
+                }),
+            }
+        }
+
+        let count = self.devices.len().max(other.devices.len());
+        let devices = (0..count)
+            .filter_map(|index| {
+                let before = self.devices.get(index).cloned();
+                let after = other.devices.get(index).cloned();
+                (before != after).then_some(DeviceChange {
+                    index,
+                    before,
+                    after,
+                })
+            })
+            .collect();
+
+        SnapshotDiff {
+            registers,
+            memory,
+            devices,
+        }
+    }
+}
+
+/// Named 16-bit registers in the order a diff lists them
+fn register_values(cpu: &CpuState) -> [(&'static str, u16); 14] {
+    let [ax, cx, dx, bx, sp, bp, si, di] = cpu.regs;
+    let [es, cs, ss, ds] = cpu.segments;
+    [
+        ("AX", ax),
+        ("BX", bx),
+        ("CX", cx),
+        ("DX", dx),
+        ("SI", si),
+        ("DI", di),
+        ("BP", bp),
+        ("SP", sp),
+        ("CS", cs),
+        ("DS", ds),
+        ("ES", es),
+        ("SS", ss),
+        ("IP", cpu.ip),
+        ("FLAGS", cpu.flags),
+    ]
+}
+
+/// Differences between two snapshots, from `Snapshot::diff`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SnapshotDiff {
+    /// Registers whose values differ
+    pub registers: Vec<RegisterChange>,
+
+    /// Runs of consecutive RAM bytes that differ, in address order
+    pub memory: Vec<MemoryChange>,
+
+    /// Devices whose debugging view differs
+    pub devices: Vec<DeviceChange>,
+}
+
+impl SnapshotDiff {
+    /// True if the snapshots match in everything compared
+    pub fn is_empty(&self) -> bool {
+        self.registers.is_empty() && self.memory.is_empty() && self.devices.is_empty()
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for reg in &self.registers {
+            writeln!(f, "{}: {:04X} -> {:04X}", reg.name, reg.before, reg.after)?;
+        }
+        for range in &self.memory {
+            writeln!(
+                f,
+                "RAM {:05X}-{:05X}: {:02X?} -> {:02X?}",
+                range.start,
+                range.end() - 1,
+                range.before,
+                range.after
+            )?;
+        }
+        for device in &self.devices {
+            writeln!(
+                f,
+                "device {}: {:?} -> {:?}",
+                device.index, device.before, device.after
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A register that differs between two snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterChange {
+    /// Register name, e.g. `AX` or `FLAGS`
+    pub name: &'static str,
+    pub before: u16,
+    pub after: u16,
+}
+
+/// A run of RAM bytes that differ between two snapshots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryChange {
+    /// Linear address of the first differing byte
+    pub start: u32,
+    pub before: Vec<u8>,
+    pub after: Vec<u8>,
+}
+
+impl MemoryChange {
+    /// Linear address just past the last differing byte
+    pub fn end(&self) -> u32 {
+        self.start + self.before.len() as u32
+    }
+}
+
+/// A device whose state differs between two snapshots
+///
+/// Devices are matched by position in `Snapshot::devices`. A device present
+/// in only one snapshot has `None` on the other side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceChange {
+    /// Position in `Snapshot::devices`
+    pub index: usize,
+    pub before: Option<DeviceState>,
+    pub after: Option<DeviceState>,
+}
+
+/// Host input delivered to the machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    /// A keyboard scancode pushed onto the host queue
+    Scancode(u8),
+
+    /// An IRQ line driven from outside the emulated peripherals
+    Irq { line: u8, level: bool },
+}
+
+/// Input events recorded with the CPU cycle count they were delivered at
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputLog {
+    /// `(cycle, event)` pairs in delivery order
+    pub events: Vec<(u64, InputEvent)>,
+
+    /// Cycle count at which recording stopped
+    pub end_cycle: u64,
+}
+
+impl InputLog {
+    /// Create an empty log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an event delivered at `cycle`
+    pub fn push(&mut self, cycle: u64, event: InputEvent) {
+        self.events.push((cycle, event));
+    }
+}
@@ -1,6 +1,6 @@
 //! Arithmetic instruction tests (ADD, INC, DEC, etc.)
 
-use ezpc::cpu::CpuHarness;
+use ezpc::cpu::{CpuHarness, CpuModel};
 
 #[test]
 fn test_inc_r16() {
@@ -1184,6 +1184,85 @@ fn test_daa_multi_digit_bcd_addition() {
 
 // DAS (Decimal Adjust After Subtraction) tests
 
+#[test]
+fn test_daa_preset_cf0_af0_no_adjust() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0x12; DAA with CF=0, AF=0 (no adjustment)
+    harness.load_program(&[0xB0, 0x12, 0x27], 0);
+
+    harness.step(); // MOV AL, 0x12
+    harness.cpu.set_flags(0);
+    harness.step(); // DAA
+
+    assert_eq!(harness.cpu.read_reg8(0), 0x12);
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::CF), false);
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::AF), false);
+}
+
+#[test]
+fn test_daa_preset_cf0_af1() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0x12; DAA with CF=0, AF=1 (low nibble adjusted by AF)
+    harness.load_program(&[0xB0, 0x12, 0x27], 0);
+
+    harness.step(); // MOV AL, 0x12
+    harness.cpu.set_flags(ezpc::cpu::Cpu::AF);
+    harness.step(); // DAA
+
+    assert_eq!(harness.cpu.read_reg8(0), 0x18); // 0x12 + 0x06
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::CF), false);
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::AF), true);
+}
+
+#[test]
+fn test_daa_preset_cf1_af0() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0x12; DAA with CF=1, AF=0 (high nibble adjusted by CF)
+    harness.load_program(&[0xB0, 0x12, 0x27], 0);
+
+    harness.step(); // MOV AL, 0x12
+    harness.cpu.set_flags(ezpc::cpu::Cpu::CF);
+    harness.step(); // DAA
+
+    assert_eq!(harness.cpu.read_reg8(0), 0x72); // 0x12 + 0x60
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::CF), true);
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::AF), false);
+}
+
+#[test]
+fn test_daa_preset_cf1_af1() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0x12; DAA with CF=1, AF=1 (both nibbles adjusted)
+    harness.load_program(&[0xB0, 0x12, 0x27], 0);
+
+    harness.step(); // MOV AL, 0x12
+    harness
+        .cpu
+        .set_flags(ezpc::cpu::Cpu::CF | ezpc::cpu::Cpu::AF);
+    harness.step(); // DAA
+
+    assert_eq!(harness.cpu.read_reg8(0), 0x78); // 0x12 + 0x66
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::CF), true);
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::AF), true);
+}
+
+#[test]
+fn test_daa_no_adjust_clears_stale_cf() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0xF0; ADD AL, 0x22 (CF=1, AF=0); MOV AL, 0x12; CLC; DAA
+    // CF is computed lazily from the ADD; after CLC, DAA must report CF=0
+    harness.load_program(&[0xB0, 0xF0, 0x04, 0x22, 0xB0, 0x12, 0xF8, 0x27], 0);
+
+    harness.step_n(2);
+    assert!(harness.cpu.get_flag(ezpc::cpu::Cpu::CF));
+
+    harness.step_n(3); // MOV AL, 0x12; CLC; DAA
+
+    assert_eq!(harness.cpu.read_reg8(0), 0x12);
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::CF), false);
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::AF), false);
+}
+
 #[test]
 fn test_das_no_adjustment() {
     let mut harness = CpuHarness::new();
@@ -1453,6 +1532,83 @@ fn test_das_multi_digit_bcd_subtraction() {
     assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::CF), false);
 }
 
+#[test]
+fn test_das_preset_cf0_af0_no_adjust() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0x12; DAS with CF=0, AF=0 (no adjustment)
+    harness.load_program(&[0xB0, 0x12, 0x2F], 0);
+
+    harness.step(); // MOV AL, 0x12
+    harness.cpu.set_flags(0);
+    harness.step(); // DAS
+
+    assert_eq!(harness.cpu.read_reg8(0), 0x12);
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::CF), false);
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::AF), false);
+}
+
+#[test]
+fn test_das_preset_cf0_af1() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0x12; DAS with CF=0, AF=1 (low nibble adjusted by AF)
+    harness.load_program(&[0xB0, 0x12, 0x2F], 0);
+
+    harness.step(); // MOV AL, 0x12
+    harness.cpu.set_flags(ezpc::cpu::Cpu::AF);
+    harness.step(); // DAS
+
+    assert_eq!(harness.cpu.read_reg8(0), 0x0C); // 0x12 - 0x06
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::CF), false);
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::AF), true);
+}
+
+#[test]
+fn test_das_preset_cf1_af0() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0x12; DAS with CF=1, AF=0 (high nibble adjusted by CF)
+    harness.load_program(&[0xB0, 0x12, 0x2F], 0);
+
+    harness.step(); // MOV AL, 0x12
+    harness.cpu.set_flags(ezpc::cpu::Cpu::CF);
+    harness.step(); // DAS
+
+    assert_eq!(harness.cpu.read_reg8(0), 0xB2); // 0x12 - 0x60
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::CF), true);
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::AF), false);
+}
+
+#[test]
+fn test_das_preset_cf1_af1() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0x12; DAS with CF=1, AF=1 (both nibbles adjusted)
+    harness.load_program(&[0xB0, 0x12, 0x2F], 0);
+
+    harness.step(); // MOV AL, 0x12
+    harness
+        .cpu
+        .set_flags(ezpc::cpu::Cpu::CF | ezpc::cpu::Cpu::AF);
+    harness.step(); // DAS
+
+    assert_eq!(harness.cpu.read_reg8(0), 0xAC); // 0x12 - 0x66
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::CF), true);
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::AF), true);
+}
+
+#[test]
+fn test_das_low_nibble_borrow_sets_cf() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0x03; DAS with AF=1: subtracting 6 borrows out of AL
+    harness.load_program(&[0xB0, 0x03, 0x2F], 0);
+
+    harness.step(); // MOV AL, 0x03
+    harness.cpu.set_flags(ezpc::cpu::Cpu::AF);
+    harness.step(); // DAS
+
+    assert_eq!(harness.cpu.read_reg8(0), 0xFD); // 0x03 - 0x06
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::CF), true);
+    assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::AF), true);
+}
+
 // === Opcode 0xFE group tests (INC/DEC r/m8) ===
 
 #[test]
@@ -1755,6 +1911,43 @@ fn test_mul_r8_edge_case_128() {
     assert_eq!(harness.cpu.get_flag(ezpc::cpu::Cpu::OF), true); // OF set
 }
 
+/// Run `program` with the given initial flags and return SF/ZF/PF afterwards
+fn szp_after(program: &[u8], steps: usize, initial_flags: u16) -> (bool, bool, bool) {
+    use ezpc::cpu::Cpu;
+
+    let mut harness = CpuHarness::new();
+    harness.load_program(program, 0);
+    harness.cpu.set_flags(initial_flags);
+    harness.step_n(steps);
+    (
+        harness.cpu.get_flag(Cpu::SF),
+        harness.cpu.get_flag(Cpu::ZF),
+        harness.cpu.get_flag(Cpu::PF),
+    )
+}
+
+#[test]
+fn test_mul_szp_from_low_half() {
+    // SZP are undefined on the 8088; the emulator defines them from AL/AX,
+    // so they must not depend on whatever flags were set before
+    let all_set = 0x08D5; // OF, SF, ZF, AF, PF, CF
+
+    // MOV AL, 0x80; MOV BL, 0x02; MUL BL -> AX = 0x0100, AL = 0
+    let mul8 = [0xB0, 0x80, 0xB3, 0x02, 0xF6, 0xE3];
+    assert_eq!(szp_after(&mul8, 3, 0), (false, true, true));
+    assert_eq!(szp_after(&mul8, 3, all_set), (false, true, true));
+
+    // MOV AX, 0x8001; MOV BX, 3; MUL BX -> DX:AX = 0x0001:8003
+    let mul16 = [0xB8, 0x01, 0x80, 0xBB, 0x03, 0x00, 0xF7, 0xE3];
+    assert_eq!(szp_after(&mul16, 3, 0), (true, false, true));
+    assert_eq!(szp_after(&mul16, 3, all_set), (true, false, true));
+
+    // MOV AL, 0xFF; MOV BL, 0x07; IMUL BL -> AX = 0xFFF9 (-7), AL = 0xF9
+    let imul8 = [0xB0, 0xFF, 0xB3, 0x07, 0xF6, 0xEB];
+    assert_eq!(szp_after(&imul8, 3, 0), (true, false, true));
+    assert_eq!(szp_after(&imul8, 3, all_set), (true, false, true));
+}
+
 #[test]
 fn test_div_r8_basic() {
     let mut harness = CpuHarness::new();
@@ -1940,6 +2133,37 @@ fn test_neg_r16_positive() {
     assert!(harness.cpu.get_flag(ezpc::cpu::Cpu::SF)); // SF set
 }
 
+#[test]
+fn test_neg_r16_overflow() {
+    let mut harness = CpuHarness::new();
+    // MOV AX, 0x8000; NEG AX (negating -32768 causes overflow)
+    // Expected: 0 - 0x8000 = 0x8000
+    harness.load_program(&[0xB8, 0x00, 0x80, 0xF7, 0xD8], 0);
+
+    harness.step(); // MOV AX, 0x8000
+    harness.step(); // NEG AX
+
+    assert_eq!(harness.cpu.regs[0], 0x8000); // AX = 0x8000
+    assert!(harness.cpu.get_flag(ezpc::cpu::Cpu::CF)); // CF set
+    assert!(harness.cpu.get_flag(ezpc::cpu::Cpu::OF)); // OF set (overflow)
+    assert!(harness.cpu.get_flag(ezpc::cpu::Cpu::SF)); // SF set
+    assert!(!harness.cpu.get_flag(ezpc::cpu::Cpu::ZF)); // ZF clear
+}
+
+#[test]
+fn test_neg_m8_overflow() {
+    let mut harness = CpuHarness::new();
+    // MOV BYTE [0x0100], 0x80; NEG BYTE [0x0100]
+    harness.load_program(&[0xC6, 0x06, 0x00, 0x01, 0x80, 0xF6, 0x1E, 0x00, 0x01], 0);
+
+    harness.step(); // MOV BYTE [0x0100], 0x80
+    harness.step(); // NEG BYTE [0x0100]
+
+    assert_eq!(harness.mem.read_u8(0x0100), 0x80);
+    assert!(harness.cpu.get_flag(ezpc::cpu::Cpu::CF)); // CF set
+    assert!(harness.cpu.get_flag(ezpc::cpu::Cpu::OF)); // OF set (overflow)
+}
+
 #[test]
 fn test_imul_r8_positive() {
     let mut harness = CpuHarness::new();
@@ -2003,6 +2227,31 @@ fn test_imul_r16_positive() {
     assert!(!harness.cpu.get_flag(ezpc::cpu::Cpu::CF)); // CF clear
 }
 
+#[test]
+fn test_imul_r16_rm16_imm_on_80186() {
+    let mut harness = CpuHarness::new();
+    harness.cpu.set_model(CpuModel::I80186);
+    // MOV BX, 300; IMUL CX, BX, -3; IMUL DX, BX, 300
+    harness.load_program(
+        &[
+            0xBB, 0x2C, 0x01, // MOV BX, 300
+            0x6B, 0xCB, 0xFD, // IMUL CX, BX, -3
+            0x69, 0xD3, 0x2C, 0x01, // IMUL DX, BX, 300
+        ],
+        0,
+    );
+
+    harness.step_n(2);
+    assert_eq!(harness.cpu.regs[1], (-900i16) as u16); // CX
+    assert!(!harness.cpu.get_flag(ezpc::cpu::Cpu::CF));
+
+    harness.step(); // 90000 does not fit in 16 bits
+    assert_eq!(harness.cpu.regs[2], (90000u32 & 0xFFFF) as u16); // DX
+    assert!(harness.cpu.get_flag(ezpc::cpu::Cpu::CF));
+    assert!(harness.cpu.get_flag(ezpc::cpu::Cpu::OF));
+    assert_eq!(harness.cpu.regs[3], 300); // BX unchanged
+}
+
 #[test]
 fn test_imul_r16_negative() {
     let mut harness = CpuHarness::new();
@@ -2018,6 +2267,55 @@ fn test_imul_r16_negative() {
     assert!(!harness.cpu.get_flag(ezpc::cpu::Cpu::CF)); // CF clear (fits in AX)
 }
 
+#[test]
+fn test_div_by_zero_with_override_enters_int0_without_override() {
+    let mut harness = CpuHarness::new();
+    // Program at 0010:0000: MOV BL, 0; DS: DIV BL; NOP
+    harness.load_program(&[0xB3, 0x00, 0x3E, 0xF6, 0xF3, 0x90], 0x0010);
+    harness.cpu.segments[3] = 0x2000; // DS
+    harness.cpu.regs[0] = 0x1234; // AX
+    harness.cpu.regs[4] = 0x1000; // SP
+    harness.cpu.regs[5] = 0x0800; // BP
+
+    // INT 0 handler at 0000:0200: MOV AL, [BP+0] (defaults to SS, not DS)
+    harness.mem.write_u16(0x0000, 0x0200);
+    harness.mem.write_u16(0x0002, 0x0000);
+    harness.mem.load(&[0x8A, 0x46, 0x00], 0x0200);
+    harness.mem.write_u8(0x0800, 0x5A); // SS:BP
+
+    harness.step(); // MOV BL, 0
+    harness.step(); // DS: DIV BL -> divide error
+
+    assert_eq!(harness.cpu.segments[1], 0x0000);
+    assert_eq!(harness.cpu.ip, 0x0200);
+    assert_eq!(harness.cpu.segment_override, None);
+    assert_eq!(harness.cpu.regs[0], 0x1234); // AX unchanged
+
+    // Return address is the instruction after DIV
+    assert_eq!(harness.cpu.regs[4], 0x0FFA);
+    assert_eq!(harness.mem.read_u16(0x0FFA), 0x0005); // IP
+    assert_eq!(harness.mem.read_u16(0x0FFC), 0x0010); // CS
+
+    harness.step(); // MOV AL, [BP+0]
+    assert_eq!(harness.cpu.read_reg8(0), 0x5A);
+}
+
+#[test]
+fn test_div_quotient_overflow_enters_int0() {
+    let mut harness = CpuHarness::new();
+    // MOV AX, 0x1000; MOV BL, 2; DIV BL (quotient 0x800 does not fit in AL)
+    harness.load_program(&[0xB8, 0x00, 0x10, 0xB3, 0x02, 0xF6, 0xF3], 0x0010);
+    harness.cpu.regs[4] = 0x1000; // SP
+    harness.mem.write_u16(0x0000, 0x0300);
+    harness.mem.write_u16(0x0002, 0x0000);
+
+    harness.step_n(3);
+
+    assert_eq!(harness.cpu.ip, 0x0300);
+    assert_eq!(harness.cpu.regs[0], 0x1000); // AX unchanged
+    assert_eq!(harness.mem.read_u16(0x0FFA), 0x0007);
+}
+
 #[test]
 fn test_idiv_r8_positive() {
     let mut harness = CpuHarness::new();
@@ -2111,6 +2409,58 @@ fn test_idiv_r16_negative() {
     assert_eq!(harness.cpu.regs[2] as i16, -12); // DX = -12 (remainder)
 }
 
+#[test]
+fn test_cwd_idiv_r16_negative_by_negative() {
+    let mut harness = CpuHarness::new();
+    // MOV AX, 0xFC18 (-1000); CWD; MOV BX, 0xFFFD (-3); IDIV BX
+    // Expected: -1000 ÷ -3 = 333 remainder -1
+    harness.load_program(
+        &[
+            0xB8, 0x18, 0xFC, // MOV AX, 0xFC18 (-1000)
+            0x99, // CWD
+            0xBB, 0xFD, 0xFF, // MOV BX, 0xFFFD (-3)
+            0xF7, 0xFB, // IDIV BX
+        ],
+        0,
+    );
+
+    harness.step(); // MOV AX, -1000
+    harness.step(); // CWD
+    assert_eq!(harness.cpu.regs[2], 0xFFFF); // DX:AX = -1000
+
+    harness.step(); // MOV BX, -3
+    harness.step(); // IDIV BX
+
+    assert_eq!(harness.cpu.regs[0] as i16, 333); // AX = 333 (quotient)
+    assert_eq!(harness.cpu.regs[2] as i16, -1); // DX = -1 (remainder, dividend's sign)
+}
+
+#[test]
+fn test_cwd_idiv_r16_positive_by_negative() {
+    let mut harness = CpuHarness::new();
+    // MOV AX, 1000; CWD; MOV BX, 0xFFFD (-3); IDIV BX
+    // Expected: 1000 ÷ -3 = -333 remainder 1
+    harness.load_program(
+        &[
+            0xB8, 0xE8, 0x03, // MOV AX, 1000
+            0x99, // CWD
+            0xBB, 0xFD, 0xFF, // MOV BX, 0xFFFD (-3)
+            0xF7, 0xFB, // IDIV BX
+        ],
+        0,
+    );
+
+    harness.step(); // MOV AX, 1000
+    harness.step(); // CWD
+    assert_eq!(harness.cpu.regs[2], 0x0000); // DX:AX = 1000
+
+    harness.step(); // MOV BX, -3
+    harness.step(); // IDIV BX
+
+    assert_eq!(harness.cpu.regs[0] as i16, -333); // AX = -333 (quotient)
+    assert_eq!(harness.cpu.regs[2] as i16, 1); // DX = 1 (remainder, dividend's sign)
+}
+
 #[test]
 fn test_not_r8() {
     let mut harness = CpuHarness::new();
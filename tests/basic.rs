@@ -1,6 +1,8 @@
 //! Basic data transfer instruction tests (MOV, XCHG, NOP)
 
 use ezpc::cpu::CpuHarness;
+use ezpc::debugger::Breakpoints;
+use ezpc::memory::linear;
 
 #[test]
 fn test_nop() {
@@ -14,6 +16,29 @@ fn test_nop() {
     assert_eq!(harness.cpu.ip, 1);
 }
 
+#[test]
+fn test_nop_is_three_cycles_and_changes_nothing() {
+    let mut harness = CpuHarness::new();
+    // ADD AX, BX (leaves lazy flags pending); NOP
+    harness.load_program(&[0x01, 0xD8, 0x90], 0);
+    harness.cpu.regs = [
+        0x8000, 0x1111, 0x2222, 0x8000, 0x0100, 0x3333, 0x4444, 0x5555,
+    ];
+
+    harness.step(); // ADD AX, BX
+    let before = harness.cpu.save_state();
+
+    let cycles = harness.step(); // NOP
+    let after = harness.cpu.save_state();
+
+    assert_eq!(cycles, 3);
+    assert_eq!(after.total_cycles - before.total_cycles, 3);
+    assert_eq!(after.ip, before.ip + 1);
+    assert_eq!(after.regs, before.regs);
+    assert_eq!(after.segments, before.segments);
+    assert_eq!(after.flags, before.flags);
+}
+
 #[test]
 fn test_mov_r16_imm() {
     let mut harness = CpuHarness::new();
@@ -168,6 +193,34 @@ fn test_xchg_ax_r16() {
     assert_eq!(harness.cpu.regs[1], 0x1111); // CX
 }
 
+#[test]
+fn test_xchg_r8_r8() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0x12; MOV BL, 0x34; XCHG BL, AL
+    harness.load_program(&[0xB0, 0x12, 0xB3, 0x34, 0x86, 0xC3], 0);
+
+    harness.step_n(2);
+    let flags_before = harness.cpu.get_flags();
+    harness.step(); // XCHG BL, AL
+
+    assert_eq!(harness.cpu.read_reg8(0), 0x34); // AL
+    assert_eq!(harness.cpu.read_reg8(3), 0x12); // BL
+    assert_eq!(harness.cpu.get_flags(), flags_before);
+}
+
+#[test]
+fn test_xchg_m16_ax() {
+    let mut harness = CpuHarness::new();
+    harness.mem.write_u16(0x1000, 0xBEEF);
+    // MOV AX, 0x1234; XCHG [0x1000], AX
+    harness.load_program(&[0xB8, 0x34, 0x12, 0x87, 0x06, 0x00, 0x10], 0);
+
+    harness.step_n(2);
+
+    assert_eq!(harness.cpu.regs[0], 0xBEEF); // AX
+    assert_eq!(harness.mem.read_u16(0x1000), 0x1234);
+}
+
 #[test]
 fn test_lea_bx_si() {
     let mut harness = CpuHarness::new();
@@ -227,6 +280,29 @@ fn test_mov_reg_to_sreg() {
     assert_eq!(harness.cpu.ip, 5);
 }
 
+#[test]
+fn test_mov_cs_is_ignored() {
+    let mut harness = CpuHarness::new();
+    // MOV AX, 0x1234; MOV CS, AX; MOV DS, AX
+    harness.load_program(
+        &[
+            0xB8, 0x34, 0x12, // MOV AX, 0x1234
+            0x8E, 0xC8, // MOV CS, AX (ModR/M=C8: reg=CS(001), rm=AX(000), mod=11)
+            0x8E, 0xD8, // MOV DS, AX
+        ],
+        0,
+    );
+    let cs = harness.cpu.segments[1];
+
+    harness.step(); // MOV AX, 0x1234
+    harness.step(); // MOV CS, AX
+    assert_eq!(harness.cpu.segments[1], cs); // CS unchanged
+    assert_eq!(harness.cpu.ip, 5); // Execution continues with the next instruction
+
+    harness.step(); // MOV DS, AX
+    assert_eq!(harness.cpu.segments[3], 0x1234);
+}
+
 #[test]
 fn test_mov_sreg_es_to_reg() {
     let mut harness = CpuHarness::new();
@@ -288,6 +364,30 @@ fn test_lea_bx_si_disp8() {
     assert_eq!(harness.cpu.regs[2], 0x1250); // DX
 }
 
+#[test]
+fn test_lea_bx_si_disp8_ignores_override_and_memory() {
+    let mut harness = CpuHarness::new();
+    harness.cpu.segments[0] = 0x3000; // ES
+                                      // MOV BX, 0x1000; MOV SI, 0x0200; ES: LEA AX, [BX+SI+4]
+    harness.load_program(
+        &[
+            0xBB, 0x00, 0x10, // MOV BX, 0x1000
+            0xBE, 0x00, 0x02, // MOV SI, 0x0200
+            0x26, 0x8D, 0x40, 0x04, // ES: LEA AX, [BX+SI+4]
+        ],
+        0,
+    );
+    harness.step_n(2);
+
+    harness.mem.enable_mem_profiling(true);
+    harness.step(); // ES: LEA AX, [BX+SI+4]
+
+    // Just the offset: no segment base and no operand read
+    assert_eq!(harness.cpu.regs[0], 0x1000 + 0x0200 + 4);
+    let pages = harness.mem.mem_hotpages(usize::MAX);
+    assert!(pages.iter().all(|page| page.base == 0), "{pages:?}");
+}
+
 #[test]
 fn test_lea_bx_si_disp16() {
     let mut harness = CpuHarness::new();
@@ -1096,6 +1196,22 @@ fn test_xlat_with_segment_override() {
     assert_eq!(harness.cpu.read_reg8(0), 0x77);
 }
 
+#[test]
+fn test_xlat_offset_wraps_within_segment() {
+    let mut harness = CpuHarness::new();
+    harness.cpu.segments[3] = 0x0100; // DS
+    harness.mem.write_u8(0x1000 + 0x0001, 0x3C); // DS:0001
+
+    harness.load_program(&[0xD7], 0x0400); // XLAT
+    harness.cpu.regs[3] = 0xFFF0; // BX
+    harness.cpu.write_reg8(0, 0x11); // AL
+
+    harness.step();
+
+    // BX + AL wraps to DS:0001 rather than carrying into the segment
+    assert_eq!(harness.cpu.read_reg8(0), 0x3C);
+}
+
 #[test]
 fn test_les_direct_address() {
     let mut harness = CpuHarness::new();
@@ -1202,6 +1318,25 @@ fn test_lds_indirect_bx_si() {
     assert_eq!(harness.cpu.segments[3], 0x99AA); // DS
 }
 
+#[test]
+fn test_lds_si_bx_reads_through_old_ds() {
+    let mut harness = CpuHarness::new();
+    harness.cpu.segments[3] = 0x0200; // DS
+    harness.cpu.regs[3] = 0x0040; // BX
+
+    // Far pointer 1234:5678 at DS:BX = 0x2040
+    harness.mem.write_u16(0x2040, 0x5678);
+    harness.mem.write_u16(0x2042, 0x1234);
+
+    // LDS SI, [BX]
+    harness.load_program(&[0xC5, 0x37], 0);
+    harness.step();
+
+    assert_eq!(harness.cpu.regs[6], 0x5678); // SI
+    assert_eq!(harness.cpu.segments[3], 0x1234); // DS
+    assert_eq!(harness.cpu.regs[3], 0x0040); // BX untouched
+}
+
 #[test]
 fn test_les_with_displacement() {
     let mut harness = CpuHarness::new();
@@ -1254,3 +1389,92 @@ fn test_lds_overwrite_existing_segment() {
     assert_eq!(harness.cpu.regs[3], 0x1111); // BX
     assert_eq!(harness.cpu.segments[3], 0x2222); // DS overwritten
 }
+
+#[test]
+fn test_linear_address_wraps_at_1mb() {
+    assert_eq!(linear(0x1234, 0x0005), 0x12345);
+    assert_eq!(linear(0xFFFF, 0x000F), 0xFFFFF);
+    assert_eq!(linear(0xFFFF, 0x0010), 0x00000);
+    assert_eq!(linear(0xFFFF, 0xFFFF), 0x0FFEF);
+}
+
+#[test]
+fn test_debugger_and_operand_agree_past_1mb() {
+    let mut harness = CpuHarness::new();
+    harness.mem.write_u8(0x00000, 0x5A);
+
+    // MOV AL, [0x0010] with DS=FFFF reads linear 0x00000
+    harness.cpu.segments[3] = 0xFFFF;
+    harness.load_program(&[0xA0, 0x10, 0x00], 0x0100);
+    harness.step();
+    assert_eq!(harness.cpu.regs[0] & 0xFF, 0x5A);
+
+    // A breakpoint on the same logical address hits from either form
+    let mut breakpoints = Breakpoints::new();
+    breakpoints.insert(linear(0xFFFF, 0x0010));
+    harness.cpu.segments[1] = 0xFFFF;
+    harness.cpu.ip = 0x0010;
+    assert!(breakpoints.hit(&harness.cpu));
+    harness.cpu.segments[1] = 0x0000;
+    harness.cpu.ip = 0x0000;
+    assert!(breakpoints.hit(&harness.cpu));
+}
+
+// ===== Prefetch Queue / Self-Modifying Code Tests =====
+
+#[test]
+fn test_store_to_next_instruction_uses_queued_byte() {
+    let mut harness = CpuHarness::new();
+    harness.cpu.set_prefetch_queue(true);
+    // MOV byte [0x0005], 0x40 (INC AX); NOP
+    harness.load_program(&[0xC6, 0x06, 0x05, 0x00, 0x40, 0x90], 0);
+
+    harness.step_n(2);
+
+    // The NOP was already in the queue, so the new INC AX is not executed
+    assert_eq!(harness.mem.read_u8(0x0005), 0x40);
+    assert_eq!(harness.cpu.regs[0], 0);
+    assert_eq!(harness.cpu.ip, 6);
+}
+
+#[test]
+fn test_store_to_next_instruction_without_queue_takes_effect() {
+    let mut harness = CpuHarness::new();
+    // MOV byte [0x0005], 0x40 (INC AX); NOP
+    harness.load_program(&[0xC6, 0x06, 0x05, 0x00, 0x40, 0x90], 0);
+
+    harness.step_n(2);
+
+    assert_eq!(harness.cpu.regs[0], 1);
+}
+
+#[test]
+fn test_store_beyond_queue_takes_effect() {
+    let mut harness = CpuHarness::new();
+    harness.cpu.set_prefetch_queue(true);
+    // MOV byte [0x0009], 0x40 (INC AX); NOP x4; NOP
+    harness.load_program(
+        &[0xC6, 0x06, 0x09, 0x00, 0x40, 0x90, 0x90, 0x90, 0x90, 0x90],
+        0,
+    );
+
+    harness.step_n(6);
+
+    // Only 4 bytes were queued, so the byte at 0x0009 is fetched after the store
+    assert_eq!(harness.cpu.regs[0], 1);
+}
+
+#[test]
+fn test_jump_flushes_stale_queue() {
+    let mut harness = CpuHarness::new();
+    harness.cpu.set_prefetch_queue(true);
+    // 0: MOV byte [0x0007], 0x40 (INC AX)
+    // 5: JMP 7
+    // 7: NOP
+    harness.load_program(&[0xC6, 0x06, 0x07, 0x00, 0x40, 0xEB, 0x00, 0x90], 0);
+
+    harness.step_n(3);
+
+    // The jump refetches from memory and sees the stored INC AX
+    assert_eq!(harness.cpu.regs[0], 1);
+}
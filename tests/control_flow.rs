@@ -26,6 +26,18 @@ fn test_jmp_short() {
     assert_eq!(harness.cpu.regs[0], 0x1234); // AX
 }
 
+#[test]
+#[should_panic(expected = "watchdog: exceeded 100 instructions at 0000:0000")]
+fn test_watchdog_stops_jmp_to_self() {
+    let mut harness = CpuHarness::new();
+    harness.load_program(&[0xEB, 0xFE], 0); // JMP $
+    harness.set_watchdog(Some(100));
+
+    loop {
+        harness.step();
+    }
+}
+
 #[test]
 fn test_jz_taken() {
     let mut harness = CpuHarness::new();
@@ -134,6 +146,135 @@ fn test_call_near() {
     assert_eq!(harness.cpu.regs[0], 0x1234);
 }
 
+#[test]
+fn test_jmp_near_rel16_wraps_past_segment_top() {
+    let mut harness = CpuHarness::new();
+    // JMP +0x10 at 0xFFFB; the instruction ends at 0xFFFE, so the target
+    // wraps to 0x000E within the segment
+    harness.mem.write_u8(0xFFFB, 0xE9);
+    harness.mem.write_u16(0xFFFC, 0x0010);
+    harness.mem.load(&[0xB8, 0x34, 0x12], 0x000E); // MOV AX, 0x1234
+    harness.cpu.segments[1] = 0x0000;
+    harness.cpu.ip = 0xFFFB;
+
+    harness.step(); // JMP
+    assert_eq!(harness.cpu.ip, 0x000E);
+    assert_eq!(harness.cpu.segments[1], 0x0000);
+
+    harness.step(); // MOV AX, 0x1234
+    assert_eq!(harness.cpu.regs[0], 0x1234);
+}
+
+#[test]
+fn test_jmp_near_rel16_ending_at_segment_top() {
+    let mut harness = CpuHarness::new();
+    // JMP +5 occupying 0xFFFD-0xFFFF; IP after the instruction wraps to 0
+    harness.mem.write_u8(0xFFFD, 0xE9);
+    harness.mem.write_u16(0xFFFE, 0x0005);
+    harness.cpu.segments[1] = 0x0000;
+    harness.cpu.ip = 0xFFFD;
+
+    harness.step();
+    assert_eq!(harness.cpu.ip, 0x0005);
+}
+
+#[test]
+fn test_jz_rel8_back_onto_itself_near_segment_start() {
+    let mut harness = CpuHarness::new();
+    // JZ -2 at 0x0002; IP after it is 0x0004, so the jump lands on itself
+    harness.mem.load(&[0x74, 0xFE], 0x0002);
+    harness.cpu.segments[1] = 0x0000;
+    harness.cpu.ip = 0x0002;
+    harness.cpu.set_flag(ezpc::cpu::Cpu::ZF, true);
+
+    harness.step();
+    assert_eq!(harness.cpu.ip, 0x0002);
+}
+
+#[test]
+fn test_jnz_rel8_wraps_below_zero() {
+    let mut harness = CpuHarness::new();
+    // JNZ -8 at 0x0000; 0x0002 - 8 wraps to the top of the segment
+    harness.mem.load(&[0x75, 0xF8], 0x0000);
+    harness.mem.load(&[0xB8, 0x34, 0x12], 0xFFFA); // MOV AX, 0x1234
+    harness.cpu.segments[1] = 0x0000;
+    harness.cpu.ip = 0x0000;
+    harness.cpu.set_flag(ezpc::cpu::Cpu::ZF, false);
+
+    harness.step(); // JNZ
+    assert_eq!(harness.cpu.ip, 0xFFFA);
+    assert_eq!(harness.cpu.segments[1], 0x0000);
+
+    harness.step(); // MOV AX, 0x1234
+    assert_eq!(harness.cpu.regs[0], 0x1234);
+}
+
+#[test]
+fn test_jc_rel8_wraps_past_segment_top() {
+    let mut harness = CpuHarness::new();
+    // JC +4 occupying 0xFFFE-0xFFFF; IP after it wraps to 0, target is 4
+    harness.mem.load(&[0x72, 0x04], 0xFFFE);
+    harness.mem.load(&[0xB8, 0x34, 0x12], 0x0004); // MOV AX, 0x1234
+    harness.cpu.segments[1] = 0x0000;
+    harness.cpu.ip = 0xFFFE;
+    harness.cpu.set_flag(ezpc::cpu::Cpu::CF, true);
+
+    harness.step(); // JC
+    assert_eq!(harness.cpu.ip, 0x0004);
+    assert_eq!(harness.cpu.segments[1], 0x0000);
+
+    harness.step(); // MOV AX, 0x1234
+    assert_eq!(harness.cpu.regs[0], 0x1234);
+}
+
+#[test]
+fn test_jc_rel8_not_taken_at_segment_top_falls_through_to_zero() {
+    let mut harness = CpuHarness::new();
+    harness.mem.load(&[0x72, 0x04], 0xFFFE); // JC +4
+    harness.cpu.segments[1] = 0x0000;
+    harness.cpu.ip = 0xFFFE;
+    harness.cpu.set_flag(ezpc::cpu::Cpu::CF, false);
+
+    harness.step();
+    assert_eq!(harness.cpu.ip, 0x0000);
+}
+
+#[test]
+fn test_jmp_short_wraps_past_segment_top() {
+    let mut harness = CpuHarness::new();
+    // JMP +4 at 0xFFFC; 0xFFFE + 4 wraps to 0x0002
+    harness.mem.load(&[0xEB, 0x04], 0xFFFC);
+    harness.cpu.segments[1] = 0x0000;
+    harness.cpu.ip = 0xFFFC;
+
+    harness.step();
+    assert_eq!(harness.cpu.ip, 0x0002);
+}
+
+#[test]
+fn test_call_near_rel16_wraps_below_zero() {
+    let mut harness = CpuHarness::new();
+    // MOV SP, 0x1000; CALL -0x10 (ends at 0x0006, target 0xFFF6)
+    harness.load_program(
+        &[
+            0xBC, 0x00, 0x10, // MOV SP, 0x1000 (offset 0-2)
+            0xE8, 0xF0, 0xFF, // CALL -0x10 (offset 3-5)
+        ],
+        0,
+    );
+    harness.mem.load(&[0xB8, 0x34, 0x12], 0xFFF6); // MOV AX, 0x1234
+
+    harness.step(); // MOV SP, 0x1000
+    harness.step(); // CALL
+
+    assert_eq!(harness.cpu.ip, 0xFFF6);
+    assert_eq!(harness.cpu.regs[4], 0x0FFE);
+    assert_eq!(harness.mem.read_u16(0x0FFE), 0x0006); // Return address
+
+    harness.step(); // MOV AX, 0x1234
+    assert_eq!(harness.cpu.regs[0], 0x1234);
+}
+
 #[test]
 fn test_call_far() {
     let mut harness = CpuHarness::new();
@@ -207,6 +348,48 @@ fn test_call_rm16_register() {
     assert_eq!(return_addr, 8);
 }
 
+#[test]
+fn test_call_m16_near_indirect() {
+    let mut harness = CpuHarness::new();
+    harness.mem.write_u16(0x0500, 0x0240); // Near pointer at DS:0500
+                                           // MOV SP, 0x1000; MOV BX, 0x0500; CALL [BX]
+    harness.load_program(
+        &[
+            0xBC, 0x00, 0x10, // MOV SP, 0x1000
+            0xBB, 0x00, 0x05, // MOV BX, 0x0500
+            0xFF, 0x17, // CALL [BX] (reg=010, rm=111=[BX])
+        ],
+        0,
+    );
+
+    harness.step_n(3);
+
+    assert_eq!(harness.cpu.ip, 0x0240);
+    assert_eq!(harness.cpu.read_seg(1), 0x0000); // Near: CS unchanged
+    assert_eq!(harness.cpu.regs[4], 0x0FFE); // Only IP pushed
+    assert_eq!(harness.mem.read_u16(0x0FFE), 0x0008);
+}
+
+#[test]
+fn test_jmp_m16_near_indirect() {
+    let mut harness = CpuHarness::new();
+    harness.mem.write_u16(0x0600, 0x0320); // Near pointer at DS:0600
+                                           // MOV SP, 0x1000; MOV SI, 0x0600; JMP [SI]
+    harness.load_program(
+        &[
+            0xBC, 0x00, 0x10, // MOV SP, 0x1000
+            0xBE, 0x00, 0x06, // MOV SI, 0x0600
+            0xFF, 0x24, // JMP [SI] (reg=100, rm=100=[SI])
+        ],
+        0,
+    );
+
+    harness.step_n(3);
+
+    assert_eq!(harness.cpu.ip, 0x0320);
+    assert_eq!(harness.cpu.regs[4], 0x1000); // Nothing pushed
+}
+
 #[test]
 fn test_call_backward() {
     let mut harness = CpuHarness::new();
@@ -371,6 +554,54 @@ fn test_ret_far_imm() {
     assert_eq!(harness.cpu.regs[0], 0x1234);
 }
 
+#[test]
+fn test_ret_near_imm_sp_wraps() {
+    let mut harness = CpuHarness::new();
+    // RET 4 with SP=0xFFFC: return address at SS:FFFC, SP wraps to 0x0002
+    harness.load_program(&[0xC2, 0x04, 0x00], 0x0100); // RET 4
+    harness.cpu.segments[2] = 0x0000; // SS
+    harness.cpu.regs[4] = 0xFFFC; // SP
+    harness.mem.write_u16(0xFFFC, 0x1234); // Return address
+
+    harness.step(); // RET 4
+
+    assert_eq!(harness.cpu.ip, 0x1234);
+    assert_eq!(harness.cpu.regs[4], 0x0002);
+}
+
+#[test]
+fn test_ret_near_imm_return_address_straddles_segment_end() {
+    let mut harness = CpuHarness::new();
+    // RET 4 with SP=0xFFFF: the return address word wraps within SS
+    harness.load_program(&[0xC2, 0x04, 0x00], 0x0100); // RET 4
+    harness.cpu.segments[2] = 0x0000; // SS
+    harness.cpu.regs[4] = 0xFFFF; // SP
+    harness.mem.write_u8(0xFFFF, 0x34); // Low byte at SS:FFFF
+    harness.mem.write_u8(0x0000, 0x12); // High byte wraps to SS:0000
+
+    harness.step(); // RET 4
+
+    assert_eq!(harness.cpu.ip, 0x1234);
+    assert_eq!(harness.cpu.regs[4], 0x0005); // 0xFFFF + 2 + 4 wraps
+}
+
+#[test]
+fn test_ret_far_imm_sp_wraps() {
+    let mut harness = CpuHarness::new();
+    // RETF 4 with SP=0xFFFC: IP at SS:FFFC, CS at SS:FFFE, SP wraps to 0x0004
+    harness.load_program(&[0xCA, 0x04, 0x00], 0x0100); // RETF 4
+    harness.cpu.segments[2] = 0x0000; // SS
+    harness.cpu.regs[4] = 0xFFFC; // SP
+    harness.mem.write_u16(0xFFFC, 0x5678); // Return IP
+    harness.mem.write_u16(0xFFFE, 0x0200); // Return CS
+
+    harness.step(); // RETF 4
+
+    assert_eq!(harness.cpu.ip, 0x5678);
+    assert_eq!(harness.cpu.segments[1], 0x0200);
+    assert_eq!(harness.cpu.regs[4], 0x0004);
+}
+
 #[test]
 fn test_jo_taken() {
     let mut harness = CpuHarness::new();
@@ -803,6 +1034,30 @@ fn test_loop_not_taken() {
     assert_eq!(harness.cpu.regs[0], 0x1234);
 }
 
+#[test]
+fn test_loop_cx_zero_wraps_and_loops_65536_times() {
+    let mut harness = CpuHarness::new();
+    // MOV CX, 0; <loop_start>: INC BX; LOOP loop_start
+    harness.load_program(
+        &[
+            0xB9, 0x00, 0x00, // MOV CX, 0 (offset 0-2)
+            0x43, // INC BX (offset 3)
+            0xE2, 0xFD, // LOOP -3 (offset 4-5)
+        ],
+        0,
+    );
+
+    harness.step_n(3); // MOV CX, 0; INC BX; LOOP
+    assert_eq!(harness.cpu.regs[1], 0xFFFF); // CX wrapped, not 0
+    assert_eq!(harness.cpu.ip, 3); // Jump taken
+
+    // The remaining 65535 iterations
+    harness.step_n(2 * 0xFFFF);
+    assert_eq!(harness.cpu.regs[1], 0);
+    assert_eq!(harness.cpu.regs[3], 0); // BX incremented 65536 times
+    assert_eq!(harness.cpu.ip, 6); // Fell through
+}
+
 #[test]
 fn test_loope_taken() {
     let mut harness = CpuHarness::new();
@@ -976,6 +1231,16 @@ fn test_jcxz_not_taken() {
     assert_eq!(harness.cpu.regs[0], 0x1234);
 }
 
+#[test]
+fn test_jcxz_tests_all_of_cx() {
+    let mut harness = CpuHarness::new();
+    // MOV CX, 0x0100 (CL = 0); JCXZ +2
+    harness.load_program(&[0xB9, 0x00, 0x01, 0xE3, 0x02], 0);
+
+    harness.step_n(2);
+    assert_eq!(harness.cpu.ip, 5); // Not taken: CH is non-zero
+}
+
 #[test]
 fn test_int_n() {
     let mut harness = CpuHarness::new();
@@ -1082,6 +1347,62 @@ fn test_int3() {
     assert!(harness.cpu.get_flag(ezpc::cpu::Cpu::SF));
 }
 
+#[test]
+fn test_into_with_overflow() {
+    let mut harness = CpuHarness::new();
+
+    // IVT entry 4 (overflow) at 0x10: 0x0700:0x0300
+    harness.mem.write_u16(0x10, 0x0300); // Offset = 0x0300
+    harness.mem.write_u16(0x12, 0x0700); // Segment = 0x0700
+
+    // Load program at CS=0x0100: INTO
+    harness.load_program(&[0xCE], 0x0100);
+    harness.cpu.regs[4] = 0x2000; // SP = 0x2000
+    harness.cpu.write_seg(2, 0x0200); // SS = 0x0200
+
+    harness.cpu.set_flag(ezpc::cpu::Cpu::OF, true);
+    harness.cpu.set_flag(ezpc::cpu::Cpu::IF, true); // This should be cleared
+    let flags_before = harness.cpu.get_flags();
+
+    let cycles = harness.step(); // INTO
+
+    assert_eq!(cycles, 53);
+    assert_eq!(harness.cpu.read_seg(1), 0x0700); // CS
+    assert_eq!(harness.cpu.ip, 0x0300); // IP
+    assert_eq!(harness.cpu.regs[4], 0x1FFA); // SP
+
+    let stacked_ip = harness.cpu.read_mem16(&harness.mem, 0x0200, 0x1FFA);
+    let stacked_cs = harness.cpu.read_mem16(&harness.mem, 0x0200, 0x1FFC);
+    let stacked_flags = harness.cpu.read_mem16(&harness.mem, 0x0200, 0x1FFE);
+    assert_eq!(stacked_ip, 1); // Return address (IP after INTO)
+    assert_eq!(stacked_cs, 0x0100); // Old CS
+    assert_eq!(stacked_flags, flags_before);
+
+    assert!(!harness.cpu.get_flag(ezpc::cpu::Cpu::IF));
+}
+
+#[test]
+fn test_into_without_overflow() {
+    let mut harness = CpuHarness::new();
+
+    harness.mem.write_u16(0x10, 0x0300);
+    harness.mem.write_u16(0x12, 0x0700);
+
+    // Load program at CS=0x0100: INTO
+    harness.load_program(&[0xCE], 0x0100);
+    harness.cpu.regs[4] = 0x2000; // SP = 0x2000
+    harness.cpu.write_seg(2, 0x0200); // SS = 0x0200
+    harness.cpu.set_flag(ezpc::cpu::Cpu::OF, false);
+
+    let cycles = harness.step(); // INTO
+
+    // Falls through to the next instruction without touching the stack
+    assert_eq!(cycles, 4);
+    assert_eq!(harness.cpu.read_seg(1), 0x0100); // CS
+    assert_eq!(harness.cpu.ip, 1); // IP
+    assert_eq!(harness.cpu.regs[4], 0x2000); // SP
+}
+
 #[test]
 fn test_iret() {
     let mut harness = CpuHarness::new();
@@ -1119,7 +1440,7 @@ fn test_iret() {
 
     // Verify FLAGS were restored
     let flags = harness.cpu.get_flags();
-    assert_eq!(flags, 0x0246); // Exact flags restored
+    assert_eq!(flags, 0xF246); // Exact flags restored, reserved high bits read as 1
 
     // Verify individual flags from restored state
     assert!(harness.cpu.get_flag(ezpc::cpu::Cpu::ZF));
@@ -1130,6 +1451,51 @@ fn test_iret() {
     assert_eq!(harness.cpu.regs[4], 0x2000); // SP = 0x1FFA + 6
 }
 
+#[test]
+fn test_iret_restores_flags_rewritten_by_handler() {
+    let mut harness = CpuHarness::new();
+    // INT 0x80 handler at 0000:0600
+    harness.mem.write_u16(0x80 * 4, 0x0600);
+    harness.mem.write_u16(0x80 * 4 + 2, 0x0000);
+    harness.mem.load(
+        &[
+            0x89, 0xE5, // MOV BP, SP
+            0xC7, 0x46, 0x04, 0x29, 0x0A, // MOV WORD [BP+4], 0x0A29
+            0xCF, // IRET
+        ],
+        0x0600,
+    );
+    harness.load_program(
+        &[
+            0xBC, 0x00, 0x10, // MOV SP, 0x1000
+            0xFD, // STD
+            0xCD, 0x80, // INT 0x80
+            0x9C, // PUSHF
+        ],
+        0,
+    );
+
+    harness.step_n(3); // MOV SP; STD; INT 0x80
+
+    // The pushed image carries the 8088's reserved bits: 1 in 12-15 and
+    // bit 1, 0 in bits 3 and 5
+    let pushed = harness.mem.read_u16(0x0FFE);
+    assert_eq!(pushed & 0xF02A, 0xF002);
+    assert_ne!(pushed & ezpc::cpu::Cpu::DF, 0);
+
+    // The handler stores OF, IF and CF, clears DF and the high nibble, and
+    // sets the reserved bits 3 and 5
+    harness.step_n(3); // MOV BP, SP; MOV [BP+4]; IRET
+    assert_eq!(harness.cpu.ip, 0x0006);
+
+    // Only the real flags are taken; reserved bits read as on hardware
+    assert_eq!(harness.cpu.get_flags(), 0xFA03);
+
+    // And the image round-trips unchanged
+    harness.step(); // PUSHF
+    assert_eq!(harness.mem.read_u16(0x0FFE), 0xFA03);
+}
+
 #[test]
 fn test_int_then_iret() {
     let mut harness = CpuHarness::new();
@@ -1195,6 +1561,49 @@ fn test_int_then_iret() {
     assert_eq!(harness.cpu.regs[0], 0x1234); // AX
 }
 
+#[test]
+fn test_interrupt_matches_int_instruction() {
+    let setup = || {
+        let mut harness = CpuHarness::new();
+        harness.mem.write_u16(0x21 * 4, 0x0100); // Offset
+        harness.mem.write_u16(0x21 * 4 + 2, 0x0300); // Segment
+        harness.load_program(&[0xCD, 0x21], 0x0100); // INT 0x21
+        harness.cpu.write_seg(2, 0x0200); // SS
+        harness.cpu.regs[4] = 0x2000; // SP
+        harness.cpu.set_flag(ezpc::cpu::Cpu::IF, true);
+        harness.cpu.set_flag(ezpc::cpu::Cpu::TF, true);
+        harness.cpu.set_flag(ezpc::cpu::Cpu::CF, true);
+        harness
+    };
+
+    let mut by_instruction = setup();
+    by_instruction.step();
+
+    // Inject at the address INT would return to
+    let mut injected = setup();
+    injected.cpu.ip = 0x0002;
+    injected.cpu.interrupt(&mut injected.mem, 0x21);
+
+    for harness in [&mut by_instruction, &mut injected] {
+        assert_eq!(harness.cpu.read_seg(1), 0x0300); // CS
+        assert_eq!(harness.cpu.ip, 0x0100);
+        assert_eq!(harness.cpu.regs[4], 0x1FFA); // SP
+        assert!(!harness.cpu.get_flag(ezpc::cpu::Cpu::IF));
+        assert!(!harness.cpu.get_flag(ezpc::cpu::Cpu::TF));
+    }
+    let stack = |harness: &CpuHarness| {
+        let base = (0x0200_u32 << 4) + 0x1FFA;
+        [
+            harness.mem.read_u16(base),     // IP
+            harness.mem.read_u16(base + 2), // CS
+            harness.mem.read_u16(base + 4), // FLAGS
+        ]
+    };
+    assert_eq!(stack(&by_instruction), stack(&injected));
+    assert_eq!(stack(&injected)[..2], [0x0002, 0x0100]);
+    assert_eq!(by_instruction.cpu.get_flags(), injected.cpu.get_flags());
+}
+
 #[test]
 fn test_hardware_interrupt_from_pic() {
     let mut harness = CpuHarness::new();
@@ -1390,6 +1799,75 @@ fn test_sti_no_delay_when_already_enabled() {
     assert_eq!(harness.cpu.read_seg(1), 0x0000);
 }
 
+/// Set up IRQ0 (INT 0x08) to a handler at 0x0100:0x1000 that just IRETs,
+/// with interrupts enabled and IRQ0 already pending
+fn setup_pending_irq0(harness: &mut CpuHarness) {
+    harness.mem.write_u16(0x20, 0x1000); // Offset
+    harness.mem.write_u16(0x22, 0x0100); // Segment
+    harness.mem.write_u8(0x01000 + 0x1000, 0xCF); // IRET
+
+    harness.cpu.regs[4] = 0x2000; // SP
+    harness.cpu.set_flag(ezpc::cpu::Cpu::IF, true);
+
+    harness.mem.pic_mut().set_imr(0x00);
+    harness.mem.pic_mut().set_irq_level(0, false);
+    harness.mem.pic_mut().set_irq_level(0, true);
+    assert!(harness.mem.pic().intr_out());
+}
+
+#[test]
+fn test_hardware_interrupt_after_segment_override_instruction() {
+    let mut harness = CpuHarness::new();
+
+    // ES: MOV AX, [0x0010]
+    harness.load_program(&[0x26, 0xA1, 0x10, 0x00], 0);
+    harness.cpu.write_seg(0, 0x0200); // ES
+    harness.mem.write_u16(0x2010, 0x5678);
+    setup_pending_irq0(&mut harness);
+
+    // The prefixed MOV completes before the interrupt is taken
+    harness.step();
+    assert_eq!(harness.cpu.regs[0], 0x5678);
+    assert_eq!(harness.cpu.read_seg(1), 0x0100);
+    assert_eq!(harness.cpu.ip, 0x1000);
+
+    // Return address is past the whole instruction, not the prefix
+    assert_eq!(harness.mem.read_u16(0x1FFA), 0x0004); // Pushed IP
+}
+
+#[test]
+fn test_hardware_interrupt_after_repeated_segment_override() {
+    let mut harness = CpuHarness::new();
+
+    // ES: ES: MOV AX, [0x0010]
+    harness.load_program(&[0x26, 0x26, 0xA1, 0x10, 0x00], 0);
+    harness.cpu.write_seg(0, 0x0200); // ES
+    harness.mem.write_u16(0x2010, 0x5678);
+    setup_pending_irq0(&mut harness);
+
+    harness.step();
+    assert_eq!(harness.cpu.regs[0], 0x5678);
+    assert_eq!(harness.cpu.ip, 0x1000);
+    assert_eq!(harness.mem.read_u16(0x1FFA), 0x0005); // Pushed IP
+}
+
+#[test]
+fn test_hardware_interrupt_after_rep_with_segment_override() {
+    let mut harness = CpuHarness::new();
+
+    // REP CS: LODSB with CX=1: a single iteration loads AL from CS:SI
+    harness.load_program(&[0xF3, 0x2E, 0xAC], 0);
+    harness.cpu.regs[1] = 1; // CX
+    harness.cpu.regs[6] = 0x0002; // SI points at the LODSB opcode
+    setup_pending_irq0(&mut harness);
+
+    harness.step();
+    assert_eq!(harness.cpu.read_reg8(0), 0xAC); // AL
+    assert_eq!(harness.cpu.regs[1], 0); // CX
+    assert_eq!(harness.cpu.ip, 0x1000);
+    assert_eq!(harness.mem.read_u16(0x1FFA), 0x0003); // Pushed IP
+}
+
 #[test]
 fn test_jmp_far() {
     let mut harness = CpuHarness::new();
@@ -1479,3 +1957,63 @@ fn test_jmp_far_indirect_direct_address() {
     assert_eq!(harness.cpu.segments[1], 0x3000); // CS
     assert_eq!(harness.cpu.ip, 0x0200);
 }
+
+#[test]
+fn test_jmp_far_indirect_es_bx() {
+    let mut harness = CpuHarness::new();
+    // JMP FAR ES:[BX] - the pointer must come from ES:BX, not DS:BX
+
+    harness.cpu.segments[0] = 0x0080; // ES
+    harness.cpu.segments[3] = 0x0000; // DS
+    harness.cpu.regs[3] = 0x0010; // BX
+
+    // Pointer at ES:BX (linear 0x0810): 0x0040:0x0100
+    harness.mem.write_u16(0x0810, 0x0100); // IP
+    harness.mem.write_u16(0x0812, 0x0040); // CS
+
+    // Decoy pointer at DS:BX (linear 0x0010)
+    harness.mem.write_u16(0x0010, 0x0BAD);
+    harness.mem.write_u16(0x0012, 0x0BAD);
+
+    // ES: JMP FAR [BX]: 0x26 0xFF 0x2F
+    // ModR/M: mod=00, reg=101 (JMP FAR), r/m=111 ([BX])
+    harness.load_program(&[0x26, 0xFF, 0x2F], 0);
+
+    harness.step(); // ES: JMP FAR [BX]
+
+    assert_eq!(harness.cpu.segments[1], 0x0040); // CS
+    assert_eq!(harness.cpu.ip, 0x0100);
+}
+
+#[test]
+fn test_call_far_indirect_ss_bx_disp() {
+    let mut harness = CpuHarness::new();
+    // CALL FAR SS:[BX+0x20] - the pointer must come from SS, not DS
+
+    harness.cpu.segments[2] = 0x0090; // SS
+    harness.cpu.segments[3] = 0x0000; // DS
+    harness.cpu.regs[3] = 0x0010; // BX
+    harness.cpu.regs[4] = 0x0100; // SP
+
+    // Pointer at SS:BX+0x20 (linear 0x0930): 0x0050:0x0004
+    harness.mem.write_u16(0x0930, 0x0004); // IP
+    harness.mem.write_u16(0x0932, 0x0050); // CS
+
+    // Decoy pointer at DS:BX+0x20 (linear 0x0030)
+    harness.mem.write_u16(0x0030, 0x0BAD);
+    harness.mem.write_u16(0x0032, 0x0BAD);
+
+    // SS: CALL FAR [BX+0x20]: 0x36 0xFF 0x5F 0x20
+    // ModR/M: mod=01, reg=011 (CALL FAR), r/m=111 ([BX+disp8])
+    harness.load_program(&[0x36, 0xFF, 0x5F, 0x20], 0);
+
+    harness.step(); // SS: CALL FAR [BX+0x20]
+
+    assert_eq!(harness.cpu.segments[1], 0x0050); // CS
+    assert_eq!(harness.cpu.ip, 0x0004);
+
+    // Return address pushed onto SS:SP
+    assert_eq!(harness.cpu.regs[4], 0x00FC);
+    assert_eq!(harness.mem.read_u16(0x09FC), 4); // IP after the instruction
+    assert_eq!(harness.mem.read_u16(0x09FE), 0); // Original CS
+}
@@ -0,0 +1,120 @@
+//! ESC/WAIT and coprocessor attachment tests
+
+use ezpc::cpu::decode::{DecodedInstruction, OperandType};
+use ezpc::cpu::{Coprocessor, Cpu, CpuHarness};
+use ezpc::memory::MemoryBus;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// What the stub saw for one ESC instruction
+#[derive(Debug, Clone, PartialEq)]
+struct EscRecord {
+    opcode: u8,
+    modrm: u8,
+    rm_type: OperandType,
+    disp: i16,
+}
+
+/// Coprocessor that records every ESC it receives
+struct StubCoprocessor {
+    received: Rc<RefCell<Vec<EscRecord>>>,
+    busy_polls: Rc<RefCell<u32>>,
+}
+
+impl Coprocessor for StubCoprocessor {
+    fn execute(&mut self, instr: &DecodedInstruction, _cpu: &mut Cpu, _mem: &mut MemoryBus) {
+        self.received.borrow_mut().push(EscRecord {
+            opcode: instr.opcode,
+            modrm: instr.src.value as u8,
+            rm_type: instr.dst.op_type,
+            disp: instr.dst.disp,
+        });
+    }
+
+    fn busy(&self) -> bool {
+        // Busy for the first two polls, then done
+        let mut polls = self.busy_polls.borrow_mut();
+        *polls += 1;
+        *polls <= 2
+    }
+}
+
+fn attach_stub(harness: &mut CpuHarness) -> (Rc<RefCell<Vec<EscRecord>>>, Rc<RefCell<u32>>) {
+    let received = Rc::new(RefCell::new(Vec::new()));
+    let busy_polls = Rc::new(RefCell::new(0));
+    harness.cpu.attach_coprocessor(Box::new(StubCoprocessor {
+        received: received.clone(),
+        busy_polls: busy_polls.clone(),
+    }));
+    (received, busy_polls)
+}
+
+#[test]
+fn test_esc_without_coprocessor_is_noop() {
+    let mut harness = CpuHarness::new();
+    // FLD qword [0x1234] (DD 06 34 12); FNINIT (DB E3); MOV AX, 1
+    harness.load_program(&[0xDD, 0x06, 0x34, 0x12, 0xDB, 0xE3, 0xB8, 0x01, 0x00], 0);
+
+    harness.step();
+    assert_eq!(harness.cpu.ip, 4);
+    harness.step();
+    assert_eq!(harness.cpu.ip, 6);
+    harness.step();
+    assert_eq!(harness.cpu.regs[0], 1);
+}
+
+#[test]
+fn test_esc_routed_to_coprocessor() {
+    let mut harness = CpuHarness::new();
+    let (received, _) = attach_stub(&mut harness);
+
+    // FLD qword [0x1234] (DD 06 34 12); FADD ST, ST(1) (D8 C1)
+    harness.load_program(&[0xDD, 0x06, 0x34, 0x12, 0xD8, 0xC1], 0);
+    harness.step_n(2);
+
+    let received = received.borrow();
+    assert_eq!(received.len(), 2);
+    assert_eq!(
+        received[0],
+        EscRecord {
+            opcode: 0xDD,
+            modrm: 0x06,
+            rm_type: OperandType::Mem16,
+            disp: 0x1234,
+        }
+    );
+    assert_eq!(received[1].opcode, 0xD8);
+    assert_eq!(received[1].modrm, 0xC1);
+    assert_eq!(received[1].rm_type, OperandType::Reg16);
+    assert_eq!(harness.cpu.ip, 6);
+}
+
+#[test]
+fn test_wait_stalls_while_coprocessor_busy() {
+    let mut harness = CpuHarness::new();
+    let (_, busy_polls) = attach_stub(&mut harness);
+
+    // WAIT; MOV AX, 1
+    harness.load_program(&[0x9B, 0xB8, 0x01, 0x00], 0);
+
+    // Busy: WAIT stays on itself
+    harness.step();
+    assert_eq!(harness.cpu.ip, 0);
+    harness.step();
+    assert_eq!(harness.cpu.ip, 0);
+
+    // Ready: WAIT completes
+    harness.step();
+    assert_eq!(harness.cpu.ip, 1);
+    assert_eq!(*busy_polls.borrow(), 3);
+}
+
+#[test]
+fn test_wait_without_coprocessor_does_not_stall() {
+    let mut harness = CpuHarness::new();
+    // WAIT; MOV AX, 1
+    harness.load_program(&[0x9B, 0xB8, 0x01, 0x00], 0);
+
+    harness.step();
+    assert_eq!(harness.cpu.ip, 1);
+}
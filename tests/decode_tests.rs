@@ -1,7 +1,7 @@
 //! Tests for instruction decoding (ModR/M and operand decoding)
 
-use ezpc::cpu::decode::{AddressingMode, ModRM, Operand, OperandType};
-use ezpc::cpu::Cpu;
+use ezpc::cpu::decode::{decode_one, AddressingMode, ModRM, Operand, OperandType};
+use ezpc::cpu::{Cpu, CpuHarness, ExceptionKind};
 use ezpc::memory::MemoryBus;
 
 #[test]
@@ -413,3 +413,92 @@ fn test_operand_constructors() {
     assert_eq!(op.op_type, OperandType::SegReg);
     assert_eq!(op.value, 2);
 }
+
+// ===== decode_one (fuzzing entry point) =====
+
+#[test]
+fn test_decode_one_valid_instruction() {
+    // MOV AX, [BX]
+    let instr = decode_one(&[0x8B, 0x07]).unwrap();
+    assert_eq!(instr.opcode, 0x8B);
+    assert_eq!(instr.length, 2);
+}
+
+#[test]
+fn test_decode_one_truncated_is_none() {
+    assert!(decode_one(&[]).is_none());
+    assert!(decode_one(&[0x8B]).is_none()); // missing ModR/M
+    assert!(decode_one(&[0xB8, 0x34]).is_none()); // MOV AX, imm16 missing a byte
+    assert!(decode_one(&[0x81, 0x06, 0x00, 0x10, 0x34]).is_none()); // ADD [disp16], imm16
+}
+
+#[test]
+fn test_decode_one_invalid_is_none() {
+    assert!(decode_one(&[0x60]).is_none()); // PUSHA (80186)
+    assert!(decode_one(&[0xFF, 0xF8]).is_none()); // 0xFF /7
+    assert!(decode_one(&[0xFE, 0xD0]).is_none()); // 0xFE /2
+    assert!(decode_one(&[0x8F, 0xC8]).is_none()); // 0x8F /1
+    assert!(decode_one(&[0x8D, 0xC0]).is_none()); // LEA AX, AX
+    assert!(decode_one(&[0xC4, 0xC0]).is_none()); // LES AX, AX
+    assert!(decode_one(&[0xFF, 0xD8]).is_none()); // CALL FAR AX
+    assert!(decode_one(&[0xFF, 0xE8]).is_none()); // JMP FAR AX
+}
+
+#[test]
+fn test_decode_one_validity_matches_execution() {
+    // Every reg field, in register and memory form
+    let modrms = (0..8u8).flat_map(|reg| [reg << 3, 0xC0 | (reg << 3)]);
+    // Prefixes execute together with the instruction after them
+    let prefixes = [0x26, 0x2E, 0x36, 0x3E, 0xF2, 0xF3];
+    for opcode in (0..=0xFFu8).filter(|opcode| !prefixes.contains(opcode)) {
+        for modrm in modrms.clone() {
+            let bytes = [opcode, modrm, 0x12, 0x34, 0x56, 0x78];
+
+            let mut harness = CpuHarness::new();
+            harness.load_program(&bytes, 0x0100);
+            harness.cpu.set_trap_exceptions(true);
+            harness.step();
+            let executes = !matches!(
+                harness.cpu.take_exception().map(|exception| exception.kind),
+                Some(ExceptionKind::InvalidOpcode(_))
+            );
+
+            assert_eq!(
+                decode_one(&bytes).is_some(),
+                executes,
+                "{:02X} {:02X}",
+                opcode,
+                modrm
+            );
+        }
+    }
+}
+
+#[test]
+fn test_decode_one_every_opcode_and_modrm_does_not_panic() {
+    for opcode in 0..=0xFFu8 {
+        for modrm in 0..=0xFFu8 {
+            decode_one(&[opcode, modrm, 0x12, 0x34, 0x56, 0x78]);
+        }
+    }
+}
+
+#[test]
+fn test_decode_one_random_bytes_do_not_panic() {
+    // xorshift32, fixed seed so failures reproduce
+    let mut state = 0x2545_F491u32;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+
+    for _ in 0..20_000 {
+        let len = (next() % 7) as usize;
+        let bytes: Vec<u8> = (0..len).map(|_| next() as u8).collect();
+        if let Some(instr) = decode_one(&bytes) {
+            assert!(instr.length as usize <= bytes.len());
+        }
+    }
+}
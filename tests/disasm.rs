@@ -0,0 +1,96 @@
+//! Disassembler tests
+
+use ezpc::cpu::disasm::{disassemble, disassemble_at, flag_names};
+use ezpc::cpu::Cpu;
+use ezpc::memory::MemoryBus;
+
+fn text(bytes: &[u8]) -> String {
+    disassemble(bytes, 0x0100).unwrap().text
+}
+
+#[test]
+fn test_add_annotation_lists_arithmetic_flags() {
+    // ADD AX, BX
+    let add = disassemble(&[0x01, 0xD8], 0).unwrap();
+    assert_eq!(add.text, "ADD AX, BX");
+    assert_eq!(add.annotated(), "ADD AX, BX ; affects CF,PF,AF,ZF,SF,OF");
+}
+
+#[test]
+fn test_mov_annotation_lists_no_flags() {
+    // MOV AX, BX
+    let mov = disassemble(&[0x89, 0xD8], 0).unwrap();
+    assert_eq!(mov.flags_affected, 0);
+    assert_eq!(mov.annotated(), "MOV AX, BX");
+}
+
+#[test]
+fn test_group_flags_depend_on_reg_field() {
+    // INC BYTE PTR [BX] leaves CF alone
+    let inc = disassemble(&[0xFE, 0x07], 0).unwrap();
+    assert_eq!(inc.text, "INC BYTE PTR [BX]");
+    assert_eq!(flag_names(inc.flags_affected), "PF,AF,ZF,SF,OF");
+
+    // ROL AL, 1 only affects CF and OF
+    let rol = disassemble(&[0xD0, 0xC0], 0).unwrap();
+    assert_eq!(rol.text, "ROL AL, 1");
+    assert_eq!(flag_names(rol.flags_affected), "CF,OF");
+
+    // STD, POPF
+    assert_eq!(disassemble(&[0xFD], 0).unwrap().flags_affected, Cpu::DF);
+    assert_eq!(
+        flag_names(disassemble(&[0x9D], 0).unwrap().flags_affected),
+        "CF,PF,AF,ZF,SF,OF,TF,IF,DF"
+    );
+}
+
+#[test]
+fn test_memory_operands() {
+    assert_eq!(text(&[0x8B, 0x40, 0x10]), "MOV AX, [BX+SI+0x10]");
+    assert_eq!(text(&[0x8A, 0x46, 0xFE]), "MOV AL, [BP-0x02]");
+    assert_eq!(text(&[0x89, 0x1E, 0x34, 0x12]), "MOV [0x1234], BX");
+    assert_eq!(
+        text(&[0xC7, 0x87, 0x00, 0x01, 0xCD, 0xAB]),
+        "MOV WORD PTR [BX+0x0100], 0xABCD"
+    );
+    assert_eq!(text(&[0x26, 0xA1, 0x10, 0x00]), "MOV AX, ES:[0x0010]");
+    assert_eq!(text(&[0x83, 0xEC, 0xFE]), "SUB SP, 0xFFFE");
+    assert_eq!(text(&[0xFF, 0x1F]), "CALL DWORD PTR [BX]");
+}
+
+#[test]
+fn test_relative_targets_and_far_pointers() {
+    // At 0x0100: JMP short -2 (to itself), JZ +5, CALL +0x10
+    assert_eq!(text(&[0xEB, 0xFE]), "JMP 0x0100");
+    assert_eq!(text(&[0x74, 0x05]), "JZ 0x0107");
+    assert_eq!(text(&[0xE8, 0x10, 0x00]), "CALL 0x0113");
+    assert_eq!(text(&[0xEA, 0x5B, 0xE0, 0x00, 0xF0]), "JMP F000:E05B");
+}
+
+#[test]
+fn test_prefixes_and_lengths() {
+    let rep = disassemble(&[0xF3, 0xA4], 0).unwrap();
+    assert_eq!(rep.text, "REP MOVSB");
+    assert_eq!(rep.length, 2);
+
+    assert_eq!(text(&[0xF3, 0xA6]), "REPE CMPSB");
+    assert_eq!(text(&[0xF2, 0xAF]), "REPNE SCASW");
+
+    // Not 8088 instructions
+    assert_eq!(text(&[0x60]), "DB 0x60");
+    assert_eq!(text(&[0x0F]), "DB 0x0F");
+
+    // Truncated instruction
+    assert_eq!(disassemble(&[0xB8, 0x34], 0), None);
+}
+
+#[test]
+fn test_disassemble_at_reads_memory() {
+    let mut mem = MemoryBus::new();
+    // 0010:0004: ADD AL, 0x05
+    mem.load(&[0x04, 0x05], 0x0104);
+
+    let instr = disassemble_at(&mem, 0x0010, 0x0004);
+    assert_eq!(instr.text, "ADD AL, 0x05");
+    assert_eq!(instr.length, 2);
+}
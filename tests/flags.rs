@@ -177,6 +177,72 @@ fn test_flags_dont_affect_other_flags() {
     assert!(harness.cpu.get_flag(Cpu::SF)); // Unchanged
 }
 
+#[test]
+fn test_flag_control_changes_only_its_flag() {
+    // (opcode, flag, value the instruction leaves it at)
+    let cases = [
+        (0xF8, Cpu::CF, false),
+        (0xF9, Cpu::CF, true),
+        (0xFA, Cpu::IF, false),
+        (0xFB, Cpu::IF, true),
+        (0xFC, Cpu::DF, false),
+        (0xFD, Cpu::DF, true),
+    ];
+    let all =
+        Cpu::CF | Cpu::PF | Cpu::AF | Cpu::ZF | Cpu::SF | Cpu::TF | Cpu::IF | Cpu::DF | Cpu::OF;
+
+    for (opcode, flag, value) in cases {
+        for start in [Cpu::FLAGS_ALWAYS_SET, Cpu::FLAGS_ALWAYS_SET | all] {
+            let mut harness = CpuHarness::new();
+            harness.cpu.set_flags(start);
+            harness.load_program(&[opcode], 0);
+            harness.step();
+
+            let expected = if value { start | flag } else { start & !flag };
+            assert_eq!(
+                harness.cpu.get_flags(),
+                expected,
+                "opcode {:#04X} from flags {:#06X}",
+                opcode,
+                start
+            );
+        }
+    }
+}
+
+#[test]
+fn test_cmc_complements_only_cf() {
+    let all =
+        Cpu::CF | Cpu::PF | Cpu::AF | Cpu::ZF | Cpu::SF | Cpu::TF | Cpu::IF | Cpu::DF | Cpu::OF;
+
+    for start in [
+        Cpu::FLAGS_ALWAYS_SET,
+        Cpu::FLAGS_ALWAYS_SET | all,
+        Cpu::FLAGS_ALWAYS_SET | (all & !Cpu::CF),
+    ] {
+        let mut harness = CpuHarness::new();
+        harness.cpu.set_flags(start);
+        harness.load_program(&[0xF5], 0); // CMC
+        harness.step();
+
+        assert_eq!(harness.cpu.get_flags(), start ^ Cpu::CF);
+    }
+}
+
+#[test]
+fn test_std_cld_set_string_direction() {
+    let mut harness = CpuHarness::new();
+    // STD; LODSB; CLD; LODSB
+    harness.load_program(&[0xFD, 0xAC, 0xFC, 0xAC], 0);
+    harness.cpu.regs[6] = 0x1000; // SI
+
+    harness.step_n(2); // STD; LODSB
+    assert_eq!(harness.cpu.regs[6], 0x0FFF); // SI decremented
+
+    harness.step_n(2); // CLD; LODSB
+    assert_eq!(harness.cpu.regs[6], 0x1000); // SI incremented
+}
+
 #[test]
 fn test_pushf() {
     let mut harness = CpuHarness::new();
@@ -341,6 +407,68 @@ fn test_sahf_lahf_roundtrip() {
     assert_eq!(ah_after, 0xC7);
 }
 
+#[test]
+fn test_lahf_sahf_restores_lazy_low_flags_only() {
+    let mut harness = CpuHarness::new();
+    harness.load_program(
+        &[
+            0xB0, 0x01, // MOV AL, 1
+            0x3C, 0x02, // CMP AL, 2 (flags still lazy)
+            0x9F, // LAHF
+            0xB3, 0x7F, // MOV BL, 0x7F
+            0x80, 0xC3, 0x01, // ADD BL, 1 (clobbers flags, sets OF)
+            0xFD, // STD
+            0x9E, // SAHF
+        ],
+        0,
+    );
+
+    harness.step_n(3);
+    // 1 - 2: SF=1 ZF=0 AF=1 PF=1 CF=1, plus the fixed bit 1
+    assert_eq!(harness.cpu.read_reg8(4), 0x97);
+
+    harness.step_n(3);
+    assert!(!harness.cpu.get_flag(Cpu::CF));
+    assert!(!harness.cpu.get_flag(Cpu::PF));
+
+    harness.step(); // SAHF
+    assert_eq!(harness.cpu.get_flags() & 0xFF, 0x97);
+    // High byte keeps what the clobbering instructions left
+    assert!(harness.cpu.get_flag(Cpu::OF));
+    assert!(harness.cpu.get_flag(Cpu::DF));
+}
+
+#[test]
+fn test_popf_restores_if_and_df_after_changes() {
+    let mut harness = CpuHarness::new();
+    harness.load_program(
+        &[
+            0xBC, 0x00, 0x10, // MOV SP, 0x1000
+            0xFB, // STI
+            0xFD, // STD
+            0x9C, // PUSHF
+            0xFA, // CLI
+            0xFC, // CLD
+            0x9D, // POPF
+        ],
+        0,
+    );
+
+    harness.step_n(4);
+    let pushed = harness.cpu.get_flags();
+    assert_eq!(harness.mem.read_u16(0x0FFE), pushed);
+
+    harness.step_n(2);
+    assert!(!harness.cpu.get_flag(Cpu::IF));
+    assert!(!harness.cpu.get_flag(Cpu::DF));
+
+    harness.step(); // POPF
+    assert!(harness.cpu.get_flag(Cpu::IF));
+    assert!(harness.cpu.get_flag(Cpu::DF));
+    assert_eq!(harness.cpu.get_flags(), pushed);
+    assert_eq!(harness.cpu.regs[4], 0x1000);
+}
+
 #[test]
 fn test_pushf_popf_preserves_all_flags() {
     let mut harness = CpuHarness::new();
@@ -1,8 +1,14 @@
 //! Tests for IO instructions (IN/OUT)
 
+use ezpc::components::dma::DmaCapable;
+use ezpc::components::uart::Uart;
 use ezpc::cpu::harness::CpuHarness;
-use ezpc::io::IoDevice;
+use ezpc::io::{IoDevice, IoWidth};
+use ezpc::memory::MemoryBus;
+use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::ops::RangeInclusive;
+use std::rc::Rc;
 
 /// Simple test device that echoes writes back on reads
 struct TestDevice {
@@ -282,3 +288,187 @@ fn test_write_to_unmapped_port() {
     harness.step();
     // No assertion - just ensuring it doesn't crash
 }
+
+/// Device at 0x300-0x301 that logs every access it sees
+struct LoggingDevice {
+    width: IoWidth,
+    byte_writes: Vec<(u16, u8)>,
+    word_writes: Vec<(u16, u16)>,
+}
+
+impl LoggingDevice {
+    fn new(width: IoWidth) -> Self {
+        Self {
+            width,
+            byte_writes: Vec::new(),
+            word_writes: Vec::new(),
+        }
+    }
+}
+
+impl IoDevice for LoggingDevice {
+    fn port_range(&self) -> RangeInclusive<u16> {
+        0x300..=0x301
+    }
+
+    fn io_width(&self) -> IoWidth {
+        self.width
+    }
+
+    fn read_u8(&mut self, _port: u16) -> u8 {
+        0x00
+    }
+
+    fn write_u8(&mut self, port: u16, value: u8) {
+        self.byte_writes.push((port, value));
+    }
+
+    fn write_u16(&mut self, port: u16, value: u16) {
+        self.word_writes.push((port, value));
+    }
+}
+
+/// Run MOV DX, 0x300; MOV AX, 0xBEEF; OUT DX, AX against a logging device
+fn out_dx_ax_to(width: IoWidth) -> Rc<RefCell<LoggingDevice>> {
+    let mut harness = CpuHarness::new();
+    let device = harness.mem.attach_device(LoggingDevice::new(width));
+
+    harness.load_program(
+        &[
+            0xBA, 0x00, 0x03, // MOV DX, 0x300
+            0xB8, 0xEF, 0xBE, // MOV AX, 0xBEEF
+            0xEF, // OUT DX, AX
+        ],
+        0,
+    );
+    harness.step_n(3);
+
+    device
+}
+
+#[test]
+fn test_out_dx_ax_splits_for_8bit_device() {
+    let device = out_dx_ax_to(IoWidth::Byte);
+
+    assert_eq!(
+        device.borrow().byte_writes,
+        vec![(0x300, 0xEF), (0x301, 0xBE)]
+    );
+    assert!(device.borrow().word_writes.is_empty());
+}
+
+#[test]
+fn test_out_dx_ax_whole_for_16bit_device() {
+    let device = out_dx_ax_to(IoWidth::Word);
+
+    assert_eq!(device.borrow().word_writes, vec![(0x300, 0xBEEF)]);
+    assert!(device.borrow().byte_writes.is_empty());
+}
+
+#[test]
+#[should_panic(expected = "Invalid opcode: 0x6e")]
+fn test_outsb_is_invalid_on_8088() {
+    let mut harness = CpuHarness::new();
+    harness.mem.register_io_device(Box::new(TestDevice::new()));
+
+    // MOV DX, 0x55; OUTSB
+    harness.load_program(&[0xBA, 0x55, 0x00, 0x6E], 0);
+
+    harness.step(); // MOV DX, 0x55
+    harness.step(); // OUTSB (80186+)
+}
+
+/// Device that feeds queued bytes to DMA and records what it is sent
+#[derive(Default)]
+struct DmaTestDevice {
+    to_memory: VecDeque<u8>,
+    terminal_counts: usize,
+}
+
+impl DmaCapable for DmaTestDevice {
+    fn dma_dreq(&self) -> bool {
+        !self.to_memory.is_empty()
+    }
+
+    fn dma_read_byte(&mut self) -> Option<u8> {
+        self.to_memory.pop_front()
+    }
+
+    fn dma_write_byte(&mut self, _value: u8) {}
+
+    fn dma_terminal_count(&mut self) {
+        self.terminal_counts += 1;
+    }
+}
+
+#[test]
+fn test_dma_channel_programmed_through_ports() {
+    let mut mem = MemoryBus::new();
+    mem.io_write_u8(0x0B, 0x45); // Channel 1: single, increment, write to memory
+    mem.io_write_u8(0x0C, 0x00); // Clear flip-flop
+    mem.io_write_u8(0x02, 0x00); // Address 0x0500
+    mem.io_write_u8(0x02, 0x05);
+    mem.io_write_u8(0x03, 0x02); // Count 2 = three bytes
+    mem.io_write_u8(0x03, 0x00);
+    mem.io_write_u8(0x83, 0x00); // Page
+    mem.io_write_u8(0x0A, 0x01); // Unmask channel 1
+    mem.dma_mut().set_dreq(1, true);
+
+    let mut device = DmaTestDevice {
+        to_memory: VecDeque::from([0xA1, 0xB2, 0xC3, 0xD4]),
+        ..Default::default()
+    };
+    assert_eq!(mem.dma_transfer_byte(1, &mut device), Some(false));
+    assert_eq!(mem.dma_transfer_byte(1, &mut device), Some(false));
+    assert_eq!(mem.dma_transfer_byte(1, &mut device), Some(true));
+
+    assert_eq!(
+        [
+            mem.read_u8(0x0500),
+            mem.read_u8(0x0501),
+            mem.read_u8(0x0502)
+        ],
+        [0xA1, 0xB2, 0xC3]
+    );
+    assert_eq!(device.terminal_counts, 1);
+
+    // Address advanced past the block, count wrapped, TC latched in status
+    mem.io_write_u8(0x0C, 0x00);
+    let addr = mem.io_read_u8(0x02) as u16 | (mem.io_read_u8(0x02) as u16) << 8;
+    let count = mem.io_read_u8(0x03) as u16 | (mem.io_read_u8(0x03) as u16) << 8;
+    assert_eq!((addr, count), (0x0503, 0xFFFF));
+    assert_ne!(mem.io_read_u8(0x08) & 0x02, 0);
+
+    // The channel masked itself at TC
+    assert_eq!(mem.dma_transfer_byte(1, &mut device), None);
+    assert_eq!(mem.read_u8(0x0503), 0x00);
+}
+
+#[test]
+fn test_guest_writes_com1_and_reads_host_input() {
+    let mut harness = CpuHarness::new();
+    let sent = Rc::new(RefCell::new(Vec::new()));
+    let sink_sent = sent.clone();
+    let mut input = VecDeque::from([b'?']);
+    harness.mem.attach_device(Uart::com1(
+        Box::new(move |byte| sink_sent.borrow_mut().push(byte)),
+        Box::new(move || input.pop_front()),
+    ));
+
+    // MOV DX, 0x3F8; MOV AL, 'A'; OUT DX, AL; ADD DL, 5; IN AL, DX;
+    // MOV AH, AL; SUB DL, 5; IN AL, DX
+    harness.load_program(
+        &[
+            0xBA, 0xF8, 0x03, 0xB0, 0x41, 0xEE, 0x80, 0xC2, 0x05, 0xEC, 0x88, 0xC4, 0x80, 0xEA,
+            0x05, 0xEC,
+        ],
+        0,
+    );
+    harness.step_n(3);
+    assert_eq!(*sent.borrow(), b"A");
+
+    harness.mem.tick(1);
+    harness.step_n(5);
+    assert_eq!(harness.cpu.read_reg8(4) & 0x01, 0x01, "LSR data ready");
+    assert_eq!(harness.cpu.read_reg8(0), b'?');
+}
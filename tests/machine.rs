@@ -0,0 +1,2119 @@
+//! Tests for the headless Machine
+
+use ezpc::components::cga::{self, CgaMode};
+use ezpc::components::expanded_memory::ExpandedMemory;
+use ezpc::components::floppy::{DiskGeometry, FloppyDisk};
+use ezpc::components::hdd::{HardDisk, HddGeometry, HDD_SECTOR_SIZE};
+use ezpc::components::ide::IDE_IRQ;
+use ezpc::cpu::{Cpu, Exception, ExceptionKind};
+use ezpc::io::{DeviceState, IoDevice};
+use ezpc::machine::{
+    HaltOutcome, Machine, MachineConfig, MachineEvent, ReturnMismatch, VideoAdapter,
+    BDA_EQUIPMENT_WORD, CYCLES_PER_FRAME,
+};
+use ezpc::memory::MemRegionKind;
+use ezpc::snapshot::{InputEvent, MemoryChange, RegisterChange};
+use std::cell::RefCell;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Minimal joystick-like device with a host-settable axis value
+struct AxisDevice {
+    axis: u8,
+    /// Last value written by the guest (starts the one-shot on real hardware)
+    last_write: Option<u8>,
+}
+
+impl IoDevice for AxisDevice {
+    fn port_range(&self) -> RangeInclusive<u16> {
+        0x201..=0x201
+    }
+
+    fn read_u8(&mut self, _port: u16) -> u8 {
+        self.axis
+    }
+
+    fn write_u8(&mut self, _port: u16, value: u8) {
+        self.last_write = Some(value);
+    }
+}
+
+/// Device with a non-zero power-on register value
+struct ResettableDevice {
+    value: u8,
+}
+
+impl IoDevice for ResettableDevice {
+    fn port_range(&self) -> RangeInclusive<u16> {
+        0x300..=0x300
+    }
+
+    fn read_u8(&mut self, _port: u16) -> u8 {
+        self.value
+    }
+
+    fn write_u8(&mut self, _port: u16, value: u8) {
+        self.value = value;
+    }
+
+    fn reset(&mut self) {
+        self.value = 0xFF;
+    }
+}
+
+#[test]
+fn test_attach_device_handle_visible_to_in() {
+    let mut machine = Machine::new();
+    let handle = machine.attach_device(AxisDevice {
+        axis: 0x00,
+        last_write: None,
+    });
+
+    // Program: MOV DX, 0x201; IN AL, DX
+    machine.memory.load(&[0xBA, 0x01, 0x02, 0xEC], 0);
+    machine.cpu.segments[1] = 0;
+    machine.cpu.ip = 0;
+
+    // Host pokes the device through the handle
+    handle.borrow_mut().axis = 0x5A;
+
+    machine.step(); // MOV DX, 0x201
+    machine.step(); // IN AL, DX
+    assert_eq!(machine.cpu.read_reg8(0), 0x5A);
+}
+
+#[test]
+fn test_attach_device_handle_sees_bus_writes() {
+    let mut machine = Machine::new();
+    let handle = machine.attach_device(AxisDevice {
+        axis: 0x00,
+        last_write: None,
+    });
+
+    // Program: MOV DX, 0x201; MOV AL, 0xFF; OUT DX, AL
+    machine
+        .memory
+        .load(&[0xBA, 0x01, 0x02, 0xB0, 0xFF, 0xEE], 0);
+    machine.cpu.segments[1] = 0;
+    machine.cpu.ip = 0;
+
+    machine.step(); // MOV DX, 0x201
+    machine.step(); // MOV AL, 0xFF
+    machine.step(); // OUT DX, AL
+    assert_eq!(handle.borrow().last_write, Some(0xFF));
+}
+
+#[test]
+fn test_reset_restores_pic_mask() {
+    let mut machine = Machine::new();
+
+    // Unmask all IRQs through the PIC data port
+    machine.memory.io_write_u8(0x21, 0x00);
+    assert_eq!(machine.memory.io_read_u8(0x21), 0x00);
+
+    machine.reset();
+
+    // Power-on state: all IRQs masked
+    assert_eq!(machine.memory.io_read_u8(0x21), 0xFF);
+}
+
+#[test]
+fn test_reset_clears_pit_counter() {
+    let mut machine = Machine::new();
+
+    // Counter 0, low+high byte, mode 2, count 0x1234
+    machine.memory.io_write_u8(0x43, 0x34);
+    machine.memory.io_write_u8(0x40, 0x34);
+    machine.memory.io_write_u8(0x40, 0x12);
+
+    machine.reset();
+
+    // Latch counter 0 and read it back: power-on count is zero
+    machine.memory.io_write_u8(0x43, 0x00);
+    assert_eq!(machine.memory.io_read_u8(0x40), 0x00);
+    assert_eq!(machine.memory.io_read_u8(0x40), 0x00);
+}
+
+#[test]
+fn test_reset_restores_cpu_reset_vector() {
+    let mut machine = Machine::new();
+    machine.cpu.segments[1] = 0x1234;
+    machine.cpu.ip = 0x5678;
+
+    machine.reset();
+
+    assert_eq!(machine.cpu.segments[1], 0xF000);
+    assert_eq!(machine.cpu.ip, 0xFFF0);
+}
+
+#[test]
+fn test_reset_reaches_attached_devices() {
+    let mut machine = Machine::new();
+    let handle = machine.attach_device(ResettableDevice { value: 0x00 });
+
+    machine.memory.io_write_u8(0x300, 0x42);
+    assert_eq!(handle.borrow().value, 0x42);
+
+    machine.reset();
+
+    assert_eq!(handle.borrow().value, 0xFF);
+}
+
+#[test]
+fn test_install_handlers_writes_ivt() {
+    let mut machine = Machine::new();
+    machine.install_handlers(&[(0x21, 0x0050, 0x0010), (0x08, 0xF000, 0xFEA5)]);
+
+    assert_eq!(machine.memory.read_u16(0x21 * 4), 0x0010); // IP
+    assert_eq!(machine.memory.read_u16(0x21 * 4 + 2), 0x0050); // CS
+    assert_eq!(machine.memory.read_u16(0x08 * 4), 0xFEA5);
+    assert_eq!(machine.memory.read_u16(0x08 * 4 + 2), 0xF000);
+}
+
+#[test]
+fn test_install_handlers_int21_stub() {
+    let mut machine = Machine::new();
+
+    // Handler stub at 0050:0000 (linear 0x500): MOV AX, 0x4242; IRET
+    machine.load_at(0x0500, &[0xB8, 0x42, 0x42, 0xCF]);
+    machine.install_handlers(&[(0x21, 0x0050, 0x0000)]);
+
+    // Guest code at 0100:0000: INT 0x21; MOV BX, AX
+    machine.load_at(0x1000, &[0xCD, 0x21, 0x89, 0xC3]);
+    machine.cpu.segments[1] = 0x0100; // CS
+    machine.cpu.ip = 0x0000;
+    machine.cpu.segments[2] = 0x0000; // SS
+    machine.cpu.regs[4] = 0x0400; // SP
+
+    machine.step(); // INT 0x21
+    assert_eq!(machine.cpu.segments[1], 0x0050);
+    assert_eq!(machine.cpu.ip, 0x0000);
+
+    machine.step(); // MOV AX, 0x4242
+    machine.step(); // IRET
+    assert_eq!(machine.cpu.segments[1], 0x0100);
+    assert_eq!(machine.cpu.ip, 0x0002);
+
+    machine.step(); // MOV BX, AX
+    assert_eq!(machine.cpu.regs[3], 0x4242);
+    assert_eq!(machine.cpu.regs[4], 0x0400);
+}
+
+#[test]
+fn test_run_frame_reports_video_mode_change() {
+    let mut machine = Machine::new();
+
+    // Program: MOV DX, 0x3B8; MOV AL, 0x29; OUT DX, AL; JMP $
+    machine.load_at(0x0000, &[0xBA, 0xB8, 0x03, 0xB0, 0x29, 0xEE, 0xEB, 0xFE]);
+    machine.cpu.segments[1] = 0;
+    machine.cpu.ip = 0;
+
+    let events = machine.run_frame();
+    assert!(events.contains(&MachineEvent::VideoModeChanged(0x29)));
+
+    // Mode is unchanged on the next frame, so no event is repeated
+    let events = machine.run_frame();
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event, MachineEvent::VideoModeChanged(_))));
+}
+
+#[test]
+fn test_run_frame_reports_one_vertical_retrace_per_frame() {
+    let mut machine = Machine::new();
+
+    // Program: JMP $ (never halts)
+    machine.load_at(0x1000, &[0xEB, 0xFE]);
+    machine.cpu.reset_to(0x0100, 0x0000);
+
+    for _ in 0..3 {
+        let retraces = machine
+            .run_frame()
+            .iter()
+            .filter(|event| **event == MachineEvent::VerticalRetrace)
+            .count();
+        assert_eq!(retraces, 1);
+    }
+
+    // Frames start with the display active; the status bit is set from the
+    // moment the retrace event fires
+    assert_eq!(machine.memory.io_read_u8(0x3BA) & 0x08, 0x00);
+    while machine.memory.io_read_u8(0x3BA) & 0x08 == 0 {
+        machine.step();
+    }
+    assert!(machine.memory.mda_mut().take_vertical_retrace());
+}
+
+#[test]
+fn test_run_frame_reports_halt_with_interrupts_disabled() {
+    let mut machine = Machine::new();
+
+    // Program: CLI; HLT
+    machine.load_at(0x0000, &[0xFA, 0xF4]);
+    machine.cpu.segments[1] = 0;
+    machine.cpu.ip = 0;
+
+    let events = machine.run_frame();
+    assert_eq!(events, vec![MachineEvent::Halted]);
+}
+
+#[test]
+fn test_run_frame_runs_one_frame_of_cycles() {
+    let mut machine = Machine::new();
+
+    // Program: JMP $
+    machine.load_at(0x0000, &[0xEB, 0xFE]);
+    machine.cpu.segments[1] = 0;
+    machine.cpu.ip = 0;
+
+    machine.run_frame();
+    assert!(machine.cpu.total_cycles >= CYCLES_PER_FRAME);
+}
+
+#[test]
+fn test_run_frame_reports_irq_summary() {
+    let mut machine = Machine::new();
+
+    // IRQ0 handler at 0050:0000: MOV AL, 0x20; OUT 0x20, AL (EOI); IRET
+    machine.load_at(0x0500, &[0xB0, 0x20, 0xE6, 0x20, 0xCF]);
+    machine.install_handlers(&[(0x08, 0x0050, 0x0000)]);
+
+    // Program: STI; JMP $
+    machine.load_at(0x1000, &[0xFB, 0xEB, 0xFE]);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+    machine.cpu.regs[4] = 0x0400; // SP
+
+    // Unmask IRQ0, then program PIT counter 0 for mode 2 with count 0x1000
+    machine.memory.io_write_u8(0x21, 0xFE);
+    machine.memory.io_write_u8(0x43, 0x34);
+    machine.memory.io_write_u8(0x40, 0x00);
+    machine.memory.io_write_u8(0x40, 0x10);
+
+    let events = machine.run_frame();
+    let irqs = events
+        .iter()
+        .find_map(|event| match event {
+            MachineEvent::IrqSummary(irqs) => Some(*irqs),
+            _ => None,
+        })
+        .expect("expected an IRQ summary");
+    assert!(irqs[0] > 0);
+    assert_eq!(irqs[1..], [0; 7]);
+}
+
+#[test]
+fn test_single_stepping_delivers_irq0() {
+    let mut machine = Machine::new();
+
+    // IRQ0 handler at 0050:0000: MOV AL, 0x20; OUT 0x20, AL (EOI); IRET
+    machine.load_at(0x0500, &[0xB0, 0x20, 0xE6, 0x20, 0xCF]);
+    machine.install_handlers(&[(0x08, 0x0050, 0x0000)]);
+
+    // Program: STI; JMP $
+    machine.load_at(0x1000, &[0xFB, 0xEB, 0xFE]);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+    machine.cpu.regs[4] = 0x0400; // SP
+
+    // Unmask IRQ0, then program PIT counter 0 for mode 2 with count 0x40
+    machine.memory.io_write_u8(0x21, 0xFE);
+    machine.memory.io_write_u8(0x43, 0x34);
+    machine.memory.io_write_u8(0x40, 0x40);
+    machine.memory.io_write_u8(0x40, 0x00);
+
+    // Step one instruction at a time, the way the GDB stub does
+    let mut steps = 0;
+    while machine.cpu.segments[1] != 0x0050 {
+        let before = machine.cpu.total_cycles;
+        machine.run_frame_until(|_| true);
+        assert!(machine.cpu.total_cycles - before < 100);
+
+        steps += 1;
+        assert!(steps < 1000, "IRQ0 was never delivered");
+    }
+    assert_eq!(machine.cpu.ip, 0x0000);
+}
+
+#[test]
+fn test_higher_priority_irq_acknowledged_first() {
+    let mut machine = Machine::new();
+
+    // IRQ0 handler at 0050:0000 and IRQ1 handler at 0060:0000, both HLT
+    machine.load_at(0x0500, &[0xF4]);
+    machine.load_at(0x0600, &[0xF4]);
+    machine.install_handlers(&[(0x08, 0x0050, 0x0000), (0x09, 0x0060, 0x0000)]);
+
+    // Program: STI; NOP
+    machine.load_at(0x1000, &[0xFB, 0x90]);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+    machine.cpu.regs[4] = 0x0400; // SP
+
+    // Raise IRQ1 before IRQ0, with both unmasked
+    machine.memory.io_write_u8(0x21, 0xFC);
+    machine.memory.pic_mut().set_irq_level(1, true);
+    machine.memory.pic_mut().set_irq_level(0, true);
+    assert_eq!(machine.memory.pic().get_irr(), 0x03);
+
+    machine.step(); // STI (interrupts recognized after the next instruction)
+    machine.step(); // NOP, then INT 08h
+
+    assert_eq!(machine.cpu.segments[1], 0x0050);
+    assert_eq!(machine.cpu.ip, 0x0000);
+    assert_eq!(machine.memory.pic().get_isr(), 0x01);
+    assert_eq!(machine.memory.pic().get_irr(), 0x02);
+}
+
+/// MOV BX, [0x06FE]; MOV BYTE [BX], tag; INC WORD [0x06FE]
+fn log_tag(tag: u8) -> [u8; 11] {
+    [
+        0x8B, 0x1E, 0xFE, 0x06, 0xC6, 0x07, tag, 0xFF, 0x06, 0xFE, 0x06,
+    ]
+}
+
+#[test]
+fn test_lower_priority_handler_preempted_after_sti() {
+    let mut machine = Machine::new();
+
+    // IRQ1 handler at 0060:0000 logs 0x11, enables interrupts, logs 0x12,
+    // then sends EOI and returns
+    let mut irq1 = vec![0x50, 0x53]; // PUSH AX; PUSH BX
+    irq1.extend(log_tag(0x11));
+    irq1.extend([0xFB, 0x90, 0x90]); // STI; NOP; NOP
+    irq1.extend(log_tag(0x12));
+    irq1.extend([0xB0, 0x20, 0xE6, 0x20, 0x5B, 0x58, 0xCF]); // EOI; POP BX; POP AX; IRET
+    machine.load_at(0x0600, &irq1);
+
+    // IRQ0 handler at 0050:0000 logs 0x01, sends EOI, logs 0x02 and returns
+    let mut irq0 = vec![0x50, 0x53];
+    irq0.extend(log_tag(0x01));
+    irq0.extend([0xB0, 0x20, 0xE6, 0x20]);
+    irq0.extend(log_tag(0x02));
+    irq0.extend([0x5B, 0x58, 0xCF]);
+    machine.load_at(0x0500, &irq0);
+
+    machine.install_handlers(&[(0x08, 0x0050, 0x0000), (0x09, 0x0060, 0x0000)]);
+    machine.memory.write_u16(0x06FE, 0x0700); // Log pointer
+
+    // Program: STI; JMP $
+    machine.load_at(0x1000, &[0xFB, 0xEB, 0xFE]);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+    machine.cpu.regs[4] = 0x0400; // SP
+
+    // Unmask IRQ0 and IRQ1 and raise IRQ1
+    machine.memory.io_write_u8(0x21, 0xFC);
+    machine.memory.pic_mut().set_irq_level(1, true);
+
+    // Run until the IRQ1 handler has executed its STI
+    let mut steps = 0;
+    while !(machine.cpu.segments[1] == 0x0060 && machine.cpu.get_flag(Cpu::IF)) {
+        machine.step();
+        steps += 1;
+        assert!(steps < 100, "IRQ1 handler never enabled interrupts");
+    }
+    assert_eq!(machine.memory.pic().get_isr(), 0x02);
+
+    // The timer fires while IRQ1 is still in service
+    machine.memory.pic_mut().set_irq_level(0, true);
+    let mut isr_in_irq0 = None;
+    while machine.cpu.segments[1] != 0x0100 {
+        machine.step();
+        if machine.cpu.segments[1] == 0x0050 && isr_in_irq0.is_none() {
+            isr_in_irq0 = Some(machine.memory.pic().get_isr());
+        }
+        steps += 1;
+        assert!(steps < 200, "handlers never returned");
+    }
+
+    // IRQ0 nested inside IRQ1 and each EOI cleared its own in-service bit
+    assert_eq!(isr_in_irq0, Some(0x03));
+    let log: Vec<u8> = (0..4).map(|i| machine.memory.read_u8(0x0700 + i)).collect();
+    assert_eq!(log, vec![0x11, 0x01, 0x02, 0x12]);
+    assert_eq!(machine.memory.read_u16(0x06FE), 0x0704);
+    assert_eq!(machine.memory.pic().get_isr(), 0x00);
+    assert_eq!(machine.memory.pic().get_irr(), 0x00);
+    assert_eq!(machine.cpu.regs[4], 0x0400);
+}
+
+#[test]
+fn test_turbo_doubles_cpu_work_but_not_timer_rate() {
+    let mut machine = Machine::new();
+
+    // IRQ0 handler at 0050:0000: PUSH AX; MOV AL, 0x20; OUT 0x20, AL; POP AX; IRET
+    machine.load_at(0x0500, &[0x50, 0xB0, 0x20, 0xE6, 0x20, 0x58, 0xCF]);
+    machine.install_handlers(&[(0x08, 0x0050, 0x0000)]);
+
+    // Program: STI; loop: INC WORD [0x0600]; JMP loop
+    machine.load_at(0x1000, &[0xFB, 0xFF, 0x06, 0x00, 0x06, 0xEB, 0xFA]);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+    machine.cpu.regs[4] = 0x0400; // SP
+
+    // Unmask IRQ0, then program PIT counter 0 for mode 2 with count 0x1000
+    machine.memory.io_write_u8(0x21, 0xFE);
+    machine.memory.io_write_u8(0x43, 0x34);
+    machine.memory.io_write_u8(0x40, 0x00);
+    machine.memory.io_write_u8(0x40, 0x10);
+
+    // Run a few frames, returning loop iterations and timer interrupts
+    let measure = |machine: &mut Machine| {
+        let start_count = machine.memory.read_u16(0x0600);
+        let start_cycles = machine.cpu.total_cycles;
+        let mut irqs = 0;
+        for _ in 0..8 {
+            for event in machine.run_frame() {
+                if let MachineEvent::IrqSummary(counts) = event {
+                    irqs += counts[0];
+                }
+            }
+        }
+        let iterations = machine.memory.read_u16(0x0600).wrapping_sub(start_count);
+        (machine.cpu.total_cycles - start_cycles, iterations, irqs)
+    };
+
+    let (stock_cycles, stock_iterations, stock_irqs) = measure(&mut machine);
+    machine.set_turbo(true);
+    assert!(machine.turbo());
+    assert_eq!(machine.cycles_per_frame(), 2 * CYCLES_PER_FRAME);
+    let (turbo_cycles, turbo_iterations, turbo_irqs) = measure(&mut machine);
+
+    // Twice the CPU cycles, and twice the loop iterations, per frame
+    assert!(stock_cycles >= 8 * CYCLES_PER_FRAME);
+    assert!(turbo_cycles >= 16 * CYCLES_PER_FRAME);
+    assert!(turbo_cycles < 2 * stock_cycles + 100);
+    let ratio = turbo_iterations as f64 / stock_iterations as f64;
+    assert!((1.9..2.1).contains(&ratio), "ratio {}", ratio);
+
+    // The PIT still fires at the same rate per frame
+    assert!(stock_irqs > 0);
+    assert!(turbo_irqs.abs_diff(stock_irqs) <= 1);
+
+    machine.set_turbo(false);
+    assert_eq!(machine.cycles_per_frame(), CYCLES_PER_FRAME);
+}
+
+#[test]
+fn test_dump_state_reports_registers_flags_and_code() {
+    let mut machine = Machine::new();
+
+    // Program: MOV AX, 0x1234; ADD AX, BX
+    machine.load_at(0x1000, &[0xB8, 0x34, 0x12, 0x01, 0xD8]);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+    machine.cpu.regs[4] = 0x0400; // SP
+    machine.cpu.set_flag(Cpu::CF, true);
+
+    let dump = machine.dump_state();
+
+    for name in ["AX=", "BX=", "CX=", "DX=", "SI=", "DI=", "BP=", "SP=0400"] {
+        assert!(dump.contains(name), "missing {} in:\n{}", name, dump);
+    }
+    assert!(dump.contains("CS:IP=0100:0000"), "{}", dump);
+    assert!(dump.contains("[CF"), "{}", dump);
+    assert!(dump.contains("0100:0000  MOV AX, 0x1234"), "{}", dump);
+    assert!(dump.contains("0100:0003  ADD AX, BX ; affects"), "{}", dump);
+    assert!(dump.contains("PIC: IRR="), "{}", dump);
+    assert!(dump.contains("PIT0:"), "{}", dump);
+    assert!(dump.contains("Video:"), "{}", dump);
+}
+
+#[test]
+fn test_snapshot_diff_names_changed_register_and_byte() {
+    let mut machine = Machine::new();
+    let before = machine.snapshot();
+
+    machine.cpu.regs[3] = 0xBEEF; // BX
+    machine.memory.write_u8(0x1234, 0x5A);
+    let after = machine.snapshot();
+
+    let diff = before.diff(&after);
+    assert_eq!(
+        diff.registers,
+        vec![RegisterChange {
+            name: "BX",
+            before: 0x0000,
+            after: 0xBEEF,
+        }]
+    );
+    assert_eq!(
+        diff.memory,
+        vec![MemoryChange {
+            start: 0x1234,
+            before: vec![0x00],
+            after: vec![0x5A],
+        }]
+    );
+    assert!(diff.devices.is_empty());
+    assert!(after.diff(&after).is_empty());
+}
+
+/// CLI; CALL 0x0008; HLT; NOP; HLT; NOP; then `subroutine` at 0x0008
+fn load_call_program(machine: &mut Machine, subroutine: &[u8]) {
+    let mut program = vec![0xFA, 0xE8, 0x04, 0x00, 0xF4, 0x90, 0xF4, 0x90];
+    program.extend_from_slice(subroutine);
+    machine.load_at(0x1000, &program);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+    machine.cpu.regs[4] = 0x0400; // SP
+    machine.enable_call_stack_check(true);
+}
+
+#[test]
+fn test_call_stack_check_accepts_balanced_call_ret() {
+    let mut machine = Machine::new();
+    load_call_program(&mut machine, &[0xC3]); // RET
+
+    let events = machine.run_frame();
+
+    assert_eq!(events, vec![MachineEvent::Halted]);
+    assert_eq!(machine.cpu.ip, 0x0005);
+}
+
+#[test]
+fn test_call_stack_check_reports_corrupted_return_address() {
+    let mut machine = Machine::new();
+    // MOV BP, SP; MOV WORD [BP+0], 0x0006; RET
+    load_call_program(
+        &mut machine,
+        &[0x89, 0xE5, 0xC7, 0x46, 0x00, 0x06, 0x00, 0xC3],
+    );
+
+    let events = machine.run_frame();
+
+    assert_eq!(
+        events,
+        vec![MachineEvent::ReturnMismatch(ReturnMismatch {
+            at: (0x0100, 0x000F),
+            expected: (0x0100, 0x0004),
+            actual: (0x0100, 0x0006),
+        })]
+    );
+    assert_eq!(machine.cpu.ip, 0x0006);
+}
+
+#[test]
+fn test_call_stack_check_reads_return_address_wrapping_in_ss() {
+    let mut machine = Machine::new();
+    load_call_program(&mut machine, &[0xC3]); // RET
+                                              // The CALL pushes its return address at SS:FFFF, so the high byte wraps
+                                              // to SS:0000 rather than landing past the end of the segment
+    machine.cpu.regs[4] = 0x0001; // SP
+    machine.memory.write_u8(0x10000, 0x12);
+
+    let events = machine.run_frame();
+
+    assert_eq!(events, vec![MachineEvent::Halted]);
+    assert_eq!(machine.cpu.ip, 0x0005);
+}
+
+#[test]
+fn test_call_stack_check_stays_out_of_memory_profile() {
+    let mut totals = Vec::new();
+    for check in [false, true] {
+        let mut machine = Machine::new();
+        load_call_program(&mut machine, &[0xC3]); // RET
+        machine.enable_call_stack_check(check);
+        machine.memory.enable_mem_profiling(true);
+        machine.run_frame();
+        totals.push(machine.memory.mem_access_totals());
+    }
+
+    assert_eq!(totals[0], totals[1]);
+}
+
+#[test]
+fn test_target_mips_throttles_instruction_rate() {
+    let mut machine = Machine::new();
+
+    // Program: JMP $
+    machine.load_at(0x1000, &[0xEB, 0xFE]);
+    machine.cpu.reset_to(0x0100, 0x0000);
+
+    // 0.01 MIPS = 10,000 instructions per second, so 3000 take 0.3s. Only
+    // the lower bound is checked, since a busy host can always run slower.
+    machine.set_target_mips(Some(0.01));
+    let start = Instant::now();
+    for _ in 0..3000 {
+        machine.step();
+    }
+
+    assert_eq!(machine.instructions_retired(), 3000);
+    assert!(start.elapsed() >= Duration::from_millis(290));
+}
+
+#[test]
+fn test_target_mips_does_not_catch_up_after_idle_gap() {
+    let mut machine = Machine::new();
+
+    // Program: JMP $
+    machine.load_at(0x1000, &[0xEB, 0xFE]);
+    machine.cpu.reset_to(0x0100, 0x0000);
+
+    // 0.01 MIPS = 10,000 instructions per second
+    machine.set_target_mips(Some(0.01));
+    for _ in 0..1000 {
+        machine.step();
+    }
+
+    // The host stops running the machine for a while
+    std::thread::sleep(Duration::from_millis(500));
+
+    // The first check after the gap finds the limiter behind and measures
+    // from there, so the last 2000 of these still take at least 0.2s
+    // rather than running flat out to make up the gap
+    let start = Instant::now();
+    for _ in 0..3000 {
+        machine.step();
+    }
+    assert!(start.elapsed() >= Duration::from_millis(190));
+}
+
+#[test]
+fn test_post_codes_recorded_in_order() {
+    let mut machine = Machine::new();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let sink_seen = seen.clone();
+    machine.set_post_code_sink(Some(Box::new(move |code| {
+        sink_seen.borrow_mut().push(code)
+    })));
+
+    // MOV AL, 0x01; OUT 0x80, AL; MOV AL, 0x02; OUT 0x80, AL; MOV AL, 0x13; OUT 0x80, AL
+    machine.load_at(
+        0x1000,
+        &[
+            0xB0, 0x01, 0xE6, 0x80, 0xB0, 0x02, 0xE6, 0x80, 0xB0, 0x13, 0xE6, 0x80,
+        ],
+    );
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+
+    assert_eq!(machine.last_post_code(), None);
+    for _ in 0..6 {
+        machine.step();
+    }
+
+    assert_eq!(machine.post_code_history(), vec![0x01, 0x02, 0x13]);
+    assert_eq!(machine.last_post_code(), Some(0x13));
+    assert_eq!(*seen.borrow(), vec![0x01, 0x02, 0x13]);
+}
+
+#[test]
+fn test_devices_state_includes_pit_and_pic() {
+    let mut machine = Machine::new();
+
+    // Mask everything but IRQ0 and program PIT counter 0 with 0x1234
+    machine.memory.io_write_u8(0x21, 0xFE);
+    machine.memory.io_write_u8(0x43, 0x34);
+    machine.memory.io_write_u8(0x40, 0x34);
+    machine.memory.io_write_u8(0x40, 0x12);
+
+    let states = machine.devices_state();
+
+    let pic = states
+        .iter()
+        .find_map(|state| match state {
+            DeviceState::Pic(pic) => Some(*pic),
+            _ => None,
+        })
+        .expect("PIC state");
+    assert_eq!(pic.imr, 0xFE);
+
+    let pit = states
+        .iter()
+        .find_map(|state| match state {
+            DeviceState::Pit(pit) => Some(*pit),
+            _ => None,
+        })
+        .expect("PIT state");
+    assert_eq!(pit.channels[0].reload, 0x1234);
+}
+
+#[test]
+fn test_mda_state_round_trips_crtc_registers() {
+    let mut machine = Machine::new();
+    let mda = |machine: &Machine| {
+        machine
+            .devices_state()
+            .iter()
+            .find_map(|state| match state {
+                DeviceState::Mda(mda) => Some(*mda),
+                _ => None,
+            })
+            .expect("MDA state")
+    };
+
+    // Mode control, then an 8-scanline character height in CRTC R9
+    machine.memory.io_write_u8(0x3B8, 0x29);
+    machine.memory.io_write_u8(0x3B4, 0x09);
+    machine.memory.io_write_u8(0x3B5, 0x07);
+    let expected = mda(&machine);
+    assert_eq!(expected.mode_control, 0x29);
+    assert_eq!(expected.crtc_index, 0x09);
+    assert_eq!(expected.crtc[9], 0x07);
+    let snapshot = machine.snapshot();
+
+    machine.memory.io_write_u8(0x3B4, 0x01);
+    machine.memory.io_write_u8(0x3B5, 0x28);
+    assert!(!snapshot.diff(&machine.snapshot()).devices.is_empty());
+
+    machine.restore(&snapshot);
+    assert_eq!(mda(&machine), expected);
+    assert!(snapshot.diff(&machine.snapshot()).is_empty());
+}
+
+#[test]
+fn test_port_61_bit_0_gates_pit_counter_2() {
+    let mut machine = Machine::new();
+    let counter_2 = |machine: &Machine| {
+        machine
+            .devices_state()
+            .iter()
+            .find_map(|state| match state {
+                DeviceState::Pit(pit) => Some(pit.channels[2]),
+                _ => None,
+            })
+            .expect("PIT state")
+    };
+
+    // Port B powers on with the gate low
+    assert!(!counter_2(&machine).gate);
+
+    // Counter 2, low+high byte, mode 1 (one-shot), count 0x10
+    machine.memory.io_write_u8(0x43, 0xB2);
+    machine.memory.io_write_u8(0x42, 0x10);
+    machine.memory.io_write_u8(0x42, 0x00);
+    machine.memory.tick(200);
+    assert!(counter_2(&machine).output);
+
+    // Raising bit 0 triggers the one-shot: OUT drops, then rises after 16 clocks
+    machine.memory.io_write_u8(0x61, 0x01);
+    assert!(counter_2(&machine).gate);
+    machine.memory.tick(8);
+    assert!(!counter_2(&machine).output);
+    machine.memory.tick(200);
+    assert!(counter_2(&machine).output);
+
+    // Lowering it again reaches the gate too
+    machine.memory.io_write_u8(0x61, 0x00);
+    assert!(!counter_2(&machine).gate);
+}
+
+#[test]
+fn test_default_equipment_word_matches_dip_switches() {
+    let mut machine = Machine::new();
+
+    // MDA, one floppy, 64K planar RAM
+    assert_eq!(machine.memory.read_u16(BDA_EQUIPMENT_WORD), 0x003D);
+
+    // Select DIP switches on port A via port B bit 7
+    machine.memory.io_write_u8(0x61, 0x80);
+    assert_eq!(machine.memory.io_read_u8(0x60), 0x3D);
+}
+
+#[test]
+fn test_equipment_word_two_floppies_cga() {
+    let config = MachineConfig {
+        video: VideoAdapter::Cga80,
+        floppy_drives: 2,
+        ..MachineConfig::default()
+    };
+    // Floppies present, 64K, CGA 80x25, two drives
+    assert_eq!(config.equipment_word(), 0b0110_1101);
+
+    let mut machine = Machine::with_config(config);
+    assert_eq!(machine.memory.read_u16(BDA_EQUIPMENT_WORD), 0x006D);
+
+    machine.memory.io_write_u8(0x61, 0x80);
+    assert_eq!(machine.memory.io_read_u8(0x60), 0x6D);
+
+    // INT 11h answers with the BDA word
+    machine.install_equipment_service(0x0050, 0x0000);
+    machine.load_at(0x1000, &[0xCD, 0x11]); // INT 0x11
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0x0000;
+    machine.cpu.regs[4] = 0x0400; // SP
+
+    for _ in 0..7 {
+        machine.step();
+    }
+    assert_eq!(machine.cpu.segments[1], 0x0100);
+    assert_eq!(machine.cpu.ip, 0x0002);
+    assert_eq!(machine.cpu.regs[0], 0x006D);
+}
+
+#[test]
+fn test_equipment_word_ports() {
+    let config = MachineConfig {
+        serial_ports: 2,
+        parallel_ports: 1,
+        game_port: true,
+        fpu: true,
+        ..MachineConfig::default()
+    };
+    assert_eq!(config.equipment_word(), 0x543F);
+}
+
+/// Run until CS:IP reaches `at`, returning the cycles it took
+fn run_to(machine: &mut Machine, at: (u16, u16)) -> u64 {
+    let start = machine.cpu.total_cycles;
+    for _ in 0..100_000 {
+        if (machine.cpu.segments[1], machine.cpu.ip) == at {
+            return machine.cpu.total_cycles - start;
+        }
+        machine.step();
+    }
+    panic!("never reached {:04X}:{:04X}", at.0, at.1);
+}
+
+#[test]
+fn test_int15_reports_extended_memory() {
+    let config = MachineConfig {
+        extended_memory_kb: 384,
+        ..MachineConfig::default()
+    };
+    let mut machine = Machine::with_config(config);
+    machine.install_system_services(0x0050, 0x0000);
+    machine.load_at(0x1000, &[0xB4, 0x88, 0xCD, 0x15]); // MOV AH, 0x88; INT 0x15
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0x0000;
+    machine.cpu.regs[4] = 0x0400; // SP
+    machine.cpu.set_flag(Cpu::CF, true);
+
+    run_to(&mut machine, (0x0100, 0x0004));
+    assert_eq!(machine.cpu.regs[0], 384);
+    assert!(!machine.cpu.get_flag(Cpu::CF));
+}
+
+#[test]
+fn test_int15_wait_counts_pit_ticks() {
+    let mut machine = Machine::new();
+    machine.install_system_services(0x0050, 0x0000);
+    machine.memory.io_write_u8(0x21, 0xFF); // Mask all IRQs
+    machine.memory.io_write_u8(0x43, 0x34); // Counter 0, low+high, mode 2
+    machine.memory.io_write_u8(0x40, 0x00);
+    machine.memory.io_write_u8(0x40, 0x00);
+
+    machine.load_at(
+        0x1000,
+        &[
+            0xB4, 0x86, // MOV AH, 0x86
+            0x31, 0xC9, // XOR CX, CX
+            0xBA, 0xE8, 0x03, // MOV DX, 1000
+            0xCD, 0x15, // INT 0x15
+        ],
+    );
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0x0000;
+    machine.cpu.regs[4] = 0x0400; // SP
+
+    run_to(&mut machine, (0x0050, 0x0000));
+    let cycles = run_to(&mut machine, (0x0100, 0x0009));
+
+    // 1000us is 1200 PIT ticks of 4 CPU cycles each, plus polling overhead
+    assert!((4800..6000).contains(&cycles), "{} cycles", cycles);
+    assert!(!machine.cpu.get_flag(Cpu::CF));
+    assert_eq!(machine.cpu.regs[2], 1000); // DX preserved
+}
+
+#[test]
+fn test_run_until_halt_stops_on_cli_hlt() {
+    let mut machine = Machine::new();
+    // MOV AX, 0x1234; CLI; HLT
+    machine.load_at(0x1000, &[0xB8, 0x34, 0x12, 0xFA, 0xF4]);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0x0000;
+
+    let report = machine.run_until_halt(1_000, false);
+    assert_eq!(report.outcome, HaltOutcome::Halted);
+    assert_eq!(report.at, (0x0100, 0x0005));
+    assert!(report.cycles < 1_000);
+    assert_eq!(machine.cpu.regs[0], 0x1234);
+
+    // JMP $ never halts
+    machine.load_at(0x1000, &[0xEB, 0xFE]);
+    machine.cpu.halted = false;
+    machine.cpu.ip = 0x0000;
+    let report = machine.run_until_halt(1_000, false);
+    assert_eq!(report.outcome, HaltOutcome::CycleLimit);
+    assert!(report.cycles >= 1_000);
+}
+
+#[test]
+fn test_run_until_halt_lets_timer_wake_hlt() {
+    let program = [
+        0xFB, // STI
+        0xF4, // HLT
+        0xFA, // CLI
+        0xF4, // HLT
+    ];
+    let setup = |machine: &mut Machine| {
+        // IRQ0 handler: MOV AL, 0x20; OUT 0x20, AL; IRET
+        machine.load_at(0x0500, &[0xB0, 0x20, 0xE6, 0x20, 0xCF]);
+        machine.install_handlers(&[(0x08, 0x0050, 0x0000)]);
+        machine.memory.io_write_u8(0x21, 0xFE); // Unmask IRQ0
+        machine.memory.io_write_u8(0x43, 0x34); // Counter 0, low+high, mode 2
+        machine.memory.io_write_u8(0x40, 0x00);
+        machine.memory.io_write_u8(0x40, 0x01);
+        machine.load_at(0x1000, &program);
+        machine.cpu.segments[1] = 0x0100;
+        machine.cpu.ip = 0x0000;
+        machine.cpu.regs[4] = 0x0400; // SP
+    };
+
+    let mut machine = Machine::new();
+    setup(&mut machine);
+    let report = machine.run_until_halt(100_000, false);
+    assert_eq!(report.outcome, HaltOutcome::Halted);
+    assert_eq!(report.at, (0x0100, 0x0004));
+
+    let mut machine = Machine::new();
+    setup(&mut machine);
+    let report = machine.run_until_halt(100_000, true);
+    assert_eq!(report.outcome, HaltOutcome::Halted);
+    assert_eq!(report.at, (0x0100, 0x0002));
+}
+
+#[test]
+fn test_hdd_read_transfers_first_sector_by_dma() {
+    let geometry = HddGeometry::ST412;
+    let mut image = vec![0u8; geometry.total_size()];
+    for (i, byte) in image[..HDD_SECTOR_SIZE].iter_mut().enumerate() {
+        *byte = i as u8 ^ 0x5A;
+    }
+    image[HDD_SECTOR_SIZE] = 0xEE; // Second sector must not arrive
+
+    let mut machine = Machine::new();
+    machine.attach_hdd(image, geometry);
+
+    // Read: drive 0, head 0, cylinder 0, sector 0, 1 sector
+    machine.load_at(0x0600, &[0x08, 0x00, 0x00, 0x00, 0x01, 0x00]);
+    machine.load_at(
+        0x1000,
+        &[
+            0xFA, // CLI
+            0xB0, 0x07, 0xE6, 0x0A, // Mask DMA channel 3
+            0xE6, 0x0C, // Clear byte flip-flop
+            0xB0, 0x47, 0xE6, 0x0B, // Single mode, device to memory, channel 3
+            0xB0, 0x00, 0xE6, 0x06, 0xB0, 0x20, 0xE6, 0x06, // Address 0x2000
+            0xB0, 0x00, 0xE6, 0x82, // Page 0
+            0xB0, 0xFF, 0xE6, 0x07, 0xB0, 0x01, 0xE6, 0x07, // Count 511
+            0xB0, 0x03, 0xE6, 0x0A, // Unmask channel 3
+            0xBA, 0x23, 0x03, 0xB0, 0x03, 0xEE, // Enable DMA and IRQ
+            0xBA, 0x22, 0x03, 0xEE, // Select
+            0xBA, 0x20, 0x03, // MOV DX, 0x320
+            0xBE, 0x00, 0x06, // MOV SI, 0x0600
+            0xB9, 0x06, 0x00, // MOV CX, 6
+            0xAC, 0xEE, 0xE2, 0xFC, // LODSB; OUT DX, AL; LOOP
+            0xBA, 0x21, 0x03, // MOV DX, 0x321
+            0xEC, 0xA8, 0x20, 0x74, 0xFB, // IN AL, DX; TEST AL, 0x20; JZ -5
+            0xBA, 0x20, 0x03, 0xEC, // MOV DX, 0x320; IN AL, DX (completion)
+            0xF4, // HLT
+        ],
+    );
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.segments[3] = 0x0000;
+    machine.cpu.ip = 0;
+
+    let report = machine.run_until_halt(100_000, true);
+    assert_eq!(report.outcome, HaltOutcome::Halted);
+    assert_eq!(machine.cpu.read_reg8(0), 0x00); // Completion: no error
+
+    let sector: Vec<u8> = (0..HDD_SECTOR_SIZE as u32)
+        .map(|i| machine.memory.read_u8(0x2000 + i))
+        .collect();
+    let expected: Vec<u8> = (0..HDD_SECTOR_SIZE).map(|i| i as u8 ^ 0x5A).collect();
+    assert_eq!(sector, expected);
+    assert_eq!(machine.memory.read_u8(0x2200), 0x00);
+    assert_eq!(machine.memory.pic().describe().irr & 0x20, 0x20); // IRQ5
+}
+
+#[test]
+fn test_int13_reads_and_writes_hard_disk() {
+    // 2 heads, 4 sectors per track; each sector filled with its LBA
+    let geometry = HddGeometry::new(8, 2, 4);
+    let image: Vec<u8> = (0..geometry.total_size())
+        .map(|i| (i / HDD_SECTOR_SIZE) as u8)
+        .collect();
+
+    let mut machine = Machine::new();
+    let hdc = machine.attach_hdd(image, geometry);
+    machine.install_disk_service(0x0050, 0x0000);
+
+    // Read C1 H1 S2 (LBA 13) to 0000:2000, then write it back at C0 H0 S1
+    machine.load_at(
+        0x1000,
+        &[
+            0xB8, 0x01, 0x02, // MOV AX, 0x0201
+            0xB9, 0x02, 0x01, // MOV CX, 0x0102
+            0xBA, 0x80, 0x01, // MOV DX, 0x0180
+            0xBB, 0x00, 0x20, // MOV BX, 0x2000
+            0xCD, 0x13, // INT 0x13
+            0xB8, 0x01, 0x03, // MOV AX, 0x0301
+            0xB9, 0x01, 0x00, // MOV CX, 0x0001
+            0xBA, 0x80, 0x00, // MOV DX, 0x0080
+            0xCD, 0x13, // INT 0x13
+        ],
+    );
+    machine.cpu.segments[0] = 0x0000; // ES
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+    machine.cpu.regs[4] = 0x0400; // SP
+    machine.cpu.set_flag(Cpu::CF, true);
+
+    run_to(&mut machine, (0x0100, 0x000E));
+    assert!(!machine.cpu.get_flag(Cpu::CF));
+    assert_eq!(machine.cpu.regs[0], 0x0001); // AH=0, AL=1 sector
+    assert_eq!(machine.cpu.regs[3], 0x2000); // BX preserved
+    assert!((0..HDD_SECTOR_SIZE as u32).all(|i| machine.memory.read_u8(0x2000 + i) == 13));
+    assert_eq!(machine.memory.read_u8(0x2000 + HDD_SECTOR_SIZE as u32), 0);
+
+    run_to(&mut machine, (0x0100, 0x0019));
+    assert!(!machine.cpu.get_flag(Cpu::CF));
+    let hdc = hdc.borrow();
+    let disk = hdc.disk(0).unwrap();
+    assert!(disk.sectors(0, 1).unwrap().iter().all(|&b| b == 13));
+    assert_eq!(disk.sectors(1, 1).unwrap()[0], 1);
+}
+
+#[test]
+fn test_int13_rejects_bad_address_and_floppy() {
+    let mut machine = Machine::new();
+    machine.attach_hdd(Vec::new(), HddGeometry::new(8, 2, 4));
+    machine.install_disk_service(0x0050, 0x0000);
+
+    machine.load_at(
+        0x1000,
+        &[
+            0xB8, 0x01, 0x02, // MOV AX, 0x0201
+            0xB9, 0x01, 0x08, // MOV CX, 0x0801 (cylinder 8 is past the end)
+            0xBA, 0x80, 0x00, // MOV DX, 0x0080
+            0xCD, 0x13, // INT 0x13
+            0xB8, 0x01, 0x02, // MOV AX, 0x0201
+            0xBA, 0x00, 0x00, // MOV DX, 0x0000 (floppy)
+            0xCD, 0x13, // INT 0x13
+        ],
+    );
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+    machine.cpu.regs[4] = 0x0400; // SP
+
+    run_to(&mut machine, (0x0100, 0x000B));
+    assert!(machine.cpu.get_flag(Cpu::CF));
+    assert_eq!(machine.cpu.regs[0] >> 8, 0x04);
+
+    run_to(&mut machine, (0x0100, 0x0013));
+    assert!(machine.cpu.get_flag(Cpu::CF));
+    assert_eq!(machine.cpu.regs[0] >> 8, 0x01);
+}
+
+#[test]
+fn test_ide_reads_sector_zero_into_memory() {
+    let image: Vec<u8> = (0..1024 * 1024)
+        .map(|i: usize| (i * 7 + i / HDD_SECTOR_SIZE) as u8)
+        .collect();
+    let geometry = HddGeometry::from_size(image.len()).unwrap();
+
+    let mut machine = Machine::new();
+    machine.attach_ide(HardDisk::new(image.clone(), geometry), IDE_IRQ);
+
+    machine.load_at(
+        0x1000,
+        &[
+            0xFA, // CLI
+            0xBA, 0xF2, 0x01, // MOV DX, 0x1F2
+            0xB0, 0x01, 0xEE, // Sector count 1
+            0x42, 0xEE, // Sector 1
+            0x42, 0x30, 0xC0, 0xEE, // Cylinder low 0
+            0x42, 0xEE, // Cylinder high 0
+            0x42, 0xB0, 0xA0, 0xEE, // Master, head 0, CHS
+            0x42, 0xB0, 0x20, 0xEE, // Read Sectors
+            0xEC, 0xA8, 0x08, 0x74, 0xFB, // IN AL, DX; TEST AL, 0x08; JZ -5
+            0xBA, 0xF0, 0x01, // MOV DX, 0x1F0
+            0xBF, 0x00, 0x20, // MOV DI, 0x2000
+            0xB9, 0x00, 0x01, // MOV CX, 256
+            0xED, 0xAB, 0xE2, 0xFC, // IN AX, DX; STOSW; LOOP
+            0xBA, 0xF6, 0x03, 0xEC, // MOV DX, 0x3F6; IN AL, DX (alternate status)
+            0xF4, // HLT
+        ],
+    );
+    machine.cpu.segments[0] = 0x0000; // ES
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+
+    let report = machine.run_until_halt(100_000, true);
+    assert_eq!(report.outcome, HaltOutcome::Halted);
+    assert_eq!(machine.cpu.read_reg8(0), 0x50); // DRDY | DSC, no DRQ
+
+    let sector: Vec<u8> = (0..HDD_SECTOR_SIZE as u32)
+        .map(|i| machine.memory.read_u8(0x2000 + i))
+        .collect();
+    assert_eq!(sector, image[..HDD_SECTOR_SIZE]);
+    assert_eq!(machine.memory.pic().raise_counts()[5], 1); // IRQ5
+}
+
+#[test]
+fn test_ide_and_xt_controller_on_separate_irqs() {
+    let geometry = HddGeometry::new(2, 2, 4);
+    let mut machine = Machine::new();
+    machine.attach_hdd(Vec::new(), geometry);
+    let ide = machine.attach_ide(HardDisk::new(Vec::new(), geometry), 3);
+    assert_eq!(ide.borrow().irq(), 3);
+}
+
+#[test]
+#[should_panic(expected = "the XT disk controller already uses IRQ5")]
+fn test_ide_rejects_xt_controller_irq() {
+    let geometry = HddGeometry::new(2, 2, 4);
+    let mut machine = Machine::new();
+    machine.attach_hdd(Vec::new(), geometry);
+    machine.attach_ide(HardDisk::new(Vec::new(), geometry), IDE_IRQ);
+}
+
+#[test]
+#[should_panic(expected = "the IDE controller already uses IRQ5")]
+fn test_xt_controller_rejects_ide_irq() {
+    let geometry = HddGeometry::new(2, 2, 4);
+    let mut machine = Machine::new();
+    machine.attach_ide(HardDisk::new(Vec::new(), geometry), IDE_IRQ);
+    machine.attach_hdd(Vec::new(), geometry);
+}
+
+#[test]
+fn test_scheduled_irq_fires_at_cycle() {
+    let mut machine = Machine::new();
+    // IRQ0 handler: MOV AL, 0x20; OUT 0x20, AL; IRET
+    machine.load_at(0x0500, &[0xB0, 0x20, 0xE6, 0x20, 0xCF]);
+    machine.install_handlers(&[(0x08, 0x0050, 0x0000)]);
+    machine.memory.io_write_u8(0x21, 0xFE); // Unmask IRQ0; the PIT is not running
+                                            // STI; JMP $
+    machine.load_at(0x1000, &[0xFB, 0xEB, 0xFE]);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0x0000;
+    machine.cpu.regs[4] = 0x0400; // SP
+
+    let target = machine.cpu.total_cycles + 1_000;
+    machine.schedule_irq(0, target);
+
+    machine.run_cycles(900);
+    assert_eq!(machine.cpu.segments[1], 0x0100);
+
+    while machine.cpu.segments[1] != 0x0050 {
+        machine.step();
+        assert!(machine.cpu.total_cycles < target + 200, "IRQ0 never taken");
+    }
+    // Delivered before the first JMP starting at the target, taken after it
+    assert!(machine.cpu.total_cycles >= target);
+
+    // Fires once
+    machine.run_cycles(1_000);
+    assert_eq!(machine.memory.pic().ack_counts()[0], 1);
+}
+
+#[test]
+fn test_irq_lines_beyond_the_pic_are_ignored() {
+    let mut machine = Machine::new();
+    machine.load_at(0x1000, &[0xEB, 0xFE]); // JMP $
+    machine.cpu.reset_to(0x0100, 0x0000);
+
+    machine.start_recording();
+    machine.schedule_irq(8, machine.cpu.total_cycles + 10);
+    machine.inject(InputEvent::Irq {
+        line: 8,
+        level: true,
+    });
+    machine.run_cycles(100);
+
+    let log = machine.stop_recording().unwrap();
+    assert!(log.events.is_empty());
+    assert!(machine.irq_stats().iter().all(|&count| count == 0));
+}
+
+#[test]
+fn test_io_delay_adds_cycles_to_in_out() {
+    // IN AL, 0x60; OUT 0x80, AL; MOV DX, 0x0201; IN AX, DX; HLT
+    let program = [0xE4, 0x60, 0xE6, 0x80, 0xBA, 0x01, 0x02, 0xED, 0xF4];
+    let run = |delay: u16, game_port: Option<u16>| {
+        let mut machine = Machine::new();
+        machine.load_at(0x1000, &program);
+        machine.cpu.reset_to(0x0100, 0x0000);
+        machine.set_io_delay(delay);
+        if let Some(cycles) = game_port {
+            machine.set_port_io_delay(0x0200..=0x020F, cycles);
+        }
+        run_to(&mut machine, (0x0100, 0x0008))
+    };
+
+    let base = run(0, None);
+    // Two byte accesses plus a word access split into two
+    assert_eq!(run(6, None), base + 4 * 6);
+    assert_eq!(run(6, Some(20)), base + 2 * 6 + 2 * 20);
+}
+
+#[test]
+fn test_irq_taken_between_rep_elements() {
+    let mut machine = Machine::new();
+    // IRQ0 handler: PUSH AX; MOV [0x0600], CX; MOV AL, 0x20; OUT 0x20, AL; POP AX; IRET
+    machine.load_at(
+        0x0500,
+        &[
+            0x50, 0x89, 0x0E, 0x00, 0x06, 0xB0, 0x20, 0xE6, 0x20, 0x58, 0xCF,
+        ],
+    );
+    machine.install_handlers(&[(0x08, 0x0050, 0x0000)]);
+    machine.memory.io_write_u8(0x21, 0xFE); // Unmask IRQ0
+    machine.memory.write_u16(0x0600, 0xFFFF);
+    // STI; CLD; MOV CX, 200; MOV DI, 0x2000; MOV AL, 0xAA; REP STOSB; HLT
+    machine.load_at(
+        0x1000,
+        &[
+            0xFB, 0xFC, 0xB9, 0xC8, 0x00, 0xBF, 0x00, 0x20, 0xB0, 0xAA, 0xF3, 0xAA, 0xF4,
+        ],
+    );
+    machine.cpu.segments = [0x0000, 0x0100, 0x0000, 0x0000];
+    machine.cpu.ip = 0x0000;
+    machine.cpu.regs[4] = 0x0400; // SP
+
+    machine.schedule_irq(0, machine.cpu.total_cycles + 500);
+    run_to(&mut machine, (0x0100, 0x000C));
+
+    // The handler ran partway through the block, and the REP then finished
+    let cx_in_handler = machine.memory.read_u16(0x0600);
+    assert!((1..200).contains(&cx_in_handler), "CX={cx_in_handler}");
+    assert_eq!(machine.cpu.regs[1], 0);
+    assert!((0x2000..0x2000 + 200).all(|addr| machine.memory.read_u8(addr) == 0xAA));
+    assert_eq!(machine.memory.read_u8(0x2000 + 200), 0x00);
+}
+
+#[test]
+fn test_popf_enabling_interrupts_delays_one_instruction() {
+    let mut machine = Machine::new();
+    machine.load_at(0x0500, &[0xCF]); // IRQ0 handler: IRET
+    machine.install_handlers(&[(0x08, 0x0050, 0x0000)]);
+    machine.memory.io_write_u8(0x21, 0xFE); // Unmask IRQ0
+                                            // MOV AX, 0x0202; PUSH AX; POPF; NOP
+    machine.load_at(0x1000, &[0xB8, 0x02, 0x02, 0x50, 0x9D, 0x90]);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0x0000;
+    machine.cpu.regs[4] = 0x0400; // SP
+    machine.schedule_irq(0, machine.cpu.total_cycles);
+
+    for _ in 0..3 {
+        machine.step(); // MOV, PUSH, POPF with IRQ0 pending
+    }
+    assert!(machine.cpu.get_flag(Cpu::IF));
+    assert_eq!((machine.cpu.segments[1], machine.cpu.ip), (0x0100, 0x0005));
+
+    machine.step(); // NOP, then IRQ0
+    assert_eq!(machine.cpu.segments[1], 0x0050);
+}
+
+/// Run `load_ss` with IRQ0 pending and IF set, returning where the CPU is
+/// after it and after the instruction following it
+fn irq_after_ss_load(load_ss: &[u8]) -> ((u16, u16), u16) {
+    let mut machine = Machine::new();
+    machine.load_at(0x0500, &[0xCF]); // IRQ0 handler: IRET
+    machine.install_handlers(&[(0x08, 0x0050, 0x0000)]);
+    machine.memory.io_write_u8(0x21, 0xFE); // Unmask IRQ0
+    let mut code = load_ss.to_vec();
+    code.extend_from_slice(&[0xBC, 0x00, 0x04]); // MOV SP, 0x0400
+    machine.load_at(0x1000, &code);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0x0000;
+    machine.cpu.regs[0] = 0x0000; // AX, for MOV SS, AX
+    machine.cpu.regs[4] = 0x0400; // SP
+    machine.cpu.set_flag(Cpu::IF, true);
+    machine.schedule_irq(0, machine.cpu.total_cycles);
+
+    machine.step();
+    let after_load = (machine.cpu.segments[1], machine.cpu.ip);
+    machine.step();
+    (after_load, machine.cpu.segments[1])
+}
+
+#[test]
+fn test_pop_ss_delays_interrupts_one_instruction() {
+    // POP SS
+    assert_eq!(irq_after_ss_load(&[0x17]), ((0x0100, 0x0001), 0x0050));
+}
+
+#[test]
+fn test_mov_ss_delays_interrupts_one_instruction() {
+    // MOV SS, AX
+    assert_eq!(irq_after_ss_load(&[0x8E, 0xD0]), ((0x0100, 0x0002), 0x0050));
+}
+
+#[test]
+fn test_guest_programs_and_reads_pit_through_in_out() {
+    let mut machine = Machine::new();
+    let program = [
+        0xB0, 0x34, 0xE6, 0x43, // MOV AL, 0x34; OUT 0x43, AL (counter 0, lo/hi, mode 2)
+        0xB0, 0x34, 0xE6, 0x40, // MOV AL, 0x34; OUT 0x40, AL
+        0xB0, 0x12, 0xE6, 0x40, // MOV AL, 0x12; OUT 0x40, AL
+        0xBA, 0x43, 0x00, 0xB0, 0x00, 0xEE, // MOV DX, 0x43; MOV AL, 0; OUT DX, AL (latch)
+        0xB2, 0x40, 0xEC, 0x88, 0xC3, // MOV DL, 0x40; IN AL, DX; MOV BL, AL
+        0xE4, 0x40, 0x88, 0xC7, // IN AL, 0x40; MOV BH, AL
+        0xF4, // HLT
+    ];
+    machine.load_at(0x1000, &program);
+    machine.cpu.reset_to(0x0100, 0x0000);
+
+    run_to(&mut machine, (0x0100, program.len() as u16 - 1));
+
+    // Counting down from 0x1234 for only a few instructions
+    let count = machine.cpu.regs[3];
+    assert!((0x1200..0x1234).contains(&count), "count={count:#06X}");
+}
+
+#[test]
+fn test_fdc_read_data_lands_in_memory_by_dma() {
+    let mut machine = Machine::new();
+    let mut disk = FloppyDisk::new(DiskGeometry::new(40, 2, 9, 512));
+    let sector: Vec<u8> = (0..512).map(|i| (i * 7) as u8).collect();
+    disk.write_sector(0, 0, 2, &sector).unwrap();
+    machine.insert_floppy(0, disk);
+    machine.load_at(0x1000, &[0x90; 0x400]); // NOPs to step through
+    machine.cpu.reset_to(0x0100, 0x0000);
+
+    let io = &mut machine.memory;
+    io.io_write_u8(0x3F2, 0x1C); // Motor A on, DMA enabled, out of reset
+    for _ in 0..4 {
+        io.io_write_u8(0x3F5, 0x08); // Sense Interrupt Status after reset
+        io.io_read_u8(0x3F5);
+        io.io_read_u8(0x3F5);
+    }
+
+    // DMA channel 2: write to memory at 0x0600, 512 bytes
+    io.io_write_u8(0x0A, 0x06);
+    io.io_write_u8(0x0B, 0x46);
+    io.io_write_u8(0x0C, 0x00);
+    io.io_write_u8(0x04, 0x00);
+    io.io_write_u8(0x04, 0x06);
+    io.io_write_u8(0x05, 0xFF);
+    io.io_write_u8(0x05, 0x01);
+    io.io_write_u8(0x81, 0x00);
+    io.io_write_u8(0x0A, 0x02);
+
+    let raised = io.pic().raise_counts()[6];
+
+    // Read Data: drive 0, C0 H0 S2, 512-byte sectors, EOT 2
+    for byte in [0x66, 0x00, 0x00, 0x00, 0x02, 0x02, 0x02, 0x1B, 0xFF] {
+        io.io_write_u8(0x3F5, byte);
+    }
+
+    // Wait for the result phase (RQM and DIO set)
+    for _ in 0..0x400 {
+        if machine.memory.io_read_u8(0x3F4) & 0xC0 == 0xC0 {
+            break;
+        }
+        machine.step();
+    }
+    machine.step();
+    assert_eq!(machine.memory.pic().raise_counts()[6], raised + 1);
+
+    let result: Vec<u8> = (0..7).map(|_| machine.memory.io_read_u8(0x3F5)).collect();
+    assert_eq!(result[0] & 0xC0, 0x00, "ST0={:#04X}", result[0]);
+    assert_eq!(&result[3..6], &[0, 0, 3]); // Next sector: C0 H0 S3
+
+    let copied: Vec<u8> = (0..512)
+        .map(|i| machine.memory.read_u8(0x0600 + i))
+        .collect();
+    assert_eq!(copied, sector);
+}
+
+#[test]
+fn test_reset_to_boot_sector_address() {
+    let mut machine = Machine::new();
+
+    // Boot sector code at 0000:7C00: MOV AX, 0xAA55
+    machine.load_at(0x7C00, &[0xB8, 0x55, 0xAA]);
+    machine.cpu.reset_to(0x0000, 0x7C00);
+
+    assert_eq!(machine.cpu.segments[1], 0x0000);
+    assert_eq!(machine.cpu.ip, 0x7C00);
+
+    machine.step();
+    assert_eq!(machine.cpu.regs[0], 0xAA55);
+    assert_eq!(machine.cpu.ip, 0x7C03);
+
+    // The override sticks across a machine reset
+    machine.reset();
+    assert_eq!(machine.cpu.segments[1], 0x0000);
+    assert_eq!(machine.cpu.ip, 0x7C00);
+}
+
+/// Load a loop that stores the last key read from port 0x60 at 0x600 and
+/// counts iterations at 0x602
+fn load_keyboard_loop(machine: &mut Machine) {
+    // 0000:0500: IN AL, 0x60; TEST AL, AL; JZ +3; MOV [0x600], AL;
+    //            INC word [0x602]; JMP 0x500
+    let program = [
+        0xE4, 0x60, 0x84, 0xC0, 0x74, 0x03, 0xA2, 0x00, 0x06, 0xFF, 0x06, 0x02, 0x06, 0xEB, 0xF1,
+    ];
+    machine.load_at(0x500, &program);
+    machine.cpu.reset_to(0x0000, 0x0500);
+}
+
+#[test]
+fn test_rewind_restores_earlier_snapshot() {
+    let mut machine = Machine::new();
+    load_keyboard_loop(&mut machine);
+    machine.enable_time_travel(2, 2);
+
+    machine.run_frame();
+    machine.run_frame();
+    let expected = machine.snapshot();
+    for _ in 0..3 {
+        machine.run_frame();
+    }
+    assert_eq!(machine.frame_count(), 5);
+    assert_ne!(machine.cpu.save_state(), expected.cpu);
+
+    // Only the snapshots from frames 2 and 4 are kept
+    assert_eq!(machine.rewind(4), None);
+    assert_eq!(machine.frame_count(), 5);
+
+    // Three frames back is frame 2, which has a snapshot of its own
+    assert_eq!(machine.rewind(3), Some(2));
+    assert_eq!(machine.frame_count(), 2);
+    assert_eq!(machine.cpu.save_state(), expected.cpu);
+    assert!(expected.diff(&machine.snapshot()).is_empty());
+}
+
+#[test]
+fn test_rewind_exact_replays_input_to_frame() {
+    let mut machine = Machine::new();
+    load_keyboard_loop(&mut machine);
+    machine.enable_time_travel(4, 2);
+
+    machine.run_frame();
+    machine.inject(InputEvent::Scancode(0x1E));
+    machine.run_frame();
+    machine.inject(InputEvent::Scancode(0x9E));
+    machine.run_frame();
+    let expected = machine.snapshot();
+    machine.inject(InputEvent::Scancode(0x30));
+    machine.run_frame();
+    machine.run_frame();
+    assert_eq!(machine.memory.read_u8(0x600), 0x30);
+
+    // Frame 3 has no snapshot: restore frame 0 and replay both keys
+    assert_eq!(machine.rewind_exact(2), Some(3));
+    assert_eq!(machine.frame_count(), 3);
+    assert_eq!(machine.cpu.save_state(), expected.cpu);
+    assert!(expected.diff(&machine.snapshot()).is_empty());
+    assert_eq!(machine.memory.read_u8(0x600), 0x9E);
+
+    // History up to frame 3 survives, so rewinding again still works
+    machine.run_frame();
+    assert_eq!(machine.rewind_exact(1), Some(3));
+    assert_eq!(machine.cpu.save_state(), expected.cpu);
+}
+
+#[test]
+fn test_rewind_exact_to_current_frame() {
+    let mut machine = Machine::new();
+    load_keyboard_loop(&mut machine);
+    machine.enable_time_travel(10, 4);
+
+    for _ in 0..12 {
+        machine.run_frame();
+    }
+    machine.inject(InputEvent::Scancode(0x1E));
+    machine.run_frame();
+    let expected = machine.snapshot();
+
+    assert_eq!(machine.rewind_exact(0), Some(13));
+    assert_eq!(machine.frame_count(), 13);
+    assert_eq!(machine.cpu.save_state(), expected.cpu);
+    assert!(expected.diff(&machine.snapshot()).is_empty());
+    assert_eq!(machine.memory.read_u8(0x600), 0x1E);
+}
+
+#[test]
+fn test_replay_reproduces_keypress() {
+    let mut machine = Machine::new();
+    load_keyboard_loop(&mut machine);
+
+    for _ in 0..50 {
+        machine.step();
+    }
+    let snapshot = machine.snapshot();
+    machine.start_recording();
+
+    for _ in 0..100 {
+        machine.step();
+    }
+    machine.inject(InputEvent::Scancode(0x1E));
+    for _ in 0..200 {
+        machine.step();
+    }
+    machine.inject(InputEvent::Scancode(0x9E));
+    for _ in 0..20 {
+        machine.step();
+    }
+    let log = machine.stop_recording().unwrap();
+
+    assert_eq!(log.events.len(), 2);
+    assert_eq!(log.end_cycle, machine.cpu.total_cycles);
+    assert_eq!(machine.memory.read_u8(0x600), 0x9E);
+
+    let mut replayed = Machine::new();
+    replayed.replay(&snapshot, &log);
+
+    assert_eq!(replayed.cpu.save_state(), machine.cpu.save_state());
+    for addr in 0x500..0x700 {
+        assert_eq!(
+            replayed.memory.read_u8(addr),
+            machine.memory.read_u8(addr),
+            "memory differs at {:#X}",
+            addr
+        );
+    }
+    assert_eq!(replayed.devices_state(), machine.devices_state());
+    assert_eq!(
+        *replayed.scancode_queue().read().unwrap(),
+        *machine.scancode_queue().read().unwrap()
+    );
+}
+
+#[test]
+fn test_memory_map_regions() {
+    let machine = Machine::new();
+    let map = machine.memory_map();
+
+    let ram = map
+        .iter()
+        .find(|region| region.kind == MemRegionKind::Ram)
+        .unwrap();
+    assert_eq!((ram.start, ram.end), (0x00000, 0x0FFFF));
+
+    let rom = map.last().unwrap();
+    assert_eq!(rom.kind, MemRegionKind::Rom);
+    assert_eq!(rom.end, 0xFFFFF);
+    assert_eq!(rom.to_string(), "F0000-FFFFF  ROM     64K  BIOS ROM");
+
+    let video = map
+        .iter()
+        .find(|region| region.kind == MemRegionKind::Video)
+        .unwrap();
+    assert_eq!((video.start, video.end), (0xB0000, 0xB0FFF));
+
+    // Regions are sorted and do not overlap
+    for pair in map.windows(2) {
+        assert!(pair[0].end < pair[1].start);
+    }
+}
+
+#[test]
+fn test_flash_region_writes_persist_and_rest_of_rom_ignores_writes() {
+    let mut machine = Machine::new();
+    machine.load_rom(&[0xEE; 0x10000]);
+    machine.memory.set_flash_region(0xFE000..0xFF000);
+
+    for addr in [0xFE000, 0xFE800, 0xFEFFF, 0xFDFFF, 0xFF000, 0xF0000] {
+        machine.memory.write_u8(addr, 0x5A);
+    }
+
+    assert_eq!(machine.memory.read_u8(0xFE000), 0x5A);
+    assert_eq!(machine.memory.read_u8(0xFE800), 0x5A);
+    assert_eq!(machine.memory.read_u8(0xFEFFF), 0x5A);
+    assert_eq!(machine.memory.read_u8(0xFDFFF), 0xEE);
+    assert_eq!(machine.memory.read_u8(0xFF000), 0xEE);
+    assert_eq!(machine.memory.read_u8(0xF0000), 0xEE);
+
+    // The guest can program flash too: MOV AX, 0xFE00; MOV ES, AX;
+    // MOV BYTE [ES:0x0010], 0x77; HLT
+    machine.load_at(
+        0x1000,
+        &[
+            0xB8, 0x00, 0xFE, 0x8E, 0xC0, 0x26, 0xC6, 0x06, 0x10, 0x00, 0x77, 0xF4,
+        ],
+    );
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+    machine.run_frame();
+    assert_eq!(machine.memory.read_u8(0xFE010), 0x77);
+
+    let kinds: Vec<_> = machine
+        .memory_map()
+        .iter()
+        .filter(|region| region.start >= 0xF0000)
+        .map(|region| (region.start, region.end, region.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (0xF0000, 0xFDFFF, MemRegionKind::Rom),
+            (0xFE000, 0xFEFFF, MemRegionKind::Flash),
+            (0xFF000, 0xFFFFF, MemRegionKind::Rom),
+        ]
+    );
+
+    // Flash contents are part of a snapshot
+    let snapshot = machine.snapshot();
+    machine.memory.write_u8(0xFE000, 0x00);
+    machine.restore(&snapshot);
+    assert_eq!(machine.memory.read_u8(0xFE000), 0x5A);
+}
+
+#[test]
+fn test_breakpoint_calls_hook_and_stops_frame() {
+    let mut machine = Machine::new();
+    // CLI; INC AX; INC AX; INC AX; HLT
+    machine.load_at(0x1000, &[0xFA, 0x40, 0x40, 0x40, 0xF4]);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+
+    let hits = Rc::new(RefCell::new(Vec::new()));
+    let sink = hits.clone();
+    machine.set_breakpoint_hook(Some(Box::new(move |cs, ip| {
+        sink.borrow_mut().push((cs, ip))
+    })));
+    machine.set_breakpoint_at(0x0100, 0x0003);
+
+    let events = machine.run_frame();
+
+    assert_eq!(events, vec![MachineEvent::Breakpoint((0x0100, 0x0003))]);
+    assert_eq!(*hits.borrow(), vec![(0x0100, 0x0003)]);
+    assert_eq!(machine.cpu.regs[0], 2); // stopped before the third INC
+
+    // Resuming executes the instruction at the breakpoint
+    let events = machine.run_frame();
+    assert_eq!(events, vec![MachineEvent::Halted]);
+    assert_eq!(machine.cpu.regs[0], 3);
+    assert_eq!(hits.borrow().len(), 1);
+}
+
+#[test]
+fn test_cleared_breakpoint_does_not_stop() {
+    let mut machine = Machine::new();
+    // CLI; INC AX; HLT
+    machine.load_at(0x1000, &[0xFA, 0x40, 0xF4]);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+
+    machine.set_breakpoint(0x1001);
+    machine.clear_breakpoint_at(0x0100, 0x0001);
+
+    assert_eq!(machine.run_frame(), vec![MachineEvent::Halted]);
+}
+
+#[test]
+fn test_memory_value_breakpoint_fires_only_on_matching_write() {
+    let mut machine = Machine::new();
+    machine.load_at(
+        0x1000,
+        &[
+            0xFA, // CLI
+            0xC6, 0x06, 0x00, 0x05, 0x01, // MOV BYTE [0500], 1
+            0xC6, 0x06, 0x01, 0x05, 0x07, // MOV BYTE [0501], 7
+            0xC6, 0x06, 0x00, 0x05, 0x07, // MOV BYTE [0500], 7
+            0xC6, 0x06, 0x00, 0x05, 0x02, // MOV BYTE [0500], 2
+            0xF4, // HLT
+        ],
+    );
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.segments[3] = 0x0000;
+    machine.cpu.ip = 0;
+
+    machine.breakpoint_on_memory_value(0x0500, 0x07);
+
+    // Writing 1 to the byte, or 7 to its neighbour, doesn't stop
+    let events = machine.run_frame();
+    assert_eq!(events, vec![MachineEvent::Watchpoint((0x0500, 0x07))]);
+    assert_eq!(machine.memory.read_u8(0x0500), 0x07);
+    assert_eq!(machine.cpu.ip, 0x0010); // just after the matching write
+
+    // Writing 2 there afterwards isn't a match either
+    assert_eq!(machine.run_frame(), vec![MachineEvent::Halted]);
+    assert_eq!(machine.memory.read_u8(0x0500), 0x02);
+}
+
+#[test]
+fn test_invalid_opcode_hook_sees_opcode_and_address() {
+    let mut machine = Machine::new();
+    // CLI; NOP; PUSHA (80186 only, undefined on the 8088); HLT
+    machine.load_at(0x1000, &[0xFA, 0x90, 0x60, 0xF4]);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+
+    let hits = Rc::new(RefCell::new(Vec::new()));
+    let sink = hits.clone();
+    machine.on_invalid_opcode(Some(Box::new(move |opcode, at| {
+        sink.borrow_mut().push((opcode, at))
+    })));
+    machine.set_pause_on_exception(true);
+
+    let events = machine.run_frame();
+
+    assert_eq!(*hits.borrow(), vec![(0x60, (0x0100, 0x0002))]);
+    assert!(matches!(
+        events.as_slice(),
+        [MachineEvent::Exception(Exception {
+            kind: ExceptionKind::InvalidOpcode(0x60),
+            ..
+        })]
+    ));
+}
+
+#[test]
+fn test_ems_page_frame_writes_reach_backing_store() {
+    let mut machine = Machine::new();
+    let ems = machine.install_expanded_memory(ExpandedMemory::new(16));
+    machine.load_at(
+        0x1000,
+        &[
+            0xFA, // CLI
+            0xBA, 0x61, 0x02, // MOV DX, 0x261 (physical page 1)
+            0xB0, 0x05, // MOV AL, 5
+            0xEE, // OUT DX, AL
+            0xB8, 0x00, 0xE0, // MOV AX, 0xE000
+            0x8E, 0xC0, // MOV ES, AX
+            0x26, 0xC6, 0x06, 0x10, 0x40, 0x5A, // MOV BYTE ES:[0x4010], 0x5A
+            0xF4, // HLT
+        ],
+    );
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+
+    assert_eq!(machine.run_frame(), vec![MachineEvent::Halted]);
+
+    assert_eq!(ems.borrow().mapping(1), Some(5));
+    assert_eq!(ems.borrow().logical_page(5)[0x10], 0x5A);
+    assert_eq!(machine.memory.read_u8(0xE4010), 0x5A);
+
+    // The byte moves with its logical page
+    ems.borrow_mut().map(1, 6);
+    ems.borrow_mut().map(3, 5);
+    assert_eq!(machine.memory.read_u8(0xE4010), 0x00);
+    assert_eq!(machine.memory.read_u8(0xEC010), 0x5A);
+
+    // Unmapped physical pages float
+    ems.borrow_mut().unmap(3);
+    assert_eq!(machine.memory.read_u8(0xEC010), 0xFF);
+
+    let frame = machine
+        .memory_map()
+        .into_iter()
+        .find(|region| region.kind == MemRegionKind::PageFrame)
+        .expect("page frame region");
+    assert_eq!((frame.start, frame.end), (0xE0000, 0xEFFFF));
+}
+
+#[test]
+fn test_speaker_square_wave_follows_pit_counter_2() {
+    let mut machine = Machine::new();
+    // Counter 2, low then high byte, mode 3: 1193182 / 1193 = 1000 Hz
+    machine.memory.io_write_u8(0x43, 0xB6);
+    machine.memory.io_write_u8(0x42, 0xA9);
+    machine.memory.io_write_u8(0x42, 0x04);
+
+    let mut buffer = [0i16; 4800];
+    machine.speaker_samples(48000, &mut buffer);
+    assert!(buffer.iter().all(|&sample| sample == 0));
+
+    // Gate and data enable on: the tone plays
+    let port_b = machine.memory.io_read_u8(0x61);
+    machine.memory.io_write_u8(0x61, port_b | 0x03);
+    machine.speaker_samples(48000, &mut buffer);
+
+    let rising_edges: Vec<usize> = (1..buffer.len())
+        .filter(|&i| buffer[i - 1] < 0 && buffer[i] > 0)
+        .collect();
+    // 100 ms of a 1 kHz tone, 48 samples per period
+    assert!((99..=100).contains(&rising_edges.len()));
+    for pair in rising_edges.windows(2) {
+        assert!((47..=49).contains(&(pair[1] - pair[0])));
+    }
+    let span = rising_edges.last().unwrap() - rising_edges[0];
+    assert!((span as f64 / (rising_edges.len() - 1) as f64 - 48.0).abs() < 0.1);
+
+    // Clearing the data bit stops it again
+    machine.memory.io_write_u8(0x61, port_b | 0x01);
+    machine.speaker_samples(48000, &mut buffer);
+    assert!(buffer.iter().all(|&sample| sample == 0));
+}
+
+#[test]
+fn test_cga_decodes_four_color_pixels() {
+    let mut machine = Machine::with_config(MachineConfig {
+        video: VideoAdapter::Cga80,
+        ..MachineConfig::default()
+    });
+    assert_eq!(machine.active_video(), VideoAdapter::Cga80);
+    assert!(machine
+        .memory_map()
+        .iter()
+        .any(|region| (region.start, region.end) == (0xB8000, 0xBFFFF)));
+
+    // Mode 4: 320x200 graphics, cyan/magenta/white palette, blue background
+    machine.memory.io_write_u8(0x3D8, 0x0A);
+    machine.memory.io_write_u8(0x3D9, 0x21);
+    // Colors 0-3 on scanline 0, then 3-0 on scanline 1 (odd bank)
+    machine.memory.write_u8(0xB8000, 0b00_01_10_11);
+    machine.memory.write_u8(0xBA000, 0b11_10_01_00);
+    // The 16KB of video RAM is decoded twice
+    assert_eq!(machine.memory.read_u8(0xBC000), 0b00_01_10_11);
+
+    let cga = machine.memory.cga().unwrap().borrow();
+    assert_eq!(cga.mode(), CgaMode::Graphics320);
+    let mut framebuffer = vec![0u8; cga::DISPLAY_WIDTH * cga::DISPLAY_HEIGHT * 4];
+    cga.render_to_framebuffer(&mut framebuffer);
+
+    let pixel = |x: usize, y: usize| {
+        let idx = (y * cga::DISPLAY_WIDTH + x * 2) * 4;
+        [framebuffer[idx], framebuffer[idx + 1], framebuffer[idx + 2]]
+    };
+    let blue = [0x00, 0x00, 0xAA];
+    let cyan = [0x00, 0xAA, 0xAA];
+    let magenta = [0xAA, 0x00, 0xAA];
+    let gray = [0xAA, 0xAA, 0xAA];
+    assert_eq!(
+        [pixel(0, 0), pixel(1, 0), pixel(2, 0), pixel(3, 0)],
+        [blue, cyan, magenta, gray]
+    );
+    assert_eq!(
+        [pixel(0, 1), pixel(1, 1), pixel(2, 1), pixel(3, 1)],
+        [gray, magenta, cyan, blue]
+    );
+}
+
+#[test]
+fn test_rom_selects_video_adapter_through_equipment_word() {
+    let mut machine = Machine::with_config(MachineConfig {
+        video: VideoAdapter::Cga40,
+        ..MachineConfig::default()
+    });
+    assert_eq!(machine.active_video(), VideoAdapter::Cga40);
+
+    // Switching the equipment word to monochrome selects the MDA
+    let word = machine.memory.read_u16(BDA_EQUIPMENT_WORD);
+    machine.memory.write_u16(BDA_EQUIPMENT_WORD, word | 0x30);
+    assert_eq!(machine.active_video(), VideoAdapter::Mda);
+
+    // Without a CGA installed, asking for one still leaves the MDA active
+    let mut machine = Machine::new();
+    machine.memory.write_u16(BDA_EQUIPMENT_WORD, 0x0020);
+    assert_eq!(machine.active_video(), VideoAdapter::Mda);
+    assert_eq!(machine.memory.read_u8(0xB8000), 0xFF);
+}
+
+#[test]
+fn test_active_cga_drives_screen_text_dump_and_events() {
+    let mut machine = Machine::with_config(MachineConfig {
+        video: VideoAdapter::Cga80,
+        ..MachineConfig::default()
+    });
+    machine.memory.write_u8(0xB8000, b'H');
+    machine.memory.write_u8(0xB8001, 0x07);
+    machine.memory.write_u8(0xB8002, b'I');
+    machine.memory.write_u8(0xB8003, 0x07);
+
+    // Program: MOV DX, 0x3D8; MOV AL, 0x29; OUT DX, AL; JMP $
+    machine.load_at(0x1000, &[0xBA, 0xD8, 0x03, 0xB0, 0x29, 0xEE, 0xEB, 0xFE]);
+    machine.cpu.reset_to(0x0100, 0x0000);
+
+    let events = machine.run_frame();
+    assert!(events.contains(&MachineEvent::VideoModeChanged(0x29)));
+    assert_eq!(
+        events
+            .iter()
+            .filter(|event| **event == MachineEvent::VerticalRetrace)
+            .count(),
+        1
+    );
+
+    // Text comes from the CGA, not the MDA's placeholder "HELLO"
+    let text = machine.screen_text();
+    assert!(text.starts_with("HI\n"), "{:?}", text);
+    assert_eq!(text.lines().count(), 25);
+    assert_eq!(machine.screen_cell(0, 1), (b'I', 0x07));
+    assert!(machine
+        .dump_state()
+        .contains("Video: Cga80 mode control=29"));
+}
+
+/// Render a CGA machine in 80x25 text with blank video RAM, after writing
+/// 0xFF to video RAM that is not displayed
+fn render_cga_write(snow: bool) -> (Vec<u8>, Machine) {
+    let mut machine = Machine::with_config(MachineConfig {
+        video: VideoAdapter::Cga80,
+        ..MachineConfig::default()
+    });
+    machine.set_cga_snow(snow);
+    assert_eq!(machine.cga_snow(), snow);
+    machine.memory.io_write_u8(0x3D8, 0x09);
+
+    // The beam starts at the top left of the display
+    machine.memory.write_u8(0xBB000, 0xFF);
+
+    let mut framebuffer = vec![0u8; 720 * 350 * 4];
+    machine.render_to_framebuffer(&mut framebuffer);
+    (framebuffer, machine)
+}
+
+fn is_blank(framebuffer: &[u8]) -> bool {
+    framebuffer
+        .chunks_exact(4)
+        .all(|pixel| pixel == [0x00, 0x00, 0x00, 0xFF])
+}
+
+#[test]
+fn test_cga_snow_shows_write_during_active_display() {
+    let (framebuffer, mut machine) = render_cga_write(true);
+    assert_eq!(&framebuffer[..4], &[0xFF, 0xFF, 0xFF, 0xFF]);
+
+    // Gone once the beam has drawn another frame over it
+    machine.run_cycles(2 * CYCLES_PER_FRAME);
+    let mut framebuffer = vec![0u8; 720 * 350 * 4];
+    machine.render_to_framebuffer(&mut framebuffer);
+    assert!(is_blank(&framebuffer));
+}
+
+#[test]
+fn test_cga_write_without_snow_is_clean() {
+    let (framebuffer, _) = render_cga_write(false);
+    assert!(is_blank(&framebuffer));
+}
+
+#[test]
+fn test_screen_text_shows_guest_writes() {
+    let mut machine = Machine::new();
+    for addr in 0xB0000..0xB1000 {
+        machine.memory.write_u8(addr, 0);
+    }
+    machine.load_at(0x0600, b"HELLO");
+    machine.load_at(
+        0x1000,
+        &[
+            0xFA, // CLI
+            0xB8, 0x00, 0xB0, // MOV AX, 0xB000
+            0x8E, 0xC0, // MOV ES, AX
+            0xBE, 0x00, 0x06, // MOV SI, 0x0600
+            0x31, 0xFF, // XOR DI, DI
+            0xB9, 0x05, 0x00, // MOV CX, 5
+            0xFC, // CLD
+            0xB4, 0x07, // MOV AH, 0x07
+            0xAC, // LODSB
+            0xAB, // STOSW
+            0xE2, 0xFC, // LOOP -4
+            0xF4, // HLT
+        ],
+    );
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.segments[3] = 0x0000;
+    machine.cpu.ip = 0;
+
+    assert_eq!(machine.run_frame(), vec![MachineEvent::Halted]);
+
+    let text = machine.screen_text();
+    assert!(text.starts_with("HELLO\n"), "{:?}", text);
+    assert_eq!(text.lines().count(), 25);
+    assert!(text.lines().skip(1).all(str::is_empty));
+    assert_eq!(machine.screen_cell(0, 1), (b'E', 0x07));
+    assert_eq!(machine.screen_cell(0, 5), (0x00, 0x00));
+}
+
+#[test]
+fn test_pause_on_exception_dumps_divide_error_context() {
+    let mut machine = Machine::new();
+    // MOV BL, 0; DIV BL; HLT
+    machine.load_at(0x1000, &[0xB3, 0x00, 0xF6, 0xF3, 0xF4]);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+    machine.cpu.regs[4] = 0x0400; // SP
+
+    let dumps = Rc::new(RefCell::new(Vec::new()));
+    let sink = dumps.clone();
+    machine.set_exception_hook(Some(Box::new(move |exception, dump| {
+        sink.borrow_mut().push((*exception, dump.to_string()))
+    })));
+    machine.set_pause_on_exception(true);
+
+    let events = machine.run_frame();
+
+    let expected = Exception {
+        kind: ExceptionKind::DivideError,
+        at: (0x0100, 0x0002),
+    };
+    assert_eq!(events, vec![MachineEvent::Exception(expected)]);
+    assert_eq!(machine.paused_on(), Some(expected));
+
+    let dumps = dumps.borrow();
+    assert_eq!(dumps.len(), 1);
+    let (exception, dump) = &dumps[0];
+    assert_eq!(*exception, expected);
+    assert!(dump.contains("CS:IP=0100:0002"), "{}", dump);
+    assert!(dump.contains("0100:0002  DIV"), "{}", dump);
+    assert_eq!(machine.cpu.regs[4], 0x0400); // nothing pushed
+
+    // Paused: further frames run nothing
+    assert!(machine.run_frame().is_empty());
+    assert_eq!(machine.cpu.ip, 0x0002);
+}
+
+#[test]
+fn test_pause_on_exception_traps_invalid_opcode() {
+    let mut machine = Machine::new();
+    // NOP; PUSHA (invalid on the 8088)
+    machine.load_at(0x1000, &[0x90, 0x60]);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+    machine.set_pause_on_exception(true);
+
+    let events = machine.run_frame();
+
+    assert_eq!(
+        events,
+        vec![MachineEvent::Exception(Exception {
+            kind: ExceptionKind::InvalidOpcode(0x60),
+            at: (0x0100, 0x0001),
+        })]
+    );
+}
+
+#[test]
+fn test_irq_stats_counts_each_irq0_tick() {
+    let mut machine = Machine::new();
+
+    // IRQ0 handler at 0050:0000: MOV AL, 0x20; OUT 0x20, AL (EOI); IRET
+    machine.load_at(0x0500, &[0xB0, 0x20, 0xE6, 0x20, 0xCF]);
+    machine.install_handlers(&[(0x08, 0x0050, 0x0000)]);
+
+    // Program: STI; JMP $
+    machine.load_at(0x1000, &[0xFB, 0xEB, 0xFE]);
+    machine.cpu.segments[1] = 0x0100;
+    machine.cpu.ip = 0;
+    machine.cpu.regs[4] = 0x0400; // SP
+    machine.memory.io_write_u8(0x21, 0xFE); // Unmask IRQ0 only
+
+    const TICKS: u64 = 7;
+    for _ in 0..TICKS {
+        machine.inject(InputEvent::Irq {
+            line: 0,
+            level: true,
+        });
+        for _ in 0..10 {
+            machine.step();
+        }
+        machine.inject(InputEvent::Irq {
+            line: 0,
+            level: false,
+        });
+    }
+
+    // A masked line is still counted
+    machine.inject(InputEvent::Irq {
+        line: 3,
+        level: true,
+    });
+
+    let stats = machine.irq_stats();
+    assert_eq!(stats[0], TICKS);
+    assert_eq!(stats[3], 1);
+    assert_eq!(machine.memory.pic().ack_counts()[0], TICKS as u32);
+    assert!(stats[8..].iter().all(|&count| count == 0));
+}
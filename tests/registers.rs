@@ -0,0 +1,61 @@
+//! Named register accessor tests
+
+use ezpc::cpu::{Cpu, Reg16, Reg8, Seg};
+
+#[test]
+fn test_ah_is_high_byte_of_ax() {
+    let mut cpu = Cpu::new();
+    cpu.regs[0] = 0x1234;
+
+    assert_eq!(cpu.ah(), 0x12);
+    assert_eq!(cpu.al(), 0x34);
+    assert_eq!(cpu.ax(), 0x1234);
+
+    cpu.set_ah(0xAB);
+    assert_eq!(cpu.regs[0], 0xAB34);
+    cpu.set_al(0xCD);
+    assert_eq!(cpu.regs[0], 0xABCD);
+}
+
+#[test]
+fn test_named_reg8_matches_numeric_encoding() {
+    let mut cpu = Cpu::new();
+    cpu.regs = [0x0100, 0x0302, 0x0504, 0x0706, 0, 0, 0, 0];
+
+    for index in 0..8 {
+        let reg = Reg8::from_index(index).unwrap();
+        assert_eq!(reg as u8, index);
+        assert_eq!(cpu.reg8(reg), cpu.read_reg8(index));
+    }
+    assert_eq!(cpu.bh(), 0x07);
+    assert_eq!(cpu.cl(), 0x02);
+    assert_eq!(Reg8::from_index(8), None);
+}
+
+#[test]
+fn test_named_reg16_matches_numeric_encoding() {
+    let mut cpu = Cpu::new();
+    cpu.set_reg16(Reg16::SI, 0x5151);
+    cpu.set_sp(0xFFFE);
+
+    assert_eq!(cpu.regs[6], 0x5151);
+    assert_eq!(cpu.read_reg16(4), 0xFFFE);
+    assert_eq!(cpu.reg16(Reg16::SP), cpu.sp());
+    assert_eq!(Reg16::from_index(7), Some(Reg16::DI));
+}
+
+#[test]
+fn test_named_segments_match_indices() {
+    let mut cpu = Cpu::new();
+    cpu.segments = [0x1000, 0x2000, 0x3000, 0x4000];
+
+    assert_eq!(cpu.es(), cpu.segments[Seg::ES as usize]);
+    assert_eq!(cpu.cs(), cpu.segments[Seg::CS as usize]);
+    assert_eq!(cpu.ss(), cpu.segments[Seg::SS as usize]);
+    assert_eq!(cpu.ds(), cpu.segments[Seg::DS as usize]);
+    assert_eq!(cpu.cs(), 0x2000);
+
+    cpu.set_seg(Seg::DS, 0xB800);
+    assert_eq!(cpu.read_seg(3), 0xB800);
+    assert_eq!(Seg::from_index(4), None);
+}
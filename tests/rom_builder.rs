@@ -0,0 +1,79 @@
+//! Tests for building test ROMs
+
+use ezpc::machine::Machine;
+use ezpc::rom_builder::{RomBuilder, RESET_VECTOR_OFFSET, ROM_SEGMENT, ROM_SIZE};
+
+/// Step until the CPU halts, failing if it never does
+fn run_until_halt(machine: &mut Machine) {
+    for _ in 0..1000 {
+        if machine.cpu.halted {
+            return;
+        }
+        machine.step();
+    }
+    panic!(
+        "CPU never halted (at {:04X}:{:04X})",
+        machine.cpu.segments[1], machine.cpu.ip
+    );
+}
+
+#[test]
+fn test_reset_vector_runs_labeled_code() {
+    let mut rom = RomBuilder::new();
+    rom.code("pad", &[0x90; 0x20])
+        // XOR AX, AX; MOV DS, AX; MOV WORD [0x0500], 0xBEEF; HLT
+        .code(
+            "start",
+            &[
+                0x31, 0xC0, 0x8E, 0xD8, 0xC7, 0x06, 0x00, 0x05, 0xEF, 0xBE, 0xF4,
+            ],
+        )
+        .reset_vector("start");
+    let image = rom.build();
+
+    assert_eq!(image.len(), ROM_SIZE);
+    assert_eq!(rom.offset_of("start"), Some(0x0020));
+    assert_eq!(
+        &image[RESET_VECTOR_OFFSET as usize..RESET_VECTOR_OFFSET as usize + 5],
+        &[0xEA, 0x20, 0x00, 0x00, 0xF0]
+    );
+
+    let mut machine = Machine::new();
+    machine.load_rom(&image);
+    run_until_halt(&mut machine);
+
+    assert_eq!(machine.memory.read_u16(0x0500), 0xBEEF);
+}
+
+#[test]
+fn test_interrupt_vectors_and_label_references() {
+    let mut rom = RomBuilder::new();
+    // INT 0x60; MOV BX, [data]; MOV [0x0502], BX; HLT
+    rom.code("start", &[0xCD, 0x60, 0x2E, 0x8B, 0x1E])
+        .word_ref("data")
+        .bytes(&[0x89, 0x1E, 0x02, 0x05, 0xF4])
+        // Handler: MOV WORD [0x0500], 0x1234; IRET
+        .code("handler", &[0xC7, 0x06, 0x00, 0x05, 0x34, 0x12, 0xCF])
+        .code("data", &[0x78, 0x56])
+        .interrupt(0x60, "handler")
+        .reset_vector("start");
+
+    let mut machine = Machine::new();
+    machine.cpu.regs[4] = 0x0400; // SP, stack in RAM at 0000:0400
+    machine.load_rom(&rom.build());
+    run_until_halt(&mut machine);
+
+    let handler = rom.offset_of("handler").unwrap();
+    assert_eq!(machine.memory.read_u16(0x60 * 4), handler);
+    assert_eq!(machine.memory.read_u16(0x60 * 4 + 2), ROM_SEGMENT);
+    assert_eq!(machine.memory.read_u16(0x0500), 0x1234);
+    assert_eq!(machine.memory.read_u16(0x0502), 0x5678);
+}
+
+#[test]
+#[should_panic(expected = "undefined ROM label 'missing'")]
+fn test_undefined_label_panics() {
+    let mut rom = RomBuilder::new();
+    rom.reset_vector("missing");
+    rom.build();
+}
@@ -1,6 +1,6 @@
 //! Tests for shift and rotate instructions (SHL, SHR, SAR, ROL, ROR, RCL, RCR)
 
-use ezpc::cpu::{Cpu, CpuHarness};
+use ezpc::cpu::{Cpu, CpuHarness, CpuModel};
 
 // ===== SHL (Shift Left) Tests =====
 
@@ -66,6 +66,21 @@ fn test_shl_r16_cl() {
     assert_eq!(harness.cpu.regs[0], 0x0100); // AX = 0x0100 (shifted left by 8)
 }
 
+#[test]
+fn test_shl_r16_imm8_on_80186() {
+    let mut harness = CpuHarness::new();
+    harness.cpu.set_model(CpuModel::I80186);
+    // MOV AX, 0x0001; SHL AX, 4; SHL AX, 33 (count masked to 1)
+    harness.load_program(&[0xB8, 0x01, 0x00, 0xC1, 0xE0, 0x04, 0xC1, 0xE0, 0x21], 0);
+
+    harness.step_n(2);
+    assert_eq!(harness.cpu.regs[0], 0x0010);
+    assert_eq!(harness.cpu.ip, 6);
+
+    harness.step();
+    assert_eq!(harness.cpu.regs[0], 0x0020);
+}
+
 // ===== SHR (Shift Right Logical) Tests =====
 
 #[test]
@@ -309,3 +324,164 @@ fn test_shr_sets_zero_flag() {
     assert_eq!(harness.cpu.read_reg8(0), 0x00); // AL = 0
     assert_eq!(harness.cpu.get_flag(Cpu::ZF), true); // ZF = 1
 }
+
+// ===== Count 0 Tests =====
+
+#[test]
+fn test_shl_r16_cl_zero_preserves_value_and_flags() {
+    let mut harness = CpuHarness::new();
+    // MOV AX, 0; SUB AX, 1; MOV CL, 0; SHL AX, CL
+    harness.load_program(
+        &[
+            0xB8, 0x00, 0x00, // MOV AX, 0
+            0x2D, 0x01, 0x00, // SUB AX, 1 (AX=0xFFFF, CF=1, SF=1, AF=1, PF=1)
+            0xB1, 0x00, // MOV CL, 0
+            0xD3, 0xE0, // SHL AX, CL
+        ],
+        0,
+    );
+
+    harness.step_n(3);
+    let flags_before = harness.cpu.get_flags();
+    assert!(flags_before & Cpu::CF != 0);
+
+    harness.step(); // SHL AX, CL (count 0)
+    assert_eq!(harness.cpu.regs[0], 0xFFFF);
+    assert_eq!(harness.cpu.get_flags(), flags_before);
+}
+
+#[test]
+fn test_sar_r8_cl_zero_preserves_flags() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0x7F; ADD AL, 1; MOV CL, 0; SAR AL, CL
+    harness.load_program(
+        &[
+            0xB0, 0x7F, // MOV AL, 0x7F
+            0x04, 0x01, // ADD AL, 1 (AL=0x80, OF=1, SF=1, AF=1)
+            0xB1, 0x00, // MOV CL, 0
+            0xD2, 0xF8, // SAR AL, CL
+        ],
+        0,
+    );
+
+    harness.step_n(3);
+    let flags_before = harness.cpu.get_flags();
+    assert!(flags_before & Cpu::OF != 0);
+
+    harness.step(); // SAR AL, CL (count 0)
+    assert_eq!(harness.cpu.read_reg8(0), 0x80);
+    assert_eq!(harness.cpu.get_flags(), flags_before);
+}
+
+#[test]
+fn test_rcl_r8_cl_zero_preserves_carry() {
+    let mut harness = CpuHarness::new();
+    // STC; MOV AL, 0x01; MOV CL, 0; RCL AL, CL
+    harness.load_program(
+        &[
+            0xF9, // STC
+            0xB0, 0x01, // MOV AL, 0x01
+            0xB1, 0x00, // MOV CL, 0
+            0xD2, 0xD0, // RCL AL, CL
+        ],
+        0,
+    );
+
+    harness.step_n(3);
+    let flags_before = harness.cpu.get_flags();
+
+    harness.step(); // RCL AL, CL (count 0)
+    assert_eq!(harness.cpu.read_reg8(0), 0x01);
+    assert_eq!(harness.cpu.get_flags(), flags_before);
+    assert!(harness.cpu.get_flag(Cpu::CF));
+}
+
+// ===== 8088 Flag Behavior Tests =====
+
+#[test]
+fn test_shl_r8_1_msb_out_sets_cf_and_of() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0x80; SHL AL, 1
+    harness.load_program(&[0xB0, 0x80, 0xD0, 0xE0], 0);
+
+    harness.step_n(2);
+    assert_eq!(harness.cpu.read_reg8(0), 0x00);
+    assert!(harness.cpu.get_flag(Cpu::CF)); // Bit 7 shifted out
+    assert!(harness.cpu.get_flag(Cpu::OF)); // Sign changed
+    assert!(harness.cpu.get_flag(Cpu::ZF));
+    assert!(!harness.cpu.get_flag(Cpu::SF));
+}
+
+#[test]
+fn test_sar_r8_1_negative_flags() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0x81; SAR AL, 1
+    harness.load_program(&[0xB0, 0x81, 0xD0, 0xF8], 0);
+
+    harness.step_n(2);
+    assert_eq!(harness.cpu.read_reg8(0), 0xC0);
+    assert!(harness.cpu.get_flag(Cpu::CF)); // Bit 0 shifted out
+    assert!(!harness.cpu.get_flag(Cpu::OF)); // SAR by 1 never overflows
+    assert!(harness.cpu.get_flag(Cpu::SF));
+    assert!(!harness.cpu.get_flag(Cpu::ZF));
+}
+
+#[test]
+fn test_rcr_r8_1_rotates_carry_in_and_keeps_szp() {
+    let mut harness = CpuHarness::new();
+    // XOR AH, AH (ZF=1, PF=1); STC; MOV AL, 0x00; RCR AL, 1
+    harness.load_program(&[0x30, 0xE4, 0xF9, 0xB0, 0x00, 0xD0, 0xD8], 0);
+
+    harness.step_n(4);
+    assert_eq!(harness.cpu.read_reg8(0), 0x80); // Old CF rotated into bit 7
+    assert!(!harness.cpu.get_flag(Cpu::CF)); // Bit 0 rotated out
+    assert!(harness.cpu.get_flag(Cpu::OF)); // Top two bits now differ
+                                            // Rotates leave SF/ZF/PF alone
+    assert!(harness.cpu.get_flag(Cpu::ZF));
+    assert!(harness.cpu.get_flag(Cpu::PF));
+    assert!(!harness.cpu.get_flag(Cpu::SF));
+}
+
+#[test]
+fn test_shl_r8_cl_count_is_not_masked() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0xFF; MOV CL, 8; SHL AL, CL; MOV AL, 0xFF; MOV CL, 9; SHL AL, CL
+    harness.load_program(
+        &[
+            0xB0, 0xFF, 0xB1, 0x08, 0xD2, 0xE0, // count 8
+            0xB0, 0xFF, 0xB1, 0x09, 0xD2, 0xE0, // count 9
+        ],
+        0,
+    );
+
+    harness.step_n(3);
+    assert_eq!(harness.cpu.read_reg8(0), 0x00);
+    assert!(harness.cpu.get_flag(Cpu::CF)); // Last bit out was bit 0 of 0xFF
+
+    harness.step_n(3);
+    assert_eq!(harness.cpu.read_reg8(0), 0x00);
+    assert!(!harness.cpu.get_flag(Cpu::CF)); // Ninth shift moves out a zero
+}
+
+#[test]
+fn test_sar_r8_cl_past_width_fills_with_sign() {
+    let mut harness = CpuHarness::new();
+    // MOV AL, 0x90; MOV CL, 12; SAR AL, CL
+    harness.load_program(&[0xB0, 0x90, 0xB1, 0x0C, 0xD2, 0xF8], 0);
+
+    harness.step_n(3);
+    assert_eq!(harness.cpu.read_reg8(0), 0xFF);
+    assert!(harness.cpu.get_flag(Cpu::CF)); // Sign bit shifted out last
+}
+
+#[test]
+fn test_rcl_r8_cl_count_is_not_masked() {
+    let mut harness = CpuHarness::new();
+    // CLC; MOV AL, 0x01; MOV CL, 0x21; RCL AL, CL
+    // 33 = 3 * 9 + 6, so this equals RCL AL, 6 rather than RCL AL, 1
+    harness.load_program(&[0xF8, 0xB0, 0x01, 0xB1, 0x21, 0xD2, 0xD0], 0);
+
+    harness.step_n(4);
+    assert_eq!(harness.cpu.read_reg8(0), 0x40);
+    assert!(!harness.cpu.get_flag(Cpu::CF));
+}
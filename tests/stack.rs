@@ -1,6 +1,6 @@
 //! Stack operation instruction tests (PUSH, POP)
 
-use ezpc::cpu::CpuHarness;
+use ezpc::cpu::{CpuHarness, CpuModel};
 
 #[test]
 fn test_push_pop() {
@@ -205,3 +205,120 @@ fn test_push_all_segments() {
     assert_eq!(harness.cpu.regs[4], 0x0FF8);
     assert_eq!(harness.mem.read_u16(0x0FF8), 0x4444);
 }
+
+#[test]
+fn test_push_rm16_memory() {
+    let mut harness = CpuHarness::new();
+    harness.mem.write_u16(0x2000, 0xCAFE);
+    // MOV SP, 0x1000; MOV BX, 0x2000; PUSH word [BX]
+    harness.load_program(
+        &[
+            0xBC, 0x00, 0x10, // MOV SP, 0x1000
+            0xBB, 0x00, 0x20, // MOV BX, 0x2000
+            0xFF, 0x37, // PUSH word [BX] (reg=110, rm=111=[BX])
+        ],
+        0,
+    );
+
+    harness.step_n(3);
+
+    assert_eq!(harness.cpu.regs[4], 0x0FFE);
+    assert_eq!(harness.mem.read_u16(0x0FFE), 0xCAFE);
+    assert_eq!(harness.mem.read_u16(0x2000), 0xCAFE); // Source untouched
+}
+
+#[test]
+fn test_pop_rm16_memory() {
+    let mut harness = CpuHarness::new();
+    harness.load_program(
+        &[
+            0xBC, 0x00, 0x10, // MOV SP, 0x1000
+            0xBB, 0x00, 0x20, // MOV BX, 0x2000
+            0xB8, 0xCD, 0xAB, // MOV AX, 0xABCD
+            0x50, // PUSH AX
+            0x8F, 0x07, // POP [BX]
+        ],
+        0,
+    );
+
+    harness.step_n(4);
+    assert_eq!(harness.cpu.regs[4], 0x0FFE);
+
+    let cycles = harness.step(); // POP [BX]
+    assert_eq!(harness.mem.read_u16(0x2000), 0xABCD);
+    assert_eq!(harness.cpu.regs[4], 0x1000); // SP back to original
+    assert_eq!(harness.cpu.ip, 12);
+    assert_eq!(cycles, 25 + 5); // 25 + EA ([BX] = 5)
+}
+
+#[test]
+#[should_panic(expected = "Invalid opcode: 0x8f")]
+fn test_group_8f_undefined_reg_is_invalid_opcode() {
+    let mut harness = CpuHarness::new();
+    harness.load_program(
+        &[
+            0xBC, 0x00, 0x10, // MOV SP, 0x1000
+            0x8F, 0x0F, // 8F /1 [BX] - undefined on the 8088
+        ],
+        0,
+    );
+
+    harness.step(); // MOV SP, 0x1000
+    harness.step();
+}
+
+#[test]
+#[should_panic(expected = "Invalid opcode: 0x68")]
+fn test_push_imm16_is_invalid_on_8088() {
+    let mut harness = CpuHarness::new();
+    harness.load_program(&[0x68, 0x34, 0x12], 0); // PUSH 0x1234 (80186+)
+
+    harness.step();
+}
+
+#[test]
+fn test_push_imm_on_80186() {
+    let mut harness = CpuHarness::new();
+    harness.cpu.set_model(CpuModel::I80186);
+    harness.load_program(
+        &[
+            0xBC, 0x00, 0x10, // MOV SP, 0x1000
+            0x68, 0x34, 0x12, // PUSH 0x1234
+            0x6A, 0xFE, // PUSH -2
+        ],
+        0,
+    );
+
+    harness.step_n(2);
+    assert_eq!(harness.cpu.regs[4], 0x0FFE);
+    assert_eq!(harness.mem.read_u16(0x0FFE), 0x1234);
+    assert_eq!(harness.cpu.ip, 6);
+
+    harness.step(); // PUSH imm8 is sign-extended
+    assert_eq!(harness.cpu.regs[4], 0x0FFC);
+    assert_eq!(harness.mem.read_u16(0x0FFC), 0xFFFE);
+}
+
+#[test]
+fn test_enter_leave_on_80186() {
+    let mut harness = CpuHarness::new();
+    harness.cpu.set_model(CpuModel::I80186);
+    harness.load_program(
+        &[
+            0xBC, 0x00, 0x10, // MOV SP, 0x1000
+            0xBD, 0x00, 0x20, // MOV BP, 0x2000
+            0xC8, 0x08, 0x00, 0x00, // ENTER 8, 0
+            0xC9, // LEAVE
+        ],
+        0,
+    );
+
+    harness.step_n(3);
+    assert_eq!(harness.mem.read_u16(0x0FFE), 0x2000); // Old BP saved
+    assert_eq!(harness.cpu.regs[5], 0x0FFE); // BP points at the frame
+    assert_eq!(harness.cpu.regs[4], 0x0FF6); // 8 bytes of locals
+
+    harness.step(); // LEAVE
+    assert_eq!(harness.cpu.regs[5], 0x2000);
+    assert_eq!(harness.cpu.regs[4], 0x1000);
+}
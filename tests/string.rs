@@ -190,6 +190,48 @@ fn test_rep_stosw() {
     assert_eq!(harness.cpu.read_reg16(7), 0x5008);
 }
 
+#[test]
+fn test_rep_stosw_mem_profiling() {
+    let mut harness = CpuHarness::new();
+    // CLD; MOV AX, 0xBEEF; MOV CX, 0x100; MOV DI, 0x5000; REP STOSW
+    harness.load_program(
+        &[
+            0xFC, // CLD
+            0xB8, 0xEF, 0xBE, // MOV AX, 0xBEEF
+            0xB9, 0x00, 0x01, // MOV CX, 0x100
+            0xBF, 0x00, 0x50, // MOV DI, 0x5000
+            0xF3, 0xAB, // REP STOSW
+        ],
+        0,
+    );
+    harness.mem.enable_mem_profiling(true);
+
+    harness.step_n(4);
+    for _ in 0..0x100 {
+        harness.step();
+    }
+    assert_eq!(harness.cpu.read_reg16(1), 0);
+
+    // 0x100 words = 0x200 byte writes, all in the page at 0x5000
+    let hot = harness.mem.mem_hotpages(2);
+    assert_eq!(hot[0].base, 0x5000);
+    assert_eq!(hot[0].writes, 0x200);
+    assert_eq!(hot[0].reads, 0);
+
+    // The only other page touched is the code page (instruction fetches)
+    assert_eq!(hot.len(), 2);
+    assert_eq!(hot[1].base, 0x0000);
+    assert_eq!(hot[1].writes, 0);
+
+    let (_, writes) = harness.mem.mem_access_totals().unwrap();
+    assert_eq!(writes, 0x200);
+
+    // Disabling drops the counters
+    harness.mem.enable_mem_profiling(false);
+    assert!(harness.mem.mem_hotpages(1).is_empty());
+    assert_eq!(harness.mem.mem_access_totals(), None);
+}
+
 #[test]
 fn test_lodsb() {
     let mut harness = CpuHarness::new();
@@ -274,6 +316,60 @@ fn test_movsb() {
     assert_eq!(harness.cpu.read_reg16(7), 0x2001);
 }
 
+#[test]
+fn test_movsb_backward() {
+    let mut harness = CpuHarness::new();
+    harness.mem.write_u8(0x1000, 0x5A);
+
+    // STD; MOV SI, 0x1000; MOV DI, 0x2000; MOVSB
+    harness.load_program(
+        &[
+            0xFD, // STD
+            0xBE, 0x00, 0x10, // MOV SI, 0x1000
+            0xBF, 0x00, 0x20, // MOV DI, 0x2000
+            0xA4, // MOVSB
+        ],
+        0,
+    );
+
+    harness.step_n(3);
+    let flags_before = harness.cpu.get_flags();
+    harness.step(); // MOVSB
+
+    assert_eq!(harness.mem.read_u8(0x2000), 0x5A);
+    assert_eq!(harness.cpu.read_reg16(6), 0x0FFF);
+    assert_eq!(harness.cpu.read_reg16(7), 0x1FFF);
+    assert_eq!(harness.cpu.get_flags(), flags_before); // MOVS affects no flags
+}
+
+#[test]
+fn test_movsb_override_applies_to_source_only() {
+    let mut harness = CpuHarness::new();
+    harness.cpu.write_seg(0, 0x0300); // ES
+    harness.cpu.write_seg(3, 0x0400); // DS
+    harness.mem.write_u8(0x1010, 0x11); // CS:0010
+    harness.mem.write_u8(0x4010, 0x22); // DS:0010
+
+    // CLD; MOV SI, 0x0010; MOV DI, 0x0020; CS: MOVSB
+    harness.load_program(
+        &[
+            0xFC, // CLD
+            0xBE, 0x10, 0x00, // MOV SI, 0x0010
+            0xBF, 0x20, 0x00, // MOV DI, 0x0020
+            0x2E, 0xA4, // CS: MOVSB
+        ],
+        0x0100,
+    );
+
+    harness.step_n(4);
+
+    // Source came from CS, destination is still ES
+    assert_eq!(harness.mem.read_u8(0x3020), 0x11);
+    assert_eq!(harness.mem.read_u8(0x1020), 0x00);
+    assert_eq!(harness.cpu.read_reg16(6), 0x0011);
+    assert_eq!(harness.cpu.read_reg16(7), 0x0021);
+}
+
 #[test]
 fn test_rep_movsb() {
     let mut harness = CpuHarness::new();
@@ -317,6 +413,204 @@ fn test_rep_movsb() {
     assert_eq!(harness.cpu.read_reg16(7), 0x300A);
 }
 
+#[test]
+fn test_rep_movsb_counts_cycles_per_element() {
+    let mut harness = CpuHarness::new();
+    for i in 0..4 {
+        harness.mem.write_u8(0x1000 + i, 0xC0 + i as u8);
+    }
+
+    // CLD; MOV SI, 0x1000; MOV DI, 0x2000; MOV CX, 4; REP MOVSB; NOP
+    harness.load_program(
+        &[
+            0xFC, 0xBE, 0x00, 0x10, 0xBF, 0x00, 0x20, 0xB9, 0x04, 0x00, 0xF3, 0xA4, 0x90,
+        ],
+        0,
+    );
+    harness.step_n(4);
+
+    // Each element is its own step, back at the prefix until CX runs out
+    let mut cycles = Vec::new();
+    for remaining in (0..4).rev() {
+        cycles.push(harness.step());
+        assert_eq!(harness.cpu.read_reg16(1), remaining);
+        let expected_ip = if remaining == 0 { 0x000C } else { 0x000A };
+        assert_eq!(harness.cpu.ip, expected_ip);
+    }
+    assert!(cycles.iter().all(|&c| c > 0));
+
+    for i in 0..4 {
+        assert_eq!(harness.mem.read_u8(0x2000 + i), 0xC0 + i as u8);
+    }
+}
+
+#[test]
+fn test_rep_movsb_overlapping_forward_smears_source() {
+    let mut harness = CpuHarness::new();
+
+    for i in 0..8 {
+        harness.mem.write_u8(0x1000 + i, i as u8 + 1);
+    }
+
+    // Shift the buffer up by one, the wrong way round:
+    // CLD; MOV SI, 0x1000; MOV DI, 0x1001; MOV CX, 8; REP MOVSB
+    harness.load_program(
+        &[
+            0xFC, // CLD
+            0xBE, 0x00, 0x10, // MOV SI, 0x1000
+            0xBF, 0x01, 0x10, // MOV DI, 0x1001
+            0xB9, 0x08, 0x00, // MOV CX, 8
+            0xF3, 0xA4, // REP MOVSB
+        ],
+        0,
+    );
+
+    for _ in 0..4 {
+        harness.step();
+    }
+    for _ in 0..8 {
+        harness.step();
+    }
+
+    // Every iteration reads the byte the previous one just wrote, so the
+    // first byte fills the whole destination
+    for i in 0..9 {
+        assert_eq!(harness.mem.read_u8(0x1000 + i), 1, "byte {}", i);
+    }
+    assert_eq!(harness.cpu.read_reg16(1), 0);
+}
+
+#[test]
+fn test_rep_movsb_overlapping_backward_with_df() {
+    let mut harness = CpuHarness::new();
+
+    for i in 0..8 {
+        harness.mem.write_u8(0x1000 + i, i as u8 + 1);
+    }
+
+    // Shift the buffer up by one from the top end:
+    // STD; MOV SI, 0x1007; MOV DI, 0x1008; MOV CX, 8; REP MOVSB
+    harness.load_program(
+        &[
+            0xFD, // STD
+            0xBE, 0x07, 0x10, // MOV SI, 0x1007
+            0xBF, 0x08, 0x10, // MOV DI, 0x1008
+            0xB9, 0x08, 0x00, // MOV CX, 8
+            0xF3, 0xA4, // REP MOVSB
+        ],
+        0,
+    );
+
+    for _ in 0..4 {
+        harness.step();
+    }
+    for _ in 0..8 {
+        harness.step();
+    }
+
+    // Each source byte is read before anything overwrites it
+    assert_eq!(harness.mem.read_u8(0x1000), 1);
+    for i in 0..8 {
+        assert_eq!(harness.mem.read_u8(0x1001 + i), i as u8 + 1, "byte {}", i);
+    }
+    assert_eq!(harness.cpu.read_reg16(6), 0x0FFF);
+    assert_eq!(harness.cpu.read_reg16(7), 0x1000);
+}
+
+#[test]
+fn test_rep_movsw_overlapping_copies_a_word_at_a_time() {
+    let mut harness = CpuHarness::new();
+
+    for i in 0..8 {
+        harness.mem.write_u8(0x1000 + i, i as u8 + 1);
+    }
+
+    // CLD; MOV SI, 0x1000; MOV DI, 0x1001; MOV CX, 3; REP MOVSW
+    harness.load_program(
+        &[
+            0xFC, // CLD
+            0xBE, 0x00, 0x10, // MOV SI, 0x1000
+            0xBF, 0x01, 0x10, // MOV DI, 0x1001
+            0xB9, 0x03, 0x00, // MOV CX, 3
+            0xF3, 0xA5, // REP MOVSW
+        ],
+        0,
+    );
+
+    for _ in 0..4 {
+        harness.step();
+    }
+    for _ in 0..3 {
+        harness.step();
+    }
+
+    // Both bytes of a word are read before either is written, so each
+    // word picks up one byte the previous iteration wrote
+    let bytes: Vec<u8> = (0..8).map(|i| harness.mem.read_u8(0x1000 + i)).collect();
+    assert_eq!(bytes, vec![1, 1, 2, 2, 4, 4, 6, 8]);
+}
+
+#[test]
+fn test_rep_movsw_with_cx_zero_does_nothing() {
+    let mut harness = CpuHarness::new();
+
+    harness.mem.write_u16(0x1000, 0x5678);
+    harness.mem.write_u16(0x2000, 0xAAAA);
+
+    // CLD; MOV SI, 0x1000; MOV DI, 0x2000; XOR CX, CX; REP MOVSW
+    harness.load_program(
+        &[
+            0xFC, // CLD
+            0xBE, 0x00, 0x10, // MOV SI, 0x1000
+            0xBF, 0x00, 0x20, // MOV DI, 0x2000
+            0x31, 0xC9, // XOR CX, CX
+            0xF3, 0xA5, // REP MOVSW
+        ],
+        0,
+    );
+
+    harness.step_n(4);
+    let flags = harness.cpu.get_flags();
+    let ip = harness.cpu.ip;
+
+    harness.step(); // REP MOVSW
+
+    assert_eq!(harness.cpu.ip, ip.wrapping_add(2));
+    assert_eq!(harness.cpu.read_reg16(1), 0); // CX does not wrap
+    assert_eq!(harness.cpu.read_reg16(6), 0x1000);
+    assert_eq!(harness.cpu.read_reg16(7), 0x2000);
+    assert_eq!(harness.mem.read_u16(0x2000), 0xAAAA);
+    assert_eq!(harness.cpu.get_flags(), flags);
+}
+
+#[test]
+fn test_repe_cmpsb_with_cx_zero_leaves_flags() {
+    let mut harness = CpuHarness::new();
+
+    harness.mem.write_u8(0x1000, 0x01);
+    harness.mem.write_u8(0x2000, 0x02);
+
+    // CLD; MOV SI, 0x1000; MOV DI, 0x2000; XOR CX, CX; REPE CMPSB
+    harness.load_program(
+        &[
+            0xFC, // CLD
+            0xBE, 0x00, 0x10, // MOV SI, 0x1000
+            0xBF, 0x00, 0x20, // MOV DI, 0x2000
+            0x31, 0xC9, // XOR CX, CX
+            0xF3, 0xA6, // REPE CMPSB
+        ],
+        0,
+    );
+
+    harness.step_n(5);
+
+    // ZF from XOR survives; comparing the bytes would have cleared it
+    assert!(harness.cpu.get_flag(ezpc::cpu::Cpu::ZF));
+    assert!(!harness.cpu.get_flag(ezpc::cpu::Cpu::CF));
+    assert_eq!(harness.cpu.read_reg16(6), 0x1000);
+    assert_eq!(harness.cpu.read_reg16(1), 0);
+}
+
 #[test]
 fn test_movsw() {
     let mut harness = CpuHarness::new();
@@ -524,6 +818,56 @@ fn test_repe_cmpsb_early_mismatch() {
     assert_eq!(harness.cpu.read_reg16(7), 0x2003);
 }
 
+#[test]
+fn test_repe_cmpsb_exit_flags_match_last_pair() {
+    use ezpc::cpu::Cpu;
+    const ARITH: u16 = Cpu::CF | Cpu::PF | Cpu::AF | Cpu::ZF | Cpu::SF | Cpu::OF;
+
+    let mut harness = CpuHarness::new();
+
+    // Buffers differ at index 3, where the source byte is below the destination
+    for (i, (&a, &b)) in b"ABCD!".iter().zip(b"ABCx!").enumerate() {
+        harness.mem.write_u8(0x1000 + i as u32, a);
+        harness.mem.write_u8(0x2000 + i as u32, b);
+    }
+
+    // CLD; MOV SI, 0x1000; MOV DI, 0x2000; MOV CX, 5; REPE CMPSB
+    harness.load_program(
+        &[
+            0xFC, // CLD
+            0xBE, 0x00, 0x10, // MOV SI, 0x1000
+            0xBF, 0x00, 0x20, // MOV DI, 0x2000
+            0xB9, 0x05, 0x00, // MOV CX, 5
+            0xF3, 0xA6, // REPE CMPSB
+        ],
+        0,
+    );
+    for _ in 0..4 {
+        harness.step();
+    }
+    while harness.cpu.ip != 12 {
+        harness.step();
+    }
+
+    // Stopped after comparing 'D' with 'x': four iterations, one left
+    assert_eq!(harness.cpu.read_reg16(1), 1); // CX
+    assert_eq!(harness.cpu.read_reg16(6), 0x1004);
+    assert_eq!(harness.cpu.read_reg16(7), 0x2004);
+    assert!(!harness.cpu.get_flag(Cpu::ZF));
+    assert!(harness.cpu.get_flag(Cpu::CF));
+
+    // Every arithmetic flag matches CMP of the last pair
+    let mut reference = CpuHarness::new();
+    // MOV AL, 'D'; CMP AL, 'x'
+    reference.load_program(&[0xB0, b'D', 0x3C, b'x'], 0);
+    reference.step();
+    reference.step();
+    assert_eq!(
+        harness.cpu.get_flags() & ARITH,
+        reference.cpu.get_flags() & ARITH
+    );
+}
+
 #[test]
 fn test_repne_cmpsb_find_match() {
     let mut harness = CpuHarness::new();
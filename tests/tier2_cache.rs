@@ -201,3 +201,50 @@ fn test_cache_hit_rate() {
     let hit_rate = harness.cpu.decode_cache.hit_rate();
     assert!(hit_rate > 0.5, "Hit rate should be > 50% for a tight loop");
 }
+
+/// Test that the same offset in different segments does not share an entry
+#[test]
+fn test_same_offset_in_two_segments() {
+    let mut harness = CpuHarness::new();
+
+    // 0100:0000 = MOV AX, 0x1111; 0200:0000 = MOV AX, 0x2222
+    harness.load_program(&[0xB8, 0x11, 0x11], 0x0100);
+    harness.load_program(&[0xB8, 0x22, 0x22], 0x0200);
+
+    for _ in 0..2 {
+        harness.cpu.segments[1] = 0x0100;
+        harness.cpu.ip = 0;
+        harness.step();
+        assert_eq!(harness.cpu.regs[0], 0x1111);
+
+        harness.cpu.segments[1] = 0x0200;
+        harness.cpu.ip = 0;
+        harness.step();
+        assert_eq!(harness.cpu.regs[0], 0x2222);
+    }
+
+    assert!(harness.cpu.decode_cache.contains(0x1000));
+    assert!(harness.cpu.decode_cache.contains(0x2000));
+    assert_eq!(harness.cpu.decode_cache.total_hits(), 2);
+}
+
+/// Test that code changed without going through the CPU is re-decoded
+#[test]
+fn test_stale_bytes_are_redecoded() {
+    let mut harness = CpuHarness::new();
+
+    // MOV AX, 0x1234
+    harness.load_program(&[0xB8, 0x34, 0x12], 0);
+    harness.step();
+    assert_eq!(harness.cpu.regs[0], 0x1234);
+
+    // Write straight to the bus, so the cache is not invalidated (as DMA
+    // into a code buffer would)
+    harness.mem.write_u8(0x0001, 0x78);
+    harness.mem.write_u8(0x0002, 0x56);
+
+    harness.cpu.ip = 0;
+    harness.step();
+    assert_eq!(harness.cpu.regs[0], 0x5678);
+    assert_eq!(harness.cpu.decode_cache.total_hits(), 0);
+}